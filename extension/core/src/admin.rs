@@ -0,0 +1,103 @@
+use crate::backend::{Declaration, Query, QueryHandler, Reliability, RxSample};
+use crate::blocks::PayloadBlocks;
+use crate::Core;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use zenoh::key_expr::KeyExpr;
+
+/// Operations reachable through the admin queryable, each at `<prefix>/<operation>`.
+pub const ADMIN_OPERATIONS: &[&str] = &[
+    "session_info",
+    "get_topics",
+    "list_subscriptions",
+    "list_recordings",
+    "start_flight_recorder",
+    "dump_flight_recorder",
+    "list_flight_recorders",
+    "stop_flight_recorder",
+];
+
+/// The extension's control surface served over zenoh while alive.
+pub struct AdminQueryable {
+    pub prefix: String,
+    pub queries: Arc<AtomicU64>,
+    _queryable: Declaration,
+}
+
+/// Validate an admin key prefix; surrounding slashes are trimmed.
+pub fn parse_prefix(prefix: &str) -> Result<String, String> {
+    let prefix = prefix.trim_matches('/');
+    if prefix.is_empty() || prefix.contains('*') || prefix.contains('$') {
+        return Err(format!(
+            "admin_key_expr must be a plain key prefix without wildcards: {prefix}"
+        ));
+    }
+    KeyExpr::try_from(prefix).map_err(|e| format!("invalid admin_key_expr {prefix}: {e}"))?;
+    Ok(prefix.to_string())
+}
+
+/// Declare a queryable on `<prefix>/*` that runs the named operation with the
+/// query payload (JSON, empty for `{}`) as input, through [`Core::execute`]
+/// so read-only mode and error recording apply. The reply carries
+/// `{"success": true, "data": ...}` or `{"success": false, "error": ...}`.
+/// Queries that don't name one operation exactly get no reply.
+pub async fn serve(core: Core, prefix: String) -> Result<AdminQueryable, String> {
+    let queries: Arc<AtomicU64> = Arc::default();
+    let handler: QueryHandler = {
+        let (core, prefix, queries) = (core.clone(), prefix.clone(), queries.clone());
+        Arc::new(move |query: Query| {
+            let (core, prefix, queries) = (core.clone(), prefix.clone(), queries.clone());
+            Box::pin(async move {
+                let Some(operation) = query
+                    .key_expr
+                    .strip_prefix(&prefix)
+                    .and_then(|rest| rest.strip_prefix('/'))
+                    .filter(|op| ADMIN_OPERATIONS.contains(op))
+                else {
+                    return Vec::new();
+                };
+                queries.fetch_add(1, Ordering::Relaxed);
+                let reply = match parse_input(query.payload.as_deref()) {
+                    Ok(input) => {
+                        let mut blocks = PayloadBlocks::new(false);
+                        core.execute(operation, &input, &mut blocks).await
+                    }
+                    Err(e) => Err(e),
+                };
+                let body = match reply {
+                    Ok(data) => serde_json::json!({ "success": true, "data": data }),
+                    Err(error) => serde_json::json!({ "success": false, "error": error }),
+                };
+                vec![RxSample {
+                    key_expr: query.key_expr,
+                    payload: serde_json::to_vec(&body).unwrap(),
+                    encoding: "application/json".into(),
+                    source: None,
+                    timestamp: None,
+                    reliability: Reliability::Reliable,
+                    shm: false,
+                    delete: false,
+                }]
+            })
+        })
+    };
+    let queryable = core
+        .backend
+        .declare_queryable(&format!("{prefix}/*"), handler)
+        .await?;
+
+    Ok(AdminQueryable {
+        prefix,
+        queries,
+        _queryable: queryable,
+    })
+}
+
+fn parse_input(payload: Option<&[u8]>) -> Result<Value, String> {
+    match payload {
+        Some(bytes) if !bytes.trim_ascii().is_empty() => serde_json::from_slice(bytes)
+            .map_err(|e| format!("admin query payload is not JSON: {e}")),
+        _ => Ok(Value::Object(Default::default())),
+    }
+}
@@ -0,0 +1,405 @@
+use crate::expr::Expression;
+use crate::jsonpath;
+use crate::notify;
+use crate::state::{AppState, BufferedSample, StateLock};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use zenoh::key_expr::KeyExpr;
+
+/// Maximum alert events retained for `poll_alerts`; oldest are dropped first.
+const EVENT_CAPACITY: usize = 1000;
+
+/// How often the evaluator checks time-based conditions.
+const EVALUATE_EVERY: Duration = Duration::from_secs(1);
+
+/// What an alert rule watches for.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "condition", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Aggregate discovery rate of matching topics fell below `min_hz`.
+    RateBelow {
+        key_expr: String,
+        min_hz: f64,
+        #[serde(default = "default_window_secs")]
+        window_secs: u64,
+    },
+    /// A numeric payload field on a subscription left `[min, max]`.
+    FieldOutOfRange {
+        sub_id: String,
+        path: String,
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    /// Every discovered topic matching `key_expr` has expired.
+    TopicDisappeared { key_expr: String },
+    /// A subscription's buffer dropped samples since the last check.
+    BufferOverflow { sub_id: String },
+    /// An expression over a subscription's samples is true for the latest one.
+    Expression { sub_id: String, expression: String },
+}
+
+fn default_window_secs() -> u64 {
+    5
+}
+
+impl AlertCondition {
+    /// Check constraints the input shape alone can't express.
+    pub fn validate(mut self) -> Result<Self, String> {
+        match &mut self {
+            AlertCondition::RateBelow {
+                key_expr,
+                window_secs,
+                ..
+            } => {
+                validate_key_expr(key_expr)?;
+                *window_secs = (*window_secs).max(1);
+            }
+            AlertCondition::FieldOutOfRange { min, max, .. } => {
+                if min.is_none() && max.is_none() {
+                    return Err("field_out_of_range needs at least one of min/max".into());
+                }
+            }
+            AlertCondition::TopicDisappeared { key_expr } => validate_key_expr(key_expr)?,
+            AlertCondition::BufferOverflow { .. } => {}
+            AlertCondition::Expression { expression, .. } => {
+                Expression::parse(expression)?;
+            }
+        }
+        Ok(self)
+    }
+}
+
+fn validate_key_expr(key_expr: &str) -> Result<(), String> {
+    KeyExpr::try_from(key_expr)
+        .map(|_| ())
+        .map_err(|e| format!("invalid key_expr {key_expr}: {e}"))
+}
+
+/// A registered alert rule and its current firing state.
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct AlertRule {
+    pub alert_id: String,
+    #[serde(flatten)]
+    pub condition: AlertCondition,
+    pub created_at: DateTime<Utc>,
+    pub firing: bool,
+    pub fire_count: u64,
+    pub last_fired: Option<DateTime<Utc>>,
+    /// Condition-specific bookkeeping between evaluations.
+    #[serde(skip)]
+    tracker: Tracker,
+}
+
+#[derive(Clone, Default)]
+struct Tracker {
+    /// Rate rules: (check instant, summed sample count) at the window start.
+    rate_window: Option<(Instant, u64)>,
+    /// Disappearance rules: whether a matching topic was ever seen.
+    seen: bool,
+    /// Overflow rules: overflow count at the last check.
+    last_overflow: Option<u64>,
+    /// Expression rules: the parsed expression.
+    expression: Option<Expression>,
+}
+
+impl AlertRule {
+    pub fn new(condition: AlertCondition) -> Self {
+        let expression = match &condition {
+            AlertCondition::Expression { expression, .. } => Expression::parse(expression).ok(),
+            _ => None,
+        };
+        Self {
+            alert_id: uuid::Uuid::new_v4().to_string(),
+            condition,
+            created_at: Utc::now(),
+            firing: false,
+            fire_count: 0,
+            last_fired: None,
+            tracker: Tracker {
+                expression,
+                ..Tracker::default()
+            },
+        }
+    }
+}
+
+/// A firing or resolution transition of an alert rule.
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct AlertEvent {
+    pub alert_id: String,
+    pub state: &'static str,
+    pub message: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Registered rules plus the bounded queue of events awaiting `poll_alerts`.
+#[derive(Default)]
+pub struct AlertState {
+    pub rules: HashMap<String, AlertRule>,
+    pub events: VecDeque<AlertEvent>,
+    pub events_dropped: u64,
+}
+
+impl AlertState {
+    /// Start every rate window afresh, so a gap in discovery does not read
+    /// as a rate drop.
+    pub fn restart_rate_windows(&mut self) {
+        for rule in self.rules.values_mut() {
+            rule.tracker.rate_window = None;
+        }
+    }
+
+    /// Move a rule to `firing`, recording an event only on a change.
+    /// Returns the event for [`announce`] once the state lock is released.
+    fn transition(&mut self, alert_id: &str, firing: bool, message: String) -> Option<AlertEvent> {
+        let rule = self.rules.get_mut(alert_id)?;
+        if rule.firing == firing {
+            return None;
+        }
+        rule.firing = firing;
+        let now = Utc::now();
+        if firing {
+            rule.fire_count += 1;
+            rule.last_fired = Some(now);
+        }
+
+        let event = AlertEvent {
+            alert_id: alert_id.to_string(),
+            state: if firing { "firing" } else { "resolved" },
+            message,
+            timestamp: now,
+        };
+        if self.events.len() >= EVENT_CAPACITY {
+            self.events.pop_front();
+            self.events_dropped += 1;
+        }
+        self.events.push_back(event.clone());
+        Some(event)
+    }
+
+    pub fn drain_events(&mut self, limit: usize) -> Vec<AlertEvent> {
+        let n = limit.min(self.events.len());
+        self.events.drain(..n).collect()
+    }
+
+    /// Evaluate field-range rules bound to `sub_id` against a freshly received
+    /// sample, returning the transitions to [`announce`].
+    pub fn on_sample(&mut self, sub_id: &str, sample: &BufferedSample) -> Vec<AlertEvent> {
+        // Parsed at most once, however many rules look at the sample
+        let mut json = None;
+        let checks: Vec<(String, Option<f64>, f64, f64)> = self
+            .rules
+            .values()
+            .filter_map(|rule| match &rule.condition {
+                AlertCondition::FieldOutOfRange {
+                    sub_id: rule_sub,
+                    path,
+                    min,
+                    max,
+                } if rule_sub == sub_id => {
+                    let value = json
+                        .get_or_insert_with(|| sample.json())
+                        .as_ref()
+                        .and_then(|v| jsonpath::lookup(v, path).and_then(|f| f.as_f64()));
+                    Some((
+                        rule.alert_id.clone(),
+                        value,
+                        min.unwrap_or(f64::NEG_INFINITY),
+                        max.unwrap_or(f64::INFINITY),
+                    ))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let mut events = Vec::new();
+        for (alert_id, value, min, max) in checks {
+            // Samples without the field leave the rule as it is
+            let Some(value) = value else { continue };
+            let out_of_range = value < min || value > max;
+            let message = format!(
+                "{} = {value} on {} ({})",
+                field_path(&self.rules[&alert_id].condition),
+                sample.key_expr,
+                if out_of_range {
+                    "out of range"
+                } else {
+                    "back in range"
+                },
+            );
+            events.extend(self.transition(&alert_id, out_of_range, message));
+        }
+
+        let mut vars = None;
+        let results: Vec<(String, bool, String)> = self
+            .rules
+            .values()
+            .filter_map(|rule| match (&rule.condition, &rule.tracker.expression) {
+                (
+                    AlertCondition::Expression {
+                        sub_id: rule_sub, ..
+                    },
+                    Some(expr),
+                ) if rule_sub == sub_id => {
+                    let vars = vars.get_or_insert_with(|| {
+                        let json = json.get_or_insert_with(|| sample.json()).clone();
+                        sample.expr_vars_of(json)
+                    });
+                    // Evaluation errors and non-bool results leave the rule as it is
+                    let matched = expr.eval(vars).ok()?.as_bool()?;
+                    Some((rule.alert_id.clone(), matched, expr.source().to_string()))
+                }
+                _ => None,
+            })
+            .collect();
+        for (alert_id, matched, source) in results {
+            let message = match matched {
+                true => format!("{source} matched on {}", sample.key_expr),
+                false => format!("{source} no longer matches on {}", sample.key_expr),
+            };
+            events.extend(self.transition(&alert_id, matched, message));
+        }
+        events
+    }
+}
+
+/// Send `alert` notifications for transitions, after the state lock that
+/// produced them is released so a slow stdout never holds it up.
+pub fn announce(events: Vec<AlertEvent>) {
+    for event in events {
+        notify::notify("alert", serde_json::to_value(&event).unwrap());
+    }
+}
+
+fn field_path(condition: &AlertCondition) -> &str {
+    match condition {
+        AlertCondition::FieldOutOfRange { path, .. } => path,
+        _ => "",
+    }
+}
+
+/// Spawn the background task that evaluates time-based alert conditions.
+pub fn spawn_evaluator(state: Arc<StateLock>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EVALUATE_EVERY);
+        loop {
+            interval.tick().await;
+            let events = evaluate(&mut *state.write().await, Instant::now());
+            announce(events);
+        }
+    });
+}
+
+fn evaluate(st: &mut AppState, now: Instant) -> Vec<AlertEvent> {
+    let mut events = Vec::new();
+    let ids: Vec<String> = st.alerts.rules.keys().cloned().collect();
+    for alert_id in ids {
+        let outcome = match &st.alerts.rules[&alert_id].condition {
+            AlertCondition::RateBelow {
+                key_expr,
+                min_hz,
+                window_secs,
+            } => evaluate_rate(st, &alert_id, key_expr.clone(), *min_hz, *window_secs, now),
+            AlertCondition::TopicDisappeared { key_expr } => {
+                evaluate_disappeared(st, &alert_id, key_expr.clone())
+            }
+            AlertCondition::BufferOverflow { sub_id } => {
+                evaluate_overflow(st, &alert_id, sub_id.clone())
+            }
+            AlertCondition::FieldOutOfRange { .. } | AlertCondition::Expression { .. } => None,
+        };
+        if let Some((firing, message)) = outcome {
+            events.extend(st.alerts.transition(&alert_id, firing, message));
+        }
+    }
+    events
+}
+
+/// Topics in the discovery table whose key intersects `key_expr`.
+fn matching_topics<'a>(
+    st: &'a AppState,
+    key_expr: &str,
+) -> impl Iterator<Item = &'a crate::state::TopicMeta> {
+    let filter = KeyExpr::try_from(key_expr.to_string()).ok();
+    st.topics.values().filter(move |t| {
+        filter.as_ref().is_some_and(|f| {
+            KeyExpr::try_from(t.key_expr.as_str()).is_ok_and(|ke| f.intersects(&ke))
+        })
+    })
+}
+
+fn evaluate_rate(
+    st: &mut AppState,
+    alert_id: &str,
+    key_expr: String,
+    min_hz: f64,
+    window_secs: u64,
+    now: Instant,
+) -> Option<(bool, String)> {
+    if !st.discovery_active {
+        return None;
+    }
+    let count: u64 = matching_topics(st, &key_expr).map(|t| t.sample_count).sum();
+    let rule = st.alerts.rules.get_mut(alert_id)?;
+    let Some((start, start_count)) = rule.tracker.rate_window else {
+        rule.tracker.rate_window = Some((now, count));
+        return None;
+    };
+    let elapsed = (now - start).as_secs_f64();
+    if elapsed < window_secs as f64 {
+        return None;
+    }
+    rule.tracker.rate_window = Some((now, count));
+    // Expired topics shrink the sum; treat that as no traffic rather than negative
+    let rate = count.saturating_sub(start_count) as f64 / elapsed;
+    Some((
+        rate < min_hz,
+        format!("{key_expr} rate {rate:.2} Hz (min {min_hz} Hz)"),
+    ))
+}
+
+fn evaluate_disappeared(
+    st: &mut AppState,
+    alert_id: &str,
+    key_expr: String,
+) -> Option<(bool, String)> {
+    if !st.discovery_active {
+        return None;
+    }
+    let present = matching_topics(st, &key_expr).next().is_some();
+    let rule = st.alerts.rules.get_mut(alert_id)?;
+    if present {
+        rule.tracker.seen = true;
+        Some((false, format!("{key_expr} is publishing again")))
+    } else if rule.tracker.seen {
+        Some((true, format!("{key_expr} disappeared from discovery")))
+    } else {
+        None
+    }
+}
+
+fn evaluate_overflow(st: &mut AppState, alert_id: &str, sub_id: String) -> Option<(bool, String)> {
+    let overflow = st.subscriptions.get(&sub_id)?.overflow_count;
+    let rule = st.alerts.rules.get_mut(alert_id)?;
+    let last = rule
+        .tracker
+        .last_overflow
+        .replace(overflow)
+        .unwrap_or(overflow);
+    let dropped = overflow - last;
+    Some(if dropped > 0 {
+        (
+            true,
+            format!("subscription {sub_id} dropped {dropped} samples"),
+        )
+    } else {
+        (
+            false,
+            format!("subscription {sub_id} buffer no longer overflowing"),
+        )
+    })
+}
@@ -0,0 +1,3368 @@
+//! Typed request and response bodies for every operation.
+
+use crate::alerts::{AlertEvent, AlertRule};
+use crate::bridge::RemapRule;
+use crate::cdr::CdrField;
+use crate::history::HistoryPoint;
+use crate::key_watch::ValueChange;
+use crate::metrics::ProcessUsage;
+use crate::probe::StepResult;
+use crate::query::ReplyError;
+use crate::recordings::RecordingInfo;
+use crate::render::Rendered;
+use crate::router_health::RouterHealth;
+use crate::skew::ClockSkewSummary;
+use crate::spill::SpillStats;
+use crate::state::{EncodingCount, InterArrivalStats, Payload, PayloadPreview};
+use crate::storages::StorageInfo;
+use crate::table::{TableChange, TableEntry};
+use crate::threshold::ThresholdCrossing;
+use crate::timeline::SourceKind;
+use crate::topology::{TopologyChange, TopologyEdge, TopologyNode};
+use crate::trace;
+use crate::versions::VersionReport;
+use chrono::{DateTime, Utc};
+use schemars::{JsonSchema, Schema};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+/// Deserialize an operation's `input`, reporting missing or mistyped fields.
+pub fn parse<T: DeserializeOwned>(input: &Value) -> Result<T, String> {
+    let since = Instant::now();
+    let parsed = T::deserialize(input).map_err(|e| format!("invalid input: {e}"));
+    trace::serialized(since);
+    parsed
+}
+
+pub fn to_value<T: Serialize>(result: T) -> Result<Value, String> {
+    let since = Instant::now();
+    let value = serde_json::to_value(result).map_err(|e| e.to_string());
+    trace::serialized(since);
+    value
+}
+
+fn all_keys() -> String {
+    "**".into()
+}
+
+fn default_redaction_action() -> String {
+    "mask".into()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_buffer_size() -> usize {
+    100
+}
+
+fn default_export_format() -> String {
+    "csv".into()
+}
+
+fn default_row_group_size() -> usize {
+    10_000
+}
+
+fn default_flight_max_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+fn default_dump_last_secs() -> u64 {
+    300
+}
+
+fn default_slop_ms() -> u64 {
+    100
+}
+
+fn default_poll_limit() -> usize {
+    10
+}
+
+fn default_timeline_limit() -> usize {
+    1000
+}
+
+fn default_alert_limit() -> usize {
+    100
+}
+
+fn default_mirror_max_keys() -> usize {
+    10_000
+}
+
+fn default_history_secs() -> u64 {
+    3600
+}
+
+fn default_top_n() -> usize {
+    10
+}
+
+fn default_completion_limit() -> usize {
+    20
+}
+
+fn default_search_limit() -> usize {
+    50
+}
+
+fn default_block_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_backfill_secs() -> u64 {
+    300
+}
+
+fn default_backfill_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_preview_rate_hz() -> f64 {
+    2.0
+}
+
+fn default_changelog_size() -> usize {
+    1000
+}
+
+fn default_watch_compare() -> String {
+    "value".into()
+}
+
+fn default_param_prefix() -> String {
+    crate::params::DEFAULT_PREFIX.into()
+}
+
+fn default_param_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_ack_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_graph_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_service_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_probe_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_health_sample_ms() -> u64 {
+    1000
+}
+
+fn default_topology_scout_ms() -> u64 {
+    1000
+}
+
+fn default_topology_interval_ms() -> u64 {
+    5000
+}
+
+fn default_topology_watch_scout_ms() -> u64 {
+    500
+}
+
+fn default_storage_volume() -> String {
+    "memory".into()
+}
+
+fn default_render_max_bytes() -> usize {
+    512
+}
+
+fn default_query_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_query_batch_size() -> usize {
+    100
+}
+
+fn default_generator_rate_hz() -> f64 {
+    10.0
+}
+
+fn default_generator_payload_size() -> usize {
+    64
+}
+
+fn default_generator_pattern() -> String {
+    "counter".into()
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct SessionInfoParams {
+    /// Also query every node's admin space for its Zenoh version and
+    /// features, to spot mixed-version deployments.
+    #[serde(default)]
+    pub versions: bool,
+    /// Timeout for the admin space query.
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SessionInfo {
+    pub zid: String,
+    pub peers: Vec<String>,
+    pub routers: Vec<String>,
+    /// `host` when the session was attached by the embedding application.
+    pub config_source: String,
+    pub backend: &'static str,
+    /// The session belongs to the embedding application, not the extension.
+    pub attached: bool,
+    pub connected: bool,
+    pub shared_memory: SharedMemoryInfo,
+    /// Zenoh versions and features of this session and the nodes answering
+    /// on their admin space, when asked for with `versions`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versions: Option<VersionReport>,
+}
+
+/// Whether large payloads between local processes can skip the network.
+#[derive(Serialize, JsonSchema)]
+pub struct SharedMemoryInfo {
+    /// Built with the `shm` feature, on a real Zenoh session.
+    pub supported: bool,
+    /// `transport/shared_memory/enabled` in the session config.
+    pub enabled: bool,
+    /// Payloads at least this large are put into shared memory for peers
+    /// that support it; `None` when that optimization is off.
+    pub message_size_threshold: Option<u64>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetConfigParams {
+    /// Slash-separated path such as `connect/endpoints`; the whole config when omitted.
+    pub key: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct GetConfigResult {
+    pub key: Option<String>,
+    pub value: Value,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UpdateConfigParams {
+    /// Slash-separated path; Zenoh accepts runtime changes under `plugins/` only.
+    pub key: String,
+    pub value: Value,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct UpdateConfigResult {
+    pub key: String,
+    /// The value read back from the live config after the update.
+    pub value: Value,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListEndpointsResult {
+    /// The session's `connect` list.
+    pub configured: Vec<String>,
+    /// Remote locators of the links currently open.
+    pub connected: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct EndpointParams {
+    /// Locator such as `tcp/192.168.1.10:7447`.
+    pub endpoint: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct EndpointsResult {
+    pub endpoints: Vec<String>,
+    /// Whether the session was reopened; false when nothing changed.
+    pub reopened: bool,
+    /// Session zid, new after a reopen.
+    pub zid: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ListStoragesParams {
+    /// Router zid; omitted asks every router that answers.
+    pub router: Option<String>,
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListStoragesResult {
+    pub count: usize,
+    /// Storages by router, then name. Key expressions are shown without the
+    /// session namespace.
+    pub storages: Vec<StorageInfo>,
+    /// Error replies, such as from routers without the storage manager.
+    pub errors: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetTopologyParams {
+    /// How long to collect scouting replies; 0 skips scouting.
+    #[serde(default = "default_topology_scout_ms")]
+    pub scout_ms: u64,
+    /// Timeout for the router admin space query.
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct GetTopologyResult {
+    /// Nodes by zid, this session included.
+    pub nodes: Vec<TopologyNode>,
+    pub edges: Vec<TopologyEdge>,
+    /// Error replies from the router admin space query.
+    pub errors: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct WatchTopologyParams {
+    /// Time between refreshes, at least 500.
+    #[serde(default = "default_topology_interval_ms")]
+    pub interval_ms: u64,
+    /// How long a node or edge must stay in its new state before the change
+    /// is reported (default one interval, so a change shows on two
+    /// refreshes in a row); 0 reports every difference at once.
+    pub debounce_ms: Option<u64>,
+    /// Scouting time per refresh, below `interval_ms`; 0 skips scouting.
+    #[serde(default = "default_topology_watch_scout_ms")]
+    pub scout_ms: u64,
+    /// Timeout for each router admin space query.
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Unpolled changes kept; the oldest is evicted beyond this.
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: usize,
+    /// Push each change as a `topology_changed` notification.
+    #[serde(default = "default_true")]
+    pub notify: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct WatchTopologyResult {
+    pub watch_id: String,
+    pub interval_ms: u64,
+    pub debounce_ms: u64,
+    pub scout_ms: u64,
+    pub buffer_size: usize,
+    pub notify: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PollTopologyWatchParams {
+    pub watch_id: String,
+    #[serde(default = "default_alert_limit")]
+    pub limit: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PollTopologyWatchResult {
+    pub watch_id: String,
+    pub change_count: usize,
+    /// Changes oldest first.
+    pub changes: Vec<TopologyChange>,
+    pub remaining: usize,
+    /// Nodes and edges as last reported.
+    pub nodes: usize,
+    pub edges: usize,
+    pub last_refresh: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListTopologyWatchesResult {
+    pub count: usize,
+    pub watches: Vec<TopologyWatchSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct TopologyWatchSummary {
+    pub watch_id: String,
+    pub interval_ms: u64,
+    pub debounce_ms: u64,
+    pub notify: bool,
+    pub nodes: usize,
+    pub edges: usize,
+    pub refreshes: u64,
+    /// Refreshes that failed outright.
+    pub failures: u64,
+    pub last_error: Option<String>,
+    pub changes: u64,
+    pub pending: usize,
+    /// Changes evicted unpolled because the buffer was full.
+    pub dropped: u64,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UnwatchTopologyParams {
+    pub watch_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct UnwatchTopologyResult {
+    pub removed: bool,
+    pub watch_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateStorageParams {
+    /// Storage name, a single admin space key chunk.
+    pub name: String,
+    /// Key expression the storage keeps, e.g. `demo/**`.
+    pub key_expr: String,
+    /// Volume backing the storage (default memory); other volumes must be
+    /// loaded on the router.
+    #[serde(default = "default_storage_volume")]
+    pub volume: String,
+    /// Prefix the storage strips from keys before storing.
+    pub strip_prefix: Option<String>,
+    /// Router zid; required when connected to more than one.
+    pub router: Option<String>,
+    /// How long to wait for the router to report the storage running.
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CreateStorageResult {
+    pub router: String,
+    pub name: String,
+    pub key_expr: String,
+    pub volume: String,
+    /// The router reported the storage running within `timeout_ms`. False
+    /// usually means its adminspace does not permit writes.
+    pub confirmed: bool,
+    /// Admin space key the configuration was written to.
+    pub admin_key: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DeleteStorageParams {
+    pub name: String,
+    /// Router zid; required when connected to more than one.
+    pub router: Option<String>,
+    /// How long to wait for the router to stop reporting the storage.
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DeleteStorageResult {
+    pub router: String,
+    pub name: String,
+    /// The router stopped reporting the storage within `timeout_ms`.
+    pub confirmed: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetRouterHealthParams {
+    /// Router zid; omitted asks every router that answers.
+    pub router: Option<String>,
+    /// Interval between the two reads that give link throughput; 0 reports
+    /// byte counters only.
+    #[serde(default = "default_health_sample_ms")]
+    pub sample_ms: u64,
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct GetRouterHealthResult {
+    /// Routers by zid. Storage key expressions are shown without the session
+    /// namespace.
+    pub routers: Vec<RouterHealth>,
+    pub warnings: usize,
+    /// Error replies, such as from routers without the storage manager.
+    pub errors: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct StartDiscoveryParams {
+    #[serde(default = "all_keys")]
+    pub key_expr: String,
+    pub seq_path: Option<String>,
+    /// Keep up to this many leading bytes of each topic's newest payload and
+    /// report them as `last_sample_preview`; at most 4096.
+    pub preview_bytes: Option<usize>,
+    /// Only catalog keys intersecting one of these key expressions.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Leave out keys intersecting any of these key expressions, e.g.
+    /// `*/camera/**`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct StartDiscoveryResult {
+    pub started: bool,
+    pub key_expr: String,
+    pub preview_bytes: Option<usize>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct StopDiscoveryResult {
+    pub stopped: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PinTopicParams {
+    /// Topic key as listed by `get_topics`; it may be pinned before it is
+    /// first seen.
+    pub key_expr: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PinTopicResult {
+    pub key_expr: String,
+    pub pinned: bool,
+    /// Whether discovery currently lists the topic.
+    pub present: bool,
+}
+
+/// Tags a topic or a subscription; pass `key_expr` or `sub_id`. Either may
+/// be tagged before it exists.
+#[derive(Deserialize, JsonSchema)]
+pub struct TagTopicParams {
+    /// Topic key as listed by `get_topics`.
+    pub key_expr: Option<String>,
+    pub sub_id: Option<String>,
+    /// Labels to add, e.g. `critical` or `under investigation`.
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct TagTopicResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_expr: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_id: Option<String>,
+    /// Its tags after the change.
+    pub tags: Vec<String>,
+    /// Whether the topic or subscription currently exists.
+    pub present: bool,
+    /// Saved to the state file set at initialize.
+    pub persisted: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ListTagsParams {
+    /// Only this tag.
+    pub tag: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListTagsResult {
+    /// By name.
+    pub tags: Vec<TagUsage>,
+    pub state_file: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct TagUsage {
+    pub tag: String,
+    pub topics: Vec<String>,
+    pub subscriptions: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PauseDiscoveryResult {
+    pub paused: bool,
+    /// Topics kept for `resume_discovery`.
+    pub topic_count: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ResumeDiscoveryResult {
+    pub resumed: bool,
+    pub key_expr: String,
+    pub topic_count: usize,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetTopicsParams {
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub mixed_encoding_only: bool,
+    /// Only topics carrying this tag.
+    pub tag: Option<String>,
+    /// Token from a previous call cut short by the response size limit;
+    /// pass the same filters with it.
+    pub continuation: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct GetTopicsResult {
+    pub discovery_active: bool,
+    /// Discovery is paused; the topics are as of the pause.
+    pub discovery_paused: bool,
+    /// Times the watchdog restarted the discovery subscriber.
+    pub discovery_restarts: u64,
+    pub topic_count: usize,
+    /// Sorted by key.
+    pub topics: Vec<TopicSummary>,
+    /// Set when more topics matched than fit the response size limit; pass
+    /// it to the next call for the rest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation: Option<String>,
+}
+
+/// Flattened per-topic summary as returned by `get_topics`.
+#[derive(Serialize, JsonSchema)]
+pub struct TopicSummary {
+    pub key_expr: String,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub sample_count: u64,
+    pub rate_hz: f64,
+    pub avg_payload_size: u64,
+    pub last_encoding: String,
+    pub encoding_count: usize,
+    pub mixed_encoding: bool,
+    pub stale: bool,
+    /// Kept in the catalog however long it stays silent.
+    pub pinned: bool,
+    pub silent_secs: i64,
+    pub missed_estimate: Option<u64>,
+    /// Start of the newest payload, when discovery keeps previews.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_sample_preview: Option<PayloadPreview>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetStatsSummaryParams {
+    /// Only aggregate topics whose key starts with this prefix.
+    #[serde(default)]
+    pub prefix: String,
+    /// Length of the top-by-rate and top-by-bandwidth lists.
+    #[serde(default = "default_top_n")]
+    pub top_n: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct StatsSummary {
+    pub discovery_active: bool,
+    pub topic_count: usize,
+    /// Topics that published within the last 5 seconds; the totals and
+    /// rankings cover only these.
+    pub active_topic_count: usize,
+    pub total_rate_hz: f64,
+    pub total_bytes_per_sec: f64,
+    pub top_by_rate: Vec<TopicRate>,
+    pub top_by_bandwidth: Vec<TopicRate>,
+    /// Samples per encoding across every topic.
+    pub encodings: Vec<EncodingCount>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct TopicRate {
+    pub key_expr: String,
+    pub rate_hz: f64,
+    pub bytes_per_sec: f64,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetHistoryParams {
+    /// Subscription to report on; give this or `key_expr`.
+    pub sub_id: Option<String>,
+    /// Discovered topic to report on.
+    pub key_expr: Option<String>,
+    /// How far back to report, up to the one hour kept.
+    #[serde(default = "default_history_secs")]
+    pub last_secs: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct GetHistoryResult {
+    pub sub_id: Option<String>,
+    pub key_expr: String,
+    pub bucket_secs: i64,
+    /// Oldest first; the last bucket is still filling.
+    pub points: Vec<HistoryPoint>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetPublisherStatsParams {
+    /// Only count topics whose key starts with this prefix.
+    #[serde(default)]
+    pub prefix: String,
+    /// Flag publisher clocks off by more than this; 100 ms when omitted.
+    pub skew_threshold_ms: Option<f64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PublisherStatsResult {
+    pub discovery_active: bool,
+    pub publisher_count: usize,
+    /// Busiest first by bytes per second.
+    pub publishers: Vec<PublisherStats>,
+    /// Publishers whose clock offset exceeds the threshold.
+    pub skewed_count: usize,
+    /// Samples that did not identify their source.
+    pub unattributed_samples: u64,
+    pub unattributed_bytes: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PublisherStats {
+    pub zid: String,
+    /// Rates cover topics active within the last 5 seconds.
+    pub msgs_per_sec: f64,
+    pub bytes_per_sec: f64,
+    pub samples: u64,
+    pub bytes: u64,
+    pub topic_count: usize,
+    /// Estimated error of the publisher's clock, when its samples are stamped.
+    pub clock: Option<ClockSkewSummary>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetTopicParams {
+    pub key_expr: String,
+    /// Flag publisher clocks off by more than this; 100 ms when omitted.
+    pub skew_threshold_ms: Option<f64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct TopicDetail {
+    #[serde(flatten)]
+    pub summary: TopicSummary,
+    pub total_payload_bytes: u64,
+    pub last_seq: Option<u64>,
+    pub recent_timestamps: VecDeque<DateTime<Utc>>,
+    /// Gaps between the recent arrivals, measured on a monotonic clock.
+    pub recent_intervals_us: Vec<u64>,
+    pub encodings: Vec<EncodingCount>,
+    pub publishers: HashMap<String, u64>,
+    /// Clock offset estimates for this topic's publishers.
+    pub publisher_clocks: Vec<ClockSkewSummary>,
+    pub covered_by: Vec<String>,
+    pub subscribed: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CompleteKeyExprParams {
+    /// What has been typed so far, e.g. `robot/ar`. Everything up to the
+    /// last `/` must match discovered keys exactly; the rest is a prefix
+    /// of the next segment.
+    #[serde(default)]
+    pub partial: String,
+    #[serde(default = "default_completion_limit")]
+    pub limit: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CompleteKeyExprResult {
+    pub partial: String,
+    /// Most topics first, then by key.
+    pub completions: Vec<KeyCompletion>,
+    /// More segments matched than `limit`.
+    pub truncated: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct KeyCompletion {
+    /// `partial` completed through the next segment, e.g. `robot/arm`.
+    pub key_expr: String,
+    pub segment: String,
+    /// Discovered topics at or under `key_expr`.
+    pub topic_count: usize,
+    /// `key_expr` is itself a discovered topic.
+    pub is_topic: bool,
+    /// Topics continue below `key_expr`.
+    pub has_children: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct SearchTopicsParams {
+    /// Whitespace-separated terms, each matched case-insensitively as a
+    /// substring or as its characters in order (`rbtjnt` finds
+    /// `robot/joint`); a topic must match every term.
+    pub query: String,
+    /// Also match field names inferred from the topic's JSON payloads, as
+    /// seen in subscription buffers and discovery previews.
+    #[serde(default)]
+    pub fields: bool,
+    #[serde(default = "default_search_limit")]
+    pub limit: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SearchTopicsResult {
+    pub query: String,
+    /// Topics that matched, before `limit`.
+    pub match_count: usize,
+    /// Best match first.
+    pub topics: Vec<TopicMatch>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct TopicMatch {
+    #[serde(flatten)]
+    pub summary: TopicSummary,
+    /// Higher is a closer match; only comparable within one search.
+    pub score: i64,
+    /// Fields that matched a term better than the key did.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub matched_fields: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SubscribeParams {
+    pub key_expr: String,
+    /// Client-chosen id; subscribing again with the same id and parameters
+    /// returns the existing subscription instead of a duplicate.
+    pub sub_id: Option<String>,
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: usize,
+    pub seq_path: Option<String>,
+    pub alert_if_silent_ms: Option<u64>,
+    /// Evict buffered samples older than this, even when the buffer has room.
+    pub max_age_ms: Option<u64>,
+    /// Hold each sample this long and buffer in source timestamp order, so
+    /// samples that arrive slightly out of order are put back in order.
+    pub reorder_window_ms: Option<u64>,
+    /// When the buffer is full: oldest (default) evicts the oldest sample,
+    /// newest discards the incoming one, block waits for a poll to make room.
+    pub drop_policy: Option<String>,
+    /// How long `block` waits before evicting the oldest anyway.
+    #[serde(default = "default_block_timeout_ms")]
+    pub block_timeout_ms: u64,
+    /// Buffer a gap marker, with the dropped count and time range, wherever
+    /// overflow drops samples.
+    #[serde(default = "default_true")]
+    pub gap_markers: bool,
+    /// Spill overflow to a temp file of at most this many bytes instead of
+    /// dropping it; `poll` reads it back in order as the buffer drains. Once
+    /// the file is full, new samples are dropped whatever the drop policy.
+    pub spill_max_bytes: Option<u64>,
+    /// Attach to each sample the fields that changed since the previous
+    /// sample on its key, compared as decoded values or JSON text.
+    #[serde(default)]
+    pub diff: bool,
+    pub decode: Option<String>,
+    /// Content hash added to each sample: xxh3, sha256 or none (default).
+    pub checksum: Option<String>,
+    /// Zenoh channel policy: fifo (default) queues every sample, ring drops
+    /// the oldest when full.
+    pub channel: Option<String>,
+    /// Zenoh channel capacity; Zenoh's default when omitted.
+    pub channel_capacity: Option<usize>,
+    /// Publishers to receive from: any (default), local (this session) or remote.
+    pub origin: Option<String>,
+    /// Keep only samples delivered reliable or best_effort. Zenoh publishers
+    /// choose the mode; omitted keeps both.
+    pub reliability: Option<String>,
+    /// Buffer only samples this expression matches, e.g.
+    /// `value.temp > 40 && key.ends_with("/cpu")`.
+    pub filter: Option<String>,
+    /// Loaded WASM plugin whose hooks run on each sample before decoding.
+    pub plugin: Option<String>,
+    /// Lua source defining `on_sample(sample)`, run on each sample after
+    /// the plugin. Return nil to drop the sample, true to keep it, or a
+    /// table replacing its `payload`, `encoding` or `tags`; call
+    /// `publish(key_expr, payload, encoding)` to derive new samples.
+    pub script: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SubscribeResult {
+    pub sub_id: String,
+    pub key_expr: String,
+    pub buffer_size: usize,
+    pub seq_path: Option<String>,
+    pub alert_if_silent_ms: Option<u64>,
+    pub max_age_ms: Option<u64>,
+    pub reorder_window_ms: Option<u64>,
+    pub drop_policy: &'static str,
+    pub block_timeout_ms: Option<u64>,
+    pub gap_markers: bool,
+    pub spill_max_bytes: Option<u64>,
+    pub diff: bool,
+    pub decode: Option<&'static str>,
+    pub checksum: Option<&'static str>,
+    pub channel: &'static str,
+    pub channel_capacity: Option<usize>,
+    pub origin: &'static str,
+    pub reliability: Option<&'static str>,
+    pub filter: Option<String>,
+    pub plugin: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct SubscribeWithBackfillParams {
+    #[serde(flatten)]
+    pub subscribe: SubscribeParams,
+    /// Oldest history to fetch from storages; `backfill_secs` before now
+    /// when omitted.
+    pub backfill_from: Option<DateTime<Utc>>,
+    #[serde(default = "default_backfill_secs")]
+    pub backfill_secs: u64,
+    #[serde(default = "default_backfill_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SubscribeWithBackfillResult {
+    #[serde(flatten)]
+    pub subscription: SubscribeResult,
+    /// The time-range query sent to storages.
+    pub backfill_selector: String,
+    /// Historical samples buffered ahead of live data; 0 when the call
+    /// returned an existing subscription.
+    pub backfilled: usize,
+    /// Samples storages returned that were not buffered: already received
+    /// live, dropped by the filter, plugin or script, or past the buffer's room.
+    pub backfill_skipped: usize,
+    /// Errors storages replied with, why the query failed, or a warning
+    /// that the live subscriber was not declared before the query.
+    pub backfill_errors: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ExportSubscriptionsResult {
+    pub count: usize,
+    /// `subscribe` parameters of every subscription, oldest first; buffered
+    /// samples and stats are not included.
+    pub subscriptions: Vec<SubscribeParams>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ImportSubscriptionsParams {
+    /// Definitions as returned by `export_subscriptions`.
+    pub subscriptions: Vec<SubscribeParams>,
+    /// Unsubscribe everything first, so the import replaces the current setup.
+    #[serde(default)]
+    pub replace: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ImportSubscriptionsResult {
+    pub imported: usize,
+    pub failed: usize,
+    /// Subscriptions removed by `replace`.
+    pub removed: usize,
+    /// One entry per definition, in order.
+    pub results: Vec<ImportedSubscription>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ImportedSubscription {
+    pub key_expr: String,
+    pub sub_id: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UnsubscribeParams {
+    pub sub_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct UnsubscribeResult {
+    pub removed: bool,
+    pub sub_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PollParams {
+    pub sub_id: String,
+    #[serde(default = "default_poll_limit")]
+    pub limit: usize,
+    /// Only drain samples on keys intersecting this expression.
+    pub key_expr: Option<String>,
+    /// Set false to return metadata and checksums without payloads.
+    #[serde(default = "default_true")]
+    pub include_payload: bool,
+    /// Add `offset_ms` to each sample: its arrival time relative to the
+    /// first sample returned, for replaying a burst with its original timing.
+    #[serde(default)]
+    pub paced: bool,
+    /// Token from a previous poll cut short by the response size limit.
+    pub continuation: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PollResult {
+    pub sub_id: String,
+    pub sample_count: usize,
+    pub overflow_count: u64,
+    /// Samples left in the buffer and its spill file.
+    pub buffered_remaining: usize,
+    pub next_seq: u64,
+    pub missed_estimate: Option<u64>,
+    /// In buffer order; a gap marker carries a `gap` object and an empty
+    /// payload where overflow dropped samples.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples_compressed: Option<String>,
+    /// Set when more samples were ready than fit the response size limit;
+    /// pass it to the next poll.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PollSyncedParams {
+    pub sub_ids: Vec<String>,
+    /// Snapshot time; defaults to the oldest of the subscriptions' newest samples.
+    pub at: Option<DateTime<Utc>>,
+    /// Remove each returned sample and everything buffered before it.
+    #[serde(default)]
+    pub drain: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PollSyncedResult {
+    pub timestamp: Option<String>,
+    /// Every subscription had a sample at or before `timestamp`.
+    pub complete: bool,
+    pub samples: Vec<SyncedSample>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SyncedSample {
+    pub sub_id: String,
+    pub sample: Option<Value>,
+    /// How far the sample lags `timestamp`.
+    pub skew_ms: Option<i64>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateSynchronizerParams {
+    /// Two to four inputs; each matched tuple has one sample per input, in order.
+    pub key_exprs: Vec<String>,
+    /// Maximum arrival spread within a matched tuple.
+    #[serde(default = "default_slop_ms")]
+    pub slop_ms: u64,
+    /// Matched tuples kept until polled.
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CreateSynchronizerResult {
+    pub sync_id: String,
+    pub key_exprs: Vec<String>,
+    pub slop_ms: u64,
+    pub buffer_size: usize,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PollSynchronizerParams {
+    pub sync_id: String,
+    #[serde(default = "default_poll_limit")]
+    pub limit: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PollSynchronizerResult {
+    pub sync_id: String,
+    pub tuple_count: usize,
+    pub tuples: Vec<SyncedTuple>,
+    pub buffered_remaining: usize,
+    pub overflow_count: u64,
+    pub dropped: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SyncedTuple {
+    pub seq: u64,
+    pub spread_ms: i64,
+    /// One sample per input key expression, in `key_exprs` order.
+    pub samples: Vec<Value>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListSynchronizersResult {
+    pub count: usize,
+    pub synchronizers: Vec<SynchronizerSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SynchronizerSummary {
+    pub sync_id: String,
+    pub key_exprs: Vec<String>,
+    pub slop_ms: u64,
+    pub buffered: usize,
+    pub buffer_capacity: usize,
+    /// Unmatched samples waiting for partners.
+    pub pending: usize,
+    pub total_matched: u64,
+    pub dropped: u64,
+    pub overflow_count: u64,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DeleteSynchronizerParams {
+    pub sync_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DeleteSynchronizerResult {
+    pub removed: bool,
+    pub sync_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ExportSamplesParams {
+    pub sub_id: String,
+    /// JSONPath fields to export as columns. When empty, CSV exports the raw
+    /// payload and Parquet infers columns from the JSON payloads.
+    #[serde(default)]
+    pub fields: Vec<String>,
+    #[serde(default = "default_export_format")]
+    pub format: String,
+    /// File to write; CSV is returned inline when omitted, Parquet needs a path.
+    /// Relative paths name a file in the recordings directory when one is
+    /// configured.
+    pub path: Option<String>,
+    /// Remove the exported samples from the buffer.
+    #[serde(default)]
+    pub drain: bool,
+    /// Rows per Parquet row group; progress is reported after each.
+    #[serde(default = "default_row_group_size")]
+    pub row_group_size: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ExportSamplesResult {
+    pub sub_id: String,
+    pub format: &'static str,
+    pub rows: usize,
+    pub columns: Vec<String>,
+    pub path: Option<String>,
+    /// The exported document, when no path was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PollToFileParams {
+    pub sub_id: String,
+    /// NDJSON file to append to. Relative paths name a file in the
+    /// recordings directory when one is configured.
+    pub path: String,
+    /// Most samples drained, up to 10000 per call (the default).
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PollToFileResult {
+    pub sub_id: String,
+    pub path: String,
+    pub sample_count: usize,
+    /// Gap markers written in place of samples dropped on overflow.
+    pub gap_markers: usize,
+    pub bytes_written: u64,
+    /// File size after appending.
+    pub file_bytes: u64,
+    pub buffered_remaining: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListRecordingsResult {
+    pub dir: String,
+    pub count: usize,
+    pub total_bytes: u64,
+    pub max_total_bytes: Option<u64>,
+    pub max_age_secs: Option<u64>,
+    /// Whether flight recorders write encrypted, with the key set at
+    /// initialize.
+    pub encrypted: bool,
+    /// Oldest first, the order pruning removes them in.
+    pub recordings: Vec<RecordingInfo>,
+    /// Flight recorder rings no running recorder owns, left by a crash;
+    /// `repair_recording` turns them into recordings.
+    pub abandoned_rings: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DeleteRecordingParams {
+    pub name: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DeleteRecordingResult {
+    pub name: String,
+    pub freed_bytes: u64,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RepairRecordingParams {
+    /// A recording, or an abandoned ring from `list_recordings`.
+    pub name: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RepairRecordingResult {
+    pub source: String,
+    /// The repaired recording; a new file when `source` was a ring.
+    pub name: String,
+    pub records: u64,
+    /// Records the ring's index had checkpointed, for rings with an index.
+    pub indexed_records: Option<u64>,
+    /// Complete lines that were not valid records and were left out.
+    pub dropped_lines: u64,
+    /// Bytes of a final record cut off mid-write.
+    pub truncated_bytes: u64,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct OpenRecordingParams {
+    /// File name in the recordings directory.
+    pub name: String,
+    /// Start at the first record at or after this time instead of the
+    /// first record.
+    pub from: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RecordingReaderSummary {
+    pub reader_id: String,
+    pub name: String,
+    /// Number of the next record to read, counting from 0.
+    pub position: u64,
+    pub records: u64,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct SeekRecordingParams {
+    pub reader_id: String,
+    /// Move to the first record at or after this time.
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SeekRecordingResult {
+    pub reader_id: String,
+    pub position: u64,
+    /// Time of the record now next to read; absent at the end.
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ReadRecordingParams {
+    pub reader_id: String,
+    #[serde(default = "default_poll_limit")]
+    pub limit: usize,
+    /// Set false to return metadata without payloads.
+    #[serde(default = "default_true")]
+    pub include_payload: bool,
+    /// Add `offset_ms` to each sample: its recorded time relative to the
+    /// first sample read since opening or seeking, for playback at the
+    /// original pace.
+    #[serde(default)]
+    pub paced: bool,
+}
+
+/// Samples read from a recording, shaped like a subscription poll. `seq`
+/// is the record number in the file.
+#[derive(Serialize, JsonSchema)]
+pub struct ReadRecordingResult {
+    pub reader_id: String,
+    pub sample_count: usize,
+    pub position: u64,
+    /// Records after the read position, as far as the reader has seen.
+    pub remaining: u64,
+    /// Nothing is left to read for now; a recording still being written
+    /// can grow.
+    pub eof: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples_compressed: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListRecordingReadersResult {
+    pub count: usize,
+    pub readers: Vec<RecordingReaderSummary>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CloseRecordingParams {
+    pub reader_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CloseRecordingResult {
+    pub removed: bool,
+    pub reader_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetTimelineParams {
+    pub key_expr: String,
+    /// Start of the range; pass this or `last_secs`.
+    pub from: Option<DateTime<Utc>>,
+    /// End of the range; defaults to now.
+    pub to: Option<DateTime<Utc>>,
+    /// Range start as seconds before `to`.
+    pub last_secs: Option<u64>,
+    /// The newest samples are kept when the range holds more.
+    #[serde(default = "default_timeline_limit")]
+    pub limit: usize,
+    #[serde(default = "default_true")]
+    pub include_payload: bool,
+    /// Also read recording files modified within the range. Set false to
+    /// use only live buffers and flight recorders.
+    #[serde(default = "default_true")]
+    pub recordings: bool,
+}
+
+/// Samples on a key expression from subscription buffers, flight recorders
+/// and recordings, in time order. Recorded samples fill in each key before
+/// its live samples start.
+#[derive(Serialize, JsonSchema)]
+pub struct GetTimelineResult {
+    pub key_expr: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub sample_count: usize,
+    /// Older samples were left out for `limit` or the response size limit;
+    /// set `to` to the first sample's time for the ones before.
+    pub truncated: bool,
+    /// Oldest live sample on any matching key.
+    pub live_from: Option<DateTime<Utc>>,
+    pub sources: Vec<TimelineSource>,
+    /// Each sample carries `source` and `source_id`; only live samples
+    /// have a `seq`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples_compressed: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct TimelineSource {
+    pub source: SourceKind,
+    /// Subscription, flight recorder or recording file name.
+    pub source_id: String,
+    pub samples: usize,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct StartFlightRecorderParams {
+    pub key_exprs: Vec<String>,
+    /// Disk budget for the ring; the oldest samples are overwritten beyond it.
+    #[serde(default = "default_flight_max_bytes")]
+    pub max_bytes: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct StartFlightRecorderResult {
+    pub recorder_id: String,
+    pub key_exprs: Vec<String>,
+    pub max_bytes: u64,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DumpFlightRecorderParams {
+    pub recorder_id: String,
+    /// How far back from now to copy.
+    #[serde(default = "default_dump_last_secs")]
+    pub last_secs: u64,
+    /// File name in the recordings directory; defaults to a timestamped name.
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DumpFlightRecorderResult {
+    pub recorder_id: String,
+    pub name: String,
+    pub path: String,
+    pub records: u64,
+    pub bytes: u64,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListFlightRecordersResult {
+    pub count: usize,
+    pub flight_recorders: Vec<FlightRecorderSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct FlightRecorderSummary {
+    pub recorder_id: String,
+    pub key_exprs: Vec<String>,
+    pub max_bytes: u64,
+    pub disk_bytes: u64,
+    pub records_written: u64,
+    pub write_errors: u64,
+    /// Timestamp of the oldest sample still in the ring.
+    pub oldest: Option<DateTime<Utc>>,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct StopFlightRecorderParams {
+    pub recorder_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct StopFlightRecorderResult {
+    pub removed: bool,
+    pub recorder_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListSubscriptionsResult {
+    pub count: usize,
+    pub subscriptions: Vec<SubscriptionSummary>,
+}
+
+/// Per-subscription stats as returned by `list_subscriptions`.
+#[derive(Serialize, JsonSchema)]
+pub struct SubscriptionSummary {
+    pub sub_id: String,
+    pub key_expr: String,
+    pub buffered: usize,
+    pub buffer_capacity: usize,
+    pub drop_policy: &'static str,
+    pub block_timeout_ms: Option<u64>,
+    pub overflow_count: u64,
+    /// Times `block` gave up waiting and evicted the oldest sample.
+    pub block_timeouts: u64,
+    pub gap_markers: bool,
+    /// Overflow waiting on disk, for subscriptions with `spill_max_bytes`.
+    pub spill: Option<SpillStats>,
+    pub max_age_ms: Option<u64>,
+    /// Samples evicted for exceeding `max_age_ms`.
+    pub expired_count: u64,
+    pub reorder_window_ms: Option<u64>,
+    /// Samples waiting out the reorder window.
+    pub held: usize,
+    /// Samples stamped earlier than a previous sample on the same key.
+    pub out_of_order_count: u64,
+    pub total_received: u64,
+    pub missed_estimate: Option<u64>,
+    pub inter_arrival: Option<InterArrivalStats>,
+    pub alert_if_silent_ms: Option<u64>,
+    pub stalled: bool,
+    pub decode: Option<&'static str>,
+    pub decode_queue_depth: usize,
+    pub checksum: Option<&'static str>,
+    pub channel: &'static str,
+    pub channel_capacity: Option<usize>,
+    pub origin: &'static str,
+    pub reliability: Option<&'static str>,
+    pub filter: Option<String>,
+    /// Samples the filter rejected.
+    pub filtered_count: u64,
+    pub plugin: Option<PluginUsage>,
+    pub script: Option<ScriptUsage>,
+    /// Delivery mode of the samples kept so far: reliable, best_effort or mixed.
+    pub effective_reliability: Option<&'static str>,
+    pub best_effort_received: u64,
+    pub reliability_rejected: u64,
+    /// Times the watchdog restarted the receiving task.
+    pub restart_count: u64,
+    pub last_restart: Option<String>,
+    pub last_restart_reason: Option<String>,
+    pub last_heartbeat: Option<String>,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListAlertsResult {
+    pub count: usize,
+    pub alerts: Vec<AlertRule>,
+    pub pending_events: usize,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DeleteAlertParams {
+    pub alert_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DeleteAlertResult {
+    pub removed: bool,
+    pub alert_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PollAlertsParams {
+    #[serde(default = "default_alert_limit")]
+    pub limit: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PollAlertsResult {
+    pub events: Vec<AlertEvent>,
+    pub event_count: usize,
+    pub remaining: usize,
+    pub dropped_count: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct StatsResult {
+    pub uptime_secs: u64,
+    pub process: Option<ProcessUsage>,
+    pub subscription_count: usize,
+    pub topic_count: usize,
+    pub buffered_samples: usize,
+    pub buffered_bytes: usize,
+    pub tasks_alive: usize,
+    pub samples_ingested: u64,
+    pub ingest_rate_hz: f64,
+    pub stdout_bytes: u64,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RosGraphParams {
+    /// Only include rmw_zenoh entities from this ROS domain.
+    pub domain_id: Option<u32>,
+    #[serde(default = "default_graph_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RosServiceCallParams {
+    pub service: String,
+    #[serde(default)]
+    pub domain_id: u32,
+    #[serde(default = "default_service_timeout_ms")]
+    pub timeout_ms: u64,
+    pub request: Option<Vec<CdrField>>,
+    /// Pre-encoded CDR request, used instead of `request`.
+    pub request_b64: Option<String>,
+    pub response_types: Option<Vec<String>>,
+    /// Service key expression; skips the liveliness lookup.
+    pub key_expr: Option<String>,
+    /// Servers to call: any (default), local (this session) or remote.
+    pub destination: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RosServiceCallResult {
+    pub service: String,
+    pub key_expr: String,
+    pub response: Option<Vec<Value>>,
+    pub payload: Payload,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ProbeDeviceParams {
+    pub zid: Option<String>,
+    pub locator: Option<String>,
+    pub liveliness_key: Option<String>,
+    pub key_expr: Option<String>,
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ProbeReport {
+    pub zid: String,
+    pub connected: bool,
+    pub liveliness: Option<StepResult>,
+    pub admin: StepResult,
+    pub get: Option<StepResult>,
+    pub locator: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct SetNamespaceParams {
+    pub namespace: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SetNamespaceResult {
+    pub namespace: Option<String>,
+    pub previous: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PublishParams {
+    pub key_expr: String,
+    /// String payload, or any other JSON value (published as JSON).
+    pub payload: Option<Value>,
+    pub payload_b64: Option<String>,
+    pub encoding: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PublishResult {
+    pub key_expr: String,
+    pub encoding: String,
+    pub bytes: usize,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PublishWithAckParams {
+    #[serde(flatten)]
+    pub publish: PublishParams,
+    /// Key expression the receiver acknowledges on.
+    pub ack_key_expr: String,
+    #[serde(default = "default_ack_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PublishWithAckResult {
+    pub key_expr: String,
+    pub encoding: String,
+    pub bytes: usize,
+    pub ack_key_expr: String,
+    pub acked: bool,
+    /// Time from the put to the ack.
+    pub latency_ms: Option<u64>,
+    pub ack: Option<AckSample>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct AckSample {
+    pub key_expr: String,
+    pub encoding: String,
+    #[serde(flatten)]
+    pub payload: Payload,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PublishTemplateParams {
+    pub key_expr: String,
+    /// JSON value whose strings may hold `{{name}}` placeholders: `timestamp`,
+    /// `timestamp_ms`, `counter`, `uuid`, or any key of `values`. A string
+    /// that is exactly one placeholder takes the value's JSON type.
+    pub template: Value,
+    #[serde(default)]
+    pub values: serde_json::Map<String, Value>,
+    pub encoding: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PublishTemplateResult {
+    pub key_expr: String,
+    pub encoding: String,
+    pub bytes: usize,
+    /// Value of `counter` for this publish; counts up per key expression.
+    pub counter: u64,
+    /// The rendered payload as published.
+    pub payload: Value,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PublishBatchParams {
+    pub entries: Vec<PublishParams>,
+    /// Zenoh priority every entry is sent at: real_time, interactive_high,
+    /// interactive_low, data_high, data (default), data_low or background.
+    pub priority: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PublishBatchResult {
+    pub priority: &'static str,
+    pub published: usize,
+    pub failed: usize,
+    /// One result per entry, in input order.
+    pub results: Vec<BatchEntryResult>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct BatchEntryResult {
+    pub key_expr: String,
+    pub encoding: String,
+    pub bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct StartGeneratorParams {
+    pub key_expr: String,
+    #[serde(default = "default_generator_rate_hz")]
+    pub rate_hz: f64,
+    /// Each send time is moved by up to this much either way.
+    #[serde(default)]
+    pub jitter_ms: u64,
+    /// Stop after this many samples; runs until stopped when omitted.
+    pub count: Option<u64>,
+    #[serde(default = "default_generator_payload_size")]
+    pub payload_size: usize,
+    /// `zeros`, `random`, `counter` (big-endian u64 sequence) or `json`.
+    #[serde(default = "default_generator_pattern")]
+    pub pattern: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct StartGeneratorResult {
+    pub gen_id: String,
+    pub key_expr: String,
+    pub encoding: &'static str,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListGeneratorsResult {
+    pub count: usize,
+    pub generators: Vec<GeneratorSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct GeneratorSummary {
+    pub gen_id: String,
+    pub key_expr: String,
+    pub pattern: &'static str,
+    pub payload_size: usize,
+    pub rate_hz: f64,
+    pub jitter_ms: u64,
+    pub count: Option<u64>,
+    pub published: u64,
+    pub errors: u64,
+    /// The configured count was reached.
+    pub finished: bool,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct StopGeneratorParams {
+    pub gen_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct StopGeneratorResult {
+    pub removed: bool,
+    pub gen_id: String,
+    pub published: u64,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct StartEchoParams {
+    /// Key expression to subscribe to.
+    pub from_key_expr: String,
+    /// Key every received sample is republished on; must not overlap `from_key_expr`.
+    pub to_key_expr: String,
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct StartEchoResult {
+    pub echo_id: String,
+    pub from_key_expr: String,
+    pub to_key_expr: String,
+    pub delay_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListEchoesResult {
+    pub count: usize,
+    pub echoes: Vec<EchoSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct EchoSummary {
+    pub echo_id: String,
+    pub from_key_expr: String,
+    pub to_key_expr: String,
+    pub delay_ms: u64,
+    pub received: u64,
+    pub echoed: u64,
+    pub errors: u64,
+    /// Samples dropped because too many were waiting out the delay.
+    pub dropped: u64,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct StopEchoParams {
+    pub echo_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct StopEchoResult {
+    pub removed: bool,
+    pub echo_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateBridgeParams {
+    /// Key expression to forward from.
+    pub key_expr: String,
+    /// Tried in order; the first rule whose `from_prefix` covers a key wins.
+    pub rules: Vec<RemapRule>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CreateBridgeResult {
+    pub bridge_id: String,
+    pub key_expr: String,
+    pub rules: Vec<RemapRule>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListBridgesResult {
+    pub count: usize,
+    pub bridges: Vec<BridgeSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct BridgeSummary {
+    pub bridge_id: String,
+    pub key_expr: String,
+    pub rules: Vec<RemapRule>,
+    pub received: u64,
+    pub forwarded: u64,
+    /// Samples whose key matched no rule.
+    pub unmatched: u64,
+    pub errors: u64,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DeleteBridgeParams {
+    pub bridge_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DeleteBridgeResult {
+    pub removed: bool,
+    pub bridge_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AddFederationPeerParams {
+    /// Origin its topics are attributed to; `local` is this instance.
+    pub name: String,
+    /// Admin prefix the peer serves, as it passed `admin_key_expr` to
+    /// initialize.
+    pub admin_key_expr: String,
+    /// Open a client session to this locator to reach a peer on another
+    /// Zenoh network; without one the peer is queried over this session.
+    pub locator: Option<String>,
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct FederationPeerSummary {
+    pub name: String,
+    pub admin_key_expr: String,
+    pub locator: Option<String>,
+    /// Zid of the peer's session, from its last `session_info`.
+    pub zid: Option<String>,
+    pub added_at: String,
+    /// Last successful call to the peer.
+    pub last_contact: Option<String>,
+    /// Why the last call failed, if it did.
+    pub last_error: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RemoveFederationPeerParams {
+    pub name: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RemoveFederationPeerResult {
+    pub removed: bool,
+    pub name: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListFederationPeersResult {
+    pub count: usize,
+    pub peers: Vec<FederationPeerSummary>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetFederatedViewParams {
+    /// Only topics whose key starts with this prefix, on every origin.
+    #[serde(default)]
+    pub prefix: String,
+    /// Include this instance's own topics and session as origin `local`.
+    #[serde(default = "default_true")]
+    pub include_local: bool,
+    /// Per peer call; peers are asked in parallel.
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct GetFederatedViewResult {
+    pub origins: Vec<FederatedOrigin>,
+    pub topic_count: usize,
+    /// `get_topics` summaries from every reachable origin, each with an
+    /// `origin`, by key and then origin.
+    pub topics: Vec<Value>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct FederatedOrigin {
+    pub origin: String,
+    pub reachable: bool,
+    pub error: Option<String>,
+    pub topic_count: usize,
+    /// The origin's `session_info`.
+    pub session: Option<Value>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateThrottledRelayParams {
+    /// Key expression to relay from.
+    pub key_expr: String,
+    /// Each sample is republished on `<to_prefix>/<source key>`.
+    pub to_prefix: String,
+    /// Per-key cap; within an interval only the newest sample is sent.
+    pub max_rate_hz: Option<f64>,
+    /// Only republish when a key's payload differs from the last one sent.
+    #[serde(default)]
+    pub on_change: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CreateThrottledRelayResult {
+    pub relay_id: String,
+    pub key_expr: String,
+    pub to_prefix: String,
+    pub max_rate_hz: Option<f64>,
+    pub on_change: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListThrottledRelaysResult {
+    pub count: usize,
+    pub relays: Vec<ThrottledRelaySummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ThrottledRelaySummary {
+    pub relay_id: String,
+    pub key_expr: String,
+    pub to_prefix: String,
+    pub max_rate_hz: Option<f64>,
+    pub on_change: bool,
+    pub received: u64,
+    pub published: u64,
+    /// Samples skipped as unchanged or superseded within the interval.
+    pub suppressed: u64,
+    pub errors: u64,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DeleteThrottledRelayParams {
+    pub relay_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DeleteThrottledRelayResult {
+    pub removed: bool,
+    pub relay_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreatePreviewParams {
+    /// Key expression to preview.
+    pub key_expr: String,
+    /// Frames per second, independent of the source rate; at most 50.
+    #[serde(default = "default_preview_rate_hz")]
+    pub rate_hz: f64,
+    /// Push each frame as a `preview_frame` notification.
+    #[serde(default)]
+    pub notify: bool,
+    /// Distinct keys kept; samples on further keys are dropped.
+    #[serde(default = "default_mirror_max_keys")]
+    pub max_keys: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CreatePreviewResult {
+    pub preview_id: String,
+    pub key_expr: String,
+    pub rate_hz: f64,
+    pub notify: bool,
+    pub max_keys: usize,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PollPreviewParams {
+    pub preview_id: String,
+    /// Only return keys updated after this frame; omit for every key.
+    pub since_frame: Option<u64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PollPreviewResult {
+    pub preview_id: String,
+    /// Latest frame; pass it as `since_frame` on the next poll.
+    pub frame: u64,
+    pub sample_count: usize,
+    /// Newest sample per key, sorted by key.
+    pub samples: Vec<Value>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListPreviewsResult {
+    pub count: usize,
+    pub previews: Vec<PreviewSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PreviewSummary {
+    pub preview_id: String,
+    pub key_expr: String,
+    pub rate_hz: f64,
+    pub notify: bool,
+    pub frame: u64,
+    pub keys: usize,
+    pub received: u64,
+    /// Samples on new keys ignored once `max_keys` was reached.
+    pub dropped_keys: u64,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DeletePreviewParams {
+    pub preview_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DeletePreviewResult {
+    pub removed: bool,
+    pub preview_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct QueryParams {
+    /// Key expression to query, optionally followed by `?parameters`.
+    pub selector: String,
+    /// How long replies are accepted.
+    #[serde(default = "default_query_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Keep only the most recent reply per key.
+    #[serde(default)]
+    pub latest: bool,
+    /// Queryables to ask: any (default), local (this session) or remote.
+    pub destination: Option<String>,
+    /// Push replies as `query_replies` notifications instead of holding them
+    /// for `poll_query`; `query_done` follows the last batch.
+    #[serde(default)]
+    pub notify: bool,
+    /// Replies per `query_replies` notification.
+    #[serde(default = "default_query_batch_size")]
+    pub batch_size: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct QueryResult {
+    pub query_id: String,
+    pub selector: String,
+    pub timeout_ms: u64,
+    pub notify: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PollQueryParams {
+    pub query_id: String,
+    /// Most replies returned by this poll.
+    #[serde(default = "default_query_batch_size")]
+    pub max_replies: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PollQueryResult {
+    pub query_id: String,
+    pub sample_count: usize,
+    pub samples: Vec<Value>,
+    /// Replies that carried an error, sharing `seq` numbering with samples.
+    pub errors: Vec<ReplyError>,
+    /// Replies received so far, polled or not.
+    pub received: u64,
+    /// Replies waiting for the next poll.
+    pub remaining: usize,
+    /// No more replies will arrive; once `remaining` is 0 the query is
+    /// forgotten.
+    pub complete: bool,
+    /// Why the query itself failed, when it did.
+    pub failure: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListQueriesResult {
+    pub count: usize,
+    pub queries: Vec<QuerySummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct QuerySummary {
+    pub query_id: String,
+    pub selector: String,
+    pub timeout_ms: u64,
+    pub notify: bool,
+    pub received: u64,
+    pub remaining: usize,
+    pub complete: bool,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CancelQueryParams {
+    pub query_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CancelQueryResult {
+    pub removed: bool,
+    pub query_id: String,
+}
+
+/// Times take RFC 3339, `now`, or a span before now like `-5m`; durations
+/// take units ms, s, m, h, d and w, as in `1h30m`.
+#[derive(Deserialize, JsonSchema)]
+pub struct QueryHistoryParams {
+    pub key_expr: String,
+    /// Start of the range; open when omitted.
+    pub from: Option<String>,
+    /// End of the range; open when omitted.
+    pub to: Option<String>,
+    /// Length of the range. Alone it reaches back from now; with `from` or
+    /// `to` it fixes the other end.
+    pub duration: Option<String>,
+    /// The newest samples are kept when storages return more.
+    #[serde(default = "default_timeline_limit")]
+    pub limit: usize,
+    #[serde(default = "default_query_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Queryables to ask: any (default), local (this session) or remote.
+    pub destination: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct QueryHistoryResult {
+    pub key_expr: String,
+    /// The selector sent, with the range as Zenoh's `_time` parameter.
+    pub selector: String,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub sample_count: usize,
+    /// Older samples were left out for `limit` or the response size limit;
+    /// set `to` to the first sample's time for the ones before.
+    pub truncated: bool,
+    /// In source timestamp order, arrival order for unstamped samples.
+    pub samples: Vec<Value>,
+    pub errors: Vec<ReplyError>,
+}
+
+/// A subscription's plugin instance and its call counts.
+#[derive(Serialize, JsonSchema)]
+pub struct PluginUsage {
+    pub name: String,
+    pub calls: u64,
+    pub errors: u64,
+    /// Samples the plugin's filter hook dropped.
+    pub filtered: u64,
+    pub last_error: Option<String>,
+}
+
+/// A subscription's Lua script and its call counts.
+#[derive(Serialize, JsonSchema)]
+pub struct ScriptUsage {
+    pub calls: u64,
+    pub errors: u64,
+    /// Samples the script dropped.
+    pub dropped: u64,
+    /// Derived samples the script published.
+    pub published: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RenderSampleParams {
+    /// Raw payload in base64.
+    pub payload_b64: Option<String>,
+    /// Payload as text, instead of `payload_b64`.
+    pub payload: Option<String>,
+    #[serde(default)]
+    pub encoding: String,
+    /// Decoder: json, cbor, protobuf or auto (default), which picks by
+    /// encoding and falls back to JSON text.
+    pub decode: Option<String>,
+    /// Loaded plugin whose hooks run first; its decode hook replaces `decode`.
+    pub plugin: Option<String>,
+    /// auto (default: image, decoded JSON, text, then hexdump), json, text or hex.
+    pub view: Option<String>,
+    /// Most bytes shown in a hexdump.
+    #[serde(default = "default_render_max_bytes")]
+    pub max_bytes: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RenderSampleResult {
+    pub encoding: String,
+    pub bytes: usize,
+    #[serde(flatten)]
+    pub rendered: Rendered,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RegisterEncodingParams {
+    /// A full encoding (`zenoh/bytes;acme.Pose`), a schema suffix alone
+    /// (`;acme.Pose`, any base) or a base alone (`application/x-acme`, any
+    /// schema). Registering an existing one replaces it.
+    pub encoding: String,
+    /// Decoder `auto` uses: json, cbor or protobuf; none marks the payload
+    /// opaque. Published JSON values are encoded with it for json and cbor.
+    pub decode: String,
+    /// View `render_sample` uses for `auto`: json, text or hex.
+    pub view: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RegisterEncodingResult {
+    pub encoding: String,
+    pub replaced: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListEncodingsResult {
+    pub count: usize,
+    /// Registrations by encoding, then the built-in `auto` rules.
+    pub encodings: Vec<EncodingSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct EncodingSummary {
+    /// For built-in rules, the word an encoding must contain.
+    pub encoding: String,
+    pub decode: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view: Option<&'static str>,
+    pub builtin: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UnregisterEncodingParams {
+    pub encoding: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct UnregisterEncodingResult {
+    pub removed: bool,
+    pub encoding: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AddRedactionRuleParams {
+    /// Samples on keys this includes are redacted.
+    #[serde(default = "all_keys")]
+    pub key_expr: String,
+    /// JSONPath to the values to redact: `$.user.email`, `items[*].token`,
+    /// or `$..password` for the key at any depth.
+    pub path: String,
+    /// mask replaces matched values, drop removes them.
+    #[serde(default = "default_redaction_action")]
+    pub action: String,
+    /// Value masked fields get; `"***"` when not given.
+    pub mask: Option<Value>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RedactionRuleSummary {
+    pub rule_id: String,
+    pub key_expr: String,
+    pub path: String,
+    pub action: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask: Option<Value>,
+    /// Values masked or dropped so far.
+    pub applied: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListRedactionRulesResult {
+    pub count: usize,
+    /// In the order they apply.
+    pub rules: Vec<RedactionRuleSummary>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RemoveRedactionRuleParams {
+    pub rule_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RemoveRedactionRuleResult {
+    pub removed: bool,
+    pub rule_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct LoadPluginParams {
+    /// Name subscriptions refer to; loading an existing name replaces it
+    /// for new subscriptions.
+    pub name: String,
+    /// The module's bytes, base64-encoded.
+    pub wasm_b64: Option<String>,
+    /// Path of a .wasm file readable by the extension.
+    pub path: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct LoadPluginResult {
+    pub name: String,
+    pub size: usize,
+    /// Hooks the module exports: decode, transform and/or filter.
+    pub hooks: Vec<&'static str>,
+    pub replaced: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListPluginsResult {
+    pub count: usize,
+    pub plugins: Vec<PluginSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PluginSummary {
+    pub name: String,
+    pub size: usize,
+    pub hooks: Vec<&'static str>,
+    /// Subscriptions running an instance of this plugin.
+    pub subscriptions: usize,
+    pub loaded_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UnloadPluginParams {
+    pub name: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct UnloadPluginResult {
+    pub removed: bool,
+    pub name: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct WatchKeyParams {
+    /// Key expression to watch; each matching key is tracked separately.
+    pub key_expr: String,
+    /// value (default) compares decoded values deeply, hash compares the
+    /// raw payload bytes.
+    #[serde(default = "default_watch_compare")]
+    pub compare: String,
+    /// Decoder for values: json, cbor, protobuf or auto. Omitted parses JSON
+    /// text and falls back to the text itself.
+    pub decode: Option<String>,
+    /// Only samples this expression matches are considered; see `subscribe`.
+    pub filter: Option<String>,
+    /// Unpolled changes kept; the oldest is evicted beyond this.
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: usize,
+    /// Push each change as a `key_changed` notification.
+    #[serde(default = "default_true")]
+    pub notify: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct WatchKeyResult {
+    pub watch_id: String,
+    pub key_expr: String,
+    pub compare: &'static str,
+    pub decode: Option<&'static str>,
+    pub filter: Option<String>,
+    pub buffer_size: usize,
+    pub notify: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PollWatchParams {
+    pub watch_id: String,
+    #[serde(default = "default_alert_limit")]
+    pub limit: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PollWatchResult {
+    pub watch_id: String,
+    pub change_count: usize,
+    /// Changes oldest first, each with the key's previous value.
+    pub changes: Vec<ValueChange>,
+    pub remaining: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListKeyWatchesResult {
+    pub count: usize,
+    pub watches: Vec<KeyWatchSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct KeyWatchSummary {
+    pub watch_id: String,
+    pub key_expr: String,
+    pub compare: &'static str,
+    pub decode: Option<&'static str>,
+    pub filter: Option<String>,
+    pub notify: bool,
+    pub keys: usize,
+    pub received: u64,
+    pub changes: u64,
+    pub pending: usize,
+    /// Changes evicted unpolled because the buffer was full.
+    pub dropped: u64,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UnwatchKeyParams {
+    pub watch_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct UnwatchKeyResult {
+    pub removed: bool,
+    pub watch_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct MaterializeTableParams {
+    /// Key expression, usually a wildcard; each concrete key it matches is
+    /// one row.
+    pub key_expr: String,
+    /// Decoder for values: json, cbor, protobuf or auto. Omitted parses JSON
+    /// text and falls back to the text itself.
+    pub decode: Option<String>,
+    /// Seed the table by querying `key_expr` for stored values.
+    #[serde(default = "default_true")]
+    pub initial_query: bool,
+    #[serde(default = "default_query_timeout_ms")]
+    pub query_timeout_ms: u64,
+    /// Changes kept for `table_changes`; the oldest is evicted beyond this.
+    #[serde(default = "default_changelog_size")]
+    pub changelog_size: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct MaterializeTableResult {
+    pub table_id: String,
+    pub key_expr: String,
+    pub decode: Option<&'static str>,
+    pub initial_query: bool,
+    pub changelog_size: usize,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct GetTableParams {
+    pub table_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct GetTableResult {
+    pub table_id: String,
+    pub key_expr: String,
+    /// Revision the rows reflect; pass it to `table_changes` to follow on.
+    pub revision: u64,
+    pub count: usize,
+    /// Rows sorted by key.
+    pub entries: Vec<TableEntry>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct TableChangesParams {
+    pub table_id: String,
+    /// Revision already seen: a `cursor` or `get_table` revision. 0 for the
+    /// oldest change kept.
+    #[serde(default)]
+    pub since: u64,
+    #[serde(default = "default_alert_limit")]
+    pub limit: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct TableChangesResult {
+    pub table_id: String,
+    pub change_count: usize,
+    /// Changes oldest first.
+    pub changes: Vec<TableChange>,
+    /// Revision to pass as `since` next time.
+    pub cursor: u64,
+    /// Changes after `since` were evicted from the changelog; re-read the
+    /// table with `get_table`.
+    pub truncated: bool,
+    /// Changes after `cursor` not returned for `limit`.
+    pub remaining: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListTablesResult {
+    pub count: usize,
+    pub tables: Vec<TableSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct TableSummary {
+    pub table_id: String,
+    pub key_expr: String,
+    pub decode: Option<&'static str>,
+    pub keys: usize,
+    pub revision: u64,
+    pub received: u64,
+    pub changelog_size: usize,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DeleteTableParams {
+    pub table_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DeleteTableResult {
+    pub removed: bool,
+    pub table_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct WatchThresholdParams {
+    /// Key expression to watch; each matching key has its own band.
+    pub key_expr: String,
+    /// JSONPath to the numeric field, e.g. `battery.percent`.
+    pub path: String,
+    /// Band boundaries in ascending order.
+    pub thresholds: Vec<f64>,
+    /// How far past a threshold a value must go to cross it, so noise
+    /// around a boundary does not flap.
+    #[serde(default)]
+    pub hysteresis: f64,
+    /// Decoder for payloads: json, cbor, protobuf or auto. Omitted parses
+    /// JSON text.
+    pub decode: Option<String>,
+    /// Only samples this expression matches are considered; see `subscribe`.
+    pub filter: Option<String>,
+    /// Unpolled crossings kept; the oldest is evicted beyond this.
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: usize,
+    /// Push each crossing as a `threshold_crossed` notification.
+    #[serde(default = "default_true")]
+    pub notify: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct WatchThresholdResult {
+    pub watch_id: String,
+    pub key_expr: String,
+    pub path: String,
+    pub thresholds: Vec<f64>,
+    pub hysteresis: f64,
+    pub decode: Option<&'static str>,
+    pub filter: Option<String>,
+    pub buffer_size: usize,
+    pub notify: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PollThresholdParams {
+    pub watch_id: String,
+    #[serde(default = "default_alert_limit")]
+    pub limit: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PollThresholdResult {
+    pub watch_id: String,
+    pub crossing_count: usize,
+    /// Crossings oldest first.
+    pub crossings: Vec<ThresholdCrossing>,
+    pub remaining: usize,
+    /// Current band per key.
+    pub bands: HashMap<String, usize>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListThresholdWatchesResult {
+    pub count: usize,
+    pub watches: Vec<ThresholdWatchSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ThresholdWatchSummary {
+    pub watch_id: String,
+    pub key_expr: String,
+    pub path: String,
+    pub thresholds: Vec<f64>,
+    pub hysteresis: f64,
+    pub filter: Option<String>,
+    pub notify: bool,
+    pub keys: usize,
+    pub received: u64,
+    /// Samples without a numeric value at `path`.
+    pub missing: u64,
+    pub crossings: u64,
+    pub pending: usize,
+    /// Crossings evicted unpolled because the buffer was full.
+    pub dropped: u64,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UnwatchThresholdParams {
+    pub watch_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct UnwatchThresholdResult {
+    pub removed: bool,
+    pub watch_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateDerivedTopicParams {
+    /// Key expressions to derive from, 1 to 8.
+    pub inputs: Vec<String>,
+    /// Key the results are published on; must not overlap an input.
+    pub output: String,
+    /// Computes the output from each sample, e.g.
+    /// `sqrt((value.x - previous.x) * (value.x - previous.x)) / dt_ms * 1000`.
+    /// Besides the `subscribe` filter variables it may use `previous` and
+    /// `dt_ms` for the last sample on the same key, and `latest`, the newest
+    /// value on each input key. A `null` result publishes nothing; strings
+    /// are published as text, anything else as JSON.
+    pub expression: Option<String>,
+    /// Lua source defining `on_sample(sample)`, as for `subscribe`; the
+    /// sample it returns is published on `output`. Set this or `expression`.
+    pub script: Option<String>,
+    /// Decoder for input payloads: json, cbor, protobuf or auto. Omitted
+    /// parses JSON text.
+    pub decode: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CreateDerivedTopicResult {
+    pub derived_id: String,
+    pub inputs: Vec<String>,
+    pub output: String,
+    pub expression: Option<String>,
+    pub script: bool,
+    pub decode: Option<&'static str>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListDerivedTopicsResult {
+    pub count: usize,
+    pub derived_topics: Vec<DerivedTopicSummary>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DerivedTopicSummary {
+    pub derived_id: String,
+    pub inputs: Vec<String>,
+    pub output: String,
+    pub expression: Option<String>,
+    pub script: Option<ScriptUsage>,
+    pub decode: Option<&'static str>,
+    pub received: u64,
+    pub published: u64,
+    /// Samples that produced no output, or whose transform failed.
+    pub skipped: u64,
+    /// Outputs that failed to publish.
+    pub errors: u64,
+    pub last_error: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DeleteDerivedTopicParams {
+    pub derived_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DeleteDerivedTopicResult {
+    pub removed: bool,
+    pub derived_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct MirrorParams {
+    pub key_expr: String,
+    /// Distinct keys cached; samples on further keys are not cached.
+    #[serde(default = "default_mirror_max_keys")]
+    pub max_keys: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct MirrorResult {
+    pub mirror_id: String,
+    pub key_expr: String,
+    pub max_keys: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ListMirrorsResult {
+    pub count: usize,
+    pub mirrors: Vec<MirrorSummary>,
+}
+
+/// Cache size and query counters for one mirror.
+#[derive(Serialize, JsonSchema)]
+pub struct MirrorSummary {
+    pub mirror_id: String,
+    pub key_expr: String,
+    pub cached_keys: usize,
+    pub max_keys: usize,
+    pub queries: u64,
+    /// Queries answered with at least one cached value.
+    pub hits: u64,
+    pub misses: u64,
+    pub replies: u64,
+    pub updates: u64,
+    pub dropped_keys: u64,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DeleteMirrorParams {
+    pub mirror_id: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DeleteMirrorResult {
+    pub removed: bool,
+    pub mirror_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ParamSetParams {
+    /// Parameter name, a plain key such as `robot1/max_speed`.
+    pub name: String,
+    pub value: Value,
+    #[serde(default = "default_param_prefix")]
+    pub prefix: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ParamSetResult {
+    pub name: String,
+    pub key_expr: String,
+    pub value: Value,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ParamGetParams {
+    pub name: String,
+    #[serde(default = "default_param_prefix")]
+    pub prefix: String,
+    #[serde(default = "default_param_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Queryables to ask: any (default), local (this session) or remote.
+    pub destination: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ParamGetResult {
+    pub name: String,
+    pub key_expr: String,
+    /// Whether any storage answered for the key.
+    pub found: bool,
+    pub value: Option<Value>,
+    pub encoding: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ParamListParams {
+    #[serde(default = "default_param_prefix")]
+    pub prefix: String,
+    #[serde(default = "default_param_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Queryables to ask: any (default), local (this session) or remote.
+    pub destination: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ParamListResult {
+    pub prefix: String,
+    pub count: usize,
+    pub params: Vec<ParamEntry>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ParamEntry {
+    pub name: String,
+    pub value: Value,
+    pub encoding: String,
+}
+
+/// Input for operations that take no parameters.
+#[derive(Deserialize, JsonSchema)]
+pub struct NoParams {}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DescribeApiResult {
+    pub version: &'static str,
+    pub operations: Vec<OperationSpec>,
+}
+
+/// One operation with self-contained JSON Schemas for its input and `data`.
+#[derive(Serialize, JsonSchema)]
+pub struct OperationSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Refused in read-only mode.
+    pub mutating: bool,
+    pub input_schema: Schema,
+    pub output_schema: Schema,
+}
+
+fn spec<I: JsonSchema, O: JsonSchema>(
+    name: &'static str,
+    description: &'static str,
+) -> OperationSpec {
+    OperationSpec {
+        name,
+        description,
+        mutating: crate::ops::MUTATING_OPERATIONS.contains(&name),
+        input_schema: schemars::schema_for!(I),
+        output_schema: schemars::schema_for!(O),
+    }
+}
+
+/// Catalog of every operation `Core::execute` accepts.
+pub fn describe() -> DescribeApiResult {
+    use crate::alerts::AlertCondition;
+    use crate::ros::RosGraph;
+
+    let operations = vec![
+        spec::<SessionInfoParams, SessionInfo>(
+            "session_info",
+            "Zenoh connection status and session metadata",
+        ),
+        spec::<GetConfigParams, GetConfigResult>(
+            "get_config",
+            "Read the live Zenoh session configuration, or one key of it",
+        ),
+        spec::<UpdateConfigParams, UpdateConfigResult>(
+            "update_config",
+            "Set a key in the live Zenoh session configuration",
+        ),
+        spec::<NoParams, ListEndpointsResult>(
+            "list_endpoints",
+            "Configured connect endpoints and the locators currently linked",
+        ),
+        spec::<EndpointParams, EndpointsResult>(
+            "add_endpoint",
+            "Add a connect endpoint and reopen the session with it; subscriptions and discovery resubscribe, other declarations must be recreated",
+        ),
+        spec::<EndpointParams, EndpointsResult>(
+            "remove_endpoint",
+            "Remove a connect endpoint and reopen the session without it; subscriptions and discovery resubscribe, other declarations must be recreated",
+        ),
+        spec::<ListStoragesParams, ListStoragesResult>(
+            "list_storages",
+            "List storages routers run, read from their storage manager admin space",
+        ),
+        spec::<GetRouterHealthParams, GetRouterHealthResult>(
+            "get_router_health",
+            "Summarize routers' admin space: sessions with link state and throughput, loaded plugins and versions, storages, and warnings",
+        ),
+        spec::<GetTopologyParams, GetTopologyResult>(
+            "get_topology",
+            "Map the network as nodes (routers, peers, clients) and links, from this session's transports, scouting and routers' admin space",
+        ),
+        spec::<WatchTopologyParams, WatchTopologyResult>(
+            "watch_topology",
+            "Refresh the topology periodically and buffer debounced events when nodes appear or disappear and links change",
+        ),
+        spec::<PollTopologyWatchParams, PollTopologyWatchResult>(
+            "poll_topology_watch",
+            "Drain buffered topology changes and read the watched node and edge counts",
+        ),
+        spec::<NoParams, ListTopologyWatchesResult>(
+            "list_topology_watches",
+            "List topology watches with refresh and change counts",
+        ),
+        spec::<UnwatchTopologyParams, UnwatchTopologyResult>(
+            "unwatch_topology",
+            "Stop and remove a topology watch",
+        ),
+        spec::<CreateStorageParams, CreateStorageResult>(
+            "create_storage",
+            "Configure a storage on a router through its admin space, where writes are permitted",
+        ),
+        spec::<DeleteStorageParams, DeleteStorageResult>(
+            "delete_storage",
+            "Remove a storage from a router's configuration through its admin space",
+        ),
+        spec::<StartDiscoveryParams, StartDiscoveryResult>(
+            "start_discovery",
+            "Subscribe to a key expression (default **) and track topic metadata",
+        ),
+        spec::<NoParams, StopDiscoveryResult>(
+            "stop_discovery",
+            "Stop the discovery subscriber and clear the topic map",
+        ),
+        spec::<PinTopicParams, PinTopicResult>(
+            "pin_topic",
+            "Keep a topic in the catalog when it goes silent instead of expiring it",
+        ),
+        spec::<PinTopicParams, PinTopicResult>(
+            "unpin_topic",
+            "Let a pinned topic expire again once silent",
+        ),
+        spec::<TagTopicParams, TagTopicResult>(
+            "tag_topic",
+            "Add or remove labels on a topic or subscription, kept in the state file",
+        ),
+        spec::<ListTagsParams, ListTagsResult>(
+            "list_tags",
+            "Tags in use and the topics and subscriptions carrying each",
+        ),
+        spec::<NoParams, PauseDiscoveryResult>(
+            "pause_discovery",
+            "Stop consuming samples but keep the topic map, without expiring topics",
+        ),
+        spec::<NoParams, ResumeDiscoveryResult>(
+            "resume_discovery",
+            "Restart a paused discovery with its original settings",
+        ),
+        spec::<GetTopicsParams, GetTopicsResult>(
+            "get_topics",
+            "Discovered topics with rate, encoding and size metadata",
+        ),
+        spec::<GetTopicParams, TopicDetail>("get_topic", "Full detail for one discovered key"),
+        spec::<CompleteKeyExprParams, CompleteKeyExprResult>(
+            "complete_key_expr",
+            "Next key segments matching a partial key, from discovered topics, for autocomplete",
+        ),
+        spec::<SearchTopicsParams, SearchTopicsResult>(
+            "search_topics",
+            "Fuzzy search over discovered keys and, optionally, their payload field names",
+        ),
+        spec::<GetStatsSummaryParams, StatsSummary>(
+            "get_stats_summary",
+            "Network-wide totals, top topics by rate and bandwidth, and encoding counts",
+        ),
+        spec::<GetHistoryParams, GetHistoryResult>(
+            "get_history",
+            "Rate and byte history in 10 s buckets over the last hour for a subscription or topic",
+        ),
+        spec::<GetPublisherStatsParams, PublisherStatsResult>(
+            "get_publisher_stats",
+            "Messages and bytes per second per publishing zid, from discovery",
+        ),
+        spec::<SubscribeParams, SubscribeResult>(
+            "subscribe",
+            "Create a buffered subscription to a key expression",
+        ),
+        spec::<SubscribeWithBackfillParams, SubscribeWithBackfillResult>(
+            "subscribe_with_backfill",
+            "Subscribe, then fetch recent history from Zenoh storages and buffer it ahead of live samples",
+        ),
+        spec::<UnsubscribeParams, UnsubscribeResult>(
+            "unsubscribe",
+            "Remove a subscription and its buffer",
+        ),
+        spec::<PollParams, PollResult>("poll", "Drain up to N samples from a subscription buffer"),
+        spec::<PollSyncedParams, PollSyncedResult>(
+            "poll_synced",
+            "Time-aligned snapshot: newest sample per subscription at a common timestamp",
+        ),
+        spec::<CreateSynchronizerParams, CreateSynchronizerResult>(
+            "create_synchronizer",
+            "Join 2-4 key expressions into approximate-time matched tuples",
+        ),
+        spec::<PollSynchronizerParams, PollSynchronizerResult>(
+            "poll_synchronizer",
+            "Drain matched tuples from a synchronizer",
+        ),
+        spec::<NoParams, ListSynchronizersResult>(
+            "list_synchronizers",
+            "List synchronizers with match and drop counts",
+        ),
+        spec::<DeleteSynchronizerParams, DeleteSynchronizerResult>(
+            "delete_synchronizer",
+            "Remove a synchronizer and its subscriptions",
+        ),
+        spec::<ExportSamplesParams, ExportSamplesResult>(
+            "export_samples",
+            "Export a subscription's buffered samples as CSV or Parquet",
+        ),
+        spec::<PollToFileParams, PollToFileResult>(
+            "poll_to_file",
+            "Drain a subscription into an NDJSON file and return only counts",
+        ),
+        spec::<NoParams, ListRecordingsResult>(
+            "list_recordings",
+            "List files in the recordings directory with sizes and retention limits",
+        ),
+        spec::<DeleteRecordingParams, DeleteRecordingResult>(
+            "delete_recording",
+            "Delete one file from the recordings directory",
+        ),
+        spec::<RepairRecordingParams, RepairRecordingResult>(
+            "repair_recording",
+            "Recover the complete records of a recording or abandoned flight recorder ring cut off by a crash",
+        ),
+        spec::<OpenRecordingParams, RecordingReaderSummary>(
+            "open_recording",
+            "Open a recording for playback, returning a reader to seek and read",
+        ),
+        spec::<SeekRecordingParams, SeekRecordingResult>(
+            "seek_recording",
+            "Move a recording reader to the first record at or after a time",
+        ),
+        spec::<ReadRecordingParams, ReadRecordingResult>(
+            "read_recording",
+            "Read the next samples from a recording reader, shaped like poll",
+        ),
+        spec::<NoParams, ListRecordingReadersResult>(
+            "list_recording_readers",
+            "List open recording readers with their positions",
+        ),
+        spec::<CloseRecordingParams, CloseRecordingResult>(
+            "close_recording",
+            "Close a recording reader",
+        ),
+        spec::<GetTimelineParams, GetTimelineResult>(
+            "get_timeline",
+            "Merge recorded and live samples on a key expression over a time range",
+        ),
+        spec::<StartFlightRecorderParams, StartFlightRecorderResult>(
+            "start_flight_recorder",
+            "Continuously record key expressions into a size-bounded on-disk ring",
+        ),
+        spec::<DumpFlightRecorderParams, DumpFlightRecorderResult>(
+            "dump_flight_recorder",
+            "Copy the last N seconds of a flight recorder into a permanent recording",
+        ),
+        spec::<NoParams, ListFlightRecordersResult>(
+            "list_flight_recorders",
+            "List flight recorders with disk usage and record counts",
+        ),
+        spec::<StopFlightRecorderParams, StopFlightRecorderResult>(
+            "stop_flight_recorder",
+            "Stop a flight recorder and delete its ring",
+        ),
+        spec::<NoParams, ListSubscriptionsResult>(
+            "list_subscriptions",
+            "List active subscriptions with stats",
+        ),
+        spec::<NoParams, ExportSubscriptionsResult>(
+            "export_subscriptions",
+            "Export the subscribe parameters of every subscription, without samples",
+        ),
+        spec::<ImportSubscriptionsParams, ImportSubscriptionsResult>(
+            "import_subscriptions",
+            "Re-create subscriptions from export_subscriptions output",
+        ),
+        spec::<AlertCondition, AlertRule>("create_alert", "Register an alert rule"),
+        spec::<NoParams, ListAlertsResult>(
+            "list_alerts",
+            "List alert rules with firing state and counts",
+        ),
+        spec::<DeleteAlertParams, DeleteAlertResult>("delete_alert", "Remove an alert rule"),
+        spec::<PollAlertsParams, PollAlertsResult>(
+            "poll_alerts",
+            "Drain queued alert firing/resolved events",
+        ),
+        spec::<NoParams, Value>(
+            "dump_state",
+            "Export the complete extension state as one JSON document",
+        ),
+        spec::<NoParams, StatsResult>("get_stats", "Process and pipeline self-metrics"),
+        spec::<RosGraphParams, RosGraph>(
+            "ros_graph",
+            "ROS 2 graph assembled from rmw_zenoh and bridge liveliness tokens",
+        ),
+        spec::<RosServiceCallParams, RosServiceCallResult>(
+            "ros_service_call",
+            "Call a ROS 2 service over rmw_zenoh with a CDR-encoded request",
+        ),
+        spec::<ProbeDeviceParams, ProbeReport>(
+            "probe_device",
+            "Probe one zid or locator and report which capabilities it answers",
+        ),
+        spec::<SetNamespaceParams, SetNamespaceResult>(
+            "set_namespace",
+            "Set or clear the session namespace",
+        ),
+        spec::<PublishParams, PublishResult>("publish", "Put a value on a key expression"),
+        spec::<PublishWithAckParams, PublishWithAckResult>(
+            "publish_with_ack",
+            "Publish a command and wait for an acknowledgment sample",
+        ),
+        spec::<PublishTemplateParams, PublishTemplateResult>(
+            "publish_template",
+            "Render a JSON template with timestamp, counter, uuid and caller values, then publish it",
+        ),
+        spec::<PublishBatchParams, PublishBatchResult>(
+            "publish_batch",
+            "Publish a group of keys back-to-back, validated up front",
+        ),
+        spec::<StartGeneratorParams, StartGeneratorResult>(
+            "start_generator",
+            "Publish synthetic payloads at a set rate in the background",
+        ),
+        spec::<NoParams, ListGeneratorsResult>(
+            "list_generators",
+            "List traffic generators with published and error counts",
+        ),
+        spec::<StopGeneratorParams, StopGeneratorResult>(
+            "stop_generator",
+            "Stop and remove a traffic generator",
+        ),
+        spec::<StartEchoParams, StartEchoResult>(
+            "start_echo",
+            "Republish every sample from one key expression on another, optionally delayed",
+        ),
+        spec::<NoParams, ListEchoesResult>(
+            "list_echoes",
+            "List echo responders with received and echoed counts",
+        ),
+        spec::<StopEchoParams, StopEchoResult>("stop_echo", "Stop and remove an echo responder"),
+        spec::<CreateBridgeParams, CreateBridgeResult>(
+            "create_bridge",
+            "Forward samples to keys rewritten by prefix remapping rules",
+        ),
+        spec::<NoParams, ListBridgesResult>(
+            "list_bridges",
+            "List bridges with forwarded and unmatched counts",
+        ),
+        spec::<DeleteBridgeParams, DeleteBridgeResult>("delete_bridge", "Stop and remove a bridge"),
+        spec::<AddFederationPeerParams, FederationPeerSummary>(
+            "add_federation_peer",
+            "Add another instance, reached through its admin queryable, to the federated view",
+        ),
+        spec::<NoParams, ListFederationPeersResult>(
+            "list_federation_peers",
+            "List federation peers with their last contact and error",
+        ),
+        spec::<RemoveFederationPeerParams, RemoveFederationPeerResult>(
+            "remove_federation_peer",
+            "Drop a peer from the federated view and close its session",
+        ),
+        spec::<GetFederatedViewParams, GetFederatedViewResult>(
+            "get_federated_view",
+            "Topic catalogs and session info of this instance and every peer, attributed by origin",
+        ),
+        spec::<CreateThrottledRelayParams, CreateThrottledRelayResult>(
+            "create_throttled_relay",
+            "Republish a topic under a prefix at a capped rate or only on change",
+        ),
+        spec::<NoParams, ListThrottledRelaysResult>(
+            "list_throttled_relays",
+            "List throttled relays with published and suppressed counts",
+        ),
+        spec::<DeleteThrottledRelayParams, DeleteThrottledRelayResult>(
+            "delete_throttled_relay",
+            "Stop and remove a throttled relay",
+        ),
+        spec::<CreatePreviewParams, CreatePreviewResult>(
+            "create_preview",
+            "Downsample a topic to fixed-rate frames of the newest value per key",
+        ),
+        spec::<PollPreviewParams, PollPreviewResult>(
+            "poll_preview",
+            "Read the newest preview value per key, optionally only keys updated since a frame",
+        ),
+        spec::<NoParams, ListPreviewsResult>(
+            "list_previews",
+            "List previews with frame and received counts",
+        ),
+        spec::<DeletePreviewParams, DeletePreviewResult>(
+            "delete_preview",
+            "Stop and remove a preview",
+        ),
+        spec::<QueryParams, QueryResult>(
+            "query",
+            "Start a query whose replies are polled or pushed in batches as they arrive",
+        ),
+        spec::<PollQueryParams, PollQueryResult>(
+            "poll_query",
+            "Take the next batch of replies to a query",
+        ),
+        spec::<NoParams, ListQueriesResult>(
+            "list_queries",
+            "List queries with reply counts and completion",
+        ),
+        spec::<CancelQueryParams, CancelQueryResult>(
+            "cancel_query",
+            "Stop a query and discard its unpolled replies",
+        ),
+        spec::<QueryHistoryParams, QueryHistoryResult>(
+            "query_history",
+            "Fetch what storages hold on a key expression over a time range, in time order",
+        ),
+        spec::<LoadPluginParams, LoadPluginResult>(
+            "load_plugin",
+            "Compile a WASM module with decode, transform or filter hooks for subscriptions",
+        ),
+        spec::<NoParams, ListPluginsResult>(
+            "list_plugins",
+            "List loaded plugins with their hooks and the subscriptions using them",
+        ),
+        spec::<UnloadPluginParams, UnloadPluginResult>(
+            "unload_plugin",
+            "Remove a plugin; subscriptions already using it keep their instance",
+        ),
+        spec::<RenderSampleParams, RenderSampleResult>(
+            "render_sample",
+            "Render a payload for reading: image metadata, pretty JSON, text or a hexdump",
+        ),
+        spec::<RegisterEncodingParams, RegisterEncodingResult>(
+            "register_encoding",
+            "Map an encoding to a decoder and view used by auto decoding, rendering and publish",
+        ),
+        spec::<NoParams, ListEncodingsResult>(
+            "list_encodings",
+            "List registered encodings and the built-in auto decoding rules",
+        ),
+        spec::<UnregisterEncodingParams, UnregisterEncodingResult>(
+            "unregister_encoding",
+            "Remove an encoding registration",
+        ),
+        spec::<AddRedactionRuleParams, RedactionRuleSummary>(
+            "add_redaction_rule",
+            "Mask or drop JSON payload fields before samples are buffered or recorded",
+        ),
+        spec::<NoParams, ListRedactionRulesResult>(
+            "list_redaction_rules",
+            "List redaction rules and how often each applied",
+        ),
+        spec::<RemoveRedactionRuleParams, RemoveRedactionRuleResult>(
+            "remove_redaction_rule",
+            "Remove a redaction rule",
+        ),
+        spec::<WatchKeyParams, WatchKeyResult>(
+            "watch_key",
+            "Buffer an event only when a key's value changes, with its previous value",
+        ),
+        spec::<PollWatchParams, PollWatchResult>(
+            "poll_watch",
+            "Drain buffered value changes from a key watch",
+        ),
+        spec::<NoParams, ListKeyWatchesResult>(
+            "list_key_watches",
+            "List key watches with received and change counts",
+        ),
+        spec::<UnwatchKeyParams, UnwatchKeyResult>(
+            "unwatch_key",
+            "Stop and remove a key watch",
+        ),
+        spec::<MaterializeTableParams, MaterializeTableResult>(
+            "materialize_table",
+            "Keep the latest value of every key a wildcard matches, with a changelog",
+        ),
+        spec::<GetTableParams, GetTableResult>(
+            "get_table",
+            "Read every row of a materialized table and the revision it reflects",
+        ),
+        spec::<TableChangesParams, TableChangesResult>(
+            "table_changes",
+            "Read a materialized table's changes after a revision cursor",
+        ),
+        spec::<NoParams, ListTablesResult>(
+            "list_tables",
+            "List materialized tables with their key counts and revisions",
+        ),
+        spec::<DeleteTableParams, DeleteTableResult>(
+            "delete_table",
+            "Stop and remove a materialized table",
+        ),
+        spec::<WatchThresholdParams, WatchThresholdResult>(
+            "watch_threshold",
+            "Buffer an event when a numeric payload field crosses a threshold, with hysteresis",
+        ),
+        spec::<PollThresholdParams, PollThresholdResult>(
+            "poll_threshold",
+            "Drain buffered threshold crossings and read each key's current band",
+        ),
+        spec::<NoParams, ListThresholdWatchesResult>(
+            "list_threshold_watches",
+            "List threshold watches with received and crossing counts",
+        ),
+        spec::<UnwatchThresholdParams, UnwatchThresholdResult>(
+            "unwatch_threshold",
+            "Stop and remove a threshold watch",
+        ),
+        spec::<CreateDerivedTopicParams, CreateDerivedTopicResult>(
+            "create_derived_topic",
+            "Publish a value computed from input samples by an expression or script on a new key",
+        ),
+        spec::<NoParams, ListDerivedTopicsResult>(
+            "list_derived_topics",
+            "List derived topics with received and published counts",
+        ),
+        spec::<DeleteDerivedTopicParams, DeleteDerivedTopicResult>(
+            "delete_derived_topic",
+            "Stop and remove a derived topic",
+        ),
+        spec::<MirrorParams, MirrorResult>(
+            "mirror",
+            "Cache the latest value per key and answer gets from the cache",
+        ),
+        spec::<NoParams, ListMirrorsResult>(
+            "list_mirrors",
+            "List mirrors with cache size and hit/miss counts",
+        ),
+        spec::<DeleteMirrorParams, DeleteMirrorResult>(
+            "delete_mirror",
+            "Remove a mirror and undeclare its queryable",
+        ),
+        spec::<ParamSetParams, ParamSetResult>(
+            "param_set",
+            "Store a parameter value as JSON under the parameter prefix",
+        ),
+        spec::<ParamGetParams, ParamGetResult>(
+            "param_get",
+            "Read the latest stored value of one parameter",
+        ),
+        spec::<ParamListParams, ParamListResult>(
+            "param_list",
+            "List every stored parameter under a prefix",
+        ),
+        spec::<NoParams, DescribeApiResult>(
+            "describe_api",
+            "This catalog of operations with input and output schemas",
+        ),
+    ];
+
+    DescribeApiResult {
+        version: env!("CARGO_PKG_VERSION"),
+        operations,
+    }
+}
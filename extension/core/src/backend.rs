@@ -0,0 +1,806 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::any::Any;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use zenoh::handlers::{FifoChannel, RingChannel};
+
+/// A received sample, independent of the backend that produced it.
+#[derive(Clone)]
+pub struct RxSample {
+    pub key_expr: String,
+    pub payload: Vec<u8>,
+    pub encoding: String,
+    /// Best-effort zid of the publishing node.
+    pub source: Option<String>,
+    /// Id of the HLC that stamped the sample, and its time, when stamped.
+    pub timestamp: Option<(String, DateTime<Utc>)>,
+    /// Delivery mode the publisher chose for this sample.
+    pub reliability: Reliability,
+    /// The payload arrived in a shared-memory buffer rather than over the
+    /// network.
+    pub shm: bool,
+    /// A DELETE: the key's value was removed and the payload is empty.
+    pub delete: bool,
+}
+
+/// Whether a sample was routed reliably. In Zenoh 1.x the publisher picks;
+/// subscribers can only observe it per sample.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Reliability {
+    #[default]
+    Reliable,
+    BestEffort,
+}
+
+impl Reliability {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "reliable" => Ok(Reliability::Reliable),
+            "best_effort" => Ok(Reliability::BestEffort),
+            other => Err(format!("unsupported reliability: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Reliability::Reliable => "reliable",
+            Reliability::BestEffort => "best_effort",
+        }
+    }
+}
+
+/// One reply to a `get`.
+pub struct Reply {
+    pub result: Result<RxSample, String>,
+    pub replier_zid: Option<String>,
+    pub received_at: Instant,
+}
+
+/// A node that answered scouting.
+pub struct Hello {
+    pub zid: String,
+    /// `router` or `peer`; clients do not answer scouts.
+    pub whatami: String,
+    pub locators: Vec<String>,
+}
+
+/// A transport this session holds to another node, with its links.
+pub struct TransportInfo {
+    pub zid: String,
+    pub whatami: String,
+    /// Local and remote locator of each link.
+    pub links: Vec<(String, String)>,
+}
+
+/// Optional parts of a `get` request.
+#[derive(Default)]
+pub struct GetOptions {
+    pub payload: Option<Vec<u8>>,
+    pub attachment: Option<Vec<u8>>,
+    /// Keep only the most recent reply per key.
+    pub latest: bool,
+    /// Which queryables may answer.
+    pub destination: Locality,
+}
+
+/// Whether data may come from this session, other sessions or both.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Locality {
+    #[default]
+    Any,
+    /// Only publishers and queryables declared on this session.
+    Local,
+    /// Only publishers and queryables in other sessions.
+    Remote,
+}
+
+impl Locality {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "any" => Ok(Locality::Any),
+            "local" => Ok(Locality::Local),
+            "remote" => Ok(Locality::Remote),
+            other => Err(format!("unsupported locality: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locality::Any => "any",
+            Locality::Local => "local",
+            Locality::Remote => "remote",
+        }
+    }
+
+    /// Whether data from an entity on this session (`local`) or another passes.
+    pub fn allows(&self, local: bool) -> bool {
+        match self {
+            Locality::Any => true,
+            Locality::Local => local,
+            Locality::Remote => !local,
+        }
+    }
+
+    fn zenoh(self) -> zenoh::sample::Locality {
+        match self {
+            Locality::Any => zenoh::sample::Locality::Any,
+            Locality::Local => zenoh::sample::Locality::SessionLocal,
+            Locality::Remote => zenoh::sample::Locality::Remote,
+        }
+    }
+}
+
+/// Zenoh message priority; messages of one priority share a transport
+/// queue and keep their order, and higher priorities are sent first.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Priority {
+    RealTime,
+    InteractiveHigh,
+    InteractiveLow,
+    DataHigh,
+    #[default]
+    Data,
+    DataLow,
+    Background,
+}
+
+impl Priority {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "real_time" => Ok(Priority::RealTime),
+            "interactive_high" => Ok(Priority::InteractiveHigh),
+            "interactive_low" => Ok(Priority::InteractiveLow),
+            "data_high" => Ok(Priority::DataHigh),
+            "data" => Ok(Priority::Data),
+            "data_low" => Ok(Priority::DataLow),
+            "background" => Ok(Priority::Background),
+            other => Err(format!("unsupported priority: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::RealTime => "real_time",
+            Priority::InteractiveHigh => "interactive_high",
+            Priority::InteractiveLow => "interactive_low",
+            Priority::DataHigh => "data_high",
+            Priority::Data => "data",
+            Priority::DataLow => "data_low",
+            Priority::Background => "background",
+        }
+    }
+
+    fn zenoh(self) -> zenoh::qos::Priority {
+        match self {
+            Priority::RealTime => zenoh::qos::Priority::RealTime,
+            Priority::InteractiveHigh => zenoh::qos::Priority::InteractiveHigh,
+            Priority::InteractiveLow => zenoh::qos::Priority::InteractiveLow,
+            Priority::DataHigh => zenoh::qos::Priority::DataHigh,
+            Priority::Data => zenoh::qos::Priority::Data,
+            Priority::DataLow => zenoh::qos::Priority::DataLow,
+            Priority::Background => zenoh::qos::Priority::Background,
+        }
+    }
+}
+
+/// One put of a [`ZenohBackend::put_batch`].
+pub struct PutEntry {
+    pub key_expr: String,
+    pub payload: Vec<u8>,
+    pub encoding: String,
+}
+
+/// How the channel between the session and a subscriber handles overflow.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum ChannelPolicy {
+    /// Samples queue until read; a full channel blocks delivery.
+    #[default]
+    Fifo,
+    /// A full channel drops its oldest sample to make room.
+    Ring,
+}
+
+impl ChannelPolicy {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "fifo" => Ok(ChannelPolicy::Fifo),
+            "ring" => Ok(ChannelPolicy::Ring),
+            other => Err(format!("unsupported channel policy: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChannelPolicy::Fifo => "fifo",
+            ChannelPolicy::Ring => "ring",
+        }
+    }
+}
+
+/// Channel feeding one subscriber. `capacity: None` keeps Zenoh's default size.
+#[derive(Clone, Copy, Default)]
+pub struct ChannelConfig {
+    pub policy: ChannelPolicy,
+    pub capacity: Option<usize>,
+}
+
+/// Optional parts of a subscription.
+#[derive(Clone, Copy, Default)]
+pub struct SubscribeOptions {
+    pub channel: ChannelConfig,
+    /// Which publishers' samples are received.
+    pub origin: Locality,
+}
+
+/// A query as delivered to a queryable.
+pub struct Query {
+    pub key_expr: String,
+    pub payload: Option<Vec<u8>>,
+}
+
+/// Answers one query; the samples are sent as replies once the future resolves.
+pub type QueryHandler =
+    Arc<dyn Fn(Query) -> Pin<Box<dyn Future<Output = Vec<RxSample>> + Send>> + Send + Sync>;
+
+/// Keeps a declared entity alive; dropping it undeclares.
+pub type Declaration = Box<dyn Any + Send + Sync>;
+
+/// Samples for one subscription; dropping the stream undeclares it.
+#[async_trait]
+pub trait SampleStream: Send {
+    /// Next sample, or `None` once the subscription is closed.
+    async fn recv(&mut self) -> Option<RxSample>;
+    /// A sample that is already queued, without waiting for one.
+    fn try_recv(&mut self) -> Option<RxSample>;
+}
+
+/// Everything the extension needs from a Zenoh session.
+#[async_trait]
+pub trait ZenohBackend: Send + Sync {
+    /// `zenoh` for a real session, `mock` for the offline fake.
+    fn kind(&self) -> &'static str;
+    /// The session belongs to the host process rather than the extension.
+    fn attached(&self) -> bool {
+        false
+    }
+    /// Send puts without waiting to batch them with others.
+    fn set_express(&self, _express: bool) {}
+    fn zid(&self) -> String;
+    async fn peers(&self) -> Vec<String>;
+    async fn routers(&self) -> Vec<String>;
+    async fn subscribe(&self, key_expr: &str) -> Result<Box<dyn SampleStream>, String> {
+        self.subscribe_with(key_expr, SubscribeOptions::default())
+            .await
+    }
+    /// Subscribe with a specific channel and origin filter.
+    async fn subscribe_with(
+        &self,
+        key_expr: &str,
+        options: SubscribeOptions,
+    ) -> Result<Box<dyn SampleStream>, String>;
+    async fn put(&self, key_expr: &str, payload: Vec<u8>, encoding: &str) -> Result<(), String>;
+    /// Put `entries` back-to-back, in order, at one `priority`, blocking
+    /// rather than dropping on a congested link so a group is not cut short.
+    /// One result per entry.
+    async fn put_batch(
+        &self,
+        entries: Vec<PutEntry>,
+        _priority: Priority,
+    ) -> Vec<Result<(), String>> {
+        let mut results = Vec::with_capacity(entries.len());
+        for entry in entries {
+            results.push(
+                self.put(&entry.key_expr, entry.payload, &entry.encoding)
+                    .await,
+            );
+        }
+        results
+    }
+    async fn delete(&self, key_expr: &str) -> Result<(), String>;
+    /// Answer every query on `key_expr` with `handler` until the returned
+    /// declaration is dropped.
+    async fn declare_queryable(
+        &self,
+        key_expr: &str,
+        handler: QueryHandler,
+    ) -> Result<Declaration, String>;
+    /// Collect every reply to a query that arrives within `timeout`.
+    async fn get(
+        &self,
+        key_expr: &str,
+        options: GetOptions,
+        timeout: Duration,
+    ) -> Result<Vec<Reply>, String>;
+    /// Hand each reply to a query to `on_reply` as it arrives, until
+    /// `timeout`. Backends that can only collect pass the replies on at the
+    /// end.
+    async fn get_each(
+        &self,
+        key_expr: &str,
+        options: GetOptions,
+        timeout: Duration,
+        on_reply: &mut (dyn FnMut(Reply) + Send),
+    ) -> Result<(), String> {
+        for reply in self.get(key_expr, options, timeout).await? {
+            on_reply(reply);
+        }
+        Ok(())
+    }
+    /// Keys of the liveliness tokens matching `key_expr`.
+    async fn liveliness_get(
+        &self,
+        key_expr: &str,
+        timeout: Duration,
+    ) -> Result<Vec<String>, String>;
+    /// Live session configuration as JSON, with private fields removed.
+    fn config_json(&self) -> String;
+    /// Set `key` in the live configuration to a JSON5 `value`.
+    fn config_insert(&self, key: &str, value: &str) -> Result<(), String>;
+    /// Endpoints in the session's `connect` list.
+    fn connect_endpoints(&self) -> Vec<String>;
+    /// Remote locators of the links currently open.
+    async fn connected_locators(&self) -> Vec<String>;
+    /// Transports currently open, with their links.
+    async fn transports(&self) -> Vec<TransportInfo>;
+    /// Routers and peers answering multicast scouting within `timeout`.
+    async fn scout(&self, timeout: Duration) -> Result<Vec<Hello>, String>;
+    /// Replace the `connect` list. A live session is reopened with it, which
+    /// drops every declaration made on the old session. A session attached
+    /// from the host cannot be reopened.
+    async fn set_connect_endpoints(&self, endpoints: Vec<String>) -> Result<(), String>;
+    async fn close(&self);
+}
+
+/// Open a real Zenoh session from `ZENOH_CONFIG`, or the default config.
+pub async fn open_from_env() -> Result<ZenohSession, String> {
+    let config = match std::env::var("ZENOH_CONFIG") {
+        Ok(path) => zenoh::Config::from_file(&path).unwrap_or_else(|e| {
+            eprintln!("zenoh: failed to load config from {path}: {e}, using default");
+            zenoh::Config::default()
+        }),
+        Err(_) => zenoh::Config::default(),
+    };
+    ZenohSession::open(config).await
+}
+
+/// [`ZenohBackend`] over a real Zenoh session. A session opened here is
+/// replaced when the connect endpoints change; one attached from the host is
+/// never reopened or closed.
+pub struct ZenohSession {
+    session: std::sync::RwLock<zenoh::Session>,
+    /// Config the current session was opened with; held while reopening.
+    /// `None` for a session attached from the host.
+    config: tokio::sync::Mutex<Option<zenoh::Config>>,
+    attached: bool,
+    express: AtomicBool,
+}
+
+impl ZenohSession {
+    pub async fn open(config: zenoh::Config) -> Result<Self, String> {
+        let session = zenoh::open(config.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            session: std::sync::RwLock::new(session),
+            config: tokio::sync::Mutex::new(Some(config)),
+            attached: false,
+            express: AtomicBool::new(false),
+        })
+    }
+
+    /// Use a session the embedding application already opened, so it does
+    /// not pay for a second one. The host keeps ownership: the extension
+    /// declares on it but never closes or reopens it.
+    pub fn attach(session: zenoh::Session) -> Self {
+        Self {
+            session: std::sync::RwLock::new(session),
+            config: tokio::sync::Mutex::new(None),
+            attached: true,
+            express: AtomicBool::new(false),
+        }
+    }
+
+    /// The current session, for host code that wants to share it rather
+    /// than open its own. It changes if the connect endpoints are edited.
+    pub fn session(&self) -> zenoh::Session {
+        self.session.read().unwrap().clone()
+    }
+}
+
+struct ZenohStream(
+    zenoh::pubsub::Subscriber<zenoh::handlers::FifoChannelHandler<zenoh::sample::Sample>>,
+);
+
+#[async_trait]
+impl SampleStream for ZenohStream {
+    async fn recv(&mut self) -> Option<RxSample> {
+        self.0.recv_async().await.ok().map(|s| rx_sample(&s))
+    }
+
+    fn try_recv(&mut self) -> Option<RxSample> {
+        self.0.try_recv().ok().flatten().map(|s| rx_sample(&s))
+    }
+}
+
+struct ZenohRingStream(
+    zenoh::pubsub::Subscriber<zenoh::handlers::RingChannelHandler<zenoh::sample::Sample>>,
+);
+
+#[async_trait]
+impl SampleStream for ZenohRingStream {
+    async fn recv(&mut self) -> Option<RxSample> {
+        self.0.recv_async().await.ok().map(|s| rx_sample(&s))
+    }
+
+    fn try_recv(&mut self) -> Option<RxSample> {
+        self.0.try_recv().ok().flatten().map(|s| rx_sample(&s))
+    }
+}
+
+fn rx_sample(sample: &zenoh::sample::Sample) -> RxSample {
+    RxSample {
+        key_expr: sample.key_expr().as_str().to_string(),
+        payload: sample.payload().to_bytes().to_vec(),
+        encoding: sample.encoding().to_string(),
+        source: sample_source(sample),
+        timestamp: sample.timestamp().map(|ts| {
+            let time = DateTime::<Utc>::from(ts.get_time().to_system_time());
+            (ts.get_id().to_string(), time)
+        }),
+        reliability: match sample.reliability() {
+            zenoh::qos::Reliability::Reliable => Reliability::Reliable,
+            zenoh::qos::Reliability::BestEffort => Reliability::BestEffort,
+        },
+        shm: via_shm(sample),
+        delete: sample.kind() == zenoh::sample::SampleKind::Delete,
+    }
+}
+
+#[cfg(feature = "shm")]
+fn via_shm(sample: &zenoh::sample::Sample) -> bool {
+    sample.payload().as_shm().is_some()
+}
+
+/// Without the `shm` feature every payload comes over the network.
+#[cfg(not(feature = "shm"))]
+fn via_shm(_: &zenoh::sample::Sample) -> bool {
+    false
+}
+
+/// Endpoints from a `connect/endpoints` value: a plain list, or per-mode
+/// lists merged in order.
+pub fn endpoint_list(value: &serde_json::Value) -> Vec<String> {
+    let lists: Vec<&serde_json::Value> = match value {
+        serde_json::Value::Object(modes) => modes.values().collect(),
+        other => vec![other],
+    };
+    let mut endpoints: Vec<String> = Vec::new();
+    for endpoint in lists.into_iter().filter_map(|l| l.as_array()).flatten() {
+        if let Some(endpoint) = endpoint.as_str() {
+            if !endpoints.iter().any(|e| e == endpoint) {
+                endpoints.push(endpoint.to_string());
+            }
+        }
+    }
+    endpoints
+}
+
+/// Best-effort zid of the node that published `sample`: the source info when
+/// the publisher attaches it, else the id of the HLC that timestamped it.
+fn sample_source(sample: &zenoh::sample::Sample) -> Option<String> {
+    if let Some(info) = sample.source_info() {
+        return Some(info.source_id().zid().to_string());
+    }
+    sample.timestamp().map(|ts| ts.get_id().to_string())
+}
+
+#[async_trait]
+impl ZenohBackend for ZenohSession {
+    fn kind(&self) -> &'static str {
+        "zenoh"
+    }
+
+    fn attached(&self) -> bool {
+        self.attached
+    }
+
+    fn set_express(&self, express: bool) {
+        self.express.store(express, Ordering::Relaxed);
+    }
+
+    fn zid(&self) -> String {
+        self.session().zid().to_string()
+    }
+
+    async fn peers(&self) -> Vec<String> {
+        self.session()
+            .info()
+            .peers_zid()
+            .await
+            .map(|z| z.to_string())
+            .collect()
+    }
+
+    async fn routers(&self) -> Vec<String> {
+        self.session()
+            .info()
+            .routers_zid()
+            .await
+            .map(|z| z.to_string())
+            .collect()
+    }
+
+    async fn subscribe_with(
+        &self,
+        key_expr: &str,
+        options: SubscribeOptions,
+    ) -> Result<Box<dyn SampleStream>, String> {
+        let channel = options.channel;
+        let session = self.session();
+        let declare = session
+            .declare_subscriber(key_expr)
+            .allowed_origin(options.origin.zenoh());
+        Ok(match channel.policy {
+            ChannelPolicy::Fifo => {
+                let handler = channel
+                    .capacity
+                    .map_or_else(FifoChannel::default, FifoChannel::new);
+                let subscriber = declare.with(handler).await.map_err(|e| e.to_string())?;
+                Box::new(ZenohStream(subscriber))
+            }
+            ChannelPolicy::Ring => {
+                let handler = channel
+                    .capacity
+                    .map_or_else(RingChannel::default, RingChannel::new);
+                let subscriber = declare.with(handler).await.map_err(|e| e.to_string())?;
+                Box::new(ZenohRingStream(subscriber))
+            }
+        })
+    }
+
+    async fn put(&self, key_expr: &str, payload: Vec<u8>, encoding: &str) -> Result<(), String> {
+        self.session()
+            .put(key_expr, payload)
+            .encoding(encoding)
+            .express(self.express.load(Ordering::Relaxed))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn put_batch(
+        &self,
+        entries: Vec<PutEntry>,
+        priority: Priority,
+    ) -> Vec<Result<(), String>> {
+        // Zenoh publishers are bound to one key, so the group shares a
+        // priority queue instead; without express the puts batch together
+        let session = self.session();
+        let mut results = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let result = session
+                .put(&entry.key_expr, entry.payload)
+                .encoding(entry.encoding)
+                .priority(priority.zenoh())
+                .congestion_control(zenoh::qos::CongestionControl::Block)
+                .express(false)
+                .await
+                .map_err(|e| e.to_string());
+            results.push(result);
+        }
+        results
+    }
+
+    async fn delete(&self, key_expr: &str) -> Result<(), String> {
+        self.session()
+            .delete(key_expr)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn declare_queryable(
+        &self,
+        key_expr: &str,
+        handler: QueryHandler,
+    ) -> Result<Declaration, String> {
+        // Callbacks run on zenoh's threads; answers are computed on ours
+        let runtime = tokio::runtime::Handle::current();
+        let queryable = self
+            .session()
+            .declare_queryable(key_expr)
+            .callback(move |query| {
+                let answer = handler(Query {
+                    key_expr: query.key_expr().as_str().to_string(),
+                    payload: query.payload().map(|p| p.to_bytes().to_vec()),
+                });
+                runtime.spawn(async move {
+                    for sample in answer.await {
+                        let reply = query
+                            .reply(&sample.key_expr, sample.payload)
+                            .encoding(sample.encoding)
+                            .await;
+                        if let Err(e) = reply {
+                            eprintln!("queryable: reply on {} failed: {e}", sample.key_expr);
+                        }
+                    }
+                });
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(Box::new(queryable))
+    }
+
+    async fn get(
+        &self,
+        key_expr: &str,
+        options: GetOptions,
+        timeout: Duration,
+    ) -> Result<Vec<Reply>, String> {
+        let mut out = Vec::new();
+        self.get_each(key_expr, options, timeout, &mut |reply| out.push(reply))
+            .await?;
+        Ok(out)
+    }
+
+    async fn get_each(
+        &self,
+        key_expr: &str,
+        options: GetOptions,
+        timeout: Duration,
+        on_reply: &mut (dyn FnMut(Reply) + Send),
+    ) -> Result<(), String> {
+        let session = self.session();
+        let mut get = session
+            .get(key_expr)
+            .timeout(timeout)
+            .allowed_destination(options.destination.zenoh());
+        if let Some(payload) = options.payload {
+            get = get.payload(payload);
+        }
+        if let Some(attachment) = options.attachment {
+            get = get.attachment(attachment);
+        }
+        if options.latest {
+            get = get.consolidation(zenoh::query::ConsolidationMode::Latest);
+        }
+        let replies = get.await.map_err(|e| e.to_string())?;
+        while let Ok(reply) = replies.recv_async().await {
+            on_reply(Reply {
+                result: reply
+                    .result()
+                    .map(rx_sample)
+                    .map_err(|e| String::from_utf8_lossy(&e.payload().to_bytes()).into_owned()),
+                replier_zid: reply.replier_id().map(|id| id.zid().to_string()),
+                received_at: Instant::now(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn liveliness_get(
+        &self,
+        key_expr: &str,
+        timeout: Duration,
+    ) -> Result<Vec<String>, String> {
+        let replies = self
+            .session()
+            .liveliness()
+            .get(key_expr)
+            .timeout(timeout)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut keys = Vec::new();
+        while let Ok(reply) = replies.recv_async().await {
+            if let Ok(sample) = reply.result() {
+                keys.push(sample.key_expr().as_str().to_string());
+            }
+        }
+        Ok(keys)
+    }
+
+    fn config_json(&self) -> String {
+        self.session().config().to_json()
+    }
+
+    fn config_insert(&self, key: &str, value: &str) -> Result<(), String> {
+        self.session()
+            .config()
+            .insert_json5(key, value)
+            .map_err(|e| e.to_string())
+    }
+
+    fn connect_endpoints(&self) -> Vec<String> {
+        self.session()
+            .config()
+            .get("connect/endpoints")
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .map(|value| endpoint_list(&value))
+            .unwrap_or_default()
+    }
+
+    async fn connected_locators(&self) -> Vec<String> {
+        self.session()
+            .info()
+            .links()
+            .await
+            .map(|link| link.dst().to_string())
+            .collect()
+    }
+
+    async fn transports(&self) -> Vec<TransportInfo> {
+        let info = self.session().info();
+        let mut transports: Vec<TransportInfo> = info
+            .transports()
+            .await
+            .map(|t| TransportInfo {
+                zid: t.zid().to_string(),
+                whatami: t.whatami().to_string(),
+                links: Vec::new(),
+            })
+            .collect();
+        for link in info.links().await {
+            let zid = link.zid().to_string();
+            if let Some(transport) = transports.iter_mut().find(|t| t.zid == zid) {
+                transport
+                    .links
+                    .push((link.src().to_string(), link.dst().to_string()));
+            }
+        }
+        transports
+    }
+
+    /// Scouts with the session's own config, so its multicast settings
+    /// apply; an attached session's config is not ours, so the default.
+    async fn scout(&self, timeout: Duration) -> Result<Vec<Hello>, String> {
+        use zenoh::config::WhatAmI;
+        let config = self.config.lock().await.clone().unwrap_or_default();
+        let scout = zenoh::scout(WhatAmI::Router | WhatAmI::Peer, config)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut hellos: Vec<Hello> = Vec::new();
+        let deadline = tokio::time::Instant::now() + timeout;
+        while let Ok(Ok(hello)) = tokio::time::timeout_at(deadline, scout.recv_async()).await {
+            let zid = hello.zid().to_string();
+            if hellos.iter().any(|h| h.zid == zid) {
+                continue;
+            }
+            hellos.push(Hello {
+                zid,
+                whatami: hello.whatami().to_string(),
+                locators: hello.locators().iter().map(|l| l.to_string()).collect(),
+            });
+        }
+        scout.stop();
+        Ok(hellos)
+    }
+
+    /// Open a session with the new list before closing the old one, so a
+    /// failed open leaves the current connection untouched.
+    async fn set_connect_endpoints(&self, endpoints: Vec<String>) -> Result<(), String> {
+        let mut guard = self.config.lock().await;
+        let Some(config) = guard.as_mut() else {
+            return Err("the session is owned by the host and cannot be reopened".into());
+        };
+        let mut next = config.clone();
+        next.insert_json5(
+            "connect/endpoints",
+            &serde_json::json!(endpoints).to_string(),
+        )
+        .map_err(|e| format!("invalid endpoints: {e}"))?;
+        let session = zenoh::open(next.clone()).await.map_err(|e| e.to_string())?;
+        let old = std::mem::replace(&mut *self.session.write().unwrap(), session);
+        *config = next;
+        let _ = old.close().await;
+        Ok(())
+    }
+
+    async fn close(&self) {
+        if !self.attached {
+            let _ = self.session().close().await;
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::state::BufferedSample;
+use base64::Engine as _;
+use serde_json::Value;
+
+/// Raw payloads collected while building one response, for transports that
+/// carry them out of band (binary framing).
+pub struct PayloadBlocks {
+    data: Option<Vec<u8>>,
+}
+
+impl PayloadBlocks {
+    /// `collect` moves payloads into blocks; otherwise they stay inline.
+    pub fn new(collect: bool) -> Self {
+        Self {
+            data: collect.then(Vec::new),
+        }
+    }
+
+    /// Whether payloads are moved out of band.
+    pub fn collecting(&self) -> bool {
+        self.data.is_some()
+    }
+
+    /// Render a sample for a response. When collecting, the payload is
+    /// moved into the block section and referenced as `payload_block`.
+    pub fn sample_value(&mut self, sample: &BufferedSample) -> Value {
+        let mut value = serde_json::to_value(sample).unwrap();
+        if let Some(data) = &mut self.data {
+            let obj = value.as_object_mut().unwrap();
+            obj.remove("payload_b64");
+            obj.remove("payload_str");
+            obj.insert(
+                "payload_block".into(),
+                serde_json::json!({ "offset": data.len(), "len": sample.payload.0.len() }),
+            );
+            data.extend_from_slice(&sample.payload.0);
+        }
+        value
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data.unwrap_or_default()
+    }
+}
+
+/// Replace every `payload_block: {offset, len}` in a binary-framed request
+/// with the bytes it references as `payload_b64`, so operations take them
+/// like an inline payload.
+pub fn resolve_inbound(value: &mut Value, blocks: &[u8]) -> Result<(), String> {
+    match value {
+        Value::Object(obj) => {
+            if let Some(block) = obj.remove("payload_block") {
+                let field = |name: &str| {
+                    block
+                        .get(name)
+                        .and_then(Value::as_u64)
+                        .ok_or_else(|| format!("payload_block needs an integer {name}"))
+                };
+                let (offset, len) = (field("offset")?, field("len")?);
+                let data = offset
+                    .checked_add(len)
+                    .and_then(|end| blocks.get(offset as usize..end as usize))
+                    .ok_or_else(|| {
+                        format!(
+                            "payload_block {offset}+{len} is past the {} bytes of blocks sent",
+                            blocks.len()
+                        )
+                    })?;
+                let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+                obj.insert("payload_b64".into(), encoded.into());
+            }
+            obj.values_mut()
+                .try_for_each(|v| resolve_inbound(v, blocks))
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .try_for_each(|v| resolve_inbound(v, blocks)),
+        _ => Ok(()),
+    }
+}
+
+/// Render a sample without its payload, for hosts comparing checksums.
+pub fn sample_metadata(sample: &BufferedSample) -> Value {
+    let mut value = serde_json::to_value(sample).unwrap();
+    let obj = value.as_object_mut().unwrap();
+    obj.remove("payload_b64");
+    obj.remove("payload_str");
+    value
+}
@@ -0,0 +1,123 @@
+use crate::backend::ZenohBackend;
+use crate::namespace;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::watch;
+use zenoh::key_expr::KeyExpr;
+
+/// Rewrite keys under `from_prefix` to the same suffix under `to_prefix`.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RemapRule {
+    pub from_prefix: String,
+    pub to_prefix: String,
+}
+
+impl RemapRule {
+    /// Trim surrounding slashes and check both prefixes are plain keys.
+    pub fn validate(self) -> Result<Self, String> {
+        let plain = |prefix: &str| {
+            let prefix = prefix.trim_matches('/').to_string();
+            if prefix.contains('*') || prefix.contains('$') {
+                return Err(format!("remap prefix must not contain wildcards: {prefix}"));
+            }
+            KeyExpr::try_from(prefix.as_str())
+                .map_err(|e| format!("invalid remap prefix {prefix}: {e}"))?;
+            Ok(prefix)
+        };
+        Ok(Self {
+            from_prefix: plain(&self.from_prefix)?,
+            to_prefix: plain(&self.to_prefix)?,
+        })
+    }
+
+    /// `key` under the target prefix, when it lies under the source prefix.
+    /// Prefixes match whole chunks: `a/b` covers `a/b` and `a/b/c`, not `a/bc`.
+    fn apply(&self, key: &str) -> Option<String> {
+        let rest = key.strip_prefix(&self.from_prefix)?;
+        match rest {
+            "" => Some(self.to_prefix.clone()),
+            _ => rest
+                .strip_prefix('/')
+                .map(|rest| format!("{}/{rest}", self.to_prefix)),
+        }
+    }
+}
+
+/// Target key for `key` under the first matching rule.
+pub fn remap(rules: &[RemapRule], key: &str) -> Option<String> {
+    rules.iter().find_map(|rule| rule.apply(key))
+}
+
+#[derive(Default)]
+pub struct BridgeStats {
+    pub received: AtomicU64,
+    pub forwarded: AtomicU64,
+    /// Samples whose key matched no rule.
+    pub unmatched: AtomicU64,
+    pub errors: AtomicU64,
+}
+
+pub struct Bridge {
+    /// Host-facing key expression, without the session namespace.
+    pub key_expr: String,
+    pub rules: Vec<RemapRule>,
+    pub stats: Arc<BridgeStats>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Subscribe to `key_expr` and put each sample on its remapped key. Rules
+/// apply to host-facing keys; `namespace` is stripped before and applied
+/// again after remapping.
+pub async fn start_bridge(
+    session: Arc<dyn ZenohBackend>,
+    key_expr: String,
+    rules: Vec<RemapRule>,
+    namespace: Option<String>,
+) -> Result<Bridge, String> {
+    let scoped = namespace::scope(namespace.as_deref(), &key_expr);
+    let mut stream = session.subscribe(&scoped).await?;
+    let stats: Arc<BridgeStats> = Arc::default();
+    let (cancel, mut cancel_rx) = watch::channel(false);
+
+    {
+        let (rules, stats) = (rules.clone(), stats.clone());
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    sample = stream.recv() => {
+                        let Some(sample) = sample else { break };
+                        stats.received.fetch_add(1, Ordering::Relaxed);
+                        let key = namespace::strip(namespace.as_deref(), &sample.key_expr);
+                        let Some(target) = remap(&rules, &key) else {
+                            stats.unmatched.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        };
+                        let target = namespace::scope(namespace.as_deref(), &target);
+                        let counter = match session.put(&target, sample.payload, &sample.encoding).await {
+                            Ok(()) => &stats.forwarded,
+                            Err(_) => &stats.errors,
+                        };
+                        counter.fetch_add(1, Ordering::Relaxed);
+                    }
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(Bridge {
+        key_expr,
+        rules,
+        stats,
+        cancel,
+        created_at: Utc::now(),
+    })
+}
@@ -0,0 +1,287 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Little-endian plain CDR encapsulation header.
+const CDR_LE: [u8; 4] = [0x00, 0x01, 0x00, 0x00];
+
+/// Primitive field types supported by the schema-light CDR codec.
+#[derive(Clone, Copy)]
+enum FieldType {
+    Bool,
+    Int8,
+    Uint8,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    Int64,
+    Uint64,
+    Float32,
+    Float64,
+    String,
+}
+
+impl FieldType {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "bool" => Ok(FieldType::Bool),
+            "int8" => Ok(FieldType::Int8),
+            "uint8" | "byte" | "char" => Ok(FieldType::Uint8),
+            "int16" => Ok(FieldType::Int16),
+            "uint16" => Ok(FieldType::Uint16),
+            "int32" => Ok(FieldType::Int32),
+            "uint32" => Ok(FieldType::Uint32),
+            "int64" => Ok(FieldType::Int64),
+            "uint64" => Ok(FieldType::Uint64),
+            "float32" => Ok(FieldType::Float32),
+            "float64" => Ok(FieldType::Float64),
+            "string" => Ok(FieldType::String),
+            other => Err(format!("unsupported CDR field type: {other}")),
+        }
+    }
+
+    /// Alignment of the value (strings align on their u32 length).
+    fn align(&self) -> usize {
+        match self {
+            FieldType::Bool | FieldType::Int8 | FieldType::Uint8 => 1,
+            FieldType::Int16 | FieldType::Uint16 => 2,
+            FieldType::Int32 | FieldType::Uint32 | FieldType::Float32 | FieldType::String => 4,
+            FieldType::Int64 | FieldType::Uint64 | FieldType::Float64 => 8,
+        }
+    }
+}
+
+/// One request field: a primitive type name and its value.
+#[derive(Deserialize, JsonSchema)]
+pub struct CdrField {
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub value: Value,
+}
+
+/// Encode fields in order as a little-endian CDR message.
+pub fn encode(fields: &[CdrField]) -> Result<Vec<u8>, String> {
+    let mut out = CDR_LE.to_vec();
+    for (i, field) in fields.iter().enumerate() {
+        let ty = FieldType::parse(&field.type_name)?;
+        let value = &field.value;
+        let bad = || format!("request field {i}: value does not fit its type");
+        // Alignment is relative to the body, after the encapsulation header
+        out.resize(4 + (out.len() - 4).next_multiple_of(ty.align()), 0);
+        let int = || value.as_i64().ok_or_else(bad);
+        let uint = || value.as_u64().ok_or_else(bad);
+        let float = || value.as_f64().ok_or_else(bad);
+        match ty {
+            FieldType::Bool => out.push(value.as_bool().ok_or_else(bad)? as u8),
+            FieldType::Int8 => out.extend(i8::try_from(int()?).map_err(|_| bad())?.to_le_bytes()),
+            FieldType::Uint8 => out.extend(u8::try_from(uint()?).map_err(|_| bad())?.to_le_bytes()),
+            FieldType::Int16 => out.extend(i16::try_from(int()?).map_err(|_| bad())?.to_le_bytes()),
+            FieldType::Uint16 => {
+                out.extend(u16::try_from(uint()?).map_err(|_| bad())?.to_le_bytes())
+            }
+            FieldType::Int32 => out.extend(i32::try_from(int()?).map_err(|_| bad())?.to_le_bytes()),
+            FieldType::Uint32 => {
+                out.extend(u32::try_from(uint()?).map_err(|_| bad())?.to_le_bytes())
+            }
+            FieldType::Int64 => out.extend(int()?.to_le_bytes()),
+            FieldType::Uint64 => out.extend(uint()?.to_le_bytes()),
+            FieldType::Float32 => out.extend((float()? as f32).to_le_bytes()),
+            FieldType::Float64 => out.extend(float()?.to_le_bytes()),
+            FieldType::String => {
+                let s = value.as_str().ok_or_else(bad)?;
+                // Length includes the NUL terminator
+                out.extend((s.len() as u32 + 1).to_le_bytes());
+                out.extend(s.as_bytes());
+                out.push(0);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Decode a CDR message as a sequence of primitive `types`.
+pub fn decode(types: &[String], payload: &[u8]) -> Result<Vec<Value>, String> {
+    let header = payload
+        .get(..4)
+        .ok_or("cdr: missing encapsulation header")?;
+    let little_endian = header[1] & 0x01 == 1;
+    let body = &payload[4..];
+    let mut pos = 0usize;
+    let mut values = Vec::with_capacity(types.len());
+
+    for name in types {
+        let ty = FieldType::parse(name)?;
+        pos = pos.next_multiple_of(ty.align());
+        let short = || format!("cdr: payload too short for {name}");
+        let mut take = |n: usize| -> Result<&[u8], String> {
+            let bytes = body.get(pos..pos + n).ok_or_else(short)?;
+            pos += n;
+            Ok(bytes)
+        };
+        macro_rules! num {
+            ($t:ty) => {{
+                let bytes = take(std::mem::size_of::<$t>())?.try_into().unwrap();
+                if little_endian {
+                    <$t>::from_le_bytes(bytes)
+                } else {
+                    <$t>::from_be_bytes(bytes)
+                }
+            }};
+        }
+        let value = match ty {
+            FieldType::Bool => Value::from(take(1)?[0] != 0),
+            FieldType::Int8 => Value::from(num!(i8)),
+            FieldType::Uint8 => Value::from(num!(u8)),
+            FieldType::Int16 => Value::from(num!(i16)),
+            FieldType::Uint16 => Value::from(num!(u16)),
+            FieldType::Int32 => Value::from(num!(i32)),
+            FieldType::Uint32 => Value::from(num!(u32)),
+            FieldType::Int64 => Value::from(num!(i64)),
+            FieldType::Uint64 => Value::from(num!(u64)),
+            FieldType::Float32 => Value::from(num!(f32)),
+            FieldType::Float64 => Value::from(num!(f64)),
+            FieldType::String => {
+                let len = num!(u32) as usize;
+                let bytes = take(len)?;
+                let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+                Value::from(String::from_utf8_lossy(bytes).into_owned())
+            }
+        };
+        values.push(value);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fields(values: &[(&str, Value)]) -> Vec<CdrField> {
+        values
+            .iter()
+            .map(|(type_name, value)| CdrField {
+                type_name: type_name.to_string(),
+                value: value.clone(),
+            })
+            .collect()
+    }
+
+    fn types(values: &[(&str, Value)]) -> Vec<String> {
+        values.iter().map(|(t, _)| t.to_string()).collect()
+    }
+
+    /// One of each type, ordered so most need padding before them.
+    fn mixed() -> Vec<(&'static str, Value)> {
+        vec![
+            ("uint8", json!(1)),
+            ("uint32", json!(0x0403_0201)),
+            ("int8", json!(-3)),
+            ("float64", json!(1.5)),
+            ("string", json!("hi")),
+            ("int16", json!(-2)),
+            ("bool", json!(true)),
+            ("int64", json!(-5)),
+            ("uint16", json!(65535)),
+            ("float32", json!(0.25)),
+            ("int32", json!(-70000)),
+            ("uint64", json!(u64::MAX)),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let mixed = mixed();
+        let payload = encode(&fields(&mixed)).unwrap();
+        let values: Vec<Value> = mixed.iter().map(|(_, v)| v.clone()).collect();
+        assert_eq!(decode(&types(&mixed), &payload), Ok(values));
+    }
+
+    #[test]
+    fn fields_align_to_their_size_within_the_body() {
+        let payload = encode(&fields(&mixed()[..5])).unwrap();
+        let mut expected = CDR_LE.to_vec();
+        expected.extend([1, 0, 0, 0]); // uint8, padded to 4
+        expected.extend([1, 2, 3, 4]); // uint32
+        expected.extend([0xfd, 0, 0, 0, 0, 0, 0, 0]); // int8, padded to 16
+        expected.extend(1.5f64.to_le_bytes());
+        expected.extend([3, 0, 0, 0, b'h', b'i', 0]); // length counts the NUL
+        assert_eq!(payload, expected);
+    }
+
+    #[test]
+    fn big_endian_payloads() {
+        // Byte 1 of the encapsulation header clear: big-endian
+        let mut payload = vec![0x00, 0x00, 0x00, 0x00];
+        payload.extend([0x01, 0x02, 0, 0]);
+        payload.extend(0x0a0b_0c0du32.to_be_bytes());
+        payload.extend((-1.5f64).to_be_bytes());
+        payload.extend(3u32.to_be_bytes());
+        payload.extend(b"ok\0");
+        let types = ["uint16", "uint32", "float64", "string"].map(String::from);
+        assert_eq!(
+            decode(&types, &payload),
+            Ok(vec![
+                json!(0x0102),
+                json!(0x0a0b_0c0d),
+                json!(-1.5),
+                json!("ok")
+            ])
+        );
+
+        payload[1] = 0x01;
+        assert_eq!(decode(&types[..1], &payload), Ok(vec![json!(0x0201)]));
+    }
+
+    #[test]
+    fn truncated_payloads_are_rejected() {
+        let mixed = mixed();
+        let types = types(&mixed);
+        let payload = encode(&fields(&mixed)).unwrap();
+        for len in 0..4 {
+            assert_eq!(
+                decode(&types, &payload[..len]),
+                Err("cdr: missing encapsulation header".into())
+            );
+        }
+        for len in 4..payload.len() {
+            let e = decode(&types, &payload[..len]).unwrap_err();
+            assert!(e.starts_with("cdr: payload too short for "), "{len}: {e}");
+        }
+        // A string length running past the end
+        let mut payload = CDR_LE.to_vec();
+        payload.extend(100u32.to_le_bytes());
+        payload.extend(b"short\0");
+        assert_eq!(
+            decode(&["string".into()], &payload),
+            Err("cdr: payload too short for string".into())
+        );
+    }
+
+    #[test]
+    fn values_must_fit_their_type() {
+        for (ty, value) in [
+            ("uint8", json!(256)),
+            ("int8", json!(-129)),
+            ("uint16", json!(-1)),
+            ("int32", json!(1.5)),
+            ("bool", json!(1)),
+            ("string", json!(5)),
+        ] {
+            assert_eq!(
+                encode(&fields(&[("uint8", json!(0)), (ty, value)])).err(),
+                Some("request field 1: value does not fit its type".into()),
+                "{ty}"
+            );
+        }
+        assert_eq!(
+            encode(&fields(&[("int128", json!(0))])).err(),
+            Some("unsupported CDR field type: int128".into())
+        );
+        assert_eq!(
+            decode(&["quaternion".into()], &CDR_LE).err(),
+            Some("unsupported CDR field type: quaternion".into())
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use sha2::{Digest, Sha256};
+
+/// Content hash attached to buffered samples, chosen per subscription.
+#[derive(Clone, Copy)]
+pub enum Checksum {
+    Xxh3,
+    Sha256,
+}
+
+impl Checksum {
+    /// Parse a subscribe `checksum` input; `"none"` disables hashing.
+    pub fn parse(s: &str) -> Result<Option<Self>, String> {
+        match s {
+            "xxh3" => Ok(Some(Checksum::Xxh3)),
+            "sha256" => Ok(Some(Checksum::Sha256)),
+            "none" => Ok(None),
+            other => Err(format!("unsupported checksum: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Checksum::Xxh3 => "xxh3",
+            Checksum::Sha256 => "sha256",
+        }
+    }
+
+    /// Hash `payload` as `<algorithm>:<lowercase hex>`, so values from
+    /// different algorithms never compare equal.
+    pub fn digest(&self, payload: &[u8]) -> String {
+        let hex = match self {
+            Checksum::Xxh3 => format!("{:016x}", xxhash_rust::xxh3::xxh3_64(payload)),
+            Checksum::Sha256 => Sha256::digest(payload)
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect(),
+        };
+        format!("{}:{hex}", self.as_str())
+    }
+}
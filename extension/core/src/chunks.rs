@@ -0,0 +1,62 @@
+use crate::checksum::Checksum;
+use base64::Engine as _;
+use serde::Serialize;
+
+/// Smallest chunk size accepted at initialize.
+pub const MIN_CHUNK_BYTES: usize = 4096;
+
+/// Share of each chunk kept for the notification envelope and the fields
+/// around `data_b64`.
+const ENVELOPE_BYTES: usize = 512;
+
+/// Stands in for a result delivered in chunks; the response carries it as
+/// `result.chunked`.
+#[derive(Serialize)]
+pub struct ChunkedResult {
+    pub handle: String,
+    pub chunks: usize,
+    /// Size of the result's JSON once reassembled.
+    pub bytes: usize,
+}
+
+/// One `result_chunk` notification. Decoding `data_b64` of every chunk in
+/// `index` order and joining them gives the result's JSON.
+#[derive(Serialize)]
+pub struct Chunk {
+    pub handle: String,
+    /// JSON-RPC id of the request the result answers.
+    pub id: u64,
+    pub index: usize,
+    pub count: usize,
+    pub data_b64: String,
+    /// `sha256:<hex>` of the whole result's JSON, on the last chunk.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+/// Split the result `json` of request `id` into chunks whose notifications
+/// each fit in about `max_bytes`.
+pub fn split(id: u64, json: &[u8], max_bytes: usize) -> (ChunkedResult, Vec<Chunk>) {
+    let handle = uuid::Uuid::new_v4().to_string();
+    // Base64 turns every 3 bytes into 4
+    let piece = (max_bytes.saturating_sub(ENVELOPE_BYTES) / 4 * 3).max(3);
+    let count = json.len().div_ceil(piece);
+    let chunks = json
+        .chunks(piece)
+        .enumerate()
+        .map(|(index, data)| Chunk {
+            handle: handle.clone(),
+            id,
+            index,
+            count,
+            data_b64: base64::engine::general_purpose::STANDARD.encode(data),
+            checksum: (index + 1 == count).then(|| Checksum::Sha256.digest(json)),
+        })
+        .collect();
+    let result = ChunkedResult {
+        handle,
+        chunks: count,
+        bytes: json.len(),
+    };
+    (result, chunks)
+}
@@ -0,0 +1,13 @@
+//! Monotonic arrival clock. Rates, intervals and offsets are measured on it
+//! so NTP steps and slews during a long session don't distort them; wall
+//! clock times are kept alongside for display.
+
+use std::sync::LazyLock;
+use std::time::Instant;
+
+static EPOCH: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// Microseconds since the clock was first read.
+pub fn now_us() -> u64 {
+    EPOCH.elapsed().as_micros() as u64
+}
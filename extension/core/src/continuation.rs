@@ -0,0 +1,57 @@
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+/// Smallest response size limit accepted at initialize.
+pub const MIN_RESPONSE_BYTES: usize = 4096;
+
+/// Share of the limit kept for the JSON-RPC envelope and the fixed fields
+/// around a result's item list.
+const ENVELOPE_BYTES: usize = 1024;
+
+/// Where a result cut short by the response size limit resumes. Handed to
+/// hosts as an opaque token and passed back on the next call.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Continuation {
+    /// The subscription still holds the samples; the token keeps `paced`
+    /// offsets relative to the first sample of the original poll.
+    Poll {
+        sub_id: String,
+        first_arrival_us: u64,
+    },
+    /// Topics sort by key, so the next page starts after the last key sent.
+    GetTopics { after: String },
+}
+
+impl Continuation {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap();
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(token: &str) -> Result<Self, String> {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(token)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| "invalid continuation token".to_string())
+    }
+}
+
+/// How many items of the given serialized sizes fit a response limited to
+/// `max_bytes`. The first item always counts, so a caller paging through a
+/// list makes progress even past an oversized item.
+pub fn fit(sizes: impl IntoIterator<Item = usize>, max_bytes: usize) -> usize {
+    let budget = max_bytes.saturating_sub(ENVELOPE_BYTES);
+    let mut used = 0;
+    let mut count = 0;
+    for size in sizes {
+        // One more byte for the separating comma
+        used += size + 1;
+        if count > 0 && used > budget {
+            break;
+        }
+        count += 1;
+    }
+    count
+}
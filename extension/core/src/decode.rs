@@ -0,0 +1,248 @@
+use crate::encodings;
+use base64::Engine as _;
+use serde_json::{Map, Value};
+use std::sync::{Arc, LazyLock};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+/// Decode jobs allowed to run at once across all subscriptions.
+static POOL: LazyLock<Arc<Semaphore>> = LazyLock::new(|| {
+    let workers = std::thread::available_parallelism().map_or(4, |n| n.get());
+    Arc::new(Semaphore::new(workers))
+});
+
+/// Structured decoding applied to payloads before buffering.
+#[derive(Clone, Copy, PartialEq)]
+pub enum DecodeFormat {
+    Json,
+    Cbor,
+    /// Schemaless protobuf wire decoding: fields keyed by number.
+    Protobuf,
+    /// Pick a decoder from the sample's encoding.
+    Auto,
+}
+
+impl DecodeFormat {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "json" => Ok(DecodeFormat::Json),
+            "cbor" => Ok(DecodeFormat::Cbor),
+            "protobuf" => Ok(DecodeFormat::Protobuf),
+            "auto" => Ok(DecodeFormat::Auto),
+            other => Err(format!("unknown decode format: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DecodeFormat::Json => "json",
+            DecodeFormat::Cbor => "cbor",
+            DecodeFormat::Protobuf => "protobuf",
+            DecodeFormat::Auto => "auto",
+        }
+    }
+}
+
+/// Decode `payload` on the shared blocking pool, bounded by [`POOL`].
+pub fn spawn(
+    format: DecodeFormat,
+    encoding: String,
+    payload: Vec<u8>,
+) -> JoinHandle<Result<Value, String>> {
+    tokio::spawn(async move {
+        let permit = POOL.clone().acquire_owned().await.unwrap();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            decode(format, &encoding, &payload)
+        })
+        .await
+        .map_err(|e| format!("decode task failed: {e}"))?
+    })
+}
+
+pub fn decode(format: DecodeFormat, encoding: &str, payload: &[u8]) -> Result<Value, String> {
+    match format {
+        DecodeFormat::Json => serde_json::from_slice(payload).map_err(|e| format!("json: {e}")),
+        DecodeFormat::Cbor => ciborium::from_reader(payload).map_err(|e| format!("cbor: {e}")),
+        DecodeFormat::Protobuf => {
+            decode_protobuf(payload).ok_or_else(|| "protobuf: malformed wire data".into())
+        }
+        DecodeFormat::Auto => {
+            if let Some(rule) = encodings::lookup(encoding) {
+                return match rule.decode {
+                    Some(format) => decode(format, encoding, payload),
+                    None => Err(format!("encoding {encoding} is registered as opaque")),
+                };
+            }
+            if encoding.contains("json") {
+                decode(DecodeFormat::Json, encoding, payload)
+            } else if encoding.contains("cbor") {
+                decode(DecodeFormat::Cbor, encoding, payload)
+            } else if encoding.contains("protobuf") {
+                decode(DecodeFormat::Protobuf, encoding, payload)
+            } else {
+                Err(format!("no decoder for encoding {encoding}"))
+            }
+        }
+    }
+}
+
+/// Decode protobuf wire format without a schema. Fields are keyed by number;
+/// repeated fields become arrays and length-delimited fields are decoded as a
+/// nested message, then UTF-8, then base64.
+fn decode_protobuf(mut buf: &[u8]) -> Option<Value> {
+    let mut fields = Map::new();
+    while !buf.is_empty() {
+        let key = read_varint(&mut buf)?;
+        let (field, wire_type) = (key >> 3, key & 0x7);
+        if field == 0 {
+            return None;
+        }
+        let value = match wire_type {
+            0 => Value::from(read_varint(&mut buf)?),
+            1 => Value::from(u64::from_le_bytes(take(&mut buf, 8)?.try_into().ok()?)),
+            2 => {
+                let len = read_varint(&mut buf)? as usize;
+                let bytes = take(&mut buf, len)?;
+                match decode_protobuf(bytes).filter(|_| !bytes.is_empty()) {
+                    Some(nested) => nested,
+                    None => match std::str::from_utf8(bytes) {
+                        Ok(s) => Value::from(s),
+                        Err(_) => {
+                            Value::from(base64::engine::general_purpose::STANDARD.encode(bytes))
+                        }
+                    },
+                }
+            }
+            5 => Value::from(u32::from_le_bytes(take(&mut buf, 4)?.try_into().ok()?)),
+            _ => return None,
+        };
+        match fields.get_mut(&field.to_string()) {
+            Some(Value::Array(items)) => items.push(value),
+            Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+            None => {
+                fields.insert(field.to_string(), value);
+            }
+        }
+    }
+    Some(Value::Object(fields))
+}
+
+fn read_varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut result = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf.split_first()?;
+        *buf = rest;
+        result |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(result);
+        }
+    }
+    None
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if buf.len() < n {
+        return None;
+    }
+    let (head, rest) = buf.split_at(n);
+    *buf = rest;
+    Some(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn protobuf(bytes: &[u8]) -> Result<Value, String> {
+        decode(DecodeFormat::Protobuf, "application/protobuf", bytes)
+    }
+
+    /// A field key: number and wire type.
+    fn key(field: u64, wire_type: u64) -> u8 {
+        (field << 3 | wire_type) as u8
+    }
+
+    #[test]
+    fn scalar_wire_types() {
+        let mut msg = vec![key(1, 0), 0x96, 0x01];
+        msg.push(key(2, 1));
+        msg.extend(u64::MAX.to_le_bytes());
+        msg.push(key(3, 5));
+        msg.extend(7u32.to_le_bytes());
+        assert_eq!(protobuf(&msg), Ok(json!({"1": 150, "2": u64::MAX, "3": 7})));
+    }
+
+    #[test]
+    fn length_delimited_fields() {
+        let mut msg = vec![key(1, 2), 5];
+        msg.extend(b"hello");
+        // Nested message {1: 1}
+        msg.extend([key(2, 2), 2, key(1, 0), 1]);
+        // Neither a message nor UTF-8
+        msg.extend([key(3, 2), 2, 0xff, 0xfe]);
+        msg.extend([key(4, 2), 0]);
+        assert_eq!(
+            protobuf(&msg),
+            Ok(json!({"1": "hello", "2": {"1": 1}, "3": "//4=", "4": ""}))
+        );
+    }
+
+    #[test]
+    fn repeated_fields_become_arrays() {
+        let msg = [key(1, 0), 1, key(2, 0), 9, key(1, 0), 2, key(1, 0), 3];
+        assert_eq!(protobuf(&msg), Ok(json!({"1": [1, 2, 3], "2": 9})));
+    }
+
+    #[test]
+    fn empty_message() {
+        assert_eq!(protobuf(&[]), Ok(json!({})));
+    }
+
+    #[test]
+    fn truncated_input_is_malformed() {
+        let mut fixed64 = vec![key(1, 1)];
+        fixed64.extend([0; 7]);
+        let cases: [&[u8]; 6] = [
+            &[key(1, 0)],
+            &[key(1, 0), 0x96],
+            &[key(1, 2), 5, b'h', b'i'],
+            &fixed64,
+            &[key(1, 5), 0, 0, 0],
+            &[0x80],
+        ];
+        for msg in cases {
+            assert_eq!(
+                protobuf(msg),
+                Err("protobuf: malformed wire data".into()),
+                "{msg:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_keys_are_malformed() {
+        // Field 0, group wire types, and a varint longer than 64 bits
+        let mut long = vec![key(1, 0)];
+        long.extend([0xff; 10]);
+        long.push(0x01);
+        for msg in [&[key(0, 0), 1][..], &[key(1, 3)], &[key(1, 4)], &long] {
+            assert!(protobuf(msg).is_err(), "{msg:?}");
+        }
+    }
+
+    #[test]
+    fn auto_picks_a_decoder_from_the_encoding() {
+        let auto = |encoding, payload: &[u8]| decode(DecodeFormat::Auto, encoding, payload);
+        assert_eq!(
+            auto("application/protobuf", &[key(1, 0), 1]),
+            Ok(json!({"1": 1}))
+        );
+        assert_eq!(auto("application/json", br#"{"a":1}"#), Ok(json!({"a": 1})));
+        assert_eq!(
+            auto("text/plain", b"x"),
+            Err("no decoder for encoding text/plain".into())
+        );
+    }
+}
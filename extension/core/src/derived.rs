@@ -0,0 +1,191 @@
+use crate::backend::{RxSample, ZenohBackend};
+use crate::decode::DecodeFormat;
+use crate::expr::{self, Expression};
+use crate::key_watch::payload_value;
+use crate::namespace;
+use crate::script::{self, Script};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// Variables a derived topic's expression may use: the sample variables,
+/// `previous` and `dt_ms` for the last sample on the same key, and
+/// `latest`, the newest value on every input key by key.
+pub const VARIABLES: &[&str] = &[
+    "key",
+    "value",
+    "encoding",
+    "bytes",
+    "timestamp_ms",
+    "previous",
+    "dt_ms",
+    "latest",
+];
+
+/// How an input sample becomes the output payload.
+pub enum Transform {
+    /// The expression's result; `null` publishes nothing.
+    Expression(Expression),
+    /// The sample as `on_sample` returns it; dropping it publishes nothing.
+    Script(Arc<Script>),
+}
+
+#[derive(Default)]
+pub struct DerivedStats {
+    pub received: AtomicU64,
+    pub published: AtomicU64,
+    /// Samples that produced no output, or whose transform failed.
+    pub skipped: AtomicU64,
+    pub errors: AtomicU64,
+    pub last_error: Mutex<Option<String>>,
+}
+
+impl DerivedStats {
+    fn fail(&self, counter: &AtomicU64, error: String) {
+        counter.fetch_add(1, Ordering::Relaxed);
+        *self.last_error.lock().unwrap() = Some(error);
+    }
+}
+
+pub struct DerivedTopic {
+    /// Host-facing key expressions, without the session namespace.
+    pub inputs: Vec<String>,
+    pub output: String,
+    pub transform: Arc<Transform>,
+    pub decode: Option<DecodeFormat>,
+    pub stats: Arc<DerivedStats>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Newest value and its time on each input key.
+type Latest = HashMap<String, (Value, i64)>;
+
+/// Evaluate `expression` for one sample and record the sample as the
+/// newest on its key.
+fn evaluate(
+    expression: &Expression,
+    latest: &Mutex<Latest>,
+    key: &str,
+    sample: &RxSample,
+    value: Value,
+    timestamp_ms: i64,
+) -> Result<Value, String> {
+    let mut vars = expr::sample_vars(
+        key,
+        &sample.encoding,
+        sample.payload.len(),
+        timestamp_ms,
+        value.clone(),
+    );
+    let mut latest = latest.lock().unwrap();
+    let previous = latest.insert(key.to_string(), (value, timestamp_ms));
+    vars["previous"] = previous.as_ref().map_or(Value::Null, |(v, _)| v.clone());
+    vars["dt_ms"] = previous.map_or(Value::Null, |(_, at)| Value::from(timestamp_ms - at));
+    vars["latest"] = Value::Object(
+        latest
+            .iter()
+            .map(|(key, (value, _))| (key.clone(), value.clone()))
+            .collect(),
+    );
+    drop(latest);
+    expression.eval(&vars)
+}
+
+/// Subscribe to every input and publish what `transform` makes of each
+/// sample on `output`. Sample times are the publisher's HLC stamps when
+/// present, so `dt_ms` reflects when samples were taken.
+pub async fn start_derived_topic(
+    session: Arc<dyn ZenohBackend>,
+    inputs: Vec<String>,
+    output: String,
+    transform: Transform,
+    decode: Option<DecodeFormat>,
+    namespace: Option<String>,
+) -> Result<DerivedTopic, String> {
+    let mut streams = Vec::with_capacity(inputs.len());
+    for key_expr in &inputs {
+        let scoped = namespace::scope(namespace.as_deref(), key_expr);
+        streams.push(session.subscribe(&scoped).await?);
+    }
+
+    let transform = Arc::new(transform);
+    let stats: Arc<DerivedStats> = Arc::default();
+    let latest: Arc<Mutex<Latest>> = Arc::default();
+    let target = namespace::scope(namespace.as_deref(), &output);
+    let (cancel, cancel_rx) = watch::channel(false);
+    for mut stream in streams {
+        let (session, transform, stats, latest, namespace, target, mut cancel_rx) = (
+            session.clone(),
+            transform.clone(),
+            stats.clone(),
+            latest.clone(),
+            namespace.clone(),
+            target.clone(),
+            cancel_rx.clone(),
+        );
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    sample = stream.recv() => {
+                        let Some(sample) = sample else { break };
+                        stats.received.fetch_add(1, Ordering::Relaxed);
+                        let key = namespace::strip(namespace.as_deref(), &sample.key_expr);
+                        let out = match &*transform {
+                            Transform::Expression(expression) => {
+                                let value = payload_value(decode, &sample.encoding, &sample.payload);
+                                let at = sample.timestamp.as_ref().map_or_else(Utc::now, |(_, at)| *at);
+                                match evaluate(expression, &latest, &key, &sample, value, at.timestamp_millis()) {
+                                    Ok(Value::Null) => None,
+                                    Ok(Value::String(text)) => Some((text.into_bytes(), "text/plain".to_string())),
+                                    Ok(value) => Some((value.to_string().into_bytes(), "application/json".to_string())),
+                                    Err(e) => {
+                                        stats.fail(&stats.skipped, e);
+                                        continue;
+                                    }
+                                }
+                            }
+                            Transform::Script(script) => {
+                                let value = decode.map(|_| payload_value(decode, &sample.encoding, &sample.payload));
+                                let scripted = script.process(&key, sample.payload, sample.encoding, value.as_ref());
+                                script::publish_derived(&*session, namespace.as_deref(), script, scripted.derived).await;
+                                match scripted.sample {
+                                    Some(out) if !scripted.failed => Some((out.payload, out.encoding)),
+                                    _ => None,
+                                }
+                            }
+                        };
+                        let Some((payload, encoding)) = out else {
+                            stats.skipped.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        };
+                        match session.put(&target, payload, &encoding).await {
+                            Ok(()) => {
+                                stats.published.fetch_add(1, Ordering::Relaxed);
+                            }
+                            Err(e) => stats.fail(&stats.errors, format!("publish failed: {e}")),
+                        }
+                    }
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(DerivedTopic {
+        inputs,
+        output,
+        transform,
+        decode,
+        stats,
+        cancel,
+        created_at: Utc::now(),
+    })
+}
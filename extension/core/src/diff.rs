@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// One field that differs between two values on a key.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// `add`, `remove` or `replace`.
+    pub op: String,
+    /// JSON Pointer to the field; empty for the whole value.
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+/// Structural diff of `old` against `new`. Objects compare by key and arrays
+/// by index, recursively; anything else that differs is replaced whole.
+pub fn diff(old: &Value, new: &Value) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    walk(&mut String::new(), old, new, &mut changes);
+    changes
+}
+
+fn walk(path: &mut String, old: &Value, new: &Value, changes: &mut Vec<FieldChange>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let len = push_segment(path, key);
+                match new.get(key) {
+                    Some(new_value) => walk(path, old_value, new_value, changes),
+                    None => changes.push(change("remove", path, Some(old_value), None)),
+                }
+                path.truncate(len);
+            }
+            for (key, new_value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                let len = push_segment(path, key);
+                changes.push(change("add", path, None, Some(new_value)));
+                path.truncate(len);
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for i in 0..old.len().max(new.len()) {
+                let len = push_segment(path, &i.to_string());
+                match (old.get(i), new.get(i)) {
+                    (Some(old_value), Some(new_value)) => walk(path, old_value, new_value, changes),
+                    (Some(old_value), None) => {
+                        changes.push(change("remove", path, Some(old_value), None))
+                    }
+                    (None, new_value) => changes.push(change("add", path, None, new_value)),
+                }
+                path.truncate(len);
+            }
+        }
+        _ if old != new => changes.push(change("replace", path, Some(old), Some(new))),
+        _ => {}
+    }
+}
+
+/// Append an escaped JSON Pointer segment, returning the length to truncate
+/// back to.
+fn push_segment(path: &mut String, segment: &str) -> usize {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    len
+}
+
+fn change(op: &str, path: &str, old: Option<&Value>, new: Option<&Value>) -> FieldChange {
+    FieldChange {
+        op: op.to_string(),
+        path: path.to_string(),
+        old: old.cloned(),
+        new: new.cloned(),
+    }
+}
@@ -0,0 +1,194 @@
+use crate::backend::ZenohBackend;
+use crate::seq::SeqSource;
+use crate::state::{StateLock, TopicMeta};
+use crate::watchdog::{self, TaskHealth};
+use chrono::Utc;
+use std::collections::hash_map::Entry;
+use std::sync::Arc;
+use tokio::sync::watch;
+use zenoh::key_expr::KeyExpr;
+
+const TOPIC_EXPIRY_SECS: i64 = 30;
+
+/// Narrows which keys under the discovery scope enter the topic catalog.
+#[derive(Clone, Default)]
+pub struct KeyFilter {
+    /// When non-empty, a key must intersect one of these.
+    pub include: Vec<String>,
+    /// A key intersecting any of these is left out.
+    pub exclude: Vec<String>,
+    parsed: (Vec<KeyExpr<'static>>, Vec<KeyExpr<'static>>),
+}
+
+impl KeyFilter {
+    pub fn new(include: Vec<String>, exclude: Vec<String>) -> Result<Self, String> {
+        let parse = |list: &[String]| {
+            list.iter()
+                .map(|ke| {
+                    KeyExpr::try_from(ke.clone()).map_err(|e| format!("invalid key_expr {ke}: {e}"))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let parsed = (parse(&include)?, parse(&exclude)?);
+        Ok(Self {
+            include,
+            exclude,
+            parsed,
+        })
+    }
+
+    pub fn admits(&self, key: &str) -> bool {
+        let (include, exclude) = &self.parsed;
+        if include.is_empty() && exclude.is_empty() {
+            return true;
+        }
+        let Ok(key) = KeyExpr::try_from(key) else {
+            return false;
+        };
+        (include.is_empty() || include.iter().any(|ke| ke.intersects(&key)))
+            && !exclude.iter().any(|ke| ke.intersects(&key))
+    }
+}
+
+/// Spawn a supervised task that subscribes to `key_expr` and updates topic metadata.
+/// Also spawns a cleanup task that removes unpinned topics silent for 30+ seconds.
+/// When `seq_source` is set, embedded sequence numbers are tracked per topic.
+/// Topic keys are recorded with `namespace` stripped.
+/// Returns the cancel sender — send `true` to stop both tasks — and the
+/// subscriber's watchdog health.
+pub fn spawn_discovery(
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+    key_expr: String,
+    seq_source: Option<SeqSource>,
+    namespace: Option<String>,
+) -> (watch::Sender<bool>, Arc<TaskHealth>) {
+    let (cancel_tx, cancel_rx) = watch::channel(false);
+    let health: Arc<TaskHealth> = Arc::default();
+
+    // Subscriber task
+    let task = format!("discovery on {key_expr}");
+    let attempt = {
+        let (state, cancel_rx, health) = (state.clone(), cancel_rx.clone(), health.clone());
+        move || {
+            run_discovery(
+                session.clone(),
+                state.clone(),
+                key_expr.clone(),
+                seq_source.clone(),
+                namespace.clone(),
+                cancel_rx.clone(),
+                health.clone(),
+            )
+        }
+    };
+    watchdog::supervise(
+        state.clone(),
+        "discovery",
+        task,
+        health.clone(),
+        cancel_rx,
+        attempt,
+    );
+
+    // Cleanup task — prune expired topics every 5 seconds
+    let mut cancel_rx2 = cancel_tx.subscribe();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let now = Utc::now();
+                    let mut guard = state.write().await;
+                    let st = &mut *guard;
+                    // Silence while paused doesn't count; expiry restarts on resume
+                    let resumed = st.discovery_resumed_at;
+                    st.topics.retain(|key, meta| {
+                        if st.pinned_topics.contains(key) {
+                            return true;
+                        }
+                        let since = resumed.map_or(meta.last_seen, |at| at.max(meta.last_seen));
+                        (now - since).num_seconds() < TOPIC_EXPIRY_SECS
+                    });
+                }
+                _ = cancel_rx2.changed() => {
+                    if *cancel_rx2.borrow() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    (cancel_tx, health)
+}
+
+/// One discovery subscriber attempt; `Err` asks the watchdog for a restart.
+async fn run_discovery(
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+    key_expr: String,
+    seq_source: Option<SeqSource>,
+    namespace: Option<String>,
+    mut cancel_rx: watch::Receiver<bool>,
+    health: Arc<TaskHealth>,
+) -> Result<(), String> {
+    let mut stream = session
+        .subscribe(&key_expr)
+        .await
+        .map_err(|e| format!("failed to subscribe to {key_expr}: {e}"))?;
+
+    let mut heartbeat = tokio::time::interval(watchdog::HEARTBEAT_INTERVAL);
+    loop {
+        tokio::select! {
+            sample = stream.recv() => {
+                let Some(sample) = sample else {
+                    return Err(format!("stream for {key_expr} closed"));
+                };
+                health.beat();
+                let received = Utc::now();
+
+                crate::metrics::record_sample();
+                let ke = crate::namespace::strip(namespace.as_deref(), &sample.key_expr);
+                let payload_len = sample.payload.len() as u64;
+                let seq = seq_source
+                    .as_ref()
+                    .and_then(|src| src.extract(&sample.payload));
+                let (encoding, publisher) = (sample.encoding, sample.source);
+
+                let mut st = state.write().await;
+                if !st.discovery_filter.admits(&ke) {
+                    continue;
+                }
+                let preview_bytes = st.discovery_preview_bytes;
+                let meta = match st.topics.entry(ke) {
+                    Entry::Occupied(entry) => {
+                        let meta = entry.into_mut();
+                        meta.update(encoding, payload_len, publisher);
+                        meta
+                    }
+                    Entry::Vacant(entry) => {
+                        let ke = entry.key().clone();
+                        entry.insert(TopicMeta::new(ke, encoding, payload_len, publisher))
+                    }
+                };
+                if let Some(seq) = seq {
+                    meta.seq.observe(seq);
+                }
+                if let Some(max) = preview_bytes {
+                    let head = &sample.payload[..sample.payload.len().min(max)];
+                    meta.last_payload_head = Some((head.to_vec(), payload_len));
+                }
+                if let Some((clock, stamped)) = sample.timestamp {
+                    st.publisher_clocks.entry(clock).or_default().observe(stamped, received);
+                }
+            }
+            _ = heartbeat.tick() => health.beat(),
+            changed = cancel_rx.changed() => {
+                if changed.is_err() || *cancel_rx.borrow() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::api::{
+    BridgeSummary, DerivedTopicSummary, EchoSummary, FederationPeerSummary, FlightRecorderSummary,
+    GeneratorSummary, KeyWatchSummary, MirrorSummary, PreviewSummary, RecordingReaderSummary,
+    SubscriptionSummary, SynchronizerSummary, TableSummary, ThresholdWatchSummary,
+    ThrottledRelaySummary, TopicSummary, TopologyWatchSummary,
+};
+use crate::ops::{
+    bridge_summary, derived_topic_summary, echo_summary, federation_peer_summary,
+    flight_recorder_summary, generator_summary, key_watch_summary, mirror_summary, preview_summary,
+    recording_reader_summary, subscription_summary, synchronizer_summary, table_summary,
+    threshold_watch_summary, throttled_relay_summary, topic_summary, topology_watch_summary,
+};
+use crate::state::{AppState, StateLock};
+use chrono::Utc;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Serialize the complete extension state into one JSON document.
+/// `session` carries whatever session metadata the caller could gather.
+pub fn snapshot(st: &AppState, session: Value) -> Value {
+    let now = Utc::now();
+    let topics: Vec<TopicSummary> = st
+        .topics
+        .values()
+        .map(|t| topic_summary(t, now, st))
+        .collect();
+    let subscriptions: Vec<SubscriptionSummary> = st
+        .subscriptions
+        .iter()
+        .map(|(id, sub)| subscription_summary(id, sub, &st.tags))
+        .collect();
+    let mirrors: Vec<MirrorSummary> = st
+        .mirrors
+        .iter()
+        .map(|(id, mirror)| mirror_summary(id, mirror))
+        .collect();
+    let synchronizers: Vec<SynchronizerSummary> = st
+        .synchronizers
+        .iter()
+        .map(|(id, sync)| synchronizer_summary(id, sync))
+        .collect();
+    let flight_recorders: Vec<FlightRecorderSummary> = st
+        .flight_recorders
+        .iter()
+        .map(|(id, recorder)| flight_recorder_summary(id, recorder))
+        .collect();
+    let recording_readers: Vec<RecordingReaderSummary> = st
+        .recording_readers
+        .iter()
+        .map(|(id, reader)| recording_reader_summary(id, reader))
+        .collect();
+    let generators: Vec<GeneratorSummary> = st
+        .generators
+        .iter()
+        .map(|(id, generator)| generator_summary(id, generator))
+        .collect();
+    let echoes: Vec<EchoSummary> = st
+        .echoes
+        .iter()
+        .map(|(id, echo)| echo_summary(id, echo))
+        .collect();
+    let bridges: Vec<BridgeSummary> = st
+        .bridges
+        .iter()
+        .map(|(id, bridge)| bridge_summary(id, bridge))
+        .collect();
+    let throttled_relays: Vec<ThrottledRelaySummary> = st
+        .throttled_relays
+        .iter()
+        .map(|(id, relay)| throttled_relay_summary(id, relay))
+        .collect();
+    let previews: Vec<PreviewSummary> = st
+        .previews
+        .iter()
+        .map(|(id, preview)| preview_summary(id, preview))
+        .collect();
+    let key_watches: Vec<KeyWatchSummary> = st
+        .key_watches
+        .iter()
+        .map(|(id, key_watch)| key_watch_summary(id, key_watch))
+        .collect();
+    let tables: Vec<TableSummary> = st
+        .tables
+        .iter()
+        .map(|(id, table)| table_summary(id, table))
+        .collect();
+    let threshold_watches: Vec<ThresholdWatchSummary> = st
+        .threshold_watches
+        .iter()
+        .map(|(id, threshold_watch)| threshold_watch_summary(id, threshold_watch))
+        .collect();
+    let topology_watches: Vec<TopologyWatchSummary> = st
+        .topology_watches
+        .iter()
+        .map(|(id, topology_watch)| topology_watch_summary(id, topology_watch))
+        .collect();
+    let derived_topics: Vec<DerivedTopicSummary> = st
+        .derived_topics
+        .iter()
+        .map(|(id, derived_topic)| derived_topic_summary(id, derived_topic))
+        .collect();
+    let federation_peers: Vec<FederationPeerSummary> = st
+        .federation_peers
+        .iter()
+        .map(|(name, peer)| federation_peer_summary(name, peer))
+        .collect();
+    let alerts: Vec<_> = st.alerts.rules.values().collect();
+
+    serde_json::json!({
+        "generated_at": now.to_rfc3339(),
+        "version": env!("CARGO_PKG_VERSION"),
+        "session": session,
+        "namespace": st.namespace,
+        "read_only": st.read_only,
+        "recordings": st.recordings,
+        "admin": st.admin.as_ref().map(|a| serde_json::json!({
+            "key_expr": format!("{}/*", a.prefix),
+            "queries": a.queries.load(std::sync::atomic::Ordering::Relaxed),
+        })),
+        "discovery": {
+            "active": st.discovery_active,
+            "paused": st.discovery_paused,
+            "pinned": st.pinned_topics,
+            "key_expr": st.discovery_key_expr,
+            "seq_path": st.discovery_seq_source.as_ref().map(|s| s.spec()),
+            "preview_bytes": st.discovery_preview_bytes,
+            "include": st.discovery_filter.include,
+            "exclude": st.discovery_filter.exclude,
+            "topic_count": topics.len(),
+            "restart_count": st.discovery_health.as_ref().map(|h| h.restarts()),
+            "last_heartbeat": st.discovery_health.as_ref().and_then(|h| h.last_heartbeat()).map(|at| at.to_rfc3339()),
+        },
+        "topics": topics,
+        "subscriptions": subscriptions,
+        "mirrors": mirrors,
+        "synchronizers": synchronizers,
+        "flight_recorders": flight_recorders,
+        "recording_readers": recording_readers,
+        "generators": generators,
+        "echoes": echoes,
+        "bridges": bridges,
+        "federation_peers": federation_peers,
+        "throttled_relays": throttled_relays,
+        "previews": previews,
+        "key_watches": key_watches,
+        "tables": tables,
+        "threshold_watches": threshold_watches,
+        "topology_watches": topology_watches,
+        "derived_topics": derived_topics,
+        "alerts": alerts,
+        "pending_alert_events": st.alerts.events.len(),
+        "recent_errors": st.recent_errors,
+    })
+}
+
+/// Install a panic hook that writes a state snapshot to `path` before the
+/// default hook runs. Falls back to session metadata alone if the state lock
+/// is held (e.g. the panic happened while it was being mutated).
+pub fn install_panic_hook(path: PathBuf, state: Arc<StateLock>, zid: String) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let session = serde_json::json!({ "zid": zid });
+        let mut doc = match state.try_read() {
+            Ok(st) => snapshot(&st, session),
+            Err(_) => serde_json::json!({
+                "session": session,
+                "state_unavailable": "state lock was held at panic time",
+            }),
+        };
+        doc["panic"] = Value::String(info.to_string());
+
+        match std::fs::write(&path, serde_json::to_vec_pretty(&doc).unwrap()) {
+            Ok(()) => eprintln!("zenoh: state dumped to {}", path.display()),
+            Err(e) => eprintln!(
+                "zenoh: failed to write state dump to {}: {e}",
+                path.display()
+            ),
+        }
+        default_hook(info);
+    }));
+}
@@ -0,0 +1,91 @@
+use crate::backend::ZenohBackend;
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+
+/// Samples allowed to wait out their delay before the subscriber side drops.
+const DELAY_QUEUE_CAPACITY: usize = 10_000;
+
+#[derive(Default)]
+pub struct EchoStats {
+    pub received: AtomicU64,
+    pub echoed: AtomicU64,
+    pub errors: AtomicU64,
+    /// Samples dropped because the delay queue was full.
+    pub dropped: AtomicU64,
+}
+
+pub struct Echo {
+    /// Host-facing key expressions, without the session namespace.
+    pub from_key_expr: String,
+    pub to_key_expr: String,
+    pub delay: Duration,
+    pub stats: Arc<EchoStats>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Subscribe on `from` and put every sample, with its encoding, on `to` once
+/// `delay` has passed since it arrived. Order is preserved.
+pub async fn start_echo(
+    session: Arc<dyn ZenohBackend>,
+    from_key_expr: String,
+    to_key_expr: String,
+    scoped_from: &str,
+    scoped_to: String,
+    delay: Duration,
+) -> Result<Echo, String> {
+    let mut stream = session.subscribe(scoped_from).await?;
+    let stats: Arc<EchoStats> = Arc::default();
+    let (cancel, mut cancel_rx) = watch::channel(false);
+    let (tx, mut rx) = mpsc::channel::<(Instant, Vec<u8>, String)>(DELAY_QUEUE_CAPACITY);
+
+    {
+        let stats = stats.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    sample = stream.recv() => {
+                        let Some(sample) = sample else { break };
+                        stats.received.fetch_add(1, Ordering::Relaxed);
+                        let due = Instant::now() + delay;
+                        if tx.try_send((due, sample.payload, sample.encoding)).is_err() {
+                            stats.dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+    {
+        // Ends once the receiving task drops its sender
+        let stats = stats.clone();
+        tokio::spawn(async move {
+            while let Some((due, payload, encoding)) = rx.recv().await {
+                tokio::time::sleep_until(due).await;
+                let counter = match session.put(&scoped_to, payload, &encoding).await {
+                    Ok(()) => &stats.echoed,
+                    Err(_) => &stats.errors,
+                };
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    }
+
+    Ok(Echo {
+        from_key_expr,
+        to_key_expr,
+        delay,
+        stats,
+        cancel,
+        created_at: Utc::now(),
+    })
+}
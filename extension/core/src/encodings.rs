@@ -0,0 +1,78 @@
+use crate::decode::DecodeFormat;
+use crate::render::View;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{LazyLock, RwLock};
+
+/// Handling registered for encodings the built-in `auto` rules don't know.
+/// Process-wide like the decode pool, so every op that decodes, renders or
+/// publishes by encoding agrees on it.
+static REGISTRY: LazyLock<RwLock<BTreeMap<String, Rule>>> = LazyLock::new(Default::default);
+
+/// Encodings `auto` recognizes without registration: any encoding containing
+/// the word picks that decoder.
+pub const BUILTIN: [DecodeFormat; 3] = [
+    DecodeFormat::Json,
+    DecodeFormat::Cbor,
+    DecodeFormat::Protobuf,
+];
+
+#[derive(Clone, Copy)]
+pub struct Rule {
+    /// Decoder for `auto`; `None` marks the payload opaque, so `auto` skips
+    /// the built-in guesses.
+    pub decode: Option<DecodeFormat>,
+    /// View `render_sample` uses when asked for `auto`.
+    pub view: Option<View>,
+}
+
+/// Register `encoding`, replacing any earlier rule for it. Returns whether
+/// one was replaced.
+pub fn register(encoding: String, rule: Rule) -> bool {
+    REGISTRY.write().unwrap().insert(encoding, rule).is_some()
+}
+
+pub fn unregister(encoding: &str) -> bool {
+    REGISTRY.write().unwrap().remove(encoding).is_some()
+}
+
+pub fn registered() -> Vec<(String, Rule)> {
+    let registry = REGISTRY.read().unwrap();
+    registry
+        .iter()
+        .map(|(k, rule)| (k.clone(), *rule))
+        .collect()
+}
+
+/// The rule for `encoding`: an exact registration first, then one for its
+/// schema suffix alone (`;acme.Pose`), then one for the part before the `;`.
+pub fn lookup(encoding: &str) -> Option<Rule> {
+    let registry = REGISTRY.read().unwrap();
+    if let Some(rule) = registry.get(encoding) {
+        return Some(*rule);
+    }
+    let (base, schema) = encoding.split_once(';')?;
+    registry
+        .get(&format!(";{schema}"))
+        .or_else(|| registry.get(base))
+        .copied()
+}
+
+/// Encode a JSON value for publishing under a registered encoding. `None`
+/// leaves the caller's default handling; structured values under an encoding
+/// without an encoder are an error rather than mislabelled JSON text.
+pub fn encode(encoding: &str, value: &Value) -> Option<Result<Vec<u8>, String>> {
+    let format = lookup(encoding)?.decode?;
+    Some(match format {
+        DecodeFormat::Json => Ok(value.to_string().into_bytes()),
+        DecodeFormat::Cbor => {
+            let mut bytes = Vec::new();
+            ciborium::into_writer(value, &mut bytes)
+                .map(|()| bytes)
+                .map_err(|e| format!("cbor: {e}"))
+        }
+        DecodeFormat::Protobuf | DecodeFormat::Auto => Err(format!(
+            "encoding {encoding} has no encoder for JSON values; send payload_b64"
+        )),
+    })
+}
@@ -0,0 +1,362 @@
+use crate::jsonpath;
+use crate::state::BufferedSample;
+use serde_json::Value;
+
+/// Columns written before the selected fields.
+const BASE_COLUMNS: [&str; 4] = ["seq", "timestamp", "key_expr", "encoding"];
+
+/// Output format for exported samples.
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "parquet" => Ok(ExportFormat::Parquet),
+            other => Err(format!("unsupported export format: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// Column names for an export: the base columns, then one per field path,
+/// or a single `payload` column when no fields are selected.
+pub fn columns(fields: &[String]) -> Vec<String> {
+    let mut columns: Vec<String> = BASE_COLUMNS.iter().map(|c| c.to_string()).collect();
+    match fields.is_empty() {
+        true => columns.push("payload".into()),
+        false => columns.extend(fields.iter().map(|f| field_column(f))),
+    }
+    columns
+}
+
+/// Column name for a field path, prefixed with `payload.` when it would
+/// collide with a base column (e.g. a payload `seq`).
+fn field_column(field: &str) -> String {
+    match BASE_COLUMNS.contains(&field) || field == "payload" {
+        true => format!("payload.{field}"),
+        false => field.to_string(),
+    }
+}
+
+/// One row of cells per sample. Fields are JSONPath lookups into the JSON
+/// (or decoded) payload; missing fields are left empty.
+pub fn rows<'a>(
+    samples: &'a [BufferedSample],
+    fields: &'a [String],
+) -> impl Iterator<Item = Vec<String>> + 'a {
+    samples.iter().map(move |sample| {
+        let mut row = vec![
+            sample.seq.to_string(),
+            sample.timestamp.to_rfc3339(),
+            sample.key_expr.clone(),
+            sample.encoding.clone(),
+        ];
+        if fields.is_empty() {
+            row.push(sample.payload.as_str().unwrap_or_default().to_string());
+        } else {
+            let json = sample.json();
+            row.extend(fields.iter().map(|path| {
+                json.as_ref()
+                    .and_then(|v| jsonpath::lookup(v, path))
+                    .map(cell)
+                    .unwrap_or_default()
+            }));
+        }
+        row
+    })
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Render a header and rows as RFC 4180 CSV.
+pub fn to_csv(columns: &[String], rows: impl Iterator<Item = Vec<String>>) -> String {
+    let mut out = String::new();
+    let mut write_row = |cells: &[String]| {
+        let line: Vec<String> = cells.iter().map(|c| csv_escape(c)).collect();
+        out.push_str(&line.join(","));
+        out.push_str("\r\n");
+    };
+    write_row(columns);
+    for row in rows {
+        write_row(&row);
+    }
+    out
+}
+
+fn csv_escape(cell: &str) -> String {
+    match cell.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", cell.replace('"', "\"\"")),
+        false => cell.to_string(),
+    }
+}
+
+/// Columns inferred from JSON payloads when no fields are selected.
+const MAX_INFERRED_COLUMNS: usize = 256;
+
+/// Physical column type inferred from the values a field takes.
+#[derive(Clone, Copy)]
+enum ColumnKind {
+    Bool,
+    Int,
+    Double,
+    Text,
+}
+
+impl ColumnKind {
+    fn infer(values: &[Option<&Value>]) -> Self {
+        let present: Vec<&Value> = values.iter().flatten().copied().collect();
+        if present.is_empty() {
+            ColumnKind::Text
+        } else if present.iter().all(|v| v.is_boolean()) {
+            ColumnKind::Bool
+        } else if present.iter().all(|v| v.is_i64()) {
+            ColumnKind::Int
+        } else if present.iter().all(|v| v.is_number()) {
+            ColumnKind::Double
+        } else {
+            ColumnKind::Text
+        }
+    }
+
+    fn parquet_type(&self, name: &str) -> Result<parquet::schema::types::Type, String> {
+        use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
+        let (physical, logical) = match self {
+            ColumnKind::Bool => (PhysicalType::BOOLEAN, None),
+            ColumnKind::Int => (PhysicalType::INT64, None),
+            ColumnKind::Double => (PhysicalType::DOUBLE, None),
+            ColumnKind::Text => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+        };
+        parquet::schema::types::Type::primitive_type_builder(name, physical)
+            .with_repetition(Repetition::OPTIONAL)
+            .with_logical_type(logical)
+            .build()
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Add the dotted path of every scalar (or array) leaf in `value` not
+/// already in `out`.
+pub fn value_fields(value: &Value, out: &mut Vec<String>) {
+    fn walk(value: &Value, prefix: &str, out: &mut Vec<String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, child) in map {
+                    let path = match prefix.is_empty() {
+                        true => key.clone(),
+                        false => format!("{prefix}.{key}"),
+                    };
+                    walk(child, &path, out);
+                }
+            }
+            _ if !prefix.is_empty() && !out.iter().any(|p| p == prefix) => {
+                out.push(prefix.to_string())
+            }
+            _ => {}
+        }
+    }
+    walk(value, "", out)
+}
+
+/// Dotted paths of every scalar (or array) leaf in the JSON payloads, in
+/// first-seen order.
+pub fn infer_fields(samples: &[BufferedSample]) -> Vec<String> {
+    let mut fields = Vec::new();
+    for json in samples.iter().filter_map(BufferedSample::json) {
+        value_fields(&json, &mut fields);
+        if fields.len() >= MAX_INFERRED_COLUMNS {
+            fields.truncate(MAX_INFERRED_COLUMNS);
+            break;
+        }
+    }
+    fields
+}
+
+/// Write samples to a Snappy-compressed Parquet file, one row group per
+/// `row_group_size` samples, calling `progress` with the rows written after
+/// each group. Field column types are inferred from the values; without
+/// JSON fields the raw payload is written as a binary column.
+pub fn write_parquet(
+    path: &str,
+    samples: &[BufferedSample],
+    fields: &[String],
+    row_group_size: usize,
+    mut progress: impl FnMut(usize),
+) -> Result<Vec<String>, String> {
+    use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
+    use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::types::Type;
+    use std::sync::Arc;
+
+    let err = |e: parquet::errors::ParquetError| format!("parquet export failed: {e}");
+
+    let json: Vec<Option<Value>> = samples.iter().map(BufferedSample::json).collect();
+    let field_values: Vec<Vec<Option<&Value>>> = fields
+        .iter()
+        .map(|path| {
+            json.iter()
+                .map(|j| {
+                    j.as_ref()
+                        .and_then(|v| jsonpath::lookup(v, path))
+                        .filter(|v| !v.is_null())
+                })
+                .collect()
+        })
+        .collect();
+    let kinds: Vec<ColumnKind> = field_values.iter().map(|v| ColumnKind::infer(v)).collect();
+
+    let required = |name: &str, physical, logical| {
+        Type::primitive_type_builder(name, physical)
+            .with_repetition(Repetition::REQUIRED)
+            .with_logical_type(logical)
+            .build()
+            .map(Arc::new)
+            .map_err(|e| e.to_string())
+    };
+    let mut schema_fields = vec![
+        required("seq", PhysicalType::INT64, None)?,
+        required(
+            "timestamp",
+            PhysicalType::INT64,
+            Some(LogicalType::Timestamp {
+                is_adjusted_to_u_t_c: true,
+                unit: parquet::basic::TimeUnit::MICROS(Default::default()),
+            }),
+        )?,
+        required(
+            "key_expr",
+            PhysicalType::BYTE_ARRAY,
+            Some(LogicalType::String),
+        )?,
+        required(
+            "encoding",
+            PhysicalType::BYTE_ARRAY,
+            Some(LogicalType::String),
+        )?,
+    ];
+    let mut columns: Vec<String> = BASE_COLUMNS.iter().map(|c| c.to_string()).collect();
+    if fields.is_empty() {
+        schema_fields.push(required("payload", PhysicalType::BYTE_ARRAY, None)?);
+        columns.push("payload".into());
+    } else {
+        for (field, kind) in fields.iter().zip(&kinds) {
+            let column = field_column(field);
+            schema_fields.push(Arc::new(kind.parquet_type(&column)?));
+            columns.push(column);
+        }
+    }
+    let schema = Type::group_type_builder("sample")
+        .with_fields(schema_fields)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let props = WriterProperties::builder()
+        .set_compression(parquet::basic::Compression::SNAPPY)
+        .build();
+    let file = std::fs::File::create(path).map_err(|e| format!("failed to create {path}: {e}"))?;
+    let mut writer =
+        SerializedFileWriter::new(file, Arc::new(schema), Arc::new(props)).map_err(err)?;
+
+    let mut written = 0;
+    for start in (0..samples.len()).step_by(row_group_size.max(1)) {
+        let rows = start..(start + row_group_size).min(samples.len());
+        let chunk = &samples[rows.clone()];
+        let mut group = writer.next_row_group().map_err(err)?;
+        let mut index = 0;
+        while let Some(mut column) = group.next_column().map_err(err)? {
+            match index {
+                0 => {
+                    let values: Vec<i64> = chunk.iter().map(|s| s.seq as i64).collect();
+                    column.typed::<Int64Type>().write_batch(&values, None, None)
+                }
+                1 => {
+                    let values: Vec<i64> = chunk
+                        .iter()
+                        .map(|s| s.timestamp.timestamp_micros())
+                        .collect();
+                    column.typed::<Int64Type>().write_batch(&values, None, None)
+                }
+                2 | 3 => {
+                    let values: Vec<ByteArray> = chunk
+                        .iter()
+                        .map(|s| match index {
+                            2 => ByteArray::from(s.key_expr.as_str()),
+                            _ => ByteArray::from(s.encoding.as_str()),
+                        })
+                        .collect();
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, None, None)
+                }
+                _ if fields.is_empty() => {
+                    let values: Vec<ByteArray> = chunk
+                        .iter()
+                        .map(|s| ByteArray::from(s.payload.0.clone()))
+                        .collect();
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, None, None)
+                }
+                _ => {
+                    let field = index - BASE_COLUMNS.len();
+                    let values = &field_values[field][rows.clone()];
+                    let levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+                    let present = values.iter().flatten();
+                    match kinds[field] {
+                        ColumnKind::Bool => {
+                            let v: Vec<bool> = present.filter_map(|v| v.as_bool()).collect();
+                            column
+                                .typed::<BoolType>()
+                                .write_batch(&v, Some(&levels), None)
+                        }
+                        ColumnKind::Int => {
+                            let v: Vec<i64> = present.filter_map(|v| v.as_i64()).collect();
+                            column
+                                .typed::<Int64Type>()
+                                .write_batch(&v, Some(&levels), None)
+                        }
+                        ColumnKind::Double => {
+                            let v: Vec<f64> = present.filter_map(|v| v.as_f64()).collect();
+                            column
+                                .typed::<DoubleType>()
+                                .write_batch(&v, Some(&levels), None)
+                        }
+                        ColumnKind::Text => {
+                            let v: Vec<ByteArray> =
+                                present.map(|v| ByteArray::from(cell(v).as_str())).collect();
+                            column
+                                .typed::<ByteArrayType>()
+                                .write_batch(&v, Some(&levels), None)
+                        }
+                    }
+                }
+            }
+            .map_err(err)?;
+            column.close().map_err(err)?;
+            index += 1;
+        }
+        group.close().map_err(err)?;
+        written += chunk.len();
+        progress(written);
+    }
+    writer.close().map_err(err)?;
+    Ok(columns)
+}
@@ -0,0 +1,727 @@
+//! A small CEL-like expression language evaluated against samples, shared by
+//! subscription filters, alert rules and watches.
+//!
+//! ```text
+//! value.battery.pct < 20 && key.starts_with("robot/")
+//! has(value.error) || encoding in ["application/json", "text/json"]
+//! size(value.points) > 0 && value.points[0].x >= -1.5
+//! ```
+//!
+//! Variables: `key`, `value` (the decoded payload, its JSON parse, or its
+//! text), `encoding`, `bytes` (payload length) and `timestamp_ms`. Fields and
+//! indices on missing or mistyped values yield `null` rather than failing.
+//!
+//! Operators by increasing precedence: `||`, `&&`, then `== != < <= > >= in`,
+//! then `+ -`, then `* / %`, then unary `! -`. Functions: `has(x)`, `size(x)`,
+//! `abs(x)`, `sqrt(x)`, `number(x)`, `string(x)`, `contains(s, t)`, `starts_with(s, t)`
+//! and `ends_with(s, t)`; the string ones may also be called as methods,
+//! `s.contains(t)`.
+
+use serde_json::{Map, Number, Value};
+use std::fmt;
+
+/// Names an expression may refer to.
+pub const VARIABLES: &[&str] = &["key", "value", "encoding", "bytes", "timestamp_ms"];
+
+const FUNCTIONS: &[(&str, usize)] = &[
+    ("has", 1),
+    ("size", 1),
+    ("abs", 1),
+    ("sqrt", 1),
+    ("number", 1),
+    ("string", 1),
+    ("contains", 2),
+    ("starts_with", 2),
+    ("ends_with", 2),
+];
+
+/// A parsed expression, keeping its source for display.
+#[derive(Clone)]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl fmt::Debug for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[derive(Clone)]
+enum Node {
+    Literal(Value),
+    Var(String),
+    Field(Box<Node>, String),
+    Index(Box<Node>, Box<Node>),
+    List(Vec<Node>),
+    Not(Box<Node>),
+    Neg(Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+    Call(String, Vec<Node>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinOp {
+    fn precedence(self) -> u8 {
+        match self {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge | BinOp::In => 3,
+            BinOp::Add | BinOp::Sub => 4,
+            BinOp::Mul | BinOp::Div | BinOp::Rem => 5,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Punct(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{n}"),
+            Token::Str(s) => write!(f, "{s:?}"),
+            Token::Ident(s) => f.write_str(s),
+            Token::Punct(p) => f.write_str(p),
+        }
+    }
+}
+
+const PUNCT: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "+", "-", "*", "/", "%", "(", ")", "[", "]",
+    ",", ".",
+];
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = src;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E'))
+                .unwrap_or(rest.len());
+            let n = rest[..end]
+                .parse()
+                .map_err(|_| format!("invalid number: {}", &rest[..end]))?;
+            tokens.push(Token::Num(n));
+            rest = &rest[end..];
+        } else if c == '"' || c == '\'' {
+            let (s, len) = string_literal(rest, c)?;
+            tokens.push(Token::Str(s));
+            rest = &rest[len..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            let p = PUNCT
+                .iter()
+                .find(|p| rest.starts_with(**p))
+                .ok_or_else(|| format!("unexpected character: {c}"))?;
+            tokens.push(Token::Punct(p));
+            rest = &rest[p.len()..];
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parse a quoted string at the start of `src`, returning it and the bytes
+/// consumed. Supports `\n`, `\t`, `\\` and escaped quotes.
+fn string_literal(src: &str, quote: char) -> Result<(String, usize), String> {
+    let mut out = String::new();
+    let mut chars = src.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, 'n')) => out.push('\n'),
+                Some((_, 't')) => out.push('\t'),
+                Some((_, c)) => out.push(c),
+                None => break,
+            },
+            c if c == quote => return Ok((out, i + 1)),
+            c => out.push(c),
+        }
+    }
+    Err("unterminated string".into())
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    variables: &'a [&'a str],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Punct(p)) if *p == punct) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), String> {
+        match self.eat(punct) {
+            true => Ok(()),
+            false => Err(match self.peek() {
+                Some(t) => format!("expected {punct}, found {t}"),
+                None => format!("expected {punct} at end of expression"),
+            }),
+        }
+    }
+
+    fn binary_op(&self) -> Option<BinOp> {
+        Some(match self.peek()? {
+            Token::Punct("||") => BinOp::Or,
+            Token::Punct("&&") => BinOp::And,
+            Token::Punct("==") => BinOp::Eq,
+            Token::Punct("!=") => BinOp::Ne,
+            Token::Punct("<") => BinOp::Lt,
+            Token::Punct("<=") => BinOp::Le,
+            Token::Punct(">") => BinOp::Gt,
+            Token::Punct(">=") => BinOp::Ge,
+            Token::Ident(s) if s == "in" => BinOp::In,
+            Token::Punct("+") => BinOp::Add,
+            Token::Punct("-") => BinOp::Sub,
+            Token::Punct("*") => BinOp::Mul,
+            Token::Punct("/") => BinOp::Div,
+            Token::Punct("%") => BinOp::Rem,
+            _ => return None,
+        })
+    }
+
+    /// Precedence climbing over binary operators at or above `min`.
+    fn expr(&mut self, min: u8) -> Result<Node, String> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.binary_op().filter(|op| op.precedence() >= min) {
+            self.pos += 1;
+            let rhs = self.expr(op.precedence() + 1)?;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat("!") {
+            return Ok(Node::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("-") {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Node, String> {
+        let mut node = self.primary()?;
+        loop {
+            if self.eat(".") {
+                let Some(Token::Ident(name)) = self.next() else {
+                    return Err("expected a field name after .".into());
+                };
+                node = match self.eat("(") {
+                    // Method call: receiver becomes the first argument
+                    true => {
+                        let mut args = vec![node];
+                        args.extend(self.args()?);
+                        call(name, args)?
+                    }
+                    false => Node::Field(Box::new(node), name),
+                };
+            } else if self.eat("[") {
+                let index = self.expr(1)?;
+                self.expect("]")?;
+                node = Node::Index(Box::new(node), Box::new(index));
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    /// Comma-separated arguments after an opening `(`, through the `)`.
+    fn args(&mut self) -> Result<Vec<Node>, String> {
+        let mut args = Vec::new();
+        if self.eat(")") {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expr(1)?);
+            if self.eat(")") {
+                return Ok(args);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Node::Literal(number(n))),
+            Some(Token::Str(s)) => Ok(Node::Literal(Value::String(s))),
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Node::Literal(Value::Bool(true))),
+                "false" => Ok(Node::Literal(Value::Bool(false))),
+                "null" => Ok(Node::Literal(Value::Null)),
+                _ if self.eat("(") => {
+                    let args = self.args()?;
+                    call(name, args)
+                }
+                _ if self.variables.contains(&name.as_str()) => Ok(Node::Var(name)),
+                _ => Err(format!(
+                    "unknown variable: {name} (expected one of {})",
+                    self.variables.join(", ")
+                )),
+            },
+            Some(Token::Punct("(")) => {
+                let node = self.expr(1)?;
+                self.expect(")")?;
+                Ok(node)
+            }
+            Some(Token::Punct("[")) => {
+                let mut items = Vec::new();
+                if !self.eat("]") {
+                    loop {
+                        items.push(self.expr(1)?);
+                        if self.eat("]") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                Ok(Node::List(items))
+            }
+            Some(t) => Err(format!("unexpected {t}")),
+            None => Err("unexpected end of expression".into()),
+        }
+    }
+}
+
+fn call(name: String, args: Vec<Node>) -> Result<Node, String> {
+    let Some((_, arity)) = FUNCTIONS.iter().find(|(f, _)| *f == name) else {
+        return Err(format!("unknown function: {name}"));
+    };
+    if args.len() != *arity {
+        return Err(format!(
+            "{name} takes {arity} argument(s), got {}",
+            args.len()
+        ));
+    }
+    Ok(Node::Call(name, args))
+}
+
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        return Value::from(n as i64);
+    }
+    Number::from_f64(n).map_or(Value::Null, Value::Number)
+}
+
+fn type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "list",
+        Value::Object(_) => "map",
+    }
+}
+
+fn as_bool(v: &Value) -> Result<bool, String> {
+    v.as_bool()
+        .ok_or_else(|| format!("expected bool, got {}", type_name(v)))
+}
+
+fn as_num(v: &Value) -> Result<f64, String> {
+    v.as_f64()
+        .ok_or_else(|| format!("expected number, got {}", type_name(v)))
+}
+
+fn as_str(v: &Value) -> Result<&str, String> {
+    v.as_str()
+        .ok_or_else(|| format!("expected string, got {}", type_name(v)))
+}
+
+/// Equality with numbers compared by value, so `1 == 1.0`.
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => x == y,
+        _ => a == b,
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        Self::parse_with(source, VARIABLES)
+    }
+
+    /// Parse an expression that may refer to `variables` instead of the
+    /// sample [`VARIABLES`].
+    pub fn parse_with(source: &str, variables: &[&str]) -> Result<Self, String> {
+        let invalid = |e| format!("invalid expression: {e}");
+        let mut parser = Parser {
+            tokens: tokenize(source).map_err(invalid)?,
+            pos: 0,
+            variables,
+        };
+        let root = parser.expr(1).map_err(invalid)?;
+        if let Some(t) = parser.peek() {
+            return Err(format!("invalid expression: unexpected {t}"));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluate against `vars`, an object holding the [`VARIABLES`].
+    pub fn eval(&self, vars: &Value) -> Result<Value, String> {
+        eval(&self.root, vars)
+    }
+
+    /// Whether the expression evaluates to `true`; errors and non-bool
+    /// results count as no match.
+    pub fn matches(&self, vars: &Value) -> bool {
+        matches!(self.eval(vars), Ok(Value::Bool(true)))
+    }
+}
+
+/// The variables for one sample.
+pub fn sample_vars(
+    key: &str,
+    encoding: &str,
+    bytes: usize,
+    timestamp_ms: i64,
+    value: Value,
+) -> Value {
+    let mut vars = Map::new();
+    vars.insert("key".into(), Value::String(key.to_string()));
+    vars.insert("value".into(), value);
+    vars.insert("encoding".into(), Value::String(encoding.to_string()));
+    vars.insert("bytes".into(), Value::from(bytes));
+    vars.insert("timestamp_ms".into(), Value::from(timestamp_ms));
+    Value::Object(vars)
+}
+
+fn eval(node: &Node, vars: &Value) -> Result<Value, String> {
+    match node {
+        Node::Literal(v) => Ok(v.clone()),
+        Node::Var(name) => Ok(vars.get(name).cloned().unwrap_or(Value::Null)),
+        Node::Field(target, name) => Ok(match eval(target, vars)? {
+            Value::Object(mut map) => map.remove(name).unwrap_or(Value::Null),
+            _ => Value::Null,
+        }),
+        Node::Index(target, index) => {
+            let target = eval(target, vars)?;
+            let index = eval(index, vars)?;
+            Ok(match (target, index) {
+                (Value::Array(mut items), Value::Number(n)) => n
+                    .as_u64()
+                    .filter(|&i| (i as usize) < items.len())
+                    .map_or(Value::Null, |i| items.swap_remove(i as usize)),
+                (Value::Object(mut map), Value::String(k)) => map.remove(&k).unwrap_or(Value::Null),
+                _ => Value::Null,
+            })
+        }
+        Node::List(items) => items
+            .iter()
+            .map(|item| eval(item, vars))
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        Node::Not(inner) => Ok(Value::Bool(!as_bool(&eval(inner, vars)?)?)),
+        Node::Neg(inner) => Ok(number(-as_num(&eval(inner, vars)?)?)),
+        Node::Binary(op, lhs, rhs) => binary(*op, lhs, rhs, vars),
+        Node::Call(name, args) => {
+            let args: Vec<Value> = args
+                .iter()
+                .map(|arg| eval(arg, vars))
+                .collect::<Result<_, _>>()?;
+            function(name, &args)
+        }
+    }
+}
+
+fn binary(op: BinOp, lhs: &Node, rhs: &Node, vars: &Value) -> Result<Value, String> {
+    // Short-circuit: the right side is not evaluated once the result is known
+    match op {
+        BinOp::And if !as_bool(&eval(lhs, vars)?)? => return Ok(Value::Bool(false)),
+        BinOp::Or if as_bool(&eval(lhs, vars)?)? => return Ok(Value::Bool(true)),
+        BinOp::And | BinOp::Or => return Ok(Value::Bool(as_bool(&eval(rhs, vars)?)?)),
+        _ => {}
+    }
+    let a = eval(lhs, vars)?;
+    let b = eval(rhs, vars)?;
+    let ordering = |a: &Value, b: &Value| match (a, b) {
+        (Value::String(x), Value::String(y)) => Ok(x.cmp(y)),
+        _ => as_num(a)?
+            .partial_cmp(&as_num(b)?)
+            .ok_or_else(|| "cannot compare NaN".to_string()),
+    };
+    Ok(match op {
+        BinOp::Eq => Value::Bool(equal(&a, &b)),
+        BinOp::Ne => Value::Bool(!equal(&a, &b)),
+        BinOp::Lt => Value::Bool(ordering(&a, &b)?.is_lt()),
+        BinOp::Le => Value::Bool(ordering(&a, &b)?.is_le()),
+        BinOp::Gt => Value::Bool(ordering(&a, &b)?.is_gt()),
+        BinOp::Ge => Value::Bool(ordering(&a, &b)?.is_ge()),
+        BinOp::In => Value::Bool(match &b {
+            Value::Array(items) => items.iter().any(|item| equal(&a, item)),
+            Value::Object(map) => map.contains_key(as_str(&a)?),
+            Value::String(s) => s.contains(as_str(&a)?),
+            other => {
+                return Err(format!(
+                    "in needs a list, map or string, got {}",
+                    type_name(other)
+                ))
+            }
+        }),
+        BinOp::Add => match (&a, &b) {
+            (Value::String(x), Value::String(y)) => Value::String(format!("{x}{y}")),
+            _ => number(as_num(&a)? + as_num(&b)?),
+        },
+        BinOp::Sub => number(as_num(&a)? - as_num(&b)?),
+        BinOp::Mul => number(as_num(&a)? * as_num(&b)?),
+        BinOp::Div | BinOp::Rem => {
+            let divisor = as_num(&b)?;
+            if divisor == 0.0 {
+                return Err("division by zero".into());
+            }
+            match op {
+                BinOp::Div => number(as_num(&a)? / divisor),
+                _ => number(as_num(&a)? % divisor),
+            }
+        }
+        BinOp::And | BinOp::Or => unreachable!(),
+    })
+}
+
+fn function(name: &str, args: &[Value]) -> Result<Value, String> {
+    Ok(match name {
+        "has" => Value::Bool(!args[0].is_null()),
+        "size" => Value::from(match &args[0] {
+            Value::String(s) => s.chars().count(),
+            Value::Array(items) => items.len(),
+            Value::Object(map) => map.len(),
+            other => {
+                return Err(format!(
+                    "size needs a string, list or map, got {}",
+                    type_name(other)
+                ))
+            }
+        }),
+        "abs" => number(as_num(&args[0])?.abs()),
+        "sqrt" => number(as_num(&args[0])?.sqrt()),
+        "number" => match &args[0] {
+            Value::String(s) => s
+                .trim()
+                .parse::<f64>()
+                .map(number)
+                .map_err(|_| format!("not a number: {s:?}"))?,
+            other => number(as_num(other)?),
+        },
+        "string" => Value::String(match &args[0] {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }),
+        "contains" => Value::Bool(as_str(&args[0])?.contains(as_str(&args[1])?)),
+        "starts_with" => Value::Bool(as_str(&args[0])?.starts_with(as_str(&args[1])?)),
+        "ends_with" => Value::Bool(as_str(&args[0])?.ends_with(as_str(&args[1])?)),
+        other => return Err(format!("unknown function: {other}")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars() -> Value {
+        let value = json!({
+            "battery": {"pct": 15, "charging": false},
+            "points": [{"x": -2.5}, {"x": 1}],
+            "name": "rover",
+        });
+        sample_vars("robot/1/state", "application/json", 64, 1000, value)
+    }
+
+    fn eval(source: &str) -> Result<Value, String> {
+        Expression::parse(source)?.eval(&vars())
+    }
+
+    fn error(source: &str) -> String {
+        Expression::parse(source)
+            .and_then(|e| e.eval(&vars()))
+            .unwrap_err()
+    }
+
+    #[test]
+    fn arithmetic_precedence() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(json!(7)));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(json!(9)));
+        assert_eq!(eval("10 - 4 - 3"), Ok(json!(3)));
+        assert_eq!(eval("7 % 4 * 2"), Ok(json!(6)));
+        assert_eq!(eval("-2 * 3 + 1"), Ok(json!(-5)));
+        assert_eq!(eval("1 / 4"), Ok(json!(0.25)));
+    }
+
+    #[test]
+    fn logical_precedence_and_short_circuit() {
+        assert_eq!(eval("true || false && false"), Ok(json!(true)));
+        assert_eq!(eval("(true || false) && false"), Ok(json!(false)));
+        assert_eq!(eval("1 + 1 == 2 && 3 > 2"), Ok(json!(true)));
+        assert_eq!(eval("!false && !(1 > 2)"), Ok(json!(true)));
+        // The right side would fail on a number, but is never evaluated
+        assert_eq!(eval("false && value.name > 1"), Ok(json!(false)));
+        assert_eq!(eval("true || value.name > 1"), Ok(json!(true)));
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(eval("1 == 1.0"), Ok(json!(true)));
+        assert_eq!(eval("\"abc\" < \"abd\""), Ok(json!(true)));
+        assert_eq!(eval("2 in [1, 2, 3]"), Ok(json!(true)));
+        assert_eq!(eval("\"pct\" in value.battery"), Ok(json!(true)));
+        assert_eq!(eval("\"ove\" in value.name"), Ok(json!(true)));
+        assert_eq!(eval("encoding != 'text/plain'"), Ok(json!(true)));
+    }
+
+    #[test]
+    fn fields_and_indices() {
+        assert_eq!(eval("value.battery.pct < 20"), Ok(json!(true)));
+        assert_eq!(eval("value.points[0].x"), Ok(json!(-2.5)));
+        assert_eq!(eval("value[\"name\"]"), Ok(json!("rover")));
+        assert_eq!(eval("value.points[1 + 0].x >= 1"), Ok(json!(true)));
+        // Missing or mistyped paths are null rather than errors
+        assert_eq!(eval("value.missing.deeper"), Ok(Value::Null));
+        assert_eq!(eval("value.points[5]"), Ok(Value::Null));
+        assert_eq!(eval("value.name.first"), Ok(Value::Null));
+        assert_eq!(eval("value.points[\"x\"]"), Ok(Value::Null));
+    }
+
+    #[test]
+    fn functions_and_methods() {
+        assert_eq!(
+            eval("has(value.battery) && !has(value.error)"),
+            Ok(json!(true))
+        );
+        assert_eq!(eval("size(value.points) + size(value.name)"), Ok(json!(7)));
+        assert_eq!(eval("abs(value.points[0].x)"), Ok(json!(2.5)));
+        assert_eq!(eval("sqrt(16)"), Ok(json!(4)));
+        assert_eq!(eval("number(\" 42 \") + 1"), Ok(json!(43)));
+        assert_eq!(eval("string(bytes)"), Ok(json!("64")));
+        assert_eq!(eval("starts_with(key, \"robot/\")"), Ok(json!(true)));
+        assert_eq!(eval("key.ends_with(\"/state\")"), Ok(json!(true)));
+        assert_eq!(eval("value.name.contains(\"ov\")"), Ok(json!(true)));
+    }
+
+    #[test]
+    fn string_literals() {
+        assert_eq!(eval(r#""a\"b" + 'c\'d'"#), Ok(json!("a\"bc'd")));
+        assert_eq!(eval(r#"size("tab\there")"#), Ok(json!(8)));
+    }
+
+    #[test]
+    fn malformed_input_is_reported() {
+        assert_eq!(
+            error("1 +"),
+            "invalid expression: unexpected end of expression"
+        );
+        assert_eq!(
+            error("(1 + 2"),
+            "invalid expression: expected ) at end of expression"
+        );
+        assert_eq!(
+            error("value.points[0"),
+            "invalid expression: expected ] at end of expression"
+        );
+        assert_eq!(error("1 2"), "invalid expression: unexpected 2");
+        assert_eq!(
+            error("1 # 2"),
+            "invalid expression: unexpected character: #"
+        );
+        assert_eq!(error("\"open"), "invalid expression: unterminated string");
+        assert_eq!(error("1.2.3"), "invalid expression: invalid number: 1.2.3");
+        assert_eq!(
+            error("value."),
+            "invalid expression: expected a field name after ."
+        );
+        assert_eq!(
+            error("[1, 2"),
+            "invalid expression: expected , at end of expression"
+        );
+        assert_eq!(
+            error("speed > 1"),
+            "invalid expression: unknown variable: speed \
+             (expected one of key, value, encoding, bytes, timestamp_ms)"
+        );
+        assert_eq!(error("avg(1)"), "invalid expression: unknown function: avg");
+        assert_eq!(
+            error("size(1, 2)"),
+            "invalid expression: size takes 1 argument(s), got 2"
+        );
+    }
+
+    #[test]
+    fn type_errors_are_reported_at_eval() {
+        assert_eq!(error("value.name > 1"), "expected number, got string");
+        assert_eq!(error("!value.name"), "expected bool, got string");
+        assert_eq!(error("value.battery && true"), "expected bool, got map");
+        assert_eq!(error("1 / 0"), "division by zero");
+        assert_eq!(
+            error("1 in 2"),
+            "in needs a list, map or string, got number"
+        );
+        assert_eq!(
+            error("size(bytes)"),
+            "size needs a string, list or map, got number"
+        );
+        assert_eq!(error("number(\"x\")"), "not a number: \"x\"");
+    }
+
+    #[test]
+    fn custom_variables() {
+        let expr = Expression::parse_with("a + b", &["a", "b"]).unwrap();
+        assert_eq!(expr.eval(&json!({"a": 1, "b": 2})), Ok(json!(3)));
+        assert_eq!(expr.source(), "a + b");
+        assert!(Expression::parse_with("key", &["a"]).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::backend::{GetOptions, ZenohBackend};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Origin reported for this instance's own topics and session.
+pub const LOCAL_ORIGIN: &str = "local";
+
+/// Most `get_topics` pages read from one peer, in case its continuation
+/// never ends.
+const MAX_TOPIC_PAGES: usize = 100;
+
+/// Another extension instance, read through the admin queryable it serves
+/// (`admin_key_expr` at its initialize).
+pub struct Peer {
+    /// The peer's admin prefix, as it passed it to initialize.
+    pub admin_key_expr: String,
+    pub locator: Option<String>,
+    /// Client session into the peer's own network, when it was added with
+    /// a locator; otherwise it is reached through this instance's session.
+    pub session: Option<Arc<dyn ZenohBackend>>,
+    pub added_at: DateTime<Utc>,
+    pub last_contact: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// The peer session's zid, from its last `session_info`.
+    pub zid: Option<String>,
+}
+
+impl Peer {
+    /// The session reaching this peer.
+    pub fn session(&self, local: &Arc<dyn ZenohBackend>) -> Arc<dyn ZenohBackend> {
+        self.session.clone().unwrap_or_else(|| local.clone())
+    }
+
+    /// Note how the last call to the peer went.
+    pub fn contacted<T>(&mut self, result: &Result<T, String>) {
+        match result {
+            Ok(_) => {
+                self.last_contact = Some(Utc::now());
+                self.last_error = None;
+            }
+            Err(e) => self.last_error = Some(e.clone()),
+        }
+    }
+}
+
+/// Run `operation` on the instance serving `admin_key_expr` and unwrap its
+/// `{"success", "data" | "error"}` reply.
+pub async fn call(
+    session: &dyn ZenohBackend,
+    admin_key_expr: &str,
+    operation: &str,
+    input: &Value,
+    timeout: Duration,
+) -> Result<Value, String> {
+    let key_expr = format!("{admin_key_expr}/{operation}");
+    let options = GetOptions {
+        payload: Some(serde_json::to_vec(input).unwrap()),
+        ..Default::default()
+    };
+    let reply = session
+        .get(&key_expr, options, timeout)
+        .await
+        .map_err(|e| format!("query {key_expr} failed: {e}"))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("no reply from {key_expr} within {}ms", timeout.as_millis()))?;
+    let sample = reply
+        .result
+        .map_err(|e| format!("{key_expr} replied with error: {e}"))?;
+    let mut body: Value = serde_json::from_slice(&sample.payload)
+        .map_err(|e| format!("{key_expr} replied with invalid JSON: {e}"))?;
+    match body["success"].as_bool() {
+        Some(true) => Ok(body["data"].take()),
+        _ => Err(match body["error"].as_str() {
+            Some(error) => format!("{operation} failed on peer: {error}"),
+            None => format!("{key_expr} replied without success or error"),
+        }),
+    }
+}
+
+/// Every topic a peer's discovery lists under `prefix`, following
+/// continuations when its responses are size-limited.
+pub async fn topics(
+    session: &dyn ZenohBackend,
+    admin_key_expr: &str,
+    prefix: &str,
+    timeout: Duration,
+) -> Result<Vec<Value>, String> {
+    let mut topics = Vec::new();
+    let mut input = serde_json::json!({ "prefix": prefix });
+    for _ in 0..MAX_TOPIC_PAGES {
+        let mut page = call(session, admin_key_expr, "get_topics", &input, timeout).await?;
+        if let Value::Array(listed) = page["topics"].take() {
+            topics.extend(listed);
+        }
+        match page["continuation"].take() {
+            Value::String(token) => input["continuation"] = Value::String(token),
+            _ => return Ok(topics),
+        }
+    }
+    Err(format!(
+        "{admin_key_expr} kept paging get_topics past {MAX_TOPIC_PAGES} pages"
+    ))
+}
@@ -0,0 +1,439 @@
+use crate::backend::ZenohBackend;
+use crate::namespace;
+use crate::recordings::{Cipher, Record};
+use crate::redaction;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Segment files the ring is split into; the oldest is deleted on rotation,
+/// so between `SEGMENTS - 1` and `SEGMENTS` segments' worth of data is kept.
+const SEGMENTS: u64 = 8;
+/// How often buffered records are flushed and the index rewritten, which
+/// bounds what a crash can lose.
+const CHECKPOINT_EVERY: Duration = Duration::from_secs(1);
+/// Index file in the ring directory.
+pub const INDEX_NAME: &str = "index.json";
+
+/// Segments of a ring and how much of each was on disk at the last
+/// checkpoint, oldest first. Rewritten atomically, so it is always whole.
+#[derive(Serialize, Deserialize)]
+pub struct RingIndex {
+    pub updated: DateTime<Utc>,
+    pub segments: Vec<IndexedSegment>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IndexedSegment {
+    pub name: String,
+    pub bytes: u64,
+    pub records: u64,
+}
+
+/// Size-bounded circular log of NDJSON [`Record`]s split into segment files.
+/// Owned by its writer thread, so no file I/O happens on the async runtime.
+struct Ring {
+    dir: PathBuf,
+    segment_bytes: u64,
+    /// Every segment on disk, the last being written.
+    segments: VecDeque<IndexedSegment>,
+    file: BufWriter<File>,
+    next_segment: u64,
+    /// Records appended since the last checkpoint.
+    unflushed: u64,
+    records: u64,
+    write_errors: u64,
+    oldest: Option<DateTime<Utc>>,
+    /// Seals each record when the recordings are encrypted.
+    cipher: Option<Cipher>,
+}
+
+impl Ring {
+    fn create(dir: PathBuf, max_bytes: u64, cipher: Option<Cipher>) -> Result<Self, String> {
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+        let first = dir.join(segment_name(0));
+        let file = File::create(&first)
+            .map_err(|e| format!("failed to create {}: {e}", first.display()))?;
+        let mut ring = Self {
+            dir,
+            segment_bytes: (max_bytes / SEGMENTS).max(1),
+            segments: VecDeque::from([IndexedSegment {
+                name: segment_name(0),
+                bytes: 0,
+                records: 0,
+            }]),
+            file: BufWriter::new(file),
+            next_segment: 1,
+            unflushed: 0,
+            records: 0,
+            write_errors: 0,
+            oldest: None,
+            cipher,
+        };
+        ring.checkpoint()?;
+        Ok(ring)
+    }
+
+    fn current(&mut self) -> &mut IndexedSegment {
+        self.segments
+            .back_mut()
+            .expect("a ring always has a segment")
+    }
+
+    fn append(&mut self, record: &Record) {
+        let line = record.to_line(self.cipher.as_ref());
+        let written = self.current().bytes;
+        if written > 0 && written + line.len() as u64 > self.segment_bytes {
+            if let Err(e) = self.rotate() {
+                eprintln!("flight recorder: {e}");
+                self.write_errors += 1;
+                return;
+            }
+        }
+        match self.file.write_all(&line) {
+            Ok(()) => {
+                let current = self.current();
+                current.bytes += line.len() as u64;
+                current.records += 1;
+                self.unflushed += 1;
+                self.records += 1;
+                self.oldest.get_or_insert(record.timestamp);
+            }
+            Err(_) => self.write_errors += 1,
+        }
+    }
+
+    /// Start a new segment, deleting the oldest once the ring is full.
+    fn rotate(&mut self) -> Result<(), String> {
+        let _ = self.file.flush();
+        let name = segment_name(self.next_segment);
+        let path = self.dir.join(&name);
+        let file =
+            File::create(&path).map_err(|e| format!("failed to create {}: {e}", path.display()))?;
+        self.file = BufWriter::new(file);
+        self.segments.push_back(IndexedSegment {
+            name,
+            bytes: 0,
+            records: 0,
+        });
+        self.next_segment += 1;
+        if self.segments.len() as u64 > SEGMENTS {
+            if let Some(oldest) = self.segments.pop_front() {
+                let _ = std::fs::remove_file(self.dir.join(oldest.name));
+            }
+            self.oldest = self
+                .segments
+                .front()
+                .and_then(|s| first_timestamp(&self.dir.join(&s.name), self.cipher.as_ref()));
+        }
+        self.checkpoint()
+    }
+
+    /// Flush buffered records and rewrite the index to match, through a
+    /// temporary file so a crash mid-write leaves the previous index.
+    fn checkpoint(&mut self) -> Result<(), String> {
+        self.file
+            .flush()
+            .map_err(|e| format!("failed to flush flight recorder: {e}"))?;
+        self.unflushed = 0;
+        let index = RingIndex {
+            updated: Utc::now(),
+            segments: self.segments.iter().cloned().collect(),
+        };
+        let path = self.dir.join(INDEX_NAME);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&index).unwrap())
+            .and_then(|()| std::fs::rename(&tmp, &path))
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+
+    fn stats(&self) -> RingStats {
+        RingStats {
+            disk_bytes: self.segments.iter().map(|s| s.bytes).sum(),
+            records: self.records,
+            write_errors: self.write_errors,
+            oldest: self.oldest,
+        }
+    }
+
+    /// Segment files oldest first.
+    fn segment_paths(&self) -> Vec<PathBuf> {
+        self.segments
+            .iter()
+            .map(|s| self.dir.join(&s.name))
+            .collect()
+    }
+}
+
+fn segment_name(index: u64) -> String {
+    format!("segment-{index:08}.ndjson")
+}
+
+fn first_timestamp(path: &Path, cipher: Option<&Cipher>) -> Option<DateTime<Utc>> {
+    let mut line = Vec::new();
+    BufReader::new(File::open(path).ok()?)
+        .read_until(b'\n', &mut line)
+        .ok()?;
+    Record::from_line(&line, cipher)
+        .ok()
+        .flatten()
+        .map(|r| r.timestamp)
+}
+
+/// Ring usage as of the last record written.
+#[derive(Clone, Default)]
+pub struct RingStats {
+    /// Bytes currently held on disk across all segments.
+    pub disk_bytes: u64,
+    pub records: u64,
+    pub write_errors: u64,
+    pub oldest: Option<DateTime<Utc>>,
+}
+
+enum Command {
+    Append(Record),
+    /// Checkpoint, then reply with the segment files oldest first.
+    Snapshot(mpsc::Sender<Result<Vec<PathBuf>, String>>),
+    /// Close the ring and delete its directory.
+    Stop,
+}
+
+/// Handle on a ring run by its writer thread. Sending never blocks, so
+/// sample tasks can append from the runtime; [`dump`] and [`records`] wait
+/// on the thread and belong in `spawn_blocking`.
+#[derive(Clone)]
+pub struct RingHandle {
+    commands: mpsc::Sender<Command>,
+    stats: Arc<Mutex<RingStats>>,
+    cipher: Option<Cipher>,
+}
+
+impl RingHandle {
+    fn spawn(ring: Ring) -> Self {
+        let (commands, rx) = mpsc::channel();
+        let stats = Arc::new(Mutex::new(ring.stats()));
+        let cipher = ring.cipher.clone();
+        std::thread::spawn({
+            let stats = stats.clone();
+            move || write_ring(ring, rx, stats)
+        });
+        Self {
+            commands,
+            stats,
+            cipher,
+        }
+    }
+
+    fn append(&self, record: Record) {
+        let _ = self.commands.send(Command::Append(record));
+    }
+
+    pub fn stats(&self) -> RingStats {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+/// Apply `commands` to `ring` until stopped, checkpointing every
+/// [`CHECKPOINT_EVERY`] while records arrive.
+fn write_ring(mut ring: Ring, commands: mpsc::Receiver<Command>, stats: Arc<Mutex<RingStats>>) {
+    let mut checkpointed = Instant::now();
+    loop {
+        let wait = CHECKPOINT_EVERY.saturating_sub(checkpointed.elapsed());
+        match commands.recv_timeout(wait) {
+            Ok(Command::Append(record)) => ring.append(&record),
+            Ok(Command::Snapshot(reply)) => {
+                let _ = reply.send(ring.checkpoint().map(|()| ring.segment_paths()));
+                checkpointed = Instant::now();
+            }
+            Ok(Command::Stop) => {
+                let dir = ring.dir.clone();
+                drop(ring);
+                if let Err(e) = std::fs::remove_dir_all(&dir) {
+                    eprintln!("flight recorder: failed to remove {}: {e}", dir.display());
+                }
+                return;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                let _ = ring.checkpoint();
+                return;
+            }
+        }
+        if checkpointed.elapsed() >= CHECKPOINT_EVERY {
+            if ring.unflushed > 0 {
+                if let Err(e) = ring.checkpoint() {
+                    eprintln!("flight recorder: {e}");
+                    ring.write_errors += 1;
+                }
+            }
+            checkpointed = Instant::now();
+        }
+        *stats.lock().unwrap() = ring.stats();
+    }
+}
+
+pub struct FlightRecorder {
+    pub key_exprs: Vec<String>,
+    pub max_bytes: u64,
+    pub ring: RingHandle,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FlightRecorder {
+    /// Stop recording and have the writer thread delete the ring's segment
+    /// files once it has finished what was queued.
+    pub fn stop(self) {
+        let _ = self.cancel.send(true);
+        let _ = self.ring.commands.send(Command::Stop);
+    }
+}
+
+/// Subscribe to every key expression and append their samples to a ring of
+/// at most `max_bytes` under `dir`. `key_exprs` are host-facing; `namespace`
+/// is applied on the wire and stripped from recorded keys.
+pub async fn start_flight_recorder(
+    session: Arc<dyn ZenohBackend>,
+    key_exprs: Vec<String>,
+    namespace: Option<String>,
+    dir: PathBuf,
+    max_bytes: u64,
+    cipher: Option<Cipher>,
+) -> Result<FlightRecorder, String> {
+    let mut streams = Vec::with_capacity(key_exprs.len());
+    for key_expr in &key_exprs {
+        let scoped = namespace::scope(namespace.as_deref(), key_expr);
+        streams.push(session.subscribe(&scoped).await?);
+    }
+
+    let ring = tokio::task::spawn_blocking(move || Ring::create(dir, max_bytes, cipher))
+        .await
+        .map_err(|e| format!("flight recorder setup failed: {e}"))??;
+    let ring = RingHandle::spawn(ring);
+    let (cancel, cancel_rx) = watch::channel(false);
+    for mut stream in streams {
+        let (ring, namespace, mut cancel_rx) = (ring.clone(), namespace.clone(), cancel_rx.clone());
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    sample = stream.recv() => {
+                        let Some(sample) = sample else { break };
+                        let key_expr = namespace::strip(namespace.as_deref(), &sample.key_expr);
+                        let payload = redaction::redact(&key_expr, sample.payload);
+                        let record = Record::new(key_expr, sample.encoding, &payload);
+                        ring.append(record);
+                    }
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(FlightRecorder {
+        key_exprs,
+        max_bytes,
+        ring,
+        cancel,
+        created_at: Utc::now(),
+    })
+}
+
+/// What [`dump`] copied out of the ring.
+pub struct DumpSummary {
+    pub records: u64,
+    pub bytes: u64,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+}
+
+/// Copy every record at or after `since` into a new file at `out`,
+/// encrypted as the ring is.
+pub fn dump(ring: &RingHandle, since: DateTime<Utc>, out: &Path) -> Result<DumpSummary, String> {
+    let (segments, cipher) = snapshot(ring)?;
+
+    let file = File::create(out).map_err(|e| format!("failed to create {}: {e}", out.display()))?;
+    let mut writer = BufWriter::new(file);
+    let mut summary = DumpSummary {
+        records: 0,
+        bytes: 0,
+        first: None,
+        last: None,
+    };
+    for segment in segments {
+        // Rotation may have deleted the oldest segment since the snapshot
+        let Ok(file) = File::open(&segment) else {
+            continue;
+        };
+        for line in BufReader::new(file).split(b'\n') {
+            let Ok(line) = line else { break };
+            let Some(record) = ring_record(&line, cipher.as_ref()) else {
+                continue;
+            };
+            if record.timestamp < since {
+                continue;
+            }
+            writer
+                .write_all(&line)
+                .and_then(|()| writer.write_all(b"\n"))
+                .map_err(|e| format!("failed to write {}: {e}", out.display()))?;
+            summary.records += 1;
+            summary.bytes += line.len() as u64 + 1;
+            summary.first.get_or_insert(record.timestamp);
+            summary.last = Some(record.timestamp);
+        }
+    }
+    writer
+        .flush()
+        .map_err(|e| format!("failed to write {}: {e}", out.display()))?;
+    Ok(summary)
+}
+
+/// Every record `keep` accepts, oldest first.
+pub fn records(ring: &RingHandle, keep: impl Fn(&Record) -> bool) -> Result<Vec<Record>, String> {
+    let (segments, cipher) = snapshot(ring)?;
+    let mut records = Vec::new();
+    for segment in segments {
+        // Rotation may have deleted the oldest segment since the snapshot
+        let Ok(file) = File::open(&segment) else {
+            continue;
+        };
+        for line in BufReader::new(file).split(b'\n') {
+            let Ok(line) = line else { break };
+            match ring_record(&line, cipher.as_ref()) {
+                Some(record) if keep(&record) => records.push(record),
+                _ => {}
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// The ring's segment files after a checkpoint, and its cipher. Blocks
+/// until the writer thread has caught up.
+fn snapshot(ring: &RingHandle) -> Result<(Vec<PathBuf>, Option<Cipher>), String> {
+    let (reply, segments) = mpsc::channel();
+    let stopped = || "flight recorder stopped".to_string();
+    ring.commands
+        .send(Command::Snapshot(reply))
+        .map_err(|_| stopped())?;
+    let segments = segments.recv().map_err(|_| stopped())??;
+    Ok((segments, ring.cipher.clone()))
+}
+
+/// A record of a ring line. The ring sealed its own lines, so one that
+/// does not open is damaged and skipped like any other bad line.
+fn ring_record(line: &[u8], cipher: Option<&Cipher>) -> Option<Record> {
+    Record::from_line(line, cipher).ok().flatten()
+}
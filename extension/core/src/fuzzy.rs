@@ -0,0 +1,109 @@
+/// Characters after which a new word of a key or field starts.
+const BOUNDARIES: [char; 5] = ['/', '.', '_', '-', ' '];
+
+/// Best subsequence score, below the worst substring score of 400.
+const MAX_SUBSEQUENCE: i64 = 399;
+
+fn starts_word(chars: &[char], i: usize) -> bool {
+    i == 0 || BOUNDARIES.contains(&chars[i - 1])
+}
+
+/// How well `term` matches `candidate`, ignoring case; `None` when it does
+/// not. Exact matches beat prefixes, which beat substrings, which beat the
+/// term's characters appearing in order. Within each, matches at a word
+/// start and shorter candidates rank higher.
+pub fn score(term: &str, candidate: &str) -> Option<i64> {
+    let term = term.to_lowercase();
+    let lower = candidate.to_lowercase();
+    if term.is_empty() {
+        return None;
+    }
+    // Shorter candidates are closer to what was typed
+    let length = (lower.chars().count() as i64).min(200) / 4;
+    if lower == term {
+        return Some(1000);
+    }
+    if lower.starts_with(&term) {
+        return Some(800 - length);
+    }
+    let chars: Vec<char> = lower.chars().collect();
+    if let Some(at) = lower.find(&term) {
+        let at = lower[..at].chars().count();
+        let bonus = match starts_word(&chars, at) {
+            true => 100,
+            false => 0,
+        };
+        return Some(500 + bonus - (at as i64).min(100) / 2 - length);
+    }
+    subsequence(&term, &chars).map(|s| s - length)
+}
+
+/// Score for the characters of `term` found in order in `chars`, taking each
+/// as early as possible. Runs of adjacent characters and word starts earn
+/// points; skipped characters cost them.
+fn subsequence(term: &str, chars: &[char]) -> Option<i64> {
+    let mut score = 100;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for c in term.chars() {
+        let at = next + chars[next..].iter().position(|&x| x == c)?;
+        if previous.is_some_and(|p| p + 1 == at) {
+            score += 15;
+        }
+        if starts_word(chars, at) {
+            score += 20;
+        }
+        score -= (at - next) as i64;
+        previous = Some(at);
+        next = at + 1;
+    }
+    Some(score.clamp(1, MAX_SUBSEQUENCE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_beats_prefix_beats_substring_beats_subsequence() {
+        let exact = score("pose", "Pose").unwrap();
+        let prefix = score("rob", "robot/pose").unwrap();
+        let substring = score("pose", "robot/pose").unwrap();
+        let subsequence = score("rp", "robot/pose").unwrap();
+        assert_eq!(exact, 1000);
+        assert_eq!(prefix, 798);
+        assert_eq!(substring, 595);
+        assert_eq!(subsequence, 133);
+    }
+
+    #[test]
+    fn far_substring_still_beats_subsequence() {
+        let far = format!("{}pose", "x".repeat(190));
+        let substring = score("pose", &far).unwrap();
+        let subsequence = score(
+            "robotposestampedcovariance",
+            "robot_pose_stamped_covariance",
+        )
+        .unwrap();
+        assert!(substring > subsequence, "{substring} <= {subsequence}");
+    }
+
+    #[test]
+    fn word_starts_rank_higher() {
+        assert!(score("pose", "robot/pose") > score("pose", "robotpose"));
+        assert!(score("rp", "robot/pose") > score("rp", "rxxxpxxxxx"));
+    }
+
+    #[test]
+    fn shorter_candidates_rank_higher() {
+        assert!(score("rob", "robot") > score("rob", "robot/pose/x"));
+        assert!(score("pose", "a/pose") > score("pose", "a/pose/covariance"));
+    }
+
+    #[test]
+    fn misses_and_empty_terms_do_not_match() {
+        assert_eq!(score("xyz", "robot/pose"), None);
+        assert_eq!(score("pr", "robot"), None);
+        assert_eq!(score("", "robot"), None);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::backend::ZenohBackend;
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// Content of each synthetic payload.
+#[derive(Clone, Copy)]
+pub enum Pattern {
+    Zeros,
+    Random,
+    /// Big-endian u64 sequence number, zero-padded to the payload size.
+    Counter,
+    /// `{"seq":N,"stamp":unix_secs,"pad":"..."}` padded to the payload size.
+    Json,
+}
+
+impl Pattern {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "zeros" => Ok(Pattern::Zeros),
+            "random" => Ok(Pattern::Random),
+            "counter" => Ok(Pattern::Counter),
+            "json" => Ok(Pattern::Json),
+            other => Err(format!("unsupported pattern: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Pattern::Zeros => "zeros",
+            Pattern::Random => "random",
+            Pattern::Counter => "counter",
+            Pattern::Json => "json",
+        }
+    }
+
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Pattern::Json => "application/json",
+            _ => "zenoh/bytes",
+        }
+    }
+
+    /// Payload number `seq`; never shorter than the pattern needs.
+    fn payload(&self, seq: u64, size: usize, rng: &mut XorShift) -> Vec<u8> {
+        match self {
+            Pattern::Zeros => vec![0; size],
+            Pattern::Random => (0..size).map(|_| rng.next() as u8).collect(),
+            Pattern::Counter => {
+                let mut payload = seq.to_be_bytes().to_vec();
+                payload.resize(size.max(8), 0);
+                payload
+            }
+            Pattern::Json => {
+                let stamp = Utc::now().timestamp_micros() as f64 / 1e6;
+                let head = format!("{{\"seq\":{seq},\"stamp\":{stamp},\"pad\":\"");
+                let pad = size.saturating_sub(head.len() + 2);
+                format!("{head}{}\"}}", "x".repeat(pad)).into_bytes()
+            }
+        }
+    }
+}
+
+/// Cheap non-cryptographic bytes for the `random` pattern and jitter.
+struct XorShift(u64);
+
+impl XorShift {
+    fn seeded() -> Self {
+        Self(Utc::now().timestamp_nanos_opt().unwrap_or(1) as u64 | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform in `[-max, max]`.
+    fn jitter(&mut self, max: Duration) -> f64 {
+        let unit = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+        (unit * 2.0 - 1.0) * max.as_secs_f64()
+    }
+}
+
+/// What a generator publishes and how often.
+#[derive(Clone)]
+pub struct GeneratorConfig {
+    pub pattern: Pattern,
+    pub payload_size: usize,
+    pub rate_hz: f64,
+    /// Each interval is moved by up to this much either way.
+    pub jitter: Duration,
+    /// Stop after this many samples; unbounded when `None`.
+    pub count: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct GeneratorStats {
+    pub published: AtomicU64,
+    pub errors: AtomicU64,
+    pub finished: AtomicBool,
+}
+
+pub struct Generator {
+    /// Host-facing key expression, without the session namespace.
+    pub key_expr: String,
+    pub config: GeneratorConfig,
+    pub stats: Arc<GeneratorStats>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Publish synthetic payloads on `scoped_key_expr` at the configured rate
+/// until `count` is reached or the generator is cancelled. Send times are
+/// scheduled from the start, so slow puts don't lower the average rate.
+pub fn start_generator(
+    session: Arc<dyn ZenohBackend>,
+    key_expr: String,
+    scoped_key_expr: String,
+    config: GeneratorConfig,
+) -> Generator {
+    let stats: Arc<GeneratorStats> = Arc::default();
+    let (cancel, mut cancel_rx) = watch::channel(false);
+    {
+        let (config, stats) = (config.clone(), stats.clone());
+        tokio::spawn(async move {
+            let mut rng = XorShift::seeded();
+            let start = Instant::now();
+            let mut seq = 0u64;
+            while config.count.is_none_or(|count| seq < count) {
+                let payload = config.pattern.payload(seq, config.payload_size, &mut rng);
+                let put = session.put(&scoped_key_expr, payload, config.pattern.encoding());
+                let counter = match put.await {
+                    Ok(()) => &stats.published,
+                    Err(_) => &stats.errors,
+                };
+                counter.fetch_add(1, Ordering::Relaxed);
+                seq += 1;
+
+                let offset = seq as f64 / config.rate_hz + rng.jitter(config.jitter);
+                let next = start + Duration::from_secs_f64(offset.max(0.0));
+                tokio::select! {
+                    _ = tokio::time::sleep_until(next) => {}
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+            stats.finished.store(true, Ordering::Relaxed);
+        });
+    }
+
+    Generator {
+        key_expr,
+        config,
+        stats,
+        cancel,
+        created_at: Utc::now(),
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Width of one history bucket.
+pub const BUCKET_SECS: i64 = 10;
+/// Buckets kept: one hour.
+const BUCKETS: usize = 360;
+
+#[derive(Clone)]
+struct Bucket {
+    start: DateTime<Utc>,
+    samples: u64,
+    bytes: u64,
+}
+
+/// Sample and byte counts in fixed time buckets over the last hour.
+#[derive(Clone, Default)]
+pub struct History {
+    buckets: VecDeque<Bucket>,
+}
+
+/// One bucket as reported by `get_history`.
+#[derive(Serialize, JsonSchema)]
+pub struct HistoryPoint {
+    pub start: String,
+    pub samples: u64,
+    pub bytes: u64,
+    pub rate_hz: f64,
+    pub bytes_per_sec: f64,
+}
+
+fn bucket_start(at: DateTime<Utc>) -> DateTime<Utc> {
+    let secs = at.timestamp().div_euclid(BUCKET_SECS) * BUCKET_SECS;
+    DateTime::from_timestamp(secs, 0).unwrap_or(at)
+}
+
+impl History {
+    pub fn record(&mut self, at: DateTime<Utc>, bytes: u64) {
+        let start = bucket_start(at);
+        match self.buckets.back_mut() {
+            Some(bucket) if bucket.start == start => {
+                bucket.samples += 1;
+                bucket.bytes += bytes;
+            }
+            _ => {
+                self.buckets.push_back(Bucket {
+                    start,
+                    samples: 1,
+                    bytes,
+                });
+            }
+        }
+        let horizon = start - TimeDelta::seconds(BUCKET_SECS * BUCKETS as i64);
+        while self.buckets.front().is_some_and(|b| b.start <= horizon) {
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Buckets covering the last `last_secs` up to `now`, oldest first.
+    /// Buckets with no traffic are reported as zero so the series has no
+    /// holes; the current bucket's rates are over its elapsed part only.
+    pub fn points(&self, now: DateTime<Utc>, last_secs: u64) -> Vec<HistoryPoint> {
+        let Some(first) = self.buckets.front() else {
+            return Vec::new();
+        };
+        let current = bucket_start(now);
+        let window = TimeDelta::seconds(last_secs.min(i64::MAX as u64) as i64);
+        let mut start = first.start.max(bucket_start(now - window));
+        let mut recorded = self.buckets.iter().peekable();
+        let mut points = Vec::new();
+        while start <= current {
+            while recorded.next_if(|b| b.start < start).is_some() {}
+            let (samples, bytes) = match recorded.next_if(|b| b.start == start) {
+                Some(bucket) => (bucket.samples, bucket.bytes),
+                None => (0, 0),
+            };
+            let span = match start == current {
+                true => ((now - start).num_milliseconds() as f64 / 1000.0).max(1.0),
+                false => BUCKET_SECS as f64,
+            };
+            points.push(HistoryPoint {
+                start: start.to_rfc3339(),
+                samples,
+                bytes,
+                rate_hz: (samples as f64 / span * 100.0).round() / 100.0,
+                bytes_per_sec: (bytes as f64 / span * 100.0).round() / 100.0,
+            });
+            start += TimeDelta::seconds(BUCKET_SECS);
+        }
+        points
+    }
+}
@@ -0,0 +1,179 @@
+use serde_json::Value;
+
+/// Resolve a simple JSONPath-style expression against a JSON value.
+///
+/// Supports dotted keys and array indices, with or without a leading `$`:
+/// `$.header.seq`, `header.seq`, `points[0].x`, `points.0.x`.
+pub fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = value;
+    for segment in segments(path) {
+        current = match current {
+            Value::Object(map) => map.get(segment)?,
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    let path = path.strip_prefix('$').unwrap_or(path);
+    path.split(['.', '[', ']']).filter(|s| !s.is_empty())
+}
+
+/// A path that may match many places: `lookup`'s syntax plus `*` for any
+/// key or index and `..name` for `name` at any depth, e.g. `$..password`
+/// or `users[*].email`.
+pub struct Pattern {
+    source: String,
+    steps: Vec<Step>,
+}
+
+enum Step {
+    Child(String),
+    Any,
+    Descendant(String),
+}
+
+enum Slot {
+    Key(String),
+    Index(usize),
+}
+
+impl Pattern {
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let normalized = path
+            .strip_prefix('$')
+            .unwrap_or(path)
+            .replace('[', ".")
+            .replace(']', "");
+        let mut steps = Vec::new();
+        let mut descend = false;
+        for (i, segment) in normalized.split('.').enumerate() {
+            match segment {
+                "" if i == 0 => {}
+                "" if descend => return Err(format!("invalid path {path}: too many dots")),
+                "" => descend = true,
+                "*" if descend => {
+                    return Err(format!("invalid path {path}: ..* matches everything"))
+                }
+                "*" => steps.push(Step::Any),
+                name if descend => {
+                    steps.push(Step::Descendant(name.to_string()));
+                    descend = false;
+                }
+                name => steps.push(Step::Child(name.to_string())),
+            }
+        }
+        if descend || steps.is_empty() {
+            return Err(format!("invalid path {path}: expected a key"));
+        }
+        Ok(Self {
+            source: path.to_string(),
+            steps,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Replace every match with `with`. Returns how many there were.
+    pub fn replace(&self, value: &mut Value, with: &Value) -> usize {
+        let mut count = 0;
+        visit(value, &self.steps, &mut |container, slots| {
+            count += slots.len();
+            for slot in slots {
+                if let Some(target) = slot_mut(container, &slot) {
+                    *target = with.clone();
+                }
+            }
+        });
+        count
+    }
+
+    /// Remove every match from its object or array. Returns how many there
+    /// were.
+    pub fn remove(&self, value: &mut Value) -> usize {
+        let mut count = 0;
+        visit(value, &self.steps, &mut |container, slots| {
+            count += slots.len();
+            match container {
+                Value::Object(map) => {
+                    for slot in slots {
+                        if let Slot::Key(key) = slot {
+                            map.remove(&key);
+                        }
+                    }
+                }
+                Value::Array(items) => {
+                    // From the back, so earlier indices stay valid
+                    for slot in slots.into_iter().rev() {
+                        if let Slot::Index(i) = slot {
+                            items.remove(i);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        });
+        count
+    }
+}
+
+/// Call `hit` with each container holding matches of the last step and the
+/// slots in it that match. Deeper matches are visited first, so `hit` may
+/// replace or remove what contains them.
+fn visit(value: &mut Value, steps: &[Step], hit: &mut dyn FnMut(&mut Value, Vec<Slot>)) {
+    let Some((step, rest)) = steps.split_first() else {
+        return;
+    };
+    if let Step::Descendant(_) = step {
+        match value {
+            Value::Object(map) => map.values_mut().for_each(|child| visit(child, steps, hit)),
+            Value::Array(items) => items.iter_mut().for_each(|child| visit(child, steps, hit)),
+            _ => {}
+        }
+    }
+    let slots = slots(value, step);
+    if rest.is_empty() {
+        if !slots.is_empty() {
+            hit(value, slots);
+        }
+        return;
+    }
+    for slot in slots {
+        if let Some(child) = slot_mut(value, &slot) {
+            visit(child, rest, hit);
+        }
+    }
+}
+
+/// Slots of `value` that `step` matches, in ascending index order.
+fn slots(value: &Value, step: &Step) -> Vec<Slot> {
+    match (value, step) {
+        (Value::Object(map), Step::Any) => map.keys().cloned().map(Slot::Key).collect(),
+        (Value::Array(items), Step::Any) => (0..items.len()).map(Slot::Index).collect(),
+        (Value::Object(map), Step::Child(name) | Step::Descendant(name)) => map
+            .contains_key(name)
+            .then(|| Slot::Key(name.clone()))
+            .into_iter()
+            .collect(),
+        (Value::Array(items), Step::Child(name) | Step::Descendant(name)) => name
+            .parse::<usize>()
+            .ok()
+            .filter(|i| *i < items.len())
+            .map(Slot::Index)
+            .into_iter()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn slot_mut<'a>(value: &'a mut Value, slot: &Slot) -> Option<&'a mut Value> {
+    match (value, slot) {
+        (Value::Object(map), Slot::Key(key)) => map.get_mut(key),
+        (Value::Array(items), Slot::Index(i)) => items.get_mut(*i),
+        _ => None,
+    }
+}
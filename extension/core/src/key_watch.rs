@@ -0,0 +1,233 @@
+use crate::backend::ZenohBackend;
+use crate::decode::{self, DecodeFormat};
+use crate::expr::{self, Expression};
+use crate::namespace;
+use crate::notify;
+use crate::redaction;
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// How two values on a key are judged equal.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Compare {
+    /// Deep comparison of the decoded values; object key order is ignored.
+    Value,
+    /// Hash of the raw payload bytes.
+    Hash,
+}
+
+impl Compare {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "value" => Ok(Compare::Value),
+            "hash" => Ok(Compare::Hash),
+            other => Err(format!("unknown compare mode: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compare::Value => "value",
+            Compare::Hash => "hash",
+        }
+    }
+}
+
+/// A key's value changed, or was seen for the first time.
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct ValueChange {
+    /// Per-watch change counter, starting at 0.
+    pub seq: u64,
+    pub key_expr: String,
+    pub timestamp: DateTime<Utc>,
+    /// `None` on the first value seen for the key.
+    pub previous: Option<Value>,
+    pub value: Value,
+}
+
+struct Last {
+    value: Value,
+    hash: u64,
+}
+
+/// Last value per key and the changes not yet polled.
+#[derive(Default)]
+pub struct Changes {
+    last: HashMap<String, Last>,
+    pub events: VecDeque<ValueChange>,
+    pub received: u64,
+    pub changes: u64,
+    /// Changes evicted unpolled because the buffer was full.
+    pub dropped: u64,
+}
+
+impl Changes {
+    pub fn keys(&self) -> usize {
+        self.last.len()
+    }
+
+    /// Record a value, returning the change if it differs from the last one
+    /// on the key.
+    fn observe(
+        &mut self,
+        key_expr: String,
+        value: Value,
+        hash: u64,
+        compare: Compare,
+        capacity: usize,
+    ) -> Option<ValueChange> {
+        self.received += 1;
+        let previous = match self.last.get(&key_expr) {
+            Some(last) => {
+                let unchanged = match compare {
+                    Compare::Value => last.value == value,
+                    Compare::Hash => last.hash == hash,
+                };
+                if unchanged {
+                    return None;
+                }
+                Some(last.value.clone())
+            }
+            None => None,
+        };
+        let change = ValueChange {
+            seq: self.changes,
+            key_expr: key_expr.clone(),
+            timestamp: Utc::now(),
+            previous,
+            value: value.clone(),
+        };
+        self.changes += 1;
+        self.last.insert(key_expr, Last { value, hash });
+        if self.events.len() >= capacity {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back(change.clone());
+        Some(change)
+    }
+}
+
+#[derive(Clone)]
+pub struct KeyWatchConfig {
+    pub compare: Compare,
+    pub decode: Option<DecodeFormat>,
+    /// Only samples this expression matches are compared.
+    pub filter: Option<Expression>,
+    /// Unpolled changes kept; the oldest is evicted beyond this.
+    pub buffer_size: usize,
+    /// Emit a `key_changed` notification for each change.
+    pub notify: bool,
+}
+
+pub struct KeyWatch {
+    /// Host-facing key expression, without the session namespace.
+    pub key_expr: String,
+    pub config: KeyWatchConfig,
+    pub changes: Arc<Mutex<Changes>>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The value compared and reported for a payload: decoded with `decode`
+/// when set, else parsed as JSON text, else the text itself; binary payloads
+/// that decode to nothing are reported as `{"payload_b64": ...}`.
+pub fn payload_value(decode: Option<DecodeFormat>, encoding: &str, payload: &[u8]) -> Value {
+    let decoded = match decode {
+        Some(format) => decode::decode(format, encoding, payload).ok(),
+        None => serde_json::from_slice(payload).ok(),
+    };
+    if let Some(value) = decoded {
+        return value;
+    }
+    match std::str::from_utf8(payload) {
+        Ok(text) => Value::String(text.to_string()),
+        Err(_) => serde_json::json!({
+            "payload_b64": base64::engine::general_purpose::STANDARD.encode(payload),
+        }),
+    }
+}
+
+/// Whether a sample passes an optional watch filter.
+pub fn passes(
+    filter: Option<&Expression>,
+    key: &str,
+    encoding: &str,
+    payload: &[u8],
+    value: &Value,
+) -> bool {
+    filter.is_none_or(|filter| {
+        let now_ms = Utc::now().timestamp_millis();
+        filter.matches(&expr::sample_vars(
+            key,
+            encoding,
+            payload.len(),
+            now_ms,
+            value.clone(),
+        ))
+    })
+}
+
+/// Subscribe to `key_expr` and buffer a change event only when a key's value
+/// differs from the last one seen, so periodically republished state costs
+/// nothing until it actually changes.
+pub async fn start_key_watch(
+    session: Arc<dyn ZenohBackend>,
+    watch_id: String,
+    key_expr: String,
+    config: KeyWatchConfig,
+    namespace: Option<String>,
+) -> Result<KeyWatch, String> {
+    let scoped = namespace::scope(namespace.as_deref(), &key_expr);
+    let mut stream = session.subscribe(&scoped).await?;
+    let changes: Arc<Mutex<Changes>> = Arc::default();
+    let (cancel, mut cancel_rx) = watch::channel(false);
+
+    {
+        let (changes, config) = (changes.clone(), config.clone());
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    sample = stream.recv() => {
+                        let Some(sample) = sample else { break };
+                        let key = namespace::strip(namespace.as_deref(), &sample.key_expr);
+                        let payload = redaction::redact(&key, sample.payload);
+                        let value = payload_value(config.decode, &sample.encoding, &payload);
+                        if !passes(config.filter.as_ref(), &key, &sample.encoding, &payload, &value) {
+                            continue;
+                        }
+                        let hash = xxhash_rust::xxh3::xxh3_64(&payload);
+                        let change = changes
+                            .lock()
+                            .unwrap()
+                            .observe(key, value, hash, config.compare, config.buffer_size);
+                        if let (true, Some(change)) = (config.notify, change) {
+                            let mut event = serde_json::to_value(change).unwrap();
+                            event["watch_id"] = Value::String(watch_id.clone());
+                            notify::notify("key_changed", event);
+                        }
+                    }
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(KeyWatch {
+        key_expr,
+        config,
+        changes,
+        cancel,
+        created_at: Utc::now(),
+    })
+}
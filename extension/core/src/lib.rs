@@ -0,0 +1,567 @@
+//! Zenoh discovery, subscription and diagnostics engine behind the
+//! `zenoh-ext` stdio binary, usable directly from other Rust programs.
+
+pub mod admin;
+pub mod alerts;
+pub mod api;
+pub mod backend;
+pub mod blocks;
+pub mod bridge;
+pub mod cdr;
+pub mod checksum;
+pub mod chunks;
+pub mod clock;
+pub mod compress;
+pub mod continuation;
+pub mod decode;
+pub mod derived;
+pub mod diff;
+pub mod discovery;
+pub mod dump;
+pub mod echo;
+pub mod encodings;
+pub mod export;
+pub mod expr;
+pub mod federation;
+pub mod flight;
+pub mod fuzzy;
+pub mod generator;
+pub mod history;
+pub mod jsonpath;
+pub mod key_watch;
+pub mod metrics;
+pub mod mirror;
+pub mod mock;
+pub mod namespace;
+pub mod notify;
+pub mod ops;
+pub mod params;
+pub mod plugin;
+pub mod preview;
+pub mod probe;
+pub mod profile;
+pub mod query;
+pub mod recordings;
+pub mod redaction;
+pub mod relay;
+pub mod render;
+pub mod ros;
+pub mod router_health;
+pub mod script;
+pub mod seq;
+pub mod skew;
+pub mod spill;
+pub mod state;
+pub mod state_file;
+pub mod storages;
+pub mod subscriber;
+pub mod synchronizer;
+pub mod table;
+pub mod tags;
+pub mod telemetry;
+pub mod template;
+pub mod threshold;
+pub mod timeline;
+pub mod timespec;
+pub mod topology;
+pub mod trace;
+pub mod versions;
+pub mod watchdog;
+
+use api::{parse, to_value};
+use backend::ZenohBackend;
+use blocks::PayloadBlocks;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use state::{AppState, StateLock};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use trace::Timing;
+
+/// How often [`Core::linger`] checks whether background tasks remain.
+const LINGER_CHECK_EVERY: Duration = Duration::from_secs(1);
+
+/// A Zenoh backend plus the shared extension state.
+#[derive(Clone)]
+pub struct Core {
+    pub backend: Arc<dyn ZenohBackend>,
+    pub state: Arc<StateLock>,
+}
+
+impl Core {
+    /// Wrap `backend` with fresh state and start the alert evaluator and
+    /// recordings pruner.
+    /// Must be called inside a Tokio runtime.
+    pub fn new(backend: Arc<dyn ZenohBackend>, state: AppState) -> Self {
+        let backend = Arc::new(trace::TracedBackend(backend));
+        let state = Arc::new(StateLock::new(state));
+        alerts::spawn_evaluator(state.clone());
+        recordings::spawn_pruner(state.clone());
+        Self { backend, state }
+    }
+
+    /// Run on a Zenoh session the host already has open instead of opening
+    /// a second one. The host remains responsible for closing it.
+    /// Must be called inside a Tokio runtime.
+    pub fn attach(session: zenoh::Session, state: AppState) -> Self {
+        Self::new(Arc::new(backend::ZenohSession::attach(session)), state)
+    }
+
+    /// Serve the admin queryable under `prefix`, replacing any previous one.
+    pub async fn serve_admin(&self, prefix: String) -> Result<(), String> {
+        let admin = admin::serve(self.clone(), prefix).await?;
+        self.state.write().await.admin = Some(admin);
+        Ok(())
+    }
+
+    /// Run one named operation, as the JSON-RPC `execute` method does.
+    /// Failures are recorded in `recent_errors`.
+    pub async fn execute(
+        &self,
+        operation: &str,
+        input: &Value,
+        blocks: &mut PayloadBlocks,
+    ) -> Result<Value, String> {
+        self.execute_timed(operation, input, blocks, Instant::now())
+            .await
+            .0
+    }
+
+    /// [`Core::execute`], also reporting where the time went since the
+    /// request was `received`. The request is exported to telemetry when
+    /// configured.
+    pub async fn execute_timed(
+        &self,
+        operation: &str,
+        input: &Value,
+        blocks: &mut PayloadBlocks,
+        received: Instant,
+    ) -> (Result<Value, String>, Timing) {
+        let (result, timing) = trace::traced(received, async {
+            let result = self.dispatch(operation, input, blocks).await;
+            if let Err(msg) = &result {
+                self.state
+                    .write()
+                    .await
+                    .record_error(operation, msg.clone());
+            }
+            result
+        })
+        .await;
+        telemetry::record_request(
+            operation,
+            result.as_ref().err().map(String::as_str),
+            &timing,
+        );
+        (result, timing)
+    }
+
+    async fn dispatch(
+        &self,
+        operation: &str,
+        input: &Value,
+        blocks: &mut PayloadBlocks,
+    ) -> Result<Value, String> {
+        let (session, state) = (&self.backend, &self.state);
+        if state.read().await.read_only && ops::MUTATING_OPERATIONS.contains(&operation) {
+            return Err(format!("{operation} is disabled in read-only mode"));
+        }
+
+        match operation {
+            "describe_api" => to_value(api::describe()),
+            "session_info" => {
+                to_value(ops::op_session_info(parse(input)?, session.as_ref()).await?)
+            }
+            "get_config" => to_value(ops::op_get_config(parse(input)?, session.as_ref())?),
+            "update_config" => to_value(ops::op_update_config(parse(input)?, session.as_ref())?),
+            "list_endpoints" => to_value(ops::op_list_endpoints(session.as_ref()).await?),
+            "add_endpoint" => {
+                to_value(ops::op_add_endpoint(parse(input)?, session.as_ref()).await?)
+            }
+            "remove_endpoint" => {
+                to_value(ops::op_remove_endpoint(parse(input)?, session.as_ref()).await?)
+            }
+            "list_storages" => to_value(
+                ops::op_list_storages(parse(input)?, session.as_ref(), state.clone()).await?,
+            ),
+            "get_router_health" => to_value(
+                ops::op_get_router_health(parse(input)?, session.as_ref(), state.clone()).await?,
+            ),
+            "get_topology" => {
+                to_value(ops::op_get_topology(parse(input)?, session.as_ref()).await?)
+            }
+            "watch_topology" => to_value(
+                ops::op_watch_topology(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "poll_topology_watch" => {
+                to_value(ops::op_poll_topology_watch(parse(input)?, state.clone()).await?)
+            }
+            "list_topology_watches" => {
+                to_value(ops::op_list_topology_watches(state.clone()).await?)
+            }
+            "unwatch_topology" => {
+                to_value(ops::op_unwatch_topology(parse(input)?, state.clone()).await?)
+            }
+            "create_storage" => to_value(
+                ops::op_create_storage(parse(input)?, session.as_ref(), state.clone()).await?,
+            ),
+            "delete_storage" => {
+                to_value(ops::op_delete_storage(parse(input)?, session.as_ref()).await?)
+            }
+            "start_discovery" => to_value(
+                ops::op_start_discovery(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "stop_discovery" => to_value(ops::op_stop_discovery(state.clone()).await?),
+            "pin_topic" => to_value(ops::op_pin_topic(parse(input)?, state.clone()).await?),
+            "unpin_topic" => to_value(ops::op_unpin_topic(parse(input)?, state.clone()).await?),
+            "tag_topic" => to_value(ops::op_tag_topic(parse(input)?, state.clone()).await?),
+            "list_tags" => to_value(ops::op_list_tags(parse(input)?, state.clone()).await?),
+            "pause_discovery" => to_value(ops::op_pause_discovery(state.clone()).await?),
+            "resume_discovery" => {
+                to_value(ops::op_resume_discovery(session.clone(), state.clone()).await?)
+            }
+            "get_topics" => to_value(ops::op_get_topics(parse(input)?, state.clone()).await?),
+            "get_topic" => to_value(ops::op_get_topic(parse(input)?, state.clone()).await?),
+            "complete_key_expr" => {
+                to_value(ops::op_complete_key_expr(parse(input)?, state.clone()).await?)
+            }
+            "search_topics" => to_value(ops::op_search_topics(parse(input)?, state.clone()).await?),
+            "get_stats_summary" => {
+                to_value(ops::op_get_stats_summary(parse(input)?, state.clone()).await?)
+            }
+            "get_history" => to_value(ops::op_get_history(parse(input)?, state.clone()).await?),
+            "get_publisher_stats" => {
+                to_value(ops::op_get_publisher_stats(parse(input)?, state.clone()).await?)
+            }
+            "subscribe" => {
+                to_value(ops::op_subscribe(parse(input)?, session.clone(), state.clone()).await?)
+            }
+            "subscribe_with_backfill" => to_value(
+                ops::op_subscribe_with_backfill(parse(input)?, session.clone(), state.clone())
+                    .await?,
+            ),
+            "unsubscribe" => to_value(ops::op_unsubscribe(parse(input)?, state.clone()).await?),
+            "poll" => to_value(ops::op_poll(parse(input)?, state.clone(), blocks).await?),
+            "poll_synced" => {
+                to_value(ops::op_poll_synced(parse(input)?, state.clone(), blocks).await?)
+            }
+            "create_synchronizer" => to_value(
+                ops::op_create_synchronizer(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "poll_synchronizer" => {
+                to_value(ops::op_poll_synchronizer(parse(input)?, state.clone(), blocks).await?)
+            }
+            "list_synchronizers" => to_value(ops::op_list_synchronizers(state.clone()).await?),
+            "delete_synchronizer" => {
+                to_value(ops::op_delete_synchronizer(parse(input)?, state.clone()).await?)
+            }
+            "export_samples" => {
+                to_value(ops::op_export_samples(parse(input)?, state.clone()).await?)
+            }
+            "poll_to_file" => to_value(ops::op_poll_to_file(parse(input)?, state.clone()).await?),
+            "list_recordings" => to_value(ops::op_list_recordings(state.clone()).await?),
+            "delete_recording" => {
+                to_value(ops::op_delete_recording(parse(input)?, state.clone()).await?)
+            }
+            "repair_recording" => {
+                to_value(ops::op_repair_recording(parse(input)?, state.clone()).await?)
+            }
+            "open_recording" => {
+                to_value(ops::op_open_recording(parse(input)?, state.clone()).await?)
+            }
+            "seek_recording" => {
+                to_value(ops::op_seek_recording(parse(input)?, state.clone()).await?)
+            }
+            "read_recording" => {
+                to_value(ops::op_read_recording(parse(input)?, state.clone(), blocks).await?)
+            }
+            "list_recording_readers" => {
+                to_value(ops::op_list_recording_readers(state.clone()).await?)
+            }
+            "close_recording" => {
+                to_value(ops::op_close_recording(parse(input)?, state.clone()).await?)
+            }
+            "get_timeline" => {
+                to_value(ops::op_get_timeline(parse(input)?, state.clone(), blocks).await?)
+            }
+            "start_flight_recorder" => to_value(
+                ops::op_start_flight_recorder(parse(input)?, session.clone(), state.clone())
+                    .await?,
+            ),
+            "dump_flight_recorder" => {
+                to_value(ops::op_dump_flight_recorder(parse(input)?, state.clone()).await?)
+            }
+            "list_flight_recorders" => {
+                to_value(ops::op_list_flight_recorders(state.clone()).await?)
+            }
+            "stop_flight_recorder" => {
+                to_value(ops::op_stop_flight_recorder(parse(input)?, state.clone()).await?)
+            }
+            "list_subscriptions" => to_value(ops::op_list_subscriptions(state.clone()).await?),
+            "export_subscriptions" => to_value(ops::op_export_subscriptions(state.clone()).await?),
+            "import_subscriptions" => to_value(
+                ops::op_import_subscriptions(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "create_alert" => to_value(ops::op_create_alert(parse(input)?, state.clone()).await?),
+            "list_alerts" => to_value(ops::op_list_alerts(state.clone()).await?),
+            "delete_alert" => to_value(ops::op_delete_alert(parse(input)?, state.clone()).await?),
+            "poll_alerts" => to_value(ops::op_poll_alerts(parse(input)?, state.clone()).await?),
+            "dump_state" => ops::op_dump_state(session.as_ref(), state.clone()).await,
+            "get_stats" => to_value(ops::op_get_stats(state.clone()).await?),
+            "ros_graph" => to_value(ops::op_ros_graph(parse(input)?, session.as_ref()).await?),
+            "ros_service_call" => {
+                to_value(ops::op_ros_service_call(parse(input)?, session.as_ref()).await?)
+            }
+            "probe_device" => to_value(
+                ops::op_probe_device(parse(input)?, session.as_ref(), state.clone()).await?,
+            ),
+            "publish" => {
+                to_value(ops::op_publish(parse(input)?, session.as_ref(), state.clone()).await?)
+            }
+            "publish_with_ack" => to_value(
+                ops::op_publish_with_ack(parse(input)?, session.as_ref(), state.clone()).await?,
+            ),
+            "publish_template" => to_value(
+                ops::op_publish_template(parse(input)?, session.as_ref(), state.clone()).await?,
+            ),
+            "publish_batch" => to_value(
+                ops::op_publish_batch(parse(input)?, session.as_ref(), state.clone()).await?,
+            ),
+            "start_generator" => to_value(
+                ops::op_start_generator(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "list_generators" => to_value(ops::op_list_generators(state.clone()).await?),
+            "stop_generator" => {
+                to_value(ops::op_stop_generator(parse(input)?, state.clone()).await?)
+            }
+            "start_echo" => {
+                to_value(ops::op_start_echo(parse(input)?, session.clone(), state.clone()).await?)
+            }
+            "list_echoes" => to_value(ops::op_list_echoes(state.clone()).await?),
+            "stop_echo" => to_value(ops::op_stop_echo(parse(input)?, state.clone()).await?),
+            "create_bridge" => to_value(
+                ops::op_create_bridge(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "list_bridges" => to_value(ops::op_list_bridges(state.clone()).await?),
+            "delete_bridge" => to_value(ops::op_delete_bridge(parse(input)?, state.clone()).await?),
+            "add_federation_peer" => to_value(
+                ops::op_add_federation_peer(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "list_federation_peers" => {
+                to_value(ops::op_list_federation_peers(state.clone()).await?)
+            }
+            "remove_federation_peer" => {
+                to_value(ops::op_remove_federation_peer(parse(input)?, state.clone()).await?)
+            }
+            "get_federated_view" => to_value(
+                ops::op_get_federated_view(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "create_throttled_relay" => to_value(
+                ops::op_create_throttled_relay(parse(input)?, session.clone(), state.clone())
+                    .await?,
+            ),
+            "list_throttled_relays" => {
+                to_value(ops::op_list_throttled_relays(state.clone()).await?)
+            }
+            "delete_throttled_relay" => {
+                to_value(ops::op_delete_throttled_relay(parse(input)?, state.clone()).await?)
+            }
+            "create_preview" => to_value(
+                ops::op_create_preview(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "poll_preview" => {
+                to_value(ops::op_poll_preview(parse(input)?, state.clone(), blocks).await?)
+            }
+            "list_previews" => to_value(ops::op_list_previews(state.clone()).await?),
+            "delete_preview" => {
+                to_value(ops::op_delete_preview(parse(input)?, state.clone()).await?)
+            }
+            "query" => {
+                to_value(ops::op_query(parse(input)?, session.clone(), state.clone()).await?)
+            }
+            "poll_query" => {
+                to_value(ops::op_poll_query(parse(input)?, state.clone(), blocks).await?)
+            }
+            "list_queries" => to_value(ops::op_list_queries(state.clone()).await?),
+            "cancel_query" => to_value(ops::op_cancel_query(parse(input)?, state.clone()).await?),
+            "query_history" => to_value(
+                ops::op_query_history(parse(input)?, session.as_ref(), state.clone(), blocks)
+                    .await?,
+            ),
+            "load_plugin" => to_value(ops::op_load_plugin(parse(input)?, state.clone()).await?),
+            "list_plugins" => to_value(ops::op_list_plugins(state.clone()).await?),
+            "unload_plugin" => to_value(ops::op_unload_plugin(parse(input)?, state.clone()).await?),
+            "render_sample" => to_value(ops::op_render_sample(parse(input)?, state.clone()).await?),
+            "register_encoding" => to_value(ops::op_register_encoding(parse(input)?)?),
+            "list_encodings" => to_value(ops::op_list_encodings()?),
+            "unregister_encoding" => to_value(ops::op_unregister_encoding(parse(input)?)?),
+            "add_redaction_rule" => to_value(ops::op_add_redaction_rule(parse(input)?)?),
+            "list_redaction_rules" => to_value(ops::op_list_redaction_rules()?),
+            "remove_redaction_rule" => to_value(ops::op_remove_redaction_rule(parse(input)?)?),
+            "watch_key" => {
+                to_value(ops::op_watch_key(parse(input)?, session.clone(), state.clone()).await?)
+            }
+            "poll_watch" => to_value(ops::op_poll_watch(parse(input)?, state.clone()).await?),
+            "list_key_watches" => to_value(ops::op_list_key_watches(state.clone()).await?),
+            "unwatch_key" => to_value(ops::op_unwatch_key(parse(input)?, state.clone()).await?),
+            "materialize_table" => to_value(
+                ops::op_materialize_table(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "get_table" => to_value(ops::op_get_table(parse(input)?, state.clone()).await?),
+            "table_changes" => to_value(ops::op_table_changes(parse(input)?, state.clone()).await?),
+            "list_tables" => to_value(ops::op_list_tables(state.clone()).await?),
+            "delete_table" => to_value(ops::op_delete_table(parse(input)?, state.clone()).await?),
+            "watch_threshold" => to_value(
+                ops::op_watch_threshold(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "poll_threshold" => {
+                to_value(ops::op_poll_threshold(parse(input)?, state.clone()).await?)
+            }
+            "list_threshold_watches" => {
+                to_value(ops::op_list_threshold_watches(state.clone()).await?)
+            }
+            "unwatch_threshold" => {
+                to_value(ops::op_unwatch_threshold(parse(input)?, state.clone()).await?)
+            }
+            "create_derived_topic" => to_value(
+                ops::op_create_derived_topic(parse(input)?, session.clone(), state.clone()).await?,
+            ),
+            "list_derived_topics" => to_value(ops::op_list_derived_topics(state.clone()).await?),
+            "delete_derived_topic" => {
+                to_value(ops::op_delete_derived_topic(parse(input)?, state.clone()).await?)
+            }
+            "mirror" => {
+                to_value(ops::op_mirror(parse(input)?, session.clone(), state.clone()).await?)
+            }
+            "list_mirrors" => to_value(ops::op_list_mirrors(state.clone()).await?),
+            "delete_mirror" => to_value(ops::op_delete_mirror(parse(input)?, state.clone()).await?),
+            "param_set" => {
+                to_value(ops::op_param_set(parse(input)?, session.as_ref(), state.clone()).await?)
+            }
+            "param_get" => {
+                to_value(ops::op_param_get(parse(input)?, session.as_ref(), state.clone()).await?)
+            }
+            "param_list" => {
+                to_value(ops::op_param_list(parse(input)?, session.as_ref(), state.clone()).await?)
+            }
+            "set_namespace" => to_value(ops::op_set_namespace(parse(input)?, state.clone()).await?),
+            _ => Err(format!("Unknown operation: {operation}")),
+        }
+    }
+
+    /// Recorders, relays and publishers, which keep working without a host.
+    pub async fn background_tasks(&self) -> usize {
+        let st = self.state.read().await;
+        st.flight_recorders.len()
+            + st.mirrors.len()
+            + st.bridges.len()
+            + st.throttled_relays.len()
+            + st.echoes.len()
+            + st.generators.len()
+            + st.derived_topics.len()
+    }
+
+    /// Let background tasks run until `deadline`, or until none are left,
+    /// for instance because they were deleted over the admin queryable.
+    pub async fn linger(&self, deadline: DateTime<Utc>) {
+        while Utc::now() < deadline && self.background_tasks().await > 0 {
+            let left = (deadline - Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(left.min(LINGER_CHECK_EVERY)).await;
+        }
+    }
+
+    /// Copy every flight recorder's ring into a recordings file, so its
+    /// capture outlives the shutdown that deletes the rings. Returns the
+    /// files written.
+    pub async fn save_flight_recordings(&self) -> Vec<PathBuf> {
+        let st = self.state.read().await;
+        let Some(policy) = st.recordings.clone() else {
+            return Vec::new();
+        };
+        let rings: Vec<_> = st
+            .flight_recorders
+            .iter()
+            .map(|(id, recorder)| (id.clone(), recorder.ring.clone()))
+            .collect();
+        drop(st);
+
+        let stamp = Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
+        let mut saved = Vec::new();
+        for (id, ring) in rings {
+            let path = policy.dir.join(format!("flight-{id}-{stamp}.ndjson"));
+            let written = tokio::task::spawn_blocking({
+                let path = path.clone();
+                move || flight::dump(&ring, DateTime::<Utc>::MIN_UTC, &path)
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+            match written {
+                Ok(_) => saved.push(path),
+                Err(e) => eprintln!("flight recorder {id}: failed to save: {e}"),
+            }
+        }
+        saved
+    }
+
+    /// Stop discovery, cancel every subscription and background entity, and
+    /// delete flight recorder rings once they are saved.
+    pub async fn shutdown(&self) {
+        for path in self.save_flight_recordings().await {
+            eprintln!("zenoh: saved flight recording {}", path.display());
+        }
+        let mut st = self.state.write().await;
+        st.admin = None;
+        if let Some(cancel) = st.discovery_cancel.take() {
+            let _ = cancel.send(true);
+        }
+        for (_, sub) in st.subscriptions.drain() {
+            let _ = sub.cancel.send(true);
+        }
+        for (_, mirror) in st.mirrors.drain() {
+            let _ = mirror.cancel.send(true);
+        }
+        for (_, sync) in st.synchronizers.drain() {
+            let _ = sync.cancel.send(true);
+        }
+        for (_, generator) in st.generators.drain() {
+            let _ = generator.cancel.send(true);
+        }
+        for (_, echo) in st.echoes.drain() {
+            let _ = echo.cancel.send(true);
+        }
+        for (_, bridge) in st.bridges.drain() {
+            let _ = bridge.cancel.send(true);
+        }
+        for (_, relay) in st.throttled_relays.drain() {
+            let _ = relay.cancel.send(true);
+        }
+        for (_, preview) in st.previews.drain() {
+            let _ = preview.cancel.send(true);
+        }
+        for (_, query) in st.queries.drain() {
+            let _ = query.cancel.send(true);
+        }
+        for (_, key_watch) in st.key_watches.drain() {
+            let _ = key_watch.cancel.send(true);
+        }
+        for (_, table) in st.tables.drain() {
+            let _ = table.cancel.send(true);
+        }
+        for (_, threshold_watch) in st.threshold_watches.drain() {
+            let _ = threshold_watch.cancel.send(true);
+        }
+        for (_, topology_watch) in st.topology_watches.drain() {
+            let _ = topology_watch.cancel.send(true);
+        }
+        for (_, derived_topic) in st.derived_topics.drain() {
+            let _ = derived_topic.cancel.send(true);
+        }
+        for (_, recorder) in st.flight_recorders.drain() {
+            recorder.stop();
+        }
+        drop(st);
+        telemetry::shutdown().await;
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Samples received by discovery and subscription tasks since startup.
+pub static SAMPLES_INGESTED: AtomicU64 = AtomicU64::new(0);
+
+/// Bytes written to stdout (responses and notifications) since startup,
+/// as reported by the host wrapper through [`record_stdout`].
+pub static STDOUT_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Ingest counter at the previous `get_stats` call, for the interval rate.
+static LAST_RATE_CHECK: Mutex<Option<(Instant, u64)>> = Mutex::new(None);
+
+pub fn record_sample() {
+    SAMPLES_INGESTED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_stdout(bytes: usize) {
+    STDOUT_BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// Samples/sec ingested since the previous call (or since `started` on the first).
+pub fn ingest_rate_hz(started: Instant) -> f64 {
+    let now = Instant::now();
+    let total = SAMPLES_INGESTED.load(Ordering::Relaxed);
+    let mut last = LAST_RATE_CHECK.lock().unwrap();
+    let (since, base) = last.unwrap_or((started, 0));
+    *last = Some((now, total));
+    let elapsed = (now - since).as_secs_f64();
+    if elapsed < 0.001 {
+        0.0
+    } else {
+        (total - base) as f64 / elapsed
+    }
+}
+
+/// CPU time and memory usage of this process.
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct ProcessUsage {
+    pub cpu_user_secs: f64,
+    pub cpu_system_secs: f64,
+    pub max_rss_bytes: u64,
+    /// Current resident set size; only available where /proc is.
+    pub rss_bytes: Option<u64>,
+}
+
+#[cfg(unix)]
+pub fn process_usage() -> Option<ProcessUsage> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    let secs = |tv: libc::timeval| tv.tv_sec as f64 + tv.tv_usec as f64 / 1e6;
+    // ru_maxrss is bytes on macOS and kilobytes elsewhere
+    let max_rss_bytes = if cfg!(target_os = "macos") {
+        usage.ru_maxrss as u64
+    } else {
+        usage.ru_maxrss as u64 * 1024
+    };
+    Some(ProcessUsage {
+        cpu_user_secs: secs(usage.ru_utime),
+        cpu_system_secs: secs(usage.ru_stime),
+        max_rss_bytes,
+        rss_bytes: current_rss_bytes(),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn process_usage() -> Option<ProcessUsage> {
+    None
+}
+
+#[cfg(unix)]
+fn current_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kb: u64 = status
+        .lines()
+        .find_map(|l| l.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kb * 1024)
+}
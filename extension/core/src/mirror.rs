@@ -0,0 +1,111 @@
+use crate::backend::{Declaration, Query, QueryHandler, RxSample, ZenohBackend};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use zenoh::key_expr::KeyExpr;
+
+/// Query and cache counters for one mirror, shared with its queryable.
+#[derive(Default)]
+pub struct MirrorStats {
+    pub queries: AtomicU64,
+    /// Queries answered with at least one cached value.
+    pub hits: AtomicU64,
+    pub misses: AtomicU64,
+    pub replies: AtomicU64,
+    pub updates: AtomicU64,
+    /// Samples on new keys ignored because the cache was full.
+    pub dropped_keys: AtomicU64,
+}
+
+/// A last-value cache: a subscriber feeding it and a queryable answering
+/// gets from it. Dropping the mirror undeclares the queryable.
+pub struct Mirror {
+    /// Host-facing key expression, without the session namespace.
+    pub key_expr: String,
+    pub max_keys: usize,
+    pub cache: Arc<Mutex<HashMap<String, RxSample>>>,
+    pub stats: Arc<MirrorStats>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+    _queryable: Declaration,
+}
+
+/// Declare the subscriber and queryable for a mirror on `scoped_key_expr`.
+pub async fn start_mirror(
+    session: Arc<dyn ZenohBackend>,
+    key_expr: String,
+    scoped_key_expr: &str,
+    max_keys: usize,
+) -> Result<Mirror, String> {
+    let mut stream = session.subscribe(scoped_key_expr).await?;
+    let cache: Arc<Mutex<HashMap<String, RxSample>>> = Arc::default();
+    let stats: Arc<MirrorStats> = Arc::default();
+
+    let handler: QueryHandler = {
+        let (cache, stats) = (cache.clone(), stats.clone());
+        Arc::new(move |query: Query| {
+            stats.queries.fetch_add(1, Ordering::Relaxed);
+            let Ok(filter) = KeyExpr::try_from(query.key_expr) else {
+                stats.misses.fetch_add(1, Ordering::Relaxed);
+                return Box::pin(std::future::ready(Vec::new()));
+            };
+            let samples: Vec<RxSample> = cache
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|s| {
+                    KeyExpr::try_from(s.key_expr.as_str()).is_ok_and(|k| filter.intersects(&k))
+                })
+                .cloned()
+                .collect();
+            let counter = match samples.is_empty() {
+                true => &stats.misses,
+                false => &stats.hits,
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+            stats
+                .replies
+                .fetch_add(samples.len() as u64, Ordering::Relaxed);
+            Box::pin(std::future::ready(samples))
+        })
+    };
+    let queryable = session.declare_queryable(scoped_key_expr, handler).await?;
+
+    let (cancel, mut cancel_rx) = watch::channel(false);
+    {
+        let (cache, stats) = (cache.clone(), stats.clone());
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    sample = stream.recv() => {
+                        let Some(sample) = sample else { break };
+                        let mut cache = cache.lock().unwrap();
+                        if cache.len() >= max_keys && !cache.contains_key(&sample.key_expr) {
+                            stats.dropped_keys.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                        cache.insert(sample.key_expr.clone(), sample);
+                        stats.updates.fetch_add(1, Ordering::Relaxed);
+                    }
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(Mirror {
+        key_expr,
+        max_keys,
+        cache,
+        stats,
+        cancel,
+        created_at: Utc::now(),
+        _queryable: queryable,
+    })
+}
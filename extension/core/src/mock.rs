@@ -0,0 +1,424 @@
+use crate::backend::{
+    endpoint_list, Declaration, GetOptions, Hello, Locality, Query, QueryHandler, Reliability,
+    Reply, RxSample, SampleStream, SubscribeOptions, TransportInfo, ZenohBackend,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use zenoh::key_expr::KeyExpr;
+
+/// Samples buffered per mock subscriber before it starts lagging.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// A synthetic topic the mock backend publishes on a timer.
+#[derive(Deserialize)]
+pub struct MockTopic {
+    pub key_expr: String,
+    pub rate_hz: f64,
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    /// Payload template; `{i}` is replaced by a counter and `{t}` by Unix time in seconds.
+    pub payload: String,
+}
+
+fn default_encoding() -> String {
+    "application/json".into()
+}
+
+/// Mock backend setup, loaded from the `--mock=<path>` JSON file.
+#[derive(Deserialize)]
+pub struct MockConfig {
+    #[serde(default)]
+    pub topics: Vec<MockTopic>,
+    /// Liveliness token keys answered by liveliness queries.
+    #[serde(default)]
+    pub liveliness: Vec<String>,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        let topic = |key_expr: &str, rate_hz: f64, payload: &str| MockTopic {
+            key_expr: key_expr.into(),
+            rate_hz,
+            encoding: default_encoding(),
+            payload: payload.into(),
+        };
+        Self {
+            topics: vec![
+                topic(
+                    "mock/robot/odom",
+                    10.0,
+                    r#"{"seq": {i}, "stamp": {t}, "x": 1.5, "y": -0.25}"#,
+                ),
+                topic(
+                    "mock/sensor/temperature",
+                    1.0,
+                    r#"{"seq": {i}, "celsius": 21.5}"#,
+                ),
+                MockTopic {
+                    encoding: "text/plain".into(),
+                    ..topic("mock/status", 0.5, "ok {i}")
+                },
+            ],
+            liveliness: Vec::new(),
+        }
+    }
+}
+
+impl MockConfig {
+    /// Read a config file, or the built-in topics when `path` is `None`.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read mock config {}: {e}", path.display()))?;
+        serde_json::from_str(&text)
+            .map_err(|e| format!("invalid mock config {}: {e}", path.display()))
+    }
+}
+
+/// A sample plus whether it was put on this session rather than generated.
+type Tagged = (RxSample, bool);
+
+/// In-process fake session: generates the configured topics, loops publishes
+/// back to matching subscribers and answers gets from the last value per key.
+/// Generated topics stand in for remote publishers; puts are session-local.
+pub struct MockBackend {
+    zid: String,
+    tx: broadcast::Sender<Tagged>,
+    store: Mutex<HashMap<String, Tagged>>,
+    liveliness: Vec<String>,
+    queryables: Arc<Mutex<HashMap<u64, (KeyExpr<'static>, QueryHandler)>>>,
+    next_queryable: AtomicU64,
+    config: Mutex<zenoh::Config>,
+}
+
+impl MockBackend {
+    /// Create the backend and spawn one generator task per configured topic.
+    pub fn start(config: MockConfig) -> Arc<Self> {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let backend = Arc::new(Self {
+            zid: uuid::Uuid::new_v4().simple().to_string(),
+            tx,
+            store: Mutex::new(HashMap::new()),
+            liveliness: config.liveliness,
+            queryables: Arc::default(),
+            next_queryable: AtomicU64::new(0),
+            config: Mutex::new(zenoh::Config::default()),
+        });
+        for topic in config.topics {
+            if topic.rate_hz > 0.0 {
+                spawn_generator(backend.clone(), topic);
+            }
+        }
+        backend
+    }
+
+    fn publish(&self, sample: RxSample, local: bool) {
+        self.store
+            .lock()
+            .unwrap()
+            .insert(sample.key_expr.clone(), (sample.clone(), local));
+        // No subscribers is fine
+        let _ = self.tx.send((sample, local));
+    }
+}
+
+fn spawn_generator(backend: Arc<MockBackend>, topic: MockTopic) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / topic.rate_hz));
+        for i in 0u64.. {
+            interval.tick().await;
+            let now = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+            let payload = topic
+                .payload
+                .replace("{i}", &i.to_string())
+                .replace("{t}", &now.to_string());
+            backend.publish(
+                RxSample {
+                    key_expr: topic.key_expr.clone(),
+                    payload: payload.into_bytes(),
+                    encoding: topic.encoding.clone(),
+                    source: Some(backend.zid.clone()),
+                    timestamp: Some((backend.zid.clone(), chrono::Utc::now())),
+                    reliability: Reliability::Reliable,
+                    shm: false,
+                    delete: false,
+                },
+                false,
+            );
+        }
+    });
+}
+
+fn parse_key_expr(key_expr: &str) -> Result<KeyExpr<'static>, String> {
+    KeyExpr::try_from(key_expr.to_string()).map_err(|e| format!("invalid key_expr {key_expr}: {e}"))
+}
+
+fn matches(filter: &KeyExpr, key_expr: &str) -> bool {
+    KeyExpr::try_from(key_expr).is_ok_and(|ke| filter.intersects(&ke))
+}
+
+/// Removes a mock queryable from the registry when dropped.
+struct MockQueryable {
+    id: u64,
+    registry: Arc<Mutex<HashMap<u64, (KeyExpr<'static>, QueryHandler)>>>,
+}
+
+impl Drop for MockQueryable {
+    fn drop(&mut self) {
+        self.registry.lock().unwrap().remove(&self.id);
+    }
+}
+
+struct MockStream {
+    filter: KeyExpr<'static>,
+    origin: Locality,
+    rx: broadcast::Receiver<Tagged>,
+}
+
+#[async_trait]
+impl SampleStream for MockStream {
+    async fn recv(&mut self) -> Option<RxSample> {
+        loop {
+            match self.rx.recv().await {
+                Ok((sample, local))
+                    if self.origin.allows(local) && matches(&self.filter, &sample.key_expr) =>
+                {
+                    return Some(sample)
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    fn try_recv(&mut self) -> Option<RxSample> {
+        loop {
+            match self.rx.try_recv() {
+                Ok((sample, local))
+                    if self.origin.allows(local) && matches(&self.filter, &sample.key_expr) =>
+                {
+                    return Some(sample)
+                }
+                Ok(_) | Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ZenohBackend for MockBackend {
+    fn kind(&self) -> &'static str {
+        "mock"
+    }
+
+    fn zid(&self) -> String {
+        self.zid.clone()
+    }
+
+    async fn peers(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    async fn routers(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// All mock subscribers share one broadcast channel, so the channel
+    /// options are ignored: a lagging subscriber always loses its oldest
+    /// samples. Generated topics count as remote, puts as local.
+    async fn subscribe_with(
+        &self,
+        key_expr: &str,
+        options: SubscribeOptions,
+    ) -> Result<Box<dyn SampleStream>, String> {
+        Ok(Box::new(MockStream {
+            filter: parse_key_expr(key_expr)?,
+            origin: options.origin,
+            rx: self.tx.subscribe(),
+        }))
+    }
+
+    async fn put(&self, key_expr: &str, payload: Vec<u8>, encoding: &str) -> Result<(), String> {
+        parse_key_expr(key_expr)?;
+        self.publish(
+            RxSample {
+                key_expr: key_expr.to_string(),
+                payload,
+                encoding: encoding.to_string(),
+                source: Some(self.zid.clone()),
+                timestamp: Some((self.zid.clone(), chrono::Utc::now())),
+                reliability: Reliability::Reliable,
+                shm: false,
+                delete: false,
+            },
+            true,
+        );
+        Ok(())
+    }
+
+    async fn delete(&self, key_expr: &str) -> Result<(), String> {
+        let filter = parse_key_expr(key_expr)?;
+        self.store
+            .lock()
+            .unwrap()
+            .retain(|key, _| !matches(&filter, key));
+        let sample = RxSample {
+            key_expr: key_expr.to_string(),
+            payload: Vec::new(),
+            encoding: String::new(),
+            source: Some(self.zid.clone()),
+            timestamp: Some((self.zid.clone(), chrono::Utc::now())),
+            reliability: Reliability::Reliable,
+            shm: false,
+            delete: true,
+        };
+        let _ = self.tx.send((sample, true));
+        Ok(())
+    }
+
+    async fn declare_queryable(
+        &self,
+        key_expr: &str,
+        handler: QueryHandler,
+    ) -> Result<Declaration, String> {
+        let filter = parse_key_expr(key_expr)?;
+        let id = self.next_queryable.fetch_add(1, Ordering::Relaxed);
+        self.queryables
+            .lock()
+            .unwrap()
+            .insert(id, (filter, handler));
+        Ok(Box::new(MockQueryable {
+            id,
+            registry: self.queryables.clone(),
+        }))
+    }
+
+    /// Answered from the last value per key plus every declared queryable
+    /// whose key expression intersects the query. Stored values keep the
+    /// locality of the put that wrote them; queryables are all local.
+    /// Selector parameters are ignored.
+    async fn get(
+        &self,
+        key_expr: &str,
+        options: GetOptions,
+        _timeout: Duration,
+    ) -> Result<Vec<Reply>, String> {
+        let filter = parse_key_expr(key_expr.split_once('?').map_or(key_expr, |(ke, _)| ke))?;
+        let mut samples: Vec<RxSample> = self
+            .store
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|(s, local)| {
+                options.destination.allows(*local) && matches(&filter, &s.key_expr)
+            })
+            .map(|(s, _)| s.clone())
+            .collect();
+        let handlers: Vec<QueryHandler> = self
+            .queryables
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|(ke, _)| options.destination.allows(true) && filter.intersects(ke))
+            .map(|(_, handler)| handler.clone())
+            .collect();
+        for handler in handlers {
+            let query = Query {
+                key_expr: key_expr.to_string(),
+                payload: options.payload.clone(),
+            };
+            samples.extend(handler(query).await);
+        }
+        if options.latest {
+            // Without timestamps, the reply seen last for a key wins
+            let mut seen = std::collections::HashSet::new();
+            samples.reverse();
+            samples.retain(|s| seen.insert(s.key_expr.clone()));
+        }
+        Ok(samples
+            .into_iter()
+            .map(|s| Reply {
+                result: Ok(s),
+                replier_zid: Some(self.zid.clone()),
+                received_at: Instant::now(),
+            })
+            .collect())
+    }
+
+    async fn liveliness_get(
+        &self,
+        key_expr: &str,
+        _timeout: Duration,
+    ) -> Result<Vec<String>, String> {
+        let filter = parse_key_expr(key_expr)?;
+        Ok(self
+            .liveliness
+            .iter()
+            .filter(|key| matches(&filter, key))
+            .cloned()
+            .collect())
+    }
+
+    fn config_json(&self) -> String {
+        self.config.lock().unwrap().to_string()
+    }
+
+    /// Like a live session, only `plugins/` keys may change at runtime.
+    fn config_insert(&self, key: &str, value: &str) -> Result<(), String> {
+        if !key.starts_with("plugins/") {
+            return Err(format!(
+                "cannot update {key}: only keys under plugins/ can change at runtime"
+            ));
+        }
+        self.config
+            .lock()
+            .unwrap()
+            .insert_json5(key, value)
+            .map_err(|e| e.to_string())
+    }
+
+    fn connect_endpoints(&self) -> Vec<String> {
+        let config = self.config.lock().unwrap();
+        config
+            .get_json("connect/endpoints")
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .map(|value| endpoint_list(&value))
+            .unwrap_or_default()
+    }
+
+    /// The mock never dials out, so nothing is ever connected.
+    async fn connected_locators(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    async fn transports(&self) -> Vec<TransportInfo> {
+        Vec::new()
+    }
+
+    async fn scout(&self, _timeout: Duration) -> Result<Vec<Hello>, String> {
+        Ok(Vec::new())
+    }
+
+    async fn set_connect_endpoints(&self, endpoints: Vec<String>) -> Result<(), String> {
+        self.config
+            .lock()
+            .unwrap()
+            .insert_json5(
+                "connect/endpoints",
+                &serde_json::json!(endpoints).to_string(),
+            )
+            .map_err(|e| format!("invalid endpoints: {e}"))
+    }
+
+    async fn close(&self) {}
+}
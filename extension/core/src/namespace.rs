@@ -0,0 +1,34 @@
+use zenoh::key_expr::KeyExpr;
+
+/// Validate a namespace prefix. Surrounding slashes are trimmed and an empty
+/// value clears the namespace.
+pub fn parse(namespace: &str) -> Result<Option<String>, String> {
+    let namespace = namespace.trim_matches('/');
+    if namespace.is_empty() {
+        return Ok(None);
+    }
+    if namespace.starts_with('@') || namespace.contains('*') || namespace.contains('$') {
+        return Err(format!(
+            "namespace must be a plain key prefix without wildcards: {namespace}"
+        ));
+    }
+    KeyExpr::try_from(namespace).map_err(|e| format!("invalid namespace {namespace}: {e}"))?;
+    Ok(Some(namespace.to_string()))
+}
+
+/// Prepend `namespace` to a key expression given by the host.
+pub fn scope(namespace: Option<&str>, key_expr: &str) -> String {
+    match namespace {
+        Some(ns) => format!("{ns}/{key_expr}"),
+        None => key_expr.to_string(),
+    }
+}
+
+/// Strip `namespace` from a key expression received from zenoh.
+pub fn strip(namespace: Option<&str>, key_expr: &str) -> String {
+    namespace
+        .and_then(|ns| key_expr.strip_prefix(ns))
+        .and_then(|rest| rest.strip_prefix('/'))
+        .unwrap_or(key_expr)
+        .to_string()
+}
@@ -0,0 +1,19 @@
+use serde_json::Value;
+use std::sync::OnceLock;
+
+type Sink = Box<dyn Fn(&str, Value) + Send + Sync>;
+
+static SINK: OnceLock<Sink> = OnceLock::new();
+
+/// Route extension events to `sink`; events before this call are dropped.
+/// Only the first sink installed takes effect.
+pub fn set_sink(sink: impl Fn(&str, Value) + Send + Sync + 'static) {
+    let _ = SINK.set(Box::new(sink));
+}
+
+/// Emit an extension event (stall, alert, ...) to the installed sink.
+pub fn notify(event: &str, data: Value) {
+    if let Some(sink) = SINK.get() {
+        sink(event, data);
+    }
+}
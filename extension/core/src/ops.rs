@@ -0,0 +1,4968 @@
+use crate::admin;
+use crate::alerts::{AlertCondition, AlertRule};
+use crate::api::*;
+use crate::backend::{
+    ChannelConfig, ChannelPolicy, GetOptions, Locality, Priority, PutEntry, Reliability,
+    SubscribeOptions, ZenohBackend,
+};
+use crate::blocks::{self, PayloadBlocks};
+use crate::bridge::{start_bridge, Bridge, RemapRule};
+use crate::cdr;
+use crate::checksum::Checksum;
+use crate::continuation::{self, Continuation};
+use crate::decode::DecodeFormat;
+use crate::derived::{self, start_derived_topic, DerivedTopic, Transform};
+use crate::discovery::{spawn_discovery, KeyFilter};
+use crate::echo::{start_echo, Echo};
+use crate::encodings::{self, Rule};
+use crate::export::{self, ExportFormat};
+use crate::expr::Expression;
+use crate::federation;
+use crate::flight::{self, start_flight_recorder, FlightRecorder};
+use crate::fuzzy;
+use crate::generator::{start_generator, Generator, GeneratorConfig, Pattern};
+use crate::history;
+use crate::jsonpath;
+use crate::key_watch::{start_key_watch, Compare, KeyWatch, KeyWatchConfig};
+use crate::metrics;
+use crate::mirror::{start_mirror, Mirror};
+use crate::namespace;
+use crate::notify;
+use crate::plugin::{Plugin, PluginInstance};
+use crate::preview::{start_preview, Preview};
+use crate::probe;
+use crate::query::{self, start_query, QueryStream};
+use crate::recordings::{self, RecordingReader};
+use crate::redaction::{self, Action, RedactionRule};
+use crate::relay::{start_relay, ThrottleConfig, ThrottledRelay};
+use crate::render::{self, View};
+use crate::ros;
+use crate::router_health;
+use crate::script::Script;
+use crate::seq::SeqSource;
+use crate::skew;
+use crate::spill::Spill;
+use crate::state::{
+    encoding_histogram, AppState, BufferedSample, DropPolicy, Payload, PayloadPreview, StateLock,
+    Subscription, TopicMeta,
+};
+use crate::storages;
+use crate::subscriber::{
+    self, spawn_housekeeping, spawn_stall_watch, spawn_subscriber, IngestConfig,
+};
+use crate::synchronizer::{start_synchronizer, Synchronizer};
+use crate::table::{start_table, Table, TableConfig, TableEntry};
+use crate::tags::{self, Tags};
+use crate::template;
+use crate::threshold::{start_threshold_watch, ThresholdConfig, ThresholdWatch};
+use crate::timeline::{self, SourceKind};
+use crate::timespec;
+use crate::topology::{self, start_topology_watch, TopologyWatch, TopologyWatchConfig};
+use crate::versions;
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+type Result<T> = std::result::Result<T, String>;
+
+/// Operations with side effects on the network, refused in read-only mode.
+pub const MUTATING_OPERATIONS: &[&str] = &[
+    "publish",
+    "publish_batch",
+    "publish_with_ack",
+    "publish_template",
+    "start_generator",
+    "start_echo",
+    "create_bridge",
+    "create_throttled_relay",
+    "create_derived_topic",
+    "ros_service_call",
+    "mirror",
+    "param_set",
+    "update_config",
+    "add_endpoint",
+    "remove_endpoint",
+    "create_storage",
+    "delete_storage",
+];
+
+/// Most payload bytes discovery keeps per topic for previews.
+const MAX_TOPIC_PREVIEW_BYTES: usize = 4096;
+
+/// Topics silent this long are reported stale.
+const TOPIC_STALE_SECS: i64 = 5;
+
+/// Longest a `block` drop policy may hold up ingest for one sample; kept
+/// well under the watchdog's stale threshold so waiting is not a hang.
+const MAX_BLOCK_TIMEOUT_MS: u64 = 10_000;
+
+/// Longest a subscription may hold samples back for reordering.
+const MAX_REORDER_WINDOW_MS: u64 = 5_000;
+
+/// Previews are for dashboards; faster than this, subscribe instead.
+const MAX_PREVIEW_RATE_HZ: f64 = 50.0;
+
+/// Each topology refresh queries every router's admin space.
+const MIN_TOPOLOGY_INTERVAL_MS: u64 = 500;
+
+/// Most samples one `poll_to_file` drains, so a deep spill is not pulled
+/// into memory at once.
+const MAX_POLL_TO_FILE: usize = 10_000;
+
+/// Lines are written in chunks of about this many bytes.
+const NDJSON_CHUNK_BYTES: usize = 64 * 1024;
+
+pub async fn op_session_info(
+    params: SessionInfoParams,
+    session: &dyn ZenohBackend,
+) -> Result<SessionInfo> {
+    let zid = session.zid();
+    let peers = session.peers().await;
+    let routers = session.routers().await;
+    let config_source = match std::env::var("ZENOH_CONFIG") {
+        _ if session.attached() => "host".into(),
+        Ok(path) => path,
+        Err(_) => "default".into(),
+    };
+
+    Ok(SessionInfo {
+        zid,
+        peers,
+        routers,
+        config_source,
+        backend: session.kind(),
+        attached: session.attached(),
+        connected: true,
+        shared_memory: shared_memory_info(session)?,
+        versions: if params.versions {
+            let timeout = Duration::from_millis(params.timeout_ms);
+            Some(versions::report(session, timeout).await?)
+        } else {
+            None
+        },
+    })
+}
+
+/// SHM support as built and configured; Zenoh copies large puts into shared
+/// memory by itself once it is enabled on both ends.
+fn shared_memory_info(session: &dyn ZenohBackend) -> Result<SharedMemoryInfo> {
+    let config = parse_config(session)?;
+    let supported = cfg!(feature = "shm") && session.kind() == "zenoh";
+    let setting = |key: &str| config_value(&config, key).ok();
+    let enabled = supported
+        && setting("transport/shared_memory/enabled").and_then(Value::as_bool) == Some(true);
+    let optimized = setting("transport/shared_memory/transport_optimization/enabled")
+        .and_then(Value::as_bool)
+        == Some(true);
+    Ok(SharedMemoryInfo {
+        supported,
+        enabled,
+        message_size_threshold: (enabled && optimized)
+            .then(|| {
+                setting("transport/shared_memory/transport_optimization/message_size_threshold")
+                    .and_then(Value::as_u64)
+            })
+            .flatten(),
+    })
+}
+
+pub fn op_get_config(
+    params: GetConfigParams,
+    session: &dyn ZenohBackend,
+) -> Result<GetConfigResult> {
+    let config = parse_config(session)?;
+    let value = match params.key.as_deref() {
+        Some(key) => config_value(&config, key)?.clone(),
+        None => config,
+    };
+    Ok(GetConfigResult {
+        key: params.key,
+        value,
+    })
+}
+
+pub fn op_update_config(
+    params: UpdateConfigParams,
+    session: &dyn ZenohBackend,
+) -> Result<UpdateConfigResult> {
+    let key = params.key.trim_matches('/').to_string();
+    session
+        .config_insert(&key, &params.value.to_string())
+        .map_err(|e| format!("update_config of {key} failed: {e}"))?;
+    let config = parse_config(session)?;
+    let value = config_value(&config, &key)?.clone();
+    Ok(UpdateConfigResult { key, value })
+}
+
+pub async fn op_list_endpoints(session: &dyn ZenohBackend) -> Result<ListEndpointsResult> {
+    Ok(ListEndpointsResult {
+        configured: session.connect_endpoints(),
+        connected: session.connected_locators().await,
+    })
+}
+
+/// Reopening closes the old session: subscriptions and discovery resubscribe
+/// through their watchdog, other declarations must be created again.
+pub async fn op_add_endpoint(
+    params: EndpointParams,
+    session: &dyn ZenohBackend,
+) -> Result<EndpointsResult> {
+    let endpoint = zenoh::config::EndPoint::try_from(params.endpoint.clone())
+        .map_err(|e| format!("invalid endpoint {}: {e}", params.endpoint))?
+        .to_string();
+    let mut endpoints = session.connect_endpoints();
+    if endpoints.contains(&endpoint) {
+        return Ok(EndpointsResult {
+            endpoints,
+            reopened: false,
+            zid: session.zid(),
+        });
+    }
+    endpoints.push(endpoint);
+    set_endpoints(session, endpoints).await
+}
+
+pub async fn op_remove_endpoint(
+    params: EndpointParams,
+    session: &dyn ZenohBackend,
+) -> Result<EndpointsResult> {
+    let mut endpoints = session.connect_endpoints();
+    let before = endpoints.len();
+    endpoints.retain(|e| *e != params.endpoint);
+    if endpoints.len() == before {
+        return Err(format!("endpoint not configured: {}", params.endpoint));
+    }
+    set_endpoints(session, endpoints).await
+}
+
+pub async fn op_list_storages(
+    params: ListStoragesParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+) -> Result<ListStoragesResult> {
+    let router = params.router.as_deref().unwrap_or("*");
+    let timeout = Duration::from_millis(params.timeout_ms);
+    let (mut storages, errors) = storages::list(session, router, timeout).await?;
+    let ns = state.read().await.namespace.clone();
+    for storage in &mut storages {
+        storage.key_expr = storage
+            .key_expr
+            .as_deref()
+            .map(|ke| namespace::strip(ns.as_deref(), ke));
+    }
+    Ok(ListStoragesResult {
+        count: storages.len(),
+        storages,
+        errors,
+    })
+}
+
+pub async fn op_get_router_health(
+    params: GetRouterHealthParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+) -> Result<GetRouterHealthResult> {
+    let router = params.router.as_deref().unwrap_or("*");
+    let (mut routers, errors) = router_health::collect(
+        session,
+        router,
+        Duration::from_millis(params.sample_ms),
+        Duration::from_millis(params.timeout_ms),
+    )
+    .await?;
+    let ns = state.read().await.namespace.clone();
+    for storage in routers.iter_mut().flat_map(|r| &mut r.storages) {
+        storage.key_expr = storage
+            .key_expr
+            .as_deref()
+            .map(|ke| namespace::strip(ns.as_deref(), ke));
+    }
+    Ok(GetRouterHealthResult {
+        warnings: routers.iter().map(|r| r.warnings.len()).sum(),
+        routers,
+        errors,
+    })
+}
+
+pub async fn op_get_topology(
+    params: GetTopologyParams,
+    session: &dyn ZenohBackend,
+) -> Result<GetTopologyResult> {
+    let (topology, errors) = topology::collect(
+        session,
+        Duration::from_millis(params.scout_ms),
+        Duration::from_millis(params.timeout_ms),
+    )
+    .await?;
+    let (nodes, edges) = topology.into_parts();
+    Ok(GetTopologyResult {
+        nodes,
+        edges,
+        errors,
+    })
+}
+
+pub async fn op_watch_topology(
+    params: WatchTopologyParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<WatchTopologyResult> {
+    if params.interval_ms < MIN_TOPOLOGY_INTERVAL_MS {
+        return Err(format!(
+            "interval_ms must be at least {MIN_TOPOLOGY_INTERVAL_MS}"
+        ));
+    }
+    if params.scout_ms >= params.interval_ms {
+        return Err("scout_ms must be below interval_ms".into());
+    }
+    if params.buffer_size == 0 {
+        return Err("buffer_size must be at least 1".into());
+    }
+    let config = TopologyWatchConfig {
+        interval: Duration::from_millis(params.interval_ms),
+        debounce: Duration::from_millis(params.debounce_ms.unwrap_or(params.interval_ms)),
+        scout: Duration::from_millis(params.scout_ms),
+        timeout: Duration::from_millis(params.timeout_ms),
+        buffer_size: params.buffer_size,
+        notify: params.notify,
+    };
+
+    let watch_id = uuid::Uuid::new_v4().to_string();
+    let topology_watch = start_topology_watch(session, watch_id.clone(), config.clone());
+    state
+        .write()
+        .await
+        .topology_watches
+        .insert(watch_id.clone(), topology_watch);
+
+    Ok(WatchTopologyResult {
+        watch_id,
+        interval_ms: params.interval_ms,
+        debounce_ms: config.debounce.as_millis() as u64,
+        scout_ms: params.scout_ms,
+        buffer_size: config.buffer_size,
+        notify: config.notify,
+    })
+}
+
+pub async fn op_poll_topology_watch(
+    params: PollTopologyWatchParams,
+    state: Arc<StateLock>,
+) -> Result<PollTopologyWatchResult> {
+    let st = state.read().await;
+    let topology_watch = st
+        .topology_watches
+        .get(&params.watch_id)
+        .ok_or_else(|| format!("topology watch not found: {}", params.watch_id))?;
+    let mut changes = topology_watch.changes.lock().unwrap();
+    let n = params.limit.min(changes.events.len());
+    let drained: Vec<_> = changes.events.drain(..n).collect();
+
+    Ok(PollTopologyWatchResult {
+        watch_id: params.watch_id,
+        change_count: drained.len(),
+        changes: drained,
+        remaining: changes.events.len(),
+        nodes: changes.nodes(),
+        edges: changes.edges(),
+        last_refresh: changes.last_refresh,
+        last_error: changes.last_error.clone(),
+    })
+}
+
+pub async fn op_list_topology_watches(state: Arc<StateLock>) -> Result<ListTopologyWatchesResult> {
+    let st = state.read().await;
+    let watches: Vec<TopologyWatchSummary> = st
+        .topology_watches
+        .iter()
+        .map(|(id, topology_watch)| topology_watch_summary(id, topology_watch))
+        .collect();
+
+    Ok(ListTopologyWatchesResult {
+        count: watches.len(),
+        watches,
+    })
+}
+
+pub async fn op_unwatch_topology(
+    params: UnwatchTopologyParams,
+    state: Arc<StateLock>,
+) -> Result<UnwatchTopologyResult> {
+    let watch_id = params.watch_id;
+    let mut st = state.write().await;
+    match st.topology_watches.remove(&watch_id) {
+        Some(topology_watch) => {
+            let _ = topology_watch.cancel.send(true);
+            Ok(UnwatchTopologyResult {
+                removed: true,
+                watch_id,
+            })
+        }
+        None => Err(format!("topology watch not found: {watch_id}")),
+    }
+}
+
+/// Admin space puts are not acknowledged; the result reports whether the
+/// router went on to list the storage as running.
+pub async fn op_create_storage(
+    params: CreateStorageParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+) -> Result<CreateStorageResult> {
+    storages::validate_name(&params.name)?;
+    let scoped = namespace::scope(state.read().await.namespace.as_deref(), &params.key_expr);
+    zenoh::key_expr::KeyExpr::try_from(scoped.as_str())
+        .map_err(|e| format!("invalid key_expr {}: {e}", params.key_expr))?;
+    let router = storages::resolve_router(session, params.router).await?;
+
+    let mut config = serde_json::json!({ "key_expr": scoped, "volume": params.volume });
+    if let Some(prefix) = &params.strip_prefix {
+        config["strip_prefix"] = Value::from(prefix.as_str());
+    }
+    let admin_key = storages::config_key(&router, &params.name);
+    session
+        .put(
+            &admin_key,
+            config.to_string().into_bytes(),
+            "application/json",
+        )
+        .await
+        .map_err(|e| format!("create_storage of {} failed: {e}", params.name))?;
+    let timeout = Duration::from_millis(params.timeout_ms);
+    let confirmed = storages::confirm(session, &router, &params.name, true, timeout).await;
+
+    Ok(CreateStorageResult {
+        router,
+        name: params.name,
+        key_expr: params.key_expr,
+        volume: params.volume,
+        confirmed,
+        admin_key,
+    })
+}
+
+pub async fn op_delete_storage(
+    params: DeleteStorageParams,
+    session: &dyn ZenohBackend,
+) -> Result<DeleteStorageResult> {
+    storages::validate_name(&params.name)?;
+    let router = storages::resolve_router(session, params.router).await?;
+    session
+        .delete(&storages::config_key(&router, &params.name))
+        .await
+        .map_err(|e| format!("delete_storage of {} failed: {e}", params.name))?;
+    let timeout = Duration::from_millis(params.timeout_ms);
+    let confirmed = storages::confirm(session, &router, &params.name, false, timeout).await;
+
+    Ok(DeleteStorageResult {
+        router,
+        name: params.name,
+        confirmed,
+    })
+}
+
+async fn set_endpoints(
+    session: &dyn ZenohBackend,
+    endpoints: Vec<String>,
+) -> Result<EndpointsResult> {
+    session
+        .set_connect_endpoints(endpoints.clone())
+        .await
+        .map_err(|e| format!("failed to reopen session: {e}"))?;
+    Ok(EndpointsResult {
+        endpoints,
+        reopened: true,
+        zid: session.zid(),
+    })
+}
+
+fn parse_config(session: &dyn ZenohBackend) -> Result<Value> {
+    serde_json::from_str(&session.config_json()).map_err(|e| format!("invalid session config: {e}"))
+}
+
+/// The value at a slash-separated `key`; array items are addressed by index.
+fn config_value<'a>(config: &'a Value, key: &str) -> Result<&'a Value> {
+    key.split('/')
+        .filter(|part| !part.is_empty())
+        .try_fold(config, |value, part| match value {
+            Value::Object(map) => map.get(part),
+            Value::Array(items) => part.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+        .ok_or_else(|| format!("unknown config key: {key}"))
+}
+
+pub async fn op_start_discovery(
+    params: StartDiscoveryParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<StartDiscoveryResult> {
+    let key_expr = params.key_expr;
+    let seq_source = parse_seq_source(params.seq_path.as_deref())?;
+    if let Some(bytes) = params.preview_bytes {
+        if !(1..=MAX_TOPIC_PREVIEW_BYTES).contains(&bytes) {
+            return Err(format!(
+                "preview_bytes must be between 1 and {MAX_TOPIC_PREVIEW_BYTES}"
+            ));
+        }
+    }
+    let filter = KeyFilter::new(params.include, params.exclude)?;
+
+    let mut st = state.write().await;
+
+    // Stop existing discovery if running
+    if let Some(cancel) = st.discovery_cancel.take() {
+        let _ = cancel.send(true);
+    }
+    st.topics.clear();
+    st.publisher_clocks.clear();
+    st.discovery_active = true;
+    st.discovery_paused = false;
+    st.discovery_resumed_at = None;
+    st.discovery_key_expr = key_expr.clone();
+    st.discovery_seq_source = seq_source.clone();
+    st.discovery_preview_bytes = params.preview_bytes;
+    st.discovery_filter = filter.clone();
+    let namespace = st.namespace.clone();
+    drop(st);
+
+    let (cancel, health) = spawn_discovery(
+        session,
+        state.clone(),
+        namespace::scope(namespace.as_deref(), &key_expr),
+        seq_source,
+        namespace,
+    );
+
+    let mut st = state.write().await;
+    st.discovery_cancel = Some(cancel);
+    st.discovery_health = Some(health);
+
+    Ok(StartDiscoveryResult {
+        started: true,
+        key_expr,
+        preview_bytes: params.preview_bytes,
+        include: filter.include,
+        exclude: filter.exclude,
+    })
+}
+
+pub async fn op_stop_discovery(state: Arc<StateLock>) -> Result<StopDiscoveryResult> {
+    let mut st = state.write().await;
+    if let Some(cancel) = st.discovery_cancel.take() {
+        let _ = cancel.send(true);
+    }
+    st.discovery_active = false;
+    st.discovery_health = None;
+    st.topics.clear();
+    st.publisher_clocks.clear();
+    st.discovery_key_expr.clear();
+    st.discovery_seq_source = None;
+    st.discovery_preview_bytes = None;
+    st.discovery_filter = KeyFilter::default();
+    st.discovery_paused = false;
+    st.discovery_resumed_at = None;
+
+    Ok(StopDiscoveryResult { stopped: true })
+}
+
+pub async fn op_pin_topic(params: PinTopicParams, state: Arc<StateLock>) -> Result<PinTopicResult> {
+    let mut st = state.write().await;
+    st.pinned_topics.insert(params.key_expr.clone());
+    Ok(PinTopicResult {
+        present: st.topics.contains_key(&params.key_expr),
+        key_expr: params.key_expr,
+        pinned: true,
+    })
+}
+
+pub async fn op_unpin_topic(
+    params: PinTopicParams,
+    state: Arc<StateLock>,
+) -> Result<PinTopicResult> {
+    let mut st = state.write().await;
+    if !st.pinned_topics.remove(&params.key_expr) {
+        return Err(format!("topic not pinned: {}", params.key_expr));
+    }
+    Ok(PinTopicResult {
+        present: st.topics.contains_key(&params.key_expr),
+        key_expr: params.key_expr,
+        pinned: false,
+    })
+}
+
+pub async fn op_tag_topic(params: TagTopicParams, state: Arc<StateLock>) -> Result<TagTopicResult> {
+    let add = params
+        .add
+        .iter()
+        .map(|tag| tags::parse(tag))
+        .collect::<Result<Vec<String>>>()?;
+    let remove: Vec<String> = params
+        .remove
+        .iter()
+        .map(|tag| tag.trim().to_string())
+        .collect();
+
+    let mut st = state.write().await;
+    let previous = st.tags.clone();
+    let (tags, present) = match (&params.key_expr, &params.sub_id) {
+        (Some(key_expr), None) => (
+            tags::update(&mut st.tags.topics, key_expr, add, &remove),
+            st.topics.contains_key(key_expr),
+        ),
+        (None, Some(sub_id)) => (
+            tags::update(&mut st.tags.subscriptions, sub_id, add, &remove),
+            st.subscriptions.contains_key(sub_id),
+        ),
+        _ => return Err("pass key_expr or sub_id".into()),
+    };
+    if let Err(e) = st.save_state_file() {
+        st.tags = previous;
+        return Err(e);
+    }
+
+    Ok(TagTopicResult {
+        key_expr: params.key_expr,
+        sub_id: params.sub_id,
+        tags,
+        present,
+        persisted: st.state_file.is_some(),
+    })
+}
+
+pub async fn op_list_tags(params: ListTagsParams, state: Arc<StateLock>) -> Result<ListTagsResult> {
+    let st = state.read().await;
+    let mut usage: BTreeMap<&str, TagUsage> = BTreeMap::new();
+    for (tagged, on_topics) in [(&st.tags.topics, true), (&st.tags.subscriptions, false)] {
+        for (id, tags) in tagged {
+            for tag in tags {
+                let used = usage.entry(tag).or_insert_with(|| TagUsage {
+                    tag: tag.clone(),
+                    topics: Vec::new(),
+                    subscriptions: Vec::new(),
+                });
+                match on_topics {
+                    true => used.topics.push(id.clone()),
+                    false => used.subscriptions.push(id.clone()),
+                }
+            }
+        }
+    }
+
+    Ok(ListTagsResult {
+        tags: usage
+            .into_values()
+            .filter(|u| params.tag.as_ref().is_none_or(|tag| u.tag == *tag))
+            .collect(),
+        state_file: st.state_file.as_ref().map(|p| p.display().to_string()),
+    })
+}
+
+/// Stop consuming samples but keep the topic table, frozen and exempt from
+/// expiry, for `resume_discovery`.
+pub async fn op_pause_discovery(state: Arc<StateLock>) -> Result<PauseDiscoveryResult> {
+    let mut st = state.write().await;
+    if !st.discovery_active {
+        return Err("discovery is not running".into());
+    }
+    if let Some(cancel) = st.discovery_cancel.take() {
+        let _ = cancel.send(true);
+    }
+    st.discovery_active = false;
+    st.discovery_paused = true;
+    st.discovery_health = None;
+
+    Ok(PauseDiscoveryResult {
+        paused: true,
+        topic_count: st.topics.len(),
+    })
+}
+
+/// Restart a paused discovery with the settings it was started with.
+pub async fn op_resume_discovery(
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<ResumeDiscoveryResult> {
+    let mut st = state.write().await;
+    if !st.discovery_paused {
+        return Err("discovery is not paused".into());
+    }
+    let key_expr = st.discovery_key_expr.clone();
+    let namespace = st.namespace.clone();
+    let (cancel, health) = spawn_discovery(
+        session,
+        state.clone(),
+        namespace::scope(namespace.as_deref(), &key_expr),
+        st.discovery_seq_source.clone(),
+        namespace,
+    );
+    st.discovery_cancel = Some(cancel);
+    st.discovery_health = Some(health);
+    st.discovery_active = true;
+    st.discovery_paused = false;
+    st.discovery_resumed_at = Some(Utc::now());
+    st.alerts.restart_rate_windows();
+
+    Ok(ResumeDiscoveryResult {
+        resumed: true,
+        key_expr,
+        topic_count: st.topics.len(),
+    })
+}
+
+pub async fn op_get_topics(
+    params: GetTopicsParams,
+    state: Arc<StateLock>,
+) -> Result<GetTopicsResult> {
+    let prefix = params.prefix.as_str();
+    let mixed_only = params.mixed_encoding_only;
+
+    let after = match params.continuation.as_deref().map(Continuation::decode) {
+        Some(Ok(Continuation::GetTopics { after })) => Some(after),
+        Some(Err(e)) => return Err(e),
+        Some(_) => return Err("continuation is not for get_topics".into()),
+        None => None,
+    };
+
+    let now = Utc::now();
+    let st = state.read().await;
+    let mut topics: Vec<TopicSummary> = st
+        .topics
+        .values()
+        .filter(|t| prefix.is_empty() || t.key_expr.starts_with(prefix))
+        .filter(|t| !mixed_only || t.mixed_encoding())
+        .filter(|t| {
+            params.tag.as_ref().is_none_or(|tag| {
+                st.tags
+                    .topics
+                    .get(&t.key_expr)
+                    .is_some_and(|tags| tags.contains(tag))
+            })
+        })
+        .filter(|t| after.as_ref().is_none_or(|after| t.key_expr > *after))
+        .map(|t| topic_summary(t, now, &st))
+        .collect();
+    topics.sort_by(|a, b| a.key_expr.cmp(&b.key_expr));
+
+    let mut continuation = None;
+    if let Some(max_bytes) = st.max_response_bytes {
+        let sizes = topics.iter().map(|t| serde_json::to_vec(t).unwrap().len());
+        let fits = continuation::fit(sizes, max_bytes);
+        if fits < topics.len() {
+            topics.truncate(fits);
+            let after = topics[fits - 1].key_expr.clone();
+            continuation = Some(Continuation::GetTopics { after }.encode());
+        }
+    }
+
+    Ok(GetTopicsResult {
+        discovery_active: st.discovery_active,
+        discovery_paused: st.discovery_paused,
+        discovery_restarts: st.discovery_health.as_ref().map_or(0, |h| h.restarts()),
+        topic_count: topics.len(),
+        topics,
+        continuation,
+    })
+}
+
+/// Aggregates over discovered topics for an overview panel.
+pub async fn op_get_stats_summary(
+    params: GetStatsSummaryParams,
+    state: Arc<StateLock>,
+) -> Result<StatsSummary> {
+    let prefix = params.prefix.as_str();
+    let now = Utc::now();
+    let st = state.read().await;
+    let topics: Vec<&TopicMeta> = st
+        .topics
+        .values()
+        .filter(|t| prefix.is_empty() || t.key_expr.starts_with(prefix))
+        .collect();
+
+    let mut encodings: HashMap<String, u64> = HashMap::new();
+    for topic in &topics {
+        for (encoding, count) in &topic.encodings {
+            *encodings.entry(encoding.clone()).or_default() += count;
+        }
+    }
+    let active: Vec<TopicRate> = topics
+        .iter()
+        .filter(|t| (now - t.last_seen).num_seconds() < TOPIC_STALE_SECS)
+        .map(|t| TopicRate {
+            key_expr: t.key_expr.clone(),
+            rate_hz: t.rate_hz(),
+            bytes_per_sec: t.rate_hz() * t.avg_payload_size() as f64,
+        })
+        .collect();
+    let round = |x: f64| (x * 100.0).round() / 100.0;
+    let top = |by: fn(&TopicRate) -> f64| {
+        let mut ranked: Vec<&TopicRate> = active.iter().collect();
+        ranked.sort_by(|a, b| by(b).total_cmp(&by(a)).then(a.key_expr.cmp(&b.key_expr)));
+        ranked
+            .into_iter()
+            .take(params.top_n)
+            .map(|t| TopicRate {
+                key_expr: t.key_expr.clone(),
+                rate_hz: round(t.rate_hz),
+                bytes_per_sec: round(t.bytes_per_sec),
+            })
+            .collect()
+    };
+
+    Ok(StatsSummary {
+        discovery_active: st.discovery_active,
+        topic_count: topics.len(),
+        active_topic_count: active.len(),
+        total_rate_hz: round(active.iter().map(|t| t.rate_hz).sum()),
+        total_bytes_per_sec: round(active.iter().map(|t| t.bytes_per_sec).sum()),
+        top_by_rate: top(|t| t.rate_hz),
+        top_by_bandwidth: top(|t| t.bytes_per_sec),
+        encodings: encoding_histogram(&encodings),
+    })
+}
+
+pub async fn op_get_history(
+    params: GetHistoryParams,
+    state: Arc<StateLock>,
+) -> Result<GetHistoryResult> {
+    let now = Utc::now();
+    let st = state.read().await;
+    let (sub_id, key_expr, history) = match (params.sub_id, params.key_expr) {
+        (Some(sub_id), None) => {
+            let sub = st
+                .subscriptions
+                .get(&sub_id)
+                .ok_or_else(|| format!("subscription not found: {sub_id}"))?;
+            (Some(sub_id), sub.key_expr.clone(), &sub.history)
+        }
+        (None, Some(key_expr)) => {
+            let topic = st
+                .topics
+                .get(&key_expr)
+                .ok_or_else(|| format!("topic not found: {key_expr}"))?;
+            (None, key_expr, &topic.history)
+        }
+        _ => return Err("give exactly one of sub_id or key_expr".into()),
+    };
+
+    Ok(GetHistoryResult {
+        sub_id,
+        key_expr,
+        bucket_secs: history::BUCKET_SECS,
+        points: history.points(now, params.last_secs),
+    })
+}
+
+/// Discovery traffic attributed to each publishing zid. A topic's rate is
+/// split between its publishers by their share of its samples and bytes.
+pub async fn op_get_publisher_stats(
+    params: GetPublisherStatsParams,
+    state: Arc<StateLock>,
+) -> Result<PublisherStatsResult> {
+    let prefix = params.prefix.as_str();
+    let threshold_ms = params
+        .skew_threshold_ms
+        .unwrap_or(skew::DEFAULT_THRESHOLD_MS);
+    let now = Utc::now();
+    let st = state.read().await;
+    let mut publishers: HashMap<&str, PublisherStats> = HashMap::new();
+    let (mut unattributed_samples, mut unattributed_bytes) = (0, 0);
+    for topic in st
+        .topics
+        .values()
+        .filter(|t| prefix.is_empty() || t.key_expr.starts_with(prefix))
+    {
+        let active = (now - topic.last_seen).num_seconds() < TOPIC_STALE_SECS;
+        let rate_hz = if active { topic.rate_hz() } else { 0.0 };
+        let per_sample = rate_hz / topic.sample_count.max(1) as f64;
+        let (mut samples_seen, mut bytes_seen) = (0, 0);
+        for (zid, &samples) in &topic.publishers {
+            let bytes = topic.publisher_bytes.get(zid).copied().unwrap_or(0);
+            let entry = publishers.entry(zid).or_insert_with(|| PublisherStats {
+                zid: zid.clone(),
+                msgs_per_sec: 0.0,
+                bytes_per_sec: 0.0,
+                samples: 0,
+                bytes: 0,
+                topic_count: 0,
+                clock: st
+                    .publisher_clocks
+                    .get(zid)
+                    .and_then(|clock| clock.summary(zid, threshold_ms)),
+            });
+            entry.msgs_per_sec += samples as f64 * per_sample;
+            entry.bytes_per_sec += bytes as f64 * per_sample;
+            entry.samples += samples;
+            entry.bytes += bytes;
+            entry.topic_count += 1;
+            samples_seen += samples;
+            bytes_seen += bytes;
+        }
+        unattributed_samples += topic.sample_count - samples_seen;
+        unattributed_bytes += topic.total_payload_bytes - bytes_seen;
+    }
+
+    let mut publishers: Vec<PublisherStats> = publishers.into_values().collect();
+    for p in &mut publishers {
+        p.msgs_per_sec = (p.msgs_per_sec * 100.0).round() / 100.0;
+        p.bytes_per_sec = (p.bytes_per_sec * 100.0).round() / 100.0;
+    }
+    publishers.sort_by(|a, b| {
+        b.bytes_per_sec
+            .total_cmp(&a.bytes_per_sec)
+            .then(b.bytes.cmp(&a.bytes))
+            .then(a.zid.cmp(&b.zid))
+    });
+
+    Ok(PublisherStatsResult {
+        discovery_active: st.discovery_active,
+        publisher_count: publishers.len(),
+        skewed_count: publishers
+            .iter()
+            .filter(|p| p.clock.as_ref().is_some_and(|c| c.skewed))
+            .count(),
+        publishers,
+        unattributed_samples,
+        unattributed_bytes,
+    })
+}
+
+pub async fn op_get_topic(params: GetTopicParams, state: Arc<StateLock>) -> Result<TopicDetail> {
+    let key_expr = params.key_expr.as_str();
+
+    let st = state.read().await;
+    let topic = st
+        .topics
+        .get(key_expr)
+        .ok_or_else(|| format!("topic not found: {key_expr}"))?;
+
+    let topic_ke = zenoh::key_expr::KeyExpr::try_from(key_expr)
+        .map_err(|e| format!("invalid key_expr {key_expr}: {e}"))?;
+    let covered_by: Vec<String> = st
+        .subscriptions
+        .iter()
+        .filter(|(_, sub)| {
+            zenoh::key_expr::KeyExpr::try_from(sub.key_expr.as_str())
+                .is_ok_and(|sub_ke| sub_ke.includes(&topic_ke))
+        })
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    let threshold_ms = params
+        .skew_threshold_ms
+        .unwrap_or(skew::DEFAULT_THRESHOLD_MS);
+    let mut publisher_clocks: Vec<_> = topic
+        .publishers
+        .keys()
+        .filter_map(|zid| st.publisher_clocks.get(zid)?.summary(zid, threshold_ms))
+        .collect();
+    publisher_clocks.sort_by(|a, b| a.clock.cmp(&b.clock));
+
+    let seq_tracked = st.discovery_seq_source.is_some();
+    Ok(TopicDetail {
+        summary: topic_summary(topic, Utc::now(), &st),
+        total_payload_bytes: topic.total_payload_bytes,
+        last_seq: seq_tracked.then_some(topic.seq.last_seq).flatten(),
+        recent_timestamps: topic.recent_timestamps.clone(),
+        recent_intervals_us: topic.recent_intervals_us(),
+        encodings: topic.encoding_histogram(),
+        publishers: topic.publishers.clone(),
+        publisher_clocks,
+        subscribed: !covered_by.is_empty(),
+        covered_by,
+    })
+}
+
+pub async fn op_complete_key_expr(
+    params: CompleteKeyExprParams,
+    state: Arc<StateLock>,
+) -> Result<CompleteKeyExprResult> {
+    let (parent, fragment) = match params.partial.rsplit_once('/') {
+        Some((parent, fragment)) => (Some(parent), fragment),
+        None => (None, params.partial.as_str()),
+    };
+
+    let st = state.read().await;
+    // Per next segment: topics at or under it, whether it is a topic, and
+    // whether any continue below it
+    let mut segments: HashMap<&str, (usize, bool, bool)> = HashMap::new();
+    for key in st.topics.keys() {
+        let rest = match parent {
+            Some(parent) => key
+                .strip_prefix(parent)
+                .and_then(|rest| rest.strip_prefix('/')),
+            None => Some(key.as_str()),
+        };
+        let Some(rest) = rest else {
+            continue;
+        };
+        let (segment, below) = match rest.split_once('/') {
+            Some((segment, _)) => (segment, true),
+            None => (rest, false),
+        };
+        if !segment.starts_with(fragment) {
+            continue;
+        }
+        let entry = segments.entry(segment).or_default();
+        entry.0 += 1;
+        match below {
+            true => entry.2 = true,
+            false => entry.1 = true,
+        }
+    }
+
+    let mut completions: Vec<KeyCompletion> = segments
+        .into_iter()
+        .map(
+            |(segment, (topic_count, is_topic, has_children))| KeyCompletion {
+                key_expr: match parent {
+                    Some(parent) => format!("{parent}/{segment}"),
+                    None => segment.to_string(),
+                },
+                segment: segment.to_string(),
+                topic_count,
+                is_topic,
+                has_children,
+            },
+        )
+        .collect();
+    completions.sort_by(|a, b| {
+        b.topic_count
+            .cmp(&a.topic_count)
+            .then_with(|| a.key_expr.cmp(&b.key_expr))
+    });
+    let truncated = completions.len() > params.limit;
+    completions.truncate(params.limit);
+
+    Ok(CompleteKeyExprResult {
+        partial: params.partial,
+        completions,
+        truncated,
+    })
+}
+
+pub async fn op_search_topics(
+    params: SearchTopicsParams,
+    state: Arc<StateLock>,
+) -> Result<SearchTopicsResult> {
+    let terms: Vec<&str> = params.query.split_whitespace().collect();
+    if terms.is_empty() {
+        return Err("query must not be empty".into());
+    }
+
+    let now = Utc::now();
+    let st = state.read().await;
+    let fields = match params.fields {
+        true => topic_fields(&st),
+        false => HashMap::new(),
+    };
+    let mut matches = Vec::new();
+    for topic in st.topics.values() {
+        let topic_fields = fields.get(&topic.key_expr).map_or(&[][..], Vec::as_slice);
+        let mut score = 0;
+        let mut matched_fields = BTreeSet::new();
+        for term in &terms {
+            let on_key = fuzzy::score(term, &topic.key_expr);
+            let on_field = topic_fields
+                .iter()
+                .filter_map(|f| Some((fuzzy::score(term, f)?, f)))
+                .max_by_key(|(score, _)| *score);
+            match (on_key, on_field) {
+                (Some(key), Some((field, name))) if field > key => {
+                    score += field;
+                    matched_fields.insert(name.clone());
+                }
+                (Some(key), _) => score += key,
+                (None, Some((field, name))) => {
+                    score += field;
+                    matched_fields.insert(name.clone());
+                }
+                (None, None) => {
+                    score = -1;
+                    break;
+                }
+            }
+        }
+        if score >= 0 {
+            matches.push((score, topic, matched_fields));
+        }
+    }
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.key_expr.cmp(&b.1.key_expr)));
+    let match_count = matches.len();
+    let topics = matches
+        .into_iter()
+        .take(params.limit)
+        .map(|(score, topic, matched_fields)| TopicMatch {
+            summary: topic_summary(topic, now, &st),
+            score,
+            matched_fields: matched_fields.into_iter().collect(),
+        })
+        .collect();
+
+    Ok(SearchTopicsResult {
+        query: params.query,
+        match_count,
+        topics,
+    })
+}
+
+/// Field names per key, from the newest JSON sample subscriptions buffer
+/// for it and from discovery's preview when it holds a whole payload.
+fn topic_fields(st: &AppState) -> HashMap<String, Vec<String>> {
+    let mut fields: HashMap<String, Vec<String>> = HashMap::new();
+    let mut seen = HashSet::new();
+    for (sub_id, sub) in &st.subscriptions {
+        for sample in sub.buffer.iter().rev().filter(|s| !s.is_gap()) {
+            if !seen.insert((sample.key_expr.as_str(), sub_id.as_str())) {
+                continue;
+            }
+            if let Some(json) = sample.json() {
+                let out = fields.entry(sample.key_expr.clone()).or_default();
+                export::value_fields(&json, out);
+            }
+        }
+    }
+    for topic in st.topics.values() {
+        let Some((head, bytes)) = &topic.last_payload_head else {
+            continue;
+        };
+        if head.len() as u64 != *bytes {
+            continue;
+        }
+        if let Ok(json) = serde_json::from_slice::<Value>(head) {
+            let out = fields.entry(topic.key_expr.clone()).or_default();
+            export::value_fields(&json, out);
+        }
+    }
+    fields
+}
+
+pub async fn op_subscribe(
+    params: SubscribeParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<SubscribeResult> {
+    if let Some(sub_id) = &params.sub_id {
+        validate_sub_id(sub_id)?;
+        // A retry of a subscribe that already went through gets the same answer
+        if let Some(existing) = state.read().await.subscriptions.get(sub_id) {
+            return match existing.definition == params {
+                true => Ok(subscribe_result(sub_id.clone(), existing)),
+                false => Err(format!(
+                    "sub_id {sub_id} is already used by a different subscription"
+                )),
+            };
+        }
+    }
+    let definition = params.clone();
+    let key_expr = params.key_expr;
+    let buffer_size = params.buffer_size;
+
+    let seq_source = parse_seq_source(params.seq_path.as_deref())?;
+
+    let alert_if_silent_ms = params.alert_if_silent_ms;
+    if alert_if_silent_ms == Some(0) {
+        return Err("alert_if_silent_ms must be greater than 0".into());
+    }
+    let max_age_ms = params.max_age_ms;
+    if max_age_ms == Some(0) {
+        return Err("max_age_ms must be greater than 0".into());
+    }
+    let reorder_window_ms = params.reorder_window_ms;
+    if reorder_window_ms.is_some_and(|w| w == 0 || w > MAX_REORDER_WINDOW_MS) {
+        return Err(format!(
+            "reorder_window_ms must be between 1 and {MAX_REORDER_WINDOW_MS}"
+        ));
+    }
+    if params.block_timeout_ms == 0 || params.block_timeout_ms > MAX_BLOCK_TIMEOUT_MS {
+        return Err(format!(
+            "block_timeout_ms must be between 1 and {MAX_BLOCK_TIMEOUT_MS}"
+        ));
+    }
+    let drop_policy = params
+        .drop_policy
+        .as_deref()
+        .map(|p| DropPolicy::parse(p, Duration::from_millis(params.block_timeout_ms)))
+        .transpose()?
+        .unwrap_or_default();
+
+    let decode = params
+        .decode
+        .as_deref()
+        .map(DecodeFormat::parse)
+        .transpose()?;
+    let checksum = params
+        .checksum
+        .as_deref()
+        .map(Checksum::parse)
+        .transpose()?
+        .flatten();
+    if params.channel_capacity == Some(0) {
+        return Err("channel_capacity must be greater than 0".into());
+    }
+    if params.spill_max_bytes == Some(0) {
+        return Err("spill_max_bytes must be greater than 0".into());
+    }
+    let tuning = state.read().await.profile.tuning();
+    let channel = match (params.channel.as_deref(), params.channel_capacity) {
+        (None, None) => tuning.channel,
+        (policy, capacity) => ChannelConfig {
+            policy: policy
+                .map(ChannelPolicy::parse)
+                .transpose()?
+                .unwrap_or_default(),
+            capacity,
+        },
+    };
+    let origin = parse_locality(params.origin.as_deref())?;
+    let filter = params
+        .filter
+        .as_deref()
+        .map(Expression::parse)
+        .transpose()?
+        .map(Arc::new);
+    let reliability = params
+        .reliability
+        .as_deref()
+        .map(Reliability::parse)
+        .transpose()?;
+    let plugin = match params.plugin.as_deref() {
+        Some(name) => {
+            let st = state.read().await;
+            let plugin = st
+                .plugins
+                .get(name)
+                .ok_or_else(|| format!("plugin not found: {name}"))?;
+            Some(PluginInstance::new(name, plugin)?)
+        }
+        None => None,
+    };
+    if decode.is_some() && plugin.as_ref().is_some_and(|p| p.has_decode()) {
+        return Err("decode cannot be combined with a plugin that decodes".into());
+    }
+    let script = match params.script.as_deref() {
+        Some(source) => {
+            let read_only = state.read().await.read_only;
+            Some(Script::compile(
+                source,
+                std::slice::from_ref(&key_expr),
+                !read_only,
+            )?)
+        }
+        None => None,
+    };
+
+    let sub_id = params
+        .sub_id
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let (cancel_tx, cancel_rx) = watch::channel(false);
+    let stall_cancel_rx = cancel_tx.subscribe();
+    let housekeeping_cancel_rx = cancel_tx.subscribe();
+
+    let namespace = state.read().await.namespace.clone();
+    let scoped_key_expr = namespace::scope(namespace.as_deref(), &key_expr);
+    let ingest = IngestConfig {
+        seq_source,
+        decode,
+        decode_queue: tuning.decode_queue,
+        ingest_batch: tuning.ingest_batch,
+        decode_queue_depth: Arc::new(AtomicUsize::new(0)),
+        checksum,
+        namespace,
+        subscribe: SubscribeOptions { channel, origin },
+        reliability,
+        reliability_stats: Arc::default(),
+        filter,
+        filtered: Arc::default(),
+        plugin,
+        script,
+        declared: Arc::new(watch::channel(false).0),
+    };
+    let mut sub = Subscription::new(
+        key_expr.clone(),
+        buffer_size,
+        cancel_tx,
+        ingest.clone(),
+        alert_if_silent_ms,
+        max_age_ms,
+        definition,
+    );
+    sub.drop_policy = drop_policy;
+    sub.gap_markers = params.gap_markers;
+    sub.spill = params.spill_max_bytes.map(Spill::new).transpose()?;
+    sub.diff_last = params.diff.then(HashMap::new);
+    sub.reorder_window_ms = reorder_window_ms;
+    let health = sub.health.clone();
+    let result = subscribe_result(sub_id.clone(), &sub);
+
+    {
+        let mut st = state.write().await;
+        if st.subscriptions.contains_key(&sub_id) {
+            return Err(format!(
+                "sub_id {sub_id} was taken by a concurrent subscribe"
+            ));
+        }
+        st.subscriptions.insert(sub_id.clone(), sub);
+    }
+
+    spawn_subscriber(
+        session,
+        state.clone(),
+        sub_id.clone(),
+        scoped_key_expr,
+        ingest,
+        cancel_rx,
+        health,
+    );
+
+    let check_every = [
+        max_age_ms.map(|ms| ms / 4),
+        reorder_window_ms.map(|ms| ms / 2),
+    ]
+    .into_iter()
+    .flatten()
+    .min();
+    if let Some(check_every) = check_every {
+        let check_every = Duration::from_millis(check_every.clamp(10, 1000));
+        spawn_housekeeping(
+            state.clone(),
+            sub_id.clone(),
+            check_every,
+            housekeeping_cancel_rx,
+        );
+    }
+    if let Some(silent_ms) = alert_if_silent_ms {
+        spawn_stall_watch(state, sub_id.clone(), silent_ms, stall_cancel_rx);
+    }
+
+    Ok(result)
+}
+
+pub async fn op_subscribe_with_backfill(
+    params: SubscribeWithBackfillParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<SubscribeWithBackfillResult> {
+    let now = Utc::now();
+    let from = params
+        .backfill_from
+        .unwrap_or_else(|| now - chrono::Duration::seconds(params.backfill_secs as i64));
+    if from > now {
+        return Err("backfill_from must not be in the future".into());
+    }
+    let retry = match &params.subscribe.sub_id {
+        Some(sub_id) => state.read().await.subscriptions.contains_key(sub_id),
+        None => false,
+    };
+    let subscription = op_subscribe(params.subscribe, session.clone(), state.clone()).await?;
+    let range = timespec::Range {
+        from: Some(from),
+        to: None,
+    };
+    let backfill_selector = range.selector(&subscription.key_expr);
+    let mut result = SubscribeWithBackfillResult {
+        subscription,
+        backfill_selector,
+        backfilled: 0,
+        backfill_skipped: 0,
+        backfill_errors: Vec::new(),
+    };
+    if retry {
+        return Ok(result);
+    }
+
+    // Wait for the subscriber to be declared so nothing published during
+    // the query is missed; copies received both ways are dropped on merge
+    let sub_id = &result.subscription.sub_id;
+    let (namespace, ingest) = {
+        let st = state.read().await;
+        let sub = st
+            .subscriptions
+            .get(sub_id)
+            .ok_or_else(|| format!("subscription not found: {sub_id}"))?;
+        (st.namespace.clone(), sub.ingest.clone())
+    };
+    let selector = namespace::scope(namespace.as_deref(), &result.backfill_selector);
+    let timeout = Duration::from_millis(params.timeout_ms);
+    let mut declared = ingest.declared.subscribe();
+    if tokio::time::timeout(timeout, declared.wait_for(|d| *d))
+        .await
+        .is_err()
+    {
+        result.backfill_errors.push(format!(
+            "subscriber not declared within {}ms; samples published during the backfill may be missed",
+            params.timeout_ms
+        ));
+    }
+    let mut samples = Vec::new();
+    match session.get(&selector, GetOptions::default(), timeout).await {
+        Ok(replies) => {
+            for reply in replies {
+                match reply.result {
+                    Ok(sample) if sample.delete => {}
+                    Ok(sample) => samples.push(sample),
+                    Err(e) => result.backfill_errors.push(e),
+                }
+            }
+        }
+        Err(e) => result.backfill_errors.push(e),
+    }
+    let fetched = samples.len();
+    let historical = subscriber::historical(&*session, &ingest, samples).await;
+    let mut st = state.write().await;
+    let sub = st
+        .subscriptions
+        .get_mut(sub_id)
+        .ok_or_else(|| format!("subscription not found: {sub_id}"))?;
+    result.backfilled = sub.backfill(historical);
+    result.backfill_skipped = fetched - result.backfilled;
+    Ok(result)
+}
+
+/// Longest client-supplied `sub_id` accepted.
+const MAX_SUB_ID_LEN: usize = 128;
+
+fn validate_sub_id(sub_id: &str) -> Result<()> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':');
+    if sub_id.is_empty() || sub_id.len() > MAX_SUB_ID_LEN || !sub_id.chars().all(allowed) {
+        return Err(format!(
+            "sub_id must be 1 to {MAX_SUB_ID_LEN} letters, digits, '-', '_', '.' or ':'"
+        ));
+    }
+    Ok(())
+}
+
+fn subscribe_result(sub_id: String, sub: &Subscription) -> SubscribeResult {
+    SubscribeResult {
+        sub_id,
+        key_expr: sub.key_expr.clone(),
+        buffer_size: sub.buffer_capacity,
+        seq_path: sub.ingest.seq_source.as_ref().map(|s| s.spec()),
+        alert_if_silent_ms: sub.alert_if_silent_ms,
+        max_age_ms: sub.max_age_ms,
+        reorder_window_ms: sub.reorder_window_ms,
+        drop_policy: sub.drop_policy.as_str(),
+        block_timeout_ms: block_timeout_ms(sub.drop_policy),
+        gap_markers: sub.gap_markers,
+        spill_max_bytes: sub.definition.spill_max_bytes,
+        diff: sub.definition.diff,
+        decode: sub.ingest.decode.map(|d| d.as_str()),
+        checksum: sub.ingest.checksum.map(|c| c.as_str()),
+        channel: sub.ingest.subscribe.channel.policy.as_str(),
+        channel_capacity: sub.ingest.subscribe.channel.capacity,
+        origin: sub.ingest.subscribe.origin.as_str(),
+        reliability: sub.ingest.reliability.map(|r| r.as_str()),
+        filter: sub.definition.filter.clone(),
+        plugin: sub.definition.plugin.clone(),
+    }
+}
+
+pub async fn op_unsubscribe(
+    params: UnsubscribeParams,
+    state: Arc<StateLock>,
+) -> Result<UnsubscribeResult> {
+    let sub_id = params.sub_id;
+
+    let mut st = state.write().await;
+    match st.subscriptions.remove(&sub_id) {
+        Some(sub) => {
+            let _ = sub.cancel.send(true);
+            sub.space.notify_waiters();
+            Ok(UnsubscribeResult {
+                removed: true,
+                sub_id,
+            })
+        }
+        None => Err(format!("subscription not found: {sub_id}")),
+    }
+}
+
+pub async fn op_export_subscriptions(state: Arc<StateLock>) -> Result<ExportSubscriptionsResult> {
+    let st = state.read().await;
+    let mut subs: Vec<_> = st.subscriptions.values().collect();
+    subs.sort_by_key(|sub| sub.created_at);
+    let subscriptions: Vec<SubscribeParams> =
+        subs.into_iter().map(|sub| sub.definition.clone()).collect();
+    Ok(ExportSubscriptionsResult {
+        count: subscriptions.len(),
+        subscriptions,
+    })
+}
+
+/// Subscribe to each definition in turn; one that fails is reported and the
+/// rest still apply.
+pub async fn op_import_subscriptions(
+    params: ImportSubscriptionsParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<ImportSubscriptionsResult> {
+    let mut removed = 0;
+    if params.replace {
+        let sub_ids: Vec<String> = state.read().await.subscriptions.keys().cloned().collect();
+        for sub_id in sub_ids {
+            if op_unsubscribe(UnsubscribeParams { sub_id }, state.clone())
+                .await
+                .is_ok()
+            {
+                removed += 1;
+            }
+        }
+    }
+
+    let mut results = Vec::with_capacity(params.subscriptions.len());
+    for definition in params.subscriptions {
+        let key_expr = definition.key_expr.clone();
+        let (sub_id, error) = match op_subscribe(definition, session.clone(), state.clone()).await {
+            Ok(result) => (Some(result.sub_id), None),
+            Err(e) => (None, Some(e)),
+        };
+        results.push(ImportedSubscription {
+            key_expr,
+            sub_id,
+            error,
+        });
+    }
+    let imported = results.iter().filter(|r| r.sub_id.is_some()).count();
+    Ok(ImportSubscriptionsResult {
+        imported,
+        failed: results.len() - imported,
+        removed,
+        results,
+    })
+}
+
+pub async fn op_poll(
+    params: PollParams,
+    state: Arc<StateLock>,
+    blocks: &mut PayloadBlocks,
+) -> Result<PollResult> {
+    let sub_id = params.sub_id;
+    let limit = params.limit;
+
+    // Optional key filter: only drain samples on keys intersecting it
+    let key_filter = match params.key_expr.as_deref() {
+        Some(ke) => Some(
+            zenoh::key_expr::KeyExpr::try_from(ke.to_string())
+                .map_err(|e| format!("invalid key_expr {ke}: {e}"))?,
+        ),
+        None => None,
+    };
+
+    let first_arrival_us = match params.continuation.as_deref().map(Continuation::decode) {
+        Some(Ok(Continuation::Poll {
+            sub_id: from,
+            first_arrival_us,
+        })) if from == sub_id => Some(first_arrival_us),
+        Some(Err(e)) => return Err(e),
+        Some(_) => return Err(format!("continuation is not for a poll of {sub_id}")),
+        None => None,
+    };
+    let render = |blocks: &mut PayloadBlocks, s: &BufferedSample| match params.include_payload {
+        true => blocks.sample_value(s),
+        false => blocks::sample_metadata(s),
+    };
+
+    let mut guard = state.write().await;
+    let st = &mut *guard;
+    match st.subscriptions.get_mut(&sub_id) {
+        Some(sub) => {
+            sub.housekeep(Utc::now());
+            // Bring spilled samples up so a large limit can take them in one poll
+            sub.refill(limit);
+            let matches = |s: &BufferedSample| {
+                s.is_gap()
+                    || key_filter.as_ref().is_none_or(|filter| {
+                        zenoh::key_expr::KeyExpr::try_from(s.key_expr.as_str())
+                            .map(|ke| filter.intersects(&ke))
+                            .unwrap_or(false)
+                    })
+            };
+            // Size the batch before draining so what doesn't fit stays buffered
+            let available = sub.buffer.iter().filter(|s| matches(s)).take(limit).count();
+            let take = match st.max_response_bytes {
+                Some(max_bytes) => {
+                    let mut sizing = PayloadBlocks::new(blocks.collecting());
+                    let sizes = sub
+                        .buffer
+                        .iter()
+                        .filter(|s| matches(s))
+                        .take(limit)
+                        .map(|s| serde_json::to_vec(&render(&mut sizing, s)).unwrap().len());
+                    continuation::fit(sizes, max_bytes)
+                }
+                None => available,
+            };
+            let samples = match &key_filter {
+                Some(_) => sub.drain_matching(take, matches),
+                None => sub.drain(take),
+            };
+            let first_arrival = first_arrival_us.or(samples.first().map(|s| s.arrived_us));
+            let continuation = (take < available).then(|| {
+                Continuation::Poll {
+                    sub_id: sub_id.clone(),
+                    first_arrival_us: first_arrival.unwrap_or_default(),
+                }
+                .encode()
+            });
+            let samples: Vec<Value> = samples
+                .iter()
+                .map(|s| {
+                    let mut value = render(blocks, s);
+                    if let (true, Some(first)) = (params.paced, first_arrival) {
+                        let offset_us = s.arrived_us.saturating_sub(first);
+                        value["offset_ms"] = Value::from(offset_us as f64 / 1000.0);
+                    }
+                    value
+                })
+                .collect();
+            let mut result = PollResult {
+                sample_count: samples.len(),
+                overflow_count: sub.overflow_count,
+                buffered_remaining: sub.buffer.len() + sub.spilled(),
+                next_seq: sub.next_seq(),
+                missed_estimate: sub.missed_estimate(),
+                sub_id,
+                samples: None,
+                compression: None,
+                samples_compressed: None,
+                continuation,
+            };
+            match st.compression {
+                Some(compression) => {
+                    let raw = serde_json::to_vec(&samples).unwrap();
+                    let packed = compression.compress(&raw);
+                    result.compression = Some(compression.as_str());
+                    result.samples_compressed =
+                        Some(base64::engine::general_purpose::STANDARD.encode(packed));
+                }
+                None => result.samples = Some(samples),
+            }
+            Ok(result)
+        }
+        None => Err(format!("subscription not found: {sub_id}")),
+    }
+}
+
+/// Newest sample per subscription not newer than a common timestamp: `at`
+/// when given, else the oldest of the subscriptions' newest samples, so every
+/// subscription with data contributes one.
+pub async fn op_poll_synced(
+    params: PollSyncedParams,
+    state: Arc<StateLock>,
+    blocks: &mut PayloadBlocks,
+) -> Result<PollSyncedResult> {
+    if params.sub_ids.is_empty() {
+        return Err("sub_ids must not be empty".into());
+    }
+    let mut st = state.write().await;
+    if let Some(missing) = params
+        .sub_ids
+        .iter()
+        .find(|id| !st.subscriptions.contains_key(*id))
+    {
+        return Err(format!("subscription not found: {missing}"));
+    }
+    let now = Utc::now();
+    for sub_id in &params.sub_ids {
+        st.subscriptions.get_mut(sub_id).unwrap().housekeep(now);
+    }
+
+    let at = params.at.or_else(|| {
+        params
+            .sub_ids
+            .iter()
+            .filter_map(|id| st.subscriptions[id].newest_timestamp())
+            .min()
+    });
+
+    let mut samples = Vec::with_capacity(params.sub_ids.len());
+    for sub_id in params.sub_ids {
+        let sub = st.subscriptions.get_mut(&sub_id).unwrap();
+        let picked = at.and_then(|at| sub.latest_at(at).map(|i| (at, i)));
+        let (sample, skew_ms) = match picked {
+            Some((at, i)) => {
+                let sample = &sub.buffer[i];
+                let skew_ms = (at - sample.timestamp).num_milliseconds();
+                let value = blocks.sample_value(sample);
+                if params.drain {
+                    sub.drain(i + 1);
+                }
+                (Some(value), Some(skew_ms))
+            }
+            None => (None, None),
+        };
+        samples.push(SyncedSample {
+            sub_id,
+            sample,
+            skew_ms,
+        });
+    }
+
+    Ok(PollSyncedResult {
+        timestamp: at.map(|t| t.to_rfc3339()),
+        complete: samples.iter().all(|s| s.sample.is_some()),
+        samples,
+    })
+}
+
+pub async fn op_create_synchronizer(
+    params: CreateSynchronizerParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<CreateSynchronizerResult> {
+    if !(2..=4).contains(&params.key_exprs.len()) {
+        return Err("key_exprs must list 2 to 4 key expressions".into());
+    }
+    if params.buffer_size == 0 {
+        return Err("buffer_size must be greater than 0".into());
+    }
+    let namespace = state.read().await.namespace.clone();
+    let sync = start_synchronizer(
+        session,
+        params.key_exprs.clone(),
+        namespace,
+        params.slop_ms,
+        params.buffer_size,
+    )
+    .await?;
+
+    let sync_id = uuid::Uuid::new_v4().to_string();
+    state
+        .write()
+        .await
+        .synchronizers
+        .insert(sync_id.clone(), sync);
+
+    Ok(CreateSynchronizerResult {
+        sync_id,
+        key_exprs: params.key_exprs,
+        slop_ms: params.slop_ms,
+        buffer_size: params.buffer_size,
+    })
+}
+
+pub async fn op_poll_synchronizer(
+    params: PollSynchronizerParams,
+    state: Arc<StateLock>,
+    blocks: &mut PayloadBlocks,
+) -> Result<PollSynchronizerResult> {
+    let st = state.read().await;
+    let sync = st
+        .synchronizers
+        .get(&params.sync_id)
+        .ok_or_else(|| format!("synchronizer not found: {}", params.sync_id))?;
+    let mut matcher = sync.matcher.lock().unwrap();
+    let n = params.limit.min(matcher.matched.len());
+    let tuples: Vec<SyncedTuple> = matcher
+        .matched
+        .drain(..n)
+        .map(|tuple| SyncedTuple {
+            seq: tuple.seq,
+            spread_ms: tuple.spread_ms,
+            samples: tuple
+                .samples
+                .iter()
+                .map(|s| blocks.sample_value(s))
+                .collect(),
+        })
+        .collect();
+
+    Ok(PollSynchronizerResult {
+        sync_id: params.sync_id,
+        tuple_count: tuples.len(),
+        tuples,
+        buffered_remaining: matcher.matched.len(),
+        overflow_count: matcher.overflow_count,
+        dropped: matcher.dropped,
+    })
+}
+
+pub async fn op_list_synchronizers(state: Arc<StateLock>) -> Result<ListSynchronizersResult> {
+    let st = state.read().await;
+    let synchronizers: Vec<SynchronizerSummary> = st
+        .synchronizers
+        .iter()
+        .map(|(id, sync)| synchronizer_summary(id, sync))
+        .collect();
+
+    Ok(ListSynchronizersResult {
+        count: synchronizers.len(),
+        synchronizers,
+    })
+}
+
+pub async fn op_delete_synchronizer(
+    params: DeleteSynchronizerParams,
+    state: Arc<StateLock>,
+) -> Result<DeleteSynchronizerResult> {
+    let sync_id = params.sync_id;
+
+    let mut st = state.write().await;
+    match st.synchronizers.remove(&sync_id) {
+        Some(sync) => {
+            let _ = sync.cancel.send(true);
+            Ok(DeleteSynchronizerResult {
+                removed: true,
+                sync_id,
+            })
+        }
+        None => Err(format!("synchronizer not found: {sync_id}")),
+    }
+}
+
+/// Write a subscription's buffered samples as a table, to `path` when given,
+/// else inline in the response (CSV only). Parquet is written off the async
+/// runtime with an `export_progress` notification per row group. Files
+/// written into the recordings directory trigger a retention pass.
+pub async fn op_export_samples(
+    params: ExportSamplesParams,
+    state: Arc<StateLock>,
+) -> Result<ExportSamplesResult> {
+    let format = ExportFormat::parse(&params.format)?;
+    let mut st = state.write().await;
+    let sub = st
+        .subscriptions
+        .get_mut(&params.sub_id)
+        .ok_or_else(|| format!("subscription not found: {}", params.sub_id))?;
+    sub.housekeep(Utc::now());
+    let samples: Vec<_> = match params.drain {
+        true => {
+            sub.refill(usize::MAX);
+            sub.drain(usize::MAX)
+        }
+        false => sub.buffer.iter().cloned().collect(),
+    };
+    // Gap markers have no payload to tabulate
+    let samples: Vec<_> = samples.into_iter().filter(|s| !s.is_gap()).collect();
+    let policy = st.recordings.clone();
+    drop(st);
+
+    let (path, policy) = match params.path {
+        Some(path) => {
+            let (path, policy) = output_path(path, policy)?;
+            (Some(path), policy)
+        }
+        None => (None, None),
+    };
+
+    let rows = samples.len();
+    let (columns, data) = match format {
+        ExportFormat::Csv => {
+            let columns = export::columns(&params.fields);
+            let content = export::to_csv(&columns, export::rows(&samples, &params.fields));
+            match &path {
+                Some(path) => {
+                    std::fs::write(path, &content)
+                        .map_err(|e| format!("failed to write {path}: {e}"))?;
+                    (columns, None)
+                }
+                None => (columns, Some(content)),
+            }
+        }
+        ExportFormat::Parquet => {
+            let path = path.clone().ok_or("parquet export needs a path")?;
+            let fields = match params.fields.is_empty() {
+                true => export::infer_fields(&samples),
+                false => params.fields,
+            };
+            let (sub_id, row_group_size) = (params.sub_id.clone(), params.row_group_size);
+            let columns = tokio::task::spawn_blocking(move || {
+                export::write_parquet(&path, &samples, &fields, row_group_size, |written| {
+                    notify::notify(
+                        "export_progress",
+                        serde_json::json!({
+                            "sub_id": sub_id,
+                            "path": path,
+                            "rows_written": written,
+                            "total_rows": rows,
+                        }),
+                    )
+                })
+            })
+            .await
+            .map_err(|e| format!("parquet export task failed: {e}"))??;
+            (columns, None)
+        }
+    };
+
+    if let Some((policy, name)) = policy {
+        tokio::task::spawn_blocking(move || recordings::prune(&policy, Some(&name)))
+            .await
+            .map_err(|e| format!("recordings prune task failed: {e}"))??;
+    }
+
+    Ok(ExportSamplesResult {
+        sub_id: params.sub_id,
+        format: format.as_str(),
+        rows,
+        columns,
+        path,
+        data,
+    })
+}
+
+/// Where a file output goes: relative paths name a file in the recordings
+/// directory when one is configured, and then come with the policy and name
+/// to prune by once written.
+fn output_path(
+    path: String,
+    policy: Option<recordings::RetentionPolicy>,
+) -> Result<(String, Option<(recordings::RetentionPolicy, String)>)> {
+    match policy {
+        Some(policy) if std::path::Path::new(&path).is_relative() => {
+            let full = recordings::path(&policy.dir, &path)?;
+            Ok((full.to_string_lossy().into_owned(), Some((policy, path))))
+        }
+        _ => Ok((path, None)),
+    }
+}
+
+/// Drain a subscription straight into an NDJSON file, one sample per line
+/// as `poll` would return it, appending to what the file already holds.
+/// Samples whose lines fail to write go back to the front of the buffer.
+pub async fn op_poll_to_file(
+    params: PollToFileParams,
+    state: Arc<StateLock>,
+) -> Result<PollToFileResult> {
+    let sub_id = params.sub_id;
+    let (path, policy) = {
+        let st = state.read().await;
+        if !st.subscriptions.contains_key(&sub_id) {
+            return Err(format!("subscription not found: {sub_id}"));
+        }
+        output_path(params.path, st.recordings.clone())?
+    };
+    // Open before draining so a bad path loses nothing
+    let open_path = path.clone();
+    let mut file = tokio::task::spawn_blocking(move || {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&open_path)
+    })
+    .await
+    .map_err(|e| format!("poll_to_file task failed: {e}"))?
+    .map_err(|e| format!("failed to open {path}: {e}"))?;
+
+    let (samples, buffered_remaining) = {
+        let mut st = state.write().await;
+        let sub = st
+            .subscriptions
+            .get_mut(&sub_id)
+            .ok_or_else(|| format!("subscription not found: {sub_id}"))?;
+        sub.housekeep(Utc::now());
+        let limit = params
+            .limit
+            .unwrap_or(MAX_POLL_TO_FILE)
+            .min(MAX_POLL_TO_FILE);
+        sub.refill(limit);
+        let samples = sub.drain(limit);
+        (samples, sub.buffer.len() + sub.spilled())
+    };
+
+    let (mut samples, written, bytes_written, error, file_bytes) =
+        tokio::task::spawn_blocking(move || {
+            let (written, bytes_written, error) = append_ndjson(&mut file, &samples);
+            let file_bytes = file.metadata().map_or(0, |m| m.len());
+            (samples, written, bytes_written, error, file_bytes)
+        })
+        .await
+        .map_err(|e| format!("poll_to_file task failed: {e}"))?;
+    if let Some(e) = error {
+        let unwritten = samples.split_off(written);
+        let returned = unwritten.len();
+        let mut st = state.write().await;
+        let outcome = match st.subscriptions.get_mut(&sub_id) {
+            Some(sub) => {
+                sub.requeue(unwritten);
+                format!("{returned} put back in the buffer")
+            }
+            None => format!("{returned} lost with the removed subscription"),
+        };
+        return Err(format!(
+            "failed to write {path}: {e}; {written} samples written, {outcome}"
+        ));
+    }
+
+    if let Some((policy, name)) = policy {
+        tokio::task::spawn_blocking(move || recordings::prune(&policy, Some(&name)))
+            .await
+            .map_err(|e| format!("recordings prune task failed: {e}"))??;
+    }
+
+    Ok(PollToFileResult {
+        sub_id,
+        path,
+        sample_count: samples.iter().filter(|s| !s.is_gap()).count(),
+        gap_markers: samples.iter().filter(|s| s.is_gap()).count(),
+        bytes_written,
+        file_bytes,
+        buffered_remaining,
+    })
+}
+
+/// Append one NDJSON line per sample. Returns how many samples were fully
+/// written, their bytes, and the error that stopped the rest.
+fn append_ndjson(
+    file: &mut std::fs::File,
+    samples: &[BufferedSample],
+) -> (usize, u64, Option<std::io::Error>) {
+    use std::io::Write as _;
+
+    let (mut written, mut bytes_written) = (0, 0);
+    let mut chunk = Vec::new();
+    for (i, sample) in samples.iter().enumerate() {
+        serde_json::to_writer(&mut chunk, sample).unwrap();
+        chunk.push(b'\n');
+        if chunk.len() < NDJSON_CHUNK_BYTES && i + 1 < samples.len() {
+            continue;
+        }
+        if let Err(e) = file.write_all(&chunk) {
+            return (written, bytes_written, Some(e));
+        }
+        written = i + 1;
+        bytes_written += chunk.len() as u64;
+        chunk.clear();
+    }
+    (written, bytes_written, None)
+}
+
+fn retention_policy(st: &AppState) -> Result<recordings::RetentionPolicy> {
+    st.recordings
+        .clone()
+        .ok_or_else(|| "no recordings directory configured; pass recordings at initialize".into())
+}
+
+pub async fn op_list_recordings(state: Arc<StateLock>) -> Result<ListRecordingsResult> {
+    let (policy, live) = {
+        let st = state.read().await;
+        let live: Vec<String> = st.flight_recorders.keys().map(ring_dir_name).collect();
+        (retention_policy(&st)?, live)
+    };
+    let recordings = recordings::list(&policy.dir)?;
+    let abandoned_rings = recordings::rings(&policy.dir)
+        .into_iter()
+        .filter(|ring| !live.contains(ring))
+        .collect();
+    Ok(ListRecordingsResult {
+        dir: policy.dir.to_string_lossy().into_owned(),
+        count: recordings.len(),
+        total_bytes: recordings.iter().map(|r| r.size_bytes).sum(),
+        max_total_bytes: policy.max_total_bytes,
+        max_age_secs: policy.max_age_secs,
+        encrypted: policy.cipher().is_some(),
+        recordings,
+        abandoned_rings,
+    })
+}
+
+fn ring_dir_name(recorder_id: &String) -> String {
+    format!("{}{recorder_id}", recordings::RING_PREFIX)
+}
+
+/// Recover a recording truncated by a crash, or turn a flight recorder ring
+/// an earlier process left behind into a recording.
+pub async fn op_repair_recording(
+    params: RepairRecordingParams,
+    state: Arc<StateLock>,
+) -> Result<RepairRecordingResult> {
+    let policy = {
+        let st = state.read().await;
+        if st
+            .flight_recorders
+            .keys()
+            .any(|id| ring_dir_name(id) == params.name)
+        {
+            return Err(format!(
+                "{} belongs to a running flight recorder; use dump_flight_recorder",
+                params.name
+            ));
+        }
+        retention_policy(&st)?
+    };
+    let name = params.name.clone();
+    let repaired = tokio::task::spawn_blocking(move || {
+        recordings::repair(&policy.dir, &name, policy.cipher())
+    })
+    .await
+    .map_err(|e| format!("repair task failed: {e}"))??;
+    Ok(RepairRecordingResult {
+        source: params.name,
+        name: repaired.name,
+        records: repaired.records,
+        indexed_records: repaired.indexed_records,
+        dropped_lines: repaired.dropped_lines,
+        truncated_bytes: repaired.truncated_bytes,
+        first: repaired.first,
+        last: repaired.last,
+    })
+}
+
+pub async fn op_start_flight_recorder(
+    params: StartFlightRecorderParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<StartFlightRecorderResult> {
+    if params.key_exprs.is_empty() {
+        return Err("key_exprs must list at least one key expression".into());
+    }
+    if params.max_bytes < 64 * 1024 {
+        return Err("max_bytes must be at least 65536".into());
+    }
+    let (policy, namespace) = {
+        let st = state.read().await;
+        (retention_policy(&st)?, st.namespace.clone())
+    };
+    let recorder_id = uuid::Uuid::new_v4().to_string();
+    // Hidden so the ring is not listed, pruned or deleted as a recording
+    let dir = policy.dir.join(ring_dir_name(&recorder_id));
+    let recorder = start_flight_recorder(
+        session,
+        params.key_exprs.clone(),
+        namespace,
+        dir,
+        params.max_bytes,
+        policy.cipher().cloned(),
+    )
+    .await?;
+
+    state
+        .write()
+        .await
+        .flight_recorders
+        .insert(recorder_id.clone(), recorder);
+
+    Ok(StartFlightRecorderResult {
+        recorder_id,
+        key_exprs: params.key_exprs,
+        max_bytes: params.max_bytes,
+    })
+}
+
+/// Snapshot the tail of a flight recorder into the recordings directory,
+/// then apply retention, keeping the new file.
+pub async fn op_dump_flight_recorder(
+    params: DumpFlightRecorderParams,
+    state: Arc<StateLock>,
+) -> Result<DumpFlightRecorderResult> {
+    let (policy, ring) = {
+        let st = state.read().await;
+        let recorder = st
+            .flight_recorders
+            .get(&params.recorder_id)
+            .ok_or_else(|| format!("flight recorder not found: {}", params.recorder_id))?;
+        (retention_policy(&st)?, recorder.ring.clone())
+    };
+    let name = params
+        .name
+        .unwrap_or_else(|| format!("flight-{}.ndjson", Utc::now().format("%Y%m%dT%H%M%S%.3fZ")));
+    let path = recordings::path(&policy.dir, &name)?;
+    let since = Utc::now() - chrono::Duration::seconds(params.last_secs as i64);
+
+    let (summary, path) = {
+        let name = name.clone();
+        tokio::task::spawn_blocking(move || {
+            let summary = flight::dump(&ring, since, &path)?;
+            recordings::prune(&policy, Some(&name))?;
+            Ok::<_, String>((summary, path))
+        })
+        .await
+        .map_err(|e| format!("flight recorder dump task failed: {e}"))??
+    };
+
+    Ok(DumpFlightRecorderResult {
+        recorder_id: params.recorder_id,
+        name,
+        path: path.to_string_lossy().into_owned(),
+        records: summary.records,
+        bytes: summary.bytes,
+        first: summary.first,
+        last: summary.last,
+    })
+}
+
+pub async fn op_list_flight_recorders(state: Arc<StateLock>) -> Result<ListFlightRecordersResult> {
+    let st = state.read().await;
+    let flight_recorders: Vec<FlightRecorderSummary> = st
+        .flight_recorders
+        .iter()
+        .map(|(id, recorder)| flight_recorder_summary(id, recorder))
+        .collect();
+
+    Ok(ListFlightRecordersResult {
+        count: flight_recorders.len(),
+        flight_recorders,
+    })
+}
+
+pub async fn op_stop_flight_recorder(
+    params: StopFlightRecorderParams,
+    state: Arc<StateLock>,
+) -> Result<StopFlightRecorderResult> {
+    let recorder_id = params.recorder_id;
+    let recorder = state
+        .write()
+        .await
+        .flight_recorders
+        .remove(&recorder_id)
+        .ok_or_else(|| format!("flight recorder not found: {recorder_id}"))?;
+    recorder.stop();
+    Ok(StopFlightRecorderResult {
+        removed: true,
+        recorder_id,
+    })
+}
+
+pub async fn op_delete_recording(
+    params: DeleteRecordingParams,
+    state: Arc<StateLock>,
+) -> Result<DeleteRecordingResult> {
+    let policy = retention_policy(&*state.read().await)?;
+    let freed_bytes = recordings::delete(&policy.dir, &params.name)?;
+    Ok(DeleteRecordingResult {
+        name: params.name,
+        freed_bytes,
+    })
+}
+
+pub async fn op_open_recording(
+    params: OpenRecordingParams,
+    state: Arc<StateLock>,
+) -> Result<RecordingReaderSummary> {
+    let policy = retention_policy(&*state.read().await)?;
+    let reader = tokio::task::spawn_blocking(move || {
+        let mut reader =
+            RecordingReader::open(&policy.dir, &params.name, policy.cipher().cloned())?;
+        if let Some(from) = params.from {
+            reader.seek(from)?;
+        }
+        Ok::<_, String>(reader)
+    })
+    .await
+    .map_err(|e| format!("open task failed: {e}"))??;
+    let reader_id = uuid::Uuid::new_v4().to_string();
+    let summary = recording_reader_summary(&reader_id, &reader);
+    state
+        .write()
+        .await
+        .recording_readers
+        .insert(reader_id, reader);
+    Ok(summary)
+}
+
+pub async fn op_seek_recording(
+    params: SeekRecordingParams,
+    state: Arc<StateLock>,
+) -> Result<SeekRecordingResult> {
+    let reader_id = params.reader_id;
+    let mut st = state.write().await;
+    let reader = st
+        .recording_readers
+        .get_mut(&reader_id)
+        .ok_or_else(|| format!("recording reader not found: {reader_id}"))?;
+    reader.seek(params.timestamp)?;
+    let next = reader.peek(1)?;
+    Ok(SeekRecordingResult {
+        position: reader.position,
+        timestamp: next.first().map(|(record, _)| record.timestamp),
+        reader_id,
+    })
+}
+
+/// The next samples of a recording, rendered and compressed as `poll` does.
+/// What doesn't fit the response size limit stays unread.
+pub async fn op_read_recording(
+    params: ReadRecordingParams,
+    state: Arc<StateLock>,
+    blocks: &mut PayloadBlocks,
+) -> Result<ReadRecordingResult> {
+    let reader_id = params.reader_id;
+    let render = |blocks: &mut PayloadBlocks, s: &BufferedSample| match params.include_payload {
+        true => blocks.sample_value(s),
+        false => blocks::sample_metadata(s),
+    };
+
+    let mut guard = state.write().await;
+    let st = &mut *guard;
+    let reader = st
+        .recording_readers
+        .get_mut(&reader_id)
+        .ok_or_else(|| format!("recording reader not found: {reader_id}"))?;
+    let records = reader.peek(params.limit.saturating_add(1))?;
+    let more = records.len() > params.limit;
+    let (records, offsets): (Vec<_>, Vec<_>) = records.into_iter().take(params.limit).unzip();
+    let samples: Vec<BufferedSample> = records
+        .into_iter()
+        .zip(reader.position..)
+        .map(|(record, seq)| record.into_sample(seq))
+        .collect();
+    let take = match st.max_response_bytes {
+        Some(max_bytes) => {
+            let mut sizing = PayloadBlocks::new(blocks.collecting());
+            let sizes = samples
+                .iter()
+                .map(|s| serde_json::to_vec(&render(&mut sizing, s)).unwrap().len());
+            continuation::fit(sizes, max_bytes)
+        }
+        None => samples.len(),
+    };
+    if let Some(offset) = take.checked_sub(1).map(|last| offsets[last]) {
+        reader.advance(take as u64, offset);
+    }
+    let samples: Vec<Value> = samples[..take]
+        .iter()
+        .map(|s| {
+            let mut value = render(blocks, s);
+            if params.paced {
+                value["offset_ms"] = Value::from(reader.offset_ms(s.timestamp));
+            }
+            value
+        })
+        .collect();
+    let mut result = ReadRecordingResult {
+        sample_count: samples.len(),
+        position: reader.position,
+        remaining: reader.records - reader.position,
+        eof: take == offsets.len() && !more,
+        reader_id,
+        samples: None,
+        compression: None,
+        samples_compressed: None,
+    };
+    match st.compression {
+        Some(compression) => {
+            let raw = serde_json::to_vec(&samples).unwrap();
+            let packed = compression.compress(&raw);
+            result.compression = Some(compression.as_str());
+            result.samples_compressed =
+                Some(base64::engine::general_purpose::STANDARD.encode(packed));
+        }
+        None => result.samples = Some(samples),
+    }
+    Ok(result)
+}
+
+pub async fn op_list_recording_readers(
+    state: Arc<StateLock>,
+) -> Result<ListRecordingReadersResult> {
+    let st = state.read().await;
+    let readers: Vec<RecordingReaderSummary> = st
+        .recording_readers
+        .iter()
+        .map(|(id, reader)| recording_reader_summary(id, reader))
+        .collect();
+    Ok(ListRecordingReadersResult {
+        count: readers.len(),
+        readers,
+    })
+}
+
+pub async fn op_close_recording(
+    params: CloseRecordingParams,
+    state: Arc<StateLock>,
+) -> Result<CloseRecordingResult> {
+    let reader_id = params.reader_id;
+    match state.write().await.recording_readers.remove(&reader_id) {
+        Some(_) => Ok(CloseRecordingResult {
+            removed: true,
+            reader_id,
+        }),
+        None => Err(format!("recording reader not found: {reader_id}")),
+    }
+}
+
+/// Live, flight recorder and recording samples on a key expression over a
+/// time range, merged in time order. Recorded samples are used for each key
+/// only before its live samples start, since the two were captured by
+/// different subscribers and cannot be matched one to one.
+pub async fn op_get_timeline(
+    params: GetTimelineParams,
+    state: Arc<StateLock>,
+    blocks: &mut PayloadBlocks,
+) -> Result<GetTimelineResult> {
+    let to = params.to.unwrap_or_else(Utc::now);
+    let from = match (params.from, params.last_secs) {
+        (Some(from), _) => from,
+        (None, Some(secs)) => to - chrono::Duration::seconds(secs as i64),
+        (None, None) => return Err("pass from or last_secs".into()),
+    };
+    let window = timeline::Window::new(&params.key_expr, from, to)?;
+
+    let (live, covered, rings, policy, max_response_bytes, compression) = {
+        let st = state.read().await;
+        let (live, covered) = timeline::live(&st.subscriptions, &window);
+        let rings: Vec<(String, flight::RingHandle)> = st
+            .flight_recorders
+            .iter()
+            .map(|(id, recorder)| (id.clone(), recorder.ring.clone()))
+            .collect();
+        let policy = params.recordings.then(|| st.recordings.clone()).flatten();
+        (
+            live,
+            covered,
+            rings,
+            policy,
+            st.max_response_bytes,
+            st.compression,
+        )
+    };
+    let live_from = covered.start();
+
+    let recorded = tokio::task::spawn_blocking(move || {
+        let keep = |r: &recordings::Record| {
+            window.contains(&r.key_expr, r.timestamp) && !covered.covers(r)
+        };
+        let mut sources = Vec::new();
+        for (id, ring) in rings {
+            let records = flight::records(&ring, keep)?;
+            sources.push((SourceKind::FlightRecorder, id, records));
+        }
+        if let Some(policy) = policy {
+            for recording in recordings::list(&policy.dir)? {
+                // A file last written before the range holds nothing in it
+                if recording.modified < window.from {
+                    continue;
+                }
+                // Pruning may have removed it since the listing, and files
+                // encrypted with another key can't be read
+                let path = policy.dir.join(&recording.name);
+                let Ok(records) = recordings::records(&path, policy.cipher(), keep) else {
+                    continue;
+                };
+                sources.push((SourceKind::Recording, recording.name, records));
+            }
+        }
+        Ok::<_, String>(timeline::recorded(sources))
+    })
+    .await
+    .map_err(|e| format!("timeline task failed: {e}"))??;
+
+    let (mut entries, mut truncated) =
+        timeline::merge(recorded.into_iter().chain(live).collect(), params.limit);
+    let render = |blocks: &mut PayloadBlocks, entry: &timeline::Entry| {
+        let mut value = match params.include_payload {
+            true => blocks.sample_value(&entry.sample),
+            false => blocks::sample_metadata(&entry.sample),
+        };
+        if entry.kind != SourceKind::Live {
+            value.as_object_mut().unwrap().remove("seq");
+        }
+        value["source"] = serde_json::to_value(entry.kind).unwrap();
+        value["source_id"] = Value::from(entry.source_id.as_str());
+        value
+    };
+    // Keep the newest samples that fit
+    if let Some(max_bytes) = max_response_bytes {
+        let mut sizing = PayloadBlocks::new(blocks.collecting());
+        let sizes = entries
+            .iter()
+            .rev()
+            .map(|e| serde_json::to_vec(&render(&mut sizing, e)).unwrap().len());
+        let fits = continuation::fit(sizes, max_bytes);
+        truncated |= fits < entries.len();
+        entries.drain(..entries.len() - fits);
+    }
+    let sources = timeline::sources(&entries)
+        .into_iter()
+        .map(|(source, source_id, samples)| TimelineSource {
+            source,
+            source_id,
+            samples,
+        })
+        .collect();
+    let samples: Vec<Value> = entries.iter().map(|e| render(blocks, e)).collect();
+    let mut result = GetTimelineResult {
+        key_expr: params.key_expr,
+        from,
+        to,
+        sample_count: samples.len(),
+        truncated,
+        live_from,
+        sources,
+        samples: None,
+        compression: None,
+        samples_compressed: None,
+    };
+    match compression {
+        Some(compression) => {
+            let raw = serde_json::to_vec(&samples).unwrap();
+            let packed = compression.compress(&raw);
+            result.compression = Some(compression.as_str());
+            result.samples_compressed =
+                Some(base64::engine::general_purpose::STANDARD.encode(packed));
+        }
+        None => result.samples = Some(samples),
+    }
+    Ok(result)
+}
+
+pub async fn op_list_subscriptions(state: Arc<StateLock>) -> Result<ListSubscriptionsResult> {
+    let st = state.read().await;
+    let subs: Vec<SubscriptionSummary> = st
+        .subscriptions
+        .iter()
+        .map(|(id, sub)| subscription_summary(id, sub, &st.tags))
+        .collect();
+
+    Ok(ListSubscriptionsResult {
+        count: subs.len(),
+        subscriptions: subs,
+    })
+}
+
+pub async fn op_create_alert(
+    condition: AlertCondition,
+    state: Arc<StateLock>,
+) -> Result<AlertRule> {
+    let rule = AlertRule::new(condition.validate()?);
+
+    let mut st = state.write().await;
+    st.alerts.rules.insert(rule.alert_id.clone(), rule.clone());
+
+    Ok(rule)
+}
+
+pub async fn op_list_alerts(state: Arc<StateLock>) -> Result<ListAlertsResult> {
+    let st = state.read().await;
+    let alerts: Vec<AlertRule> = st.alerts.rules.values().cloned().collect();
+
+    Ok(ListAlertsResult {
+        count: alerts.len(),
+        alerts,
+        pending_events: st.alerts.events.len(),
+    })
+}
+
+pub async fn op_delete_alert(
+    params: DeleteAlertParams,
+    state: Arc<StateLock>,
+) -> Result<DeleteAlertResult> {
+    let alert_id = params.alert_id;
+
+    let mut st = state.write().await;
+    match st.alerts.rules.remove(&alert_id) {
+        Some(_) => Ok(DeleteAlertResult {
+            removed: true,
+            alert_id,
+        }),
+        None => Err(format!("alert not found: {alert_id}")),
+    }
+}
+
+pub async fn op_poll_alerts(
+    params: PollAlertsParams,
+    state: Arc<StateLock>,
+) -> Result<PollAlertsResult> {
+    let mut st = state.write().await;
+    let events = st.alerts.drain_events(params.limit);
+
+    Ok(PollAlertsResult {
+        event_count: events.len(),
+        events,
+        remaining: st.alerts.events.len(),
+        dropped_count: st.alerts.events_dropped,
+    })
+}
+
+pub async fn op_dump_state(session: &dyn ZenohBackend, state: Arc<StateLock>) -> Result<Value> {
+    let params = SessionInfoParams {
+        versions: false,
+        timeout_ms: 0,
+    };
+    let session_info = to_value(op_session_info(params, session).await?)?;
+    let st = state.read().await;
+    Ok(crate::dump::snapshot(&st, session_info))
+}
+
+pub async fn op_get_stats(state: Arc<StateLock>) -> Result<StatsResult> {
+    let st = state.read().await;
+    let buffered_samples: usize = st.subscriptions.values().map(|s| s.sample_count()).sum();
+    let buffered_bytes: usize = st.subscriptions.values().map(|s| s.buffered_bytes()).sum();
+    let runtime = tokio::runtime::Handle::current().metrics();
+
+    Ok(StatsResult {
+        uptime_secs: st.started.elapsed().as_secs(),
+        process: metrics::process_usage(),
+        subscription_count: st.subscriptions.len(),
+        topic_count: st.topics.len(),
+        buffered_samples,
+        buffered_bytes,
+        tasks_alive: runtime.num_alive_tasks(),
+        samples_ingested: metrics::SAMPLES_INGESTED.load(Ordering::Relaxed),
+        ingest_rate_hz: (metrics::ingest_rate_hz(st.started) * 100.0).round() / 100.0,
+        stdout_bytes: metrics::STDOUT_BYTES.load(Ordering::Relaxed),
+    })
+}
+
+pub async fn op_ros_graph(
+    params: RosGraphParams,
+    session: &dyn ZenohBackend,
+) -> Result<ros::RosGraph> {
+    let timeout = Duration::from_millis(params.timeout_ms);
+
+    let mut keys = ros::liveliness_keys(session, ros::RMW_ZENOH_LIVELINESS, timeout).await?;
+    keys.extend(ros::liveliness_keys(session, ros::BRIDGE_LIVELINESS, timeout).await?);
+    Ok(ros::build_graph(&keys, params.domain_id))
+}
+
+pub async fn op_ros_service_call(
+    params: RosServiceCallParams,
+    session: &dyn ZenohBackend,
+) -> Result<RosServiceCallResult> {
+    let service = params.service;
+    let timeout = Duration::from_millis(params.timeout_ms);
+    let request = match (&params.request, &params.request_b64) {
+        (Some(fields), _) => cdr::encode(fields)?,
+        (None, Some(b64)) => base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .map_err(|e| format!("invalid request_b64: {e}"))?,
+        (None, None) => cdr::encode(&[])?,
+    };
+
+    let key_expr = match params.key_expr {
+        Some(ke) => ke,
+        None => ros::service_key_expr(session, params.domain_id, &service, timeout).await?,
+    };
+    let options = GetOptions {
+        payload: Some(request),
+        attachment: Some(ros::next_request_attachment()),
+        destination: parse_locality(params.destination.as_deref())?,
+        ..Default::default()
+    };
+    let reply = session
+        .get(&key_expr, options, timeout)
+        .await
+        .map_err(|e| format!("service call to {key_expr} failed: {e}"))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("no reply from {key_expr} within {}ms", timeout.as_millis()))?;
+    let payload = reply
+        .result
+        .map_err(|e| format!("service {service} replied with error: {e}"))?
+        .payload;
+    let response = params
+        .response_types
+        .map(|types| cdr::decode(&types, &payload))
+        .transpose()?;
+
+    Ok(RosServiceCallResult {
+        service,
+        key_expr,
+        response,
+        payload: Payload(payload),
+    })
+}
+
+pub async fn op_probe_device(
+    params: ProbeDeviceParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+) -> Result<ProbeReport> {
+    let locator = params.locator.as_deref();
+    let ns = state.read().await.namespace.clone();
+    let scoped = |ke: Option<String>| ke.map(|ke| namespace::scope(ns.as_deref(), &ke));
+
+    // A locator gets its own client session so the probe only reaches that device
+    let transient = match locator {
+        Some(_) if session.kind() == "mock" => {
+            return Err("locator probes need a real zenoh session".into())
+        }
+        Some(locator) => Some(probe::connect_locator(locator).await?),
+        None => None,
+    };
+    let probe_session: &dyn ZenohBackend = match &transient {
+        Some(s) => s,
+        None => session,
+    };
+    let report: Result<ProbeReport> = async {
+        let zid = match params.zid {
+            Some(zid) => zid,
+            None if transient.is_some() => probe::connected_zids(probe_session)
+                .await
+                .into_iter()
+                .next()
+                .ok_or_else(|| format!("nothing answered at {}", locator.unwrap_or_default()))?,
+            None => return Err("missing required field: zid (or locator)".into()),
+        };
+
+        let plan = probe::ProbePlan {
+            zid,
+            liveliness_key: scoped(params.liveliness_key),
+            get_key: scoped(params.key_expr),
+            timeout: Duration::from_millis(params.timeout_ms),
+        };
+        let mut report = probe::run(probe_session, &plan).await;
+        report.locator = params.locator.clone();
+        Ok(report)
+    }
+    .await;
+    // Close the locator session however the probe went
+    if let Some(s) = transient {
+        s.close().await;
+    }
+    report
+}
+
+pub async fn op_set_namespace(
+    params: SetNamespaceParams,
+    state: Arc<StateLock>,
+) -> Result<SetNamespaceResult> {
+    let namespace = match params.namespace {
+        Some(ns) => namespace::parse(&ns)?,
+        None => None,
+    };
+    let mut st = state.write().await;
+    let previous = std::mem::replace(&mut st.namespace, namespace.clone());
+
+    // Existing subscriptions and discovery keep the namespace they started with
+    Ok(SetNamespaceResult {
+        namespace,
+        previous,
+    })
+}
+
+pub async fn op_publish(
+    params: PublishParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+) -> Result<PublishResult> {
+    let (key_expr, payload, encoding) = publish_entry(params)?;
+    let scoped = namespace::scope(state.read().await.namespace.as_deref(), &key_expr);
+    let bytes = payload.len();
+    session
+        .put(&scoped, payload, &encoding)
+        .await
+        .map_err(|e| format!("publish to {key_expr} failed: {e}"))?;
+
+    Ok(PublishResult {
+        key_expr,
+        encoding,
+        bytes,
+    })
+}
+
+/// Publish a command and wait for the first sample on `ack_key_expr`. The
+/// ack subscriber is declared before the put so a fast reply is not missed.
+pub async fn op_publish_with_ack(
+    params: PublishWithAckParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+) -> Result<PublishWithAckResult> {
+    let ack_key_expr = params.ack_key_expr;
+    let (key_expr, payload, encoding) = publish_entry(params.publish)?;
+    let ns = state.read().await.namespace.clone();
+
+    let mut acks = session
+        .subscribe(&namespace::scope(ns.as_deref(), &ack_key_expr))
+        .await
+        .map_err(|e| format!("subscribe to {ack_key_expr} failed: {e}"))?;
+
+    let bytes = payload.len();
+    let sent = std::time::Instant::now();
+    session
+        .put(
+            &namespace::scope(ns.as_deref(), &key_expr),
+            payload,
+            &encoding,
+        )
+        .await
+        .map_err(|e| format!("publish to {key_expr} failed: {e}"))?;
+
+    let ack = tokio::time::timeout(Duration::from_millis(params.timeout_ms), acks.recv())
+        .await
+        .ok()
+        .flatten();
+
+    Ok(PublishWithAckResult {
+        key_expr,
+        encoding,
+        bytes,
+        ack_key_expr,
+        acked: ack.is_some(),
+        latency_ms: ack.is_some().then(|| sent.elapsed().as_millis() as u64),
+        ack: ack.map(|sample| AckSample {
+            key_expr: namespace::strip(ns.as_deref(), &sample.key_expr),
+            encoding: sample.encoding,
+            payload: Payload(sample.payload),
+        }),
+    })
+}
+
+/// Render `template` and publish it like `publish` would publish the result.
+/// `counter` advances per key expression, starting at 0, and only when the
+/// put succeeds; a failed call hands its value back unless a concurrent call
+/// has already taken the next one.
+pub async fn op_publish_template(
+    params: PublishTemplateParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+) -> Result<PublishTemplateResult> {
+    let (counter, namespace) = {
+        let mut st = state.write().await;
+        let next = st
+            .template_counters
+            .entry(params.key_expr.clone())
+            .or_default();
+        let counter = *next;
+        *next += 1;
+        (counter, st.namespace.clone())
+    };
+    let template_key = params.key_expr.clone();
+    let published = publish_rendered(params, counter, namespace, session).await;
+    if published.is_err() {
+        let mut st = state.write().await;
+        if let Some(next) = st.template_counters.get_mut(&template_key) {
+            if *next == counter + 1 {
+                *next = counter;
+            }
+        }
+    }
+    published
+}
+
+async fn publish_rendered(
+    params: PublishTemplateParams,
+    counter: u64,
+    namespace: Option<String>,
+    session: &dyn ZenohBackend,
+) -> Result<PublishTemplateResult> {
+    let ctx = template::Context {
+        counter,
+        now: Utc::now(),
+        uuid: uuid::Uuid::new_v4().to_string(),
+        values: &params.values,
+    };
+    let rendered = template::render(&params.template, &ctx)?;
+
+    let (key_expr, payload, encoding) = publish_entry(PublishParams {
+        key_expr: params.key_expr,
+        payload: Some(rendered.clone()),
+        payload_b64: None,
+        encoding: params.encoding,
+    })?;
+    let scoped = namespace::scope(namespace.as_deref(), &key_expr);
+    let bytes = payload.len();
+    session
+        .put(&scoped, payload, &encoding)
+        .await
+        .map_err(|e| format!("publish to {key_expr} failed: {e}"))?;
+
+    Ok(PublishTemplateResult {
+        key_expr,
+        encoding,
+        bytes,
+        counter,
+        payload: rendered,
+    })
+}
+
+/// Publish a group of entries back-to-back at one priority. Every entry is
+/// validated before the first put, so a malformed entry fails the batch with
+/// nothing sent.
+pub async fn op_publish_batch(
+    params: PublishBatchParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+) -> Result<PublishBatchResult> {
+    if params.entries.is_empty() {
+        return Err("entries must not be empty".into());
+    }
+    let priority = params
+        .priority
+        .as_deref()
+        .map(Priority::parse)
+        .transpose()?
+        .unwrap_or_default();
+    let entries = params
+        .entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let (key_expr, payload, encoding) =
+                publish_entry(entry).map_err(|e| format!("entry {i}: {e}"))?;
+            zenoh::key_expr::KeyExpr::try_from(key_expr.as_str())
+                .map_err(|e| format!("entry {i}: invalid key_expr {key_expr}: {e}"))?;
+            Ok((key_expr, payload, encoding))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let ns = state.read().await.namespace.clone();
+    let (puts, sent): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .map(|(key_expr, payload, encoding)| {
+            let bytes = payload.len();
+            let put = PutEntry {
+                key_expr: namespace::scope(ns.as_deref(), &key_expr),
+                payload,
+                encoding: encoding.clone(),
+            };
+            (put, (key_expr, encoding, bytes))
+        })
+        .unzip();
+    let outcomes = session.put_batch(puts, priority).await;
+    let results: Vec<_> = sent
+        .into_iter()
+        .zip(outcomes)
+        .map(|((key_expr, encoding, bytes), outcome)| BatchEntryResult {
+            key_expr,
+            encoding,
+            bytes,
+            error: outcome.err(),
+        })
+        .collect();
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    Ok(PublishBatchResult {
+        priority: priority.as_str(),
+        published: results.len() - failed,
+        failed,
+        results,
+    })
+}
+
+pub async fn op_param_set(
+    params: ParamSetParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+) -> Result<ParamSetResult> {
+    let key_expr = crate::params::key(&params.prefix, &params.name)?;
+    let scoped = namespace::scope(state.read().await.namespace.as_deref(), &key_expr);
+    session
+        .put(
+            &scoped,
+            params.value.to_string().into_bytes(),
+            "application/json",
+        )
+        .await
+        .map_err(|e| format!("param_set of {key_expr} failed: {e}"))?;
+
+    Ok(ParamSetResult {
+        name: params.name,
+        key_expr,
+        value: params.value,
+    })
+}
+
+pub async fn op_param_get(
+    params: ParamGetParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+) -> Result<ParamGetResult> {
+    let key_expr = crate::params::key(&params.prefix, &params.name)?;
+    let scoped = namespace::scope(state.read().await.namespace.as_deref(), &key_expr);
+    let options = GetOptions {
+        latest: true,
+        destination: parse_locality(params.destination.as_deref())?,
+        ..Default::default()
+    };
+    let replies = session
+        .get(&scoped, options, Duration::from_millis(params.timeout_ms))
+        .await?;
+    let sample = replies.into_iter().find_map(|r| r.result.ok());
+
+    Ok(ParamGetResult {
+        name: params.name,
+        key_expr,
+        found: sample.is_some(),
+        value: sample.as_ref().map(crate::params::value),
+        encoding: sample.map(|s| s.encoding),
+    })
+}
+
+pub async fn op_param_list(
+    params: ParamListParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+) -> Result<ParamListResult> {
+    let key_expr = crate::params::all(&params.prefix)?;
+    let ns = state.read().await.namespace.clone();
+    let options = GetOptions {
+        latest: true,
+        destination: parse_locality(params.destination.as_deref())?,
+        ..Default::default()
+    };
+    let replies = session
+        .get(
+            &namespace::scope(ns.as_deref(), &key_expr),
+            options,
+            Duration::from_millis(params.timeout_ms),
+        )
+        .await?;
+
+    let mut entries: Vec<ParamEntry> = replies
+        .into_iter()
+        .filter_map(|r| r.result.ok())
+        .map(|sample| {
+            let key = namespace::strip(ns.as_deref(), &sample.key_expr);
+            ParamEntry {
+                name: crate::params::name(&params.prefix, &key),
+                value: crate::params::value(&sample),
+                encoding: sample.encoding,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries.dedup_by(|a, b| a.name == b.name);
+
+    Ok(ParamListResult {
+        prefix: params.prefix,
+        count: entries.len(),
+        params: entries,
+    })
+}
+
+/// Resolve a publish input into key, payload bytes and encoding. Strings
+/// default to text/plain, other JSON values to application/json and
+/// `payload_b64` to zenoh/bytes.
+fn publish_entry(params: PublishParams) -> Result<(String, Vec<u8>, String)> {
+    // A registered encoding with an encoder takes the JSON value as data
+    let encoded = match (&params.payload, &params.encoding) {
+        (Some(value), Some(encoding)) => encodings::encode(encoding, value).transpose()?,
+        _ => None,
+    };
+    let (payload, default_encoding) = match (encoded, params.payload, params.payload_b64) {
+        (Some(encoded), _, _) => (encoded, "zenoh/bytes"),
+        (None, Some(Value::String(s)), _) => (s.into_bytes(), "text/plain"),
+        (None, Some(v), _) => (v.to_string().into_bytes(), "application/json"),
+        (None, None, Some(b64)) => (
+            base64::engine::general_purpose::STANDARD
+                .decode(b64)
+                .map_err(|e| format!("invalid payload_b64: {e}"))?,
+            "zenoh/bytes",
+        ),
+        (None, None, None) => return Err("missing required field: payload (or payload_b64)".into()),
+    };
+    let encoding = params
+        .encoding
+        .unwrap_or_else(|| default_encoding.to_string());
+    Ok((params.key_expr, payload, encoding))
+}
+
+pub async fn op_start_generator(
+    params: StartGeneratorParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<StartGeneratorResult> {
+    let pattern = Pattern::parse(&params.pattern)?;
+    if !(params.rate_hz > 0.0 && params.rate_hz <= 100_000.0) {
+        return Err("rate_hz must be greater than 0 and at most 100000".into());
+    }
+    if params.payload_size > 16 * 1024 * 1024 {
+        return Err("payload_size must be at most 16 MiB".into());
+    }
+    zenoh::key_expr::KeyExpr::try_from(params.key_expr.as_str())
+        .map_err(|e| format!("invalid key_expr {}: {e}", params.key_expr))?;
+
+    let scoped = namespace::scope(state.read().await.namespace.as_deref(), &params.key_expr);
+    let config = GeneratorConfig {
+        pattern,
+        payload_size: params.payload_size,
+        rate_hz: params.rate_hz,
+        jitter: Duration::from_millis(params.jitter_ms),
+        count: params.count,
+    };
+    let generator = start_generator(session, params.key_expr.clone(), scoped, config);
+
+    let gen_id = uuid::Uuid::new_v4().to_string();
+    state
+        .write()
+        .await
+        .generators
+        .insert(gen_id.clone(), generator);
+
+    Ok(StartGeneratorResult {
+        gen_id,
+        key_expr: params.key_expr,
+        encoding: pattern.encoding(),
+    })
+}
+
+pub async fn op_list_generators(state: Arc<StateLock>) -> Result<ListGeneratorsResult> {
+    let st = state.read().await;
+    let generators: Vec<GeneratorSummary> = st
+        .generators
+        .iter()
+        .map(|(id, generator)| generator_summary(id, generator))
+        .collect();
+
+    Ok(ListGeneratorsResult {
+        count: generators.len(),
+        generators,
+    })
+}
+
+pub async fn op_stop_generator(
+    params: StopGeneratorParams,
+    state: Arc<StateLock>,
+) -> Result<StopGeneratorResult> {
+    let gen_id = params.gen_id;
+    let mut st = state.write().await;
+    match st.generators.remove(&gen_id) {
+        Some(generator) => {
+            let _ = generator.cancel.send(true);
+            Ok(StopGeneratorResult {
+                removed: true,
+                gen_id,
+                published: generator.stats.published.load(Ordering::Relaxed),
+            })
+        }
+        None => Err(format!("generator not found: {gen_id}")),
+    }
+}
+
+pub async fn op_start_echo(
+    params: StartEchoParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<StartEchoResult> {
+    let parse = |key_expr: &str| {
+        zenoh::key_expr::KeyExpr::try_from(key_expr.to_string())
+            .map_err(|e| format!("invalid key_expr {key_expr}: {e}"))
+    };
+    if parse(&params.from_key_expr)?.intersects(&parse(&params.to_key_expr)?) {
+        return Err("to_key_expr overlaps from_key_expr; echoes would feed back".into());
+    }
+
+    let namespace = state.read().await.namespace.clone();
+    let echo = start_echo(
+        session,
+        params.from_key_expr.clone(),
+        params.to_key_expr.clone(),
+        &namespace::scope(namespace.as_deref(), &params.from_key_expr),
+        namespace::scope(namespace.as_deref(), &params.to_key_expr),
+        Duration::from_millis(params.delay_ms),
+    )
+    .await?;
+
+    let echo_id = uuid::Uuid::new_v4().to_string();
+    state.write().await.echoes.insert(echo_id.clone(), echo);
+
+    Ok(StartEchoResult {
+        echo_id,
+        from_key_expr: params.from_key_expr,
+        to_key_expr: params.to_key_expr,
+        delay_ms: params.delay_ms,
+    })
+}
+
+pub async fn op_list_echoes(state: Arc<StateLock>) -> Result<ListEchoesResult> {
+    let st = state.read().await;
+    let echoes: Vec<EchoSummary> = st
+        .echoes
+        .iter()
+        .map(|(id, echo)| echo_summary(id, echo))
+        .collect();
+
+    Ok(ListEchoesResult {
+        count: echoes.len(),
+        echoes,
+    })
+}
+
+/// Stop receiving; samples already waiting out their delay are still sent.
+pub async fn op_stop_echo(params: StopEchoParams, state: Arc<StateLock>) -> Result<StopEchoResult> {
+    let echo_id = params.echo_id;
+    let mut st = state.write().await;
+    match st.echoes.remove(&echo_id) {
+        Some(echo) => {
+            let _ = echo.cancel.send(true);
+            Ok(StopEchoResult {
+                removed: true,
+                echo_id,
+            })
+        }
+        None => Err(format!("echo not found: {echo_id}")),
+    }
+}
+
+pub async fn op_create_bridge(
+    params: CreateBridgeParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<CreateBridgeResult> {
+    if params.rules.is_empty() {
+        return Err("rules must list at least one remap rule".into());
+    }
+    let source = zenoh::key_expr::KeyExpr::try_from(params.key_expr.as_str())
+        .map_err(|e| format!("invalid key_expr {}: {e}", params.key_expr))?;
+    let rules = params
+        .rules
+        .into_iter()
+        .map(RemapRule::validate)
+        .collect::<Result<Vec<_>>>()?;
+    for rule in &rules {
+        let target = zenoh::key_expr::KeyExpr::try_from(format!("{}/**", rule.to_prefix))
+            .map_err(|e| format!("invalid remap prefix {}: {e}", rule.to_prefix))?;
+        if target.intersects(&source) {
+            return Err(format!(
+                "to_prefix {} overlaps key_expr; forwarded samples would feed back",
+                rule.to_prefix
+            ));
+        }
+    }
+
+    let namespace = state.read().await.namespace.clone();
+    let bridge = start_bridge(session, params.key_expr.clone(), rules.clone(), namespace).await?;
+
+    let bridge_id = uuid::Uuid::new_v4().to_string();
+    state
+        .write()
+        .await
+        .bridges
+        .insert(bridge_id.clone(), bridge);
+
+    Ok(CreateBridgeResult {
+        bridge_id,
+        key_expr: params.key_expr,
+        rules,
+    })
+}
+
+pub async fn op_list_bridges(state: Arc<StateLock>) -> Result<ListBridgesResult> {
+    let st = state.read().await;
+    let bridges: Vec<BridgeSummary> = st
+        .bridges
+        .iter()
+        .map(|(id, bridge)| bridge_summary(id, bridge))
+        .collect();
+
+    Ok(ListBridgesResult {
+        count: bridges.len(),
+        bridges,
+    })
+}
+
+pub async fn op_delete_bridge(
+    params: DeleteBridgeParams,
+    state: Arc<StateLock>,
+) -> Result<DeleteBridgeResult> {
+    let bridge_id = params.bridge_id;
+    let mut st = state.write().await;
+    match st.bridges.remove(&bridge_id) {
+        Some(bridge) => {
+            let _ = bridge.cancel.send(true);
+            Ok(DeleteBridgeResult {
+                removed: true,
+                bridge_id,
+            })
+        }
+        None => Err(format!("bridge not found: {bridge_id}")),
+    }
+}
+
+pub async fn op_add_federation_peer(
+    params: AddFederationPeerParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<FederationPeerSummary> {
+    let name = params.name;
+    if name.is_empty() || name == federation::LOCAL_ORIGIN {
+        return Err(format!(
+            "name must be non-empty and not {}",
+            federation::LOCAL_ORIGIN
+        ));
+    }
+    if state.read().await.federation_peers.contains_key(&name) {
+        return Err(format!("federation peer already exists: {name}"));
+    }
+    let admin_key_expr = admin::parse_prefix(&params.admin_key_expr)?;
+    let peer_session: Option<Arc<dyn ZenohBackend>> = match params.locator.as_deref() {
+        Some(_) if session.kind() == "mock" => {
+            return Err("locator peers need a real zenoh session".into())
+        }
+        Some(locator) => Some(Arc::new(probe::connect_locator(locator).await?)),
+        None => None,
+    };
+    let mut peer = federation::Peer {
+        admin_key_expr,
+        locator: params.locator,
+        session: peer_session,
+        added_at: Utc::now(),
+        last_contact: None,
+        last_error: None,
+        zid: None,
+    };
+
+    // An unreachable peer is still added; it may come up later
+    let info = federation::call(
+        &*peer.session(&session),
+        &peer.admin_key_expr,
+        "session_info",
+        &serde_json::json!({}),
+        Duration::from_millis(params.timeout_ms),
+    )
+    .await;
+    peer.contacted(&info);
+    if let Ok(info) = &info {
+        peer.zid = info["zid"].as_str().map(str::to_string);
+    }
+
+    let mut st = state.write().await;
+    if st.federation_peers.contains_key(&name) {
+        return Err(format!("federation peer {name} was added concurrently"));
+    }
+    let summary = federation_peer_summary(&name, &peer);
+    st.federation_peers.insert(name, peer);
+    Ok(summary)
+}
+
+pub async fn op_list_federation_peers(state: Arc<StateLock>) -> Result<ListFederationPeersResult> {
+    let st = state.read().await;
+    let mut peers: Vec<FederationPeerSummary> = st
+        .federation_peers
+        .iter()
+        .map(|(name, peer)| federation_peer_summary(name, peer))
+        .collect();
+    peers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(ListFederationPeersResult {
+        count: peers.len(),
+        peers,
+    })
+}
+
+pub async fn op_remove_federation_peer(
+    params: RemoveFederationPeerParams,
+    state: Arc<StateLock>,
+) -> Result<RemoveFederationPeerResult> {
+    let name = params.name;
+    match state.write().await.federation_peers.remove(&name) {
+        Some(_) => Ok(RemoveFederationPeerResult {
+            removed: true,
+            name,
+        }),
+        None => Err(format!("federation peer not found: {name}")),
+    }
+}
+
+pub async fn op_get_federated_view(
+    params: GetFederatedViewParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<GetFederatedViewResult> {
+    let timeout = Duration::from_millis(params.timeout_ms);
+    let mut origins = Vec::new();
+    let mut topics: Vec<(String, Value)> = Vec::new();
+
+    let (peers, local_topics) = {
+        let st = state.read().await;
+        let peers: Vec<(String, String, Arc<dyn ZenohBackend>)> = st
+            .federation_peers
+            .iter()
+            .map(|(name, peer)| {
+                let peer_session = peer.session(&session);
+                (name.clone(), peer.admin_key_expr.clone(), peer_session)
+            })
+            .collect();
+        let now = Utc::now();
+        let local_topics: Vec<TopicSummary> = match params.include_local {
+            true => st
+                .topics
+                .values()
+                .filter(|t| t.key_expr.starts_with(&params.prefix))
+                .map(|t| topic_summary(t, now, &st))
+                .collect(),
+            false => Vec::new(),
+        };
+        (peers, local_topics)
+    };
+
+    // Peers are asked in parallel so one slow origin costs one timeout
+    let calls: Vec<_> = peers
+        .into_iter()
+        .map(|(name, admin_key_expr, peer_session)| {
+            let prefix = params.prefix.clone();
+            tokio::spawn(async move {
+                let no_input = serde_json::json!({});
+                let info = federation::call(
+                    &*peer_session,
+                    &admin_key_expr,
+                    "session_info",
+                    &no_input,
+                    timeout,
+                )
+                .await;
+                let listed = match &info {
+                    Ok(_) => {
+                        federation::topics(&*peer_session, &admin_key_expr, &prefix, timeout).await
+                    }
+                    Err(e) => Err(e.clone()),
+                };
+                (name, info, listed)
+            })
+        })
+        .collect();
+
+    if params.include_local {
+        let info = op_session_info(
+            SessionInfoParams {
+                versions: false,
+                timeout_ms: params.timeout_ms,
+            },
+            &*session,
+        )
+        .await
+        .and_then(to_value);
+        origins.push(FederatedOrigin {
+            origin: federation::LOCAL_ORIGIN.into(),
+            reachable: true,
+            error: info.as_ref().err().cloned(),
+            topic_count: local_topics.len(),
+            session: info.ok(),
+        });
+        for topic in local_topics {
+            topics.push((federation::LOCAL_ORIGIN.into(), to_value(topic)?));
+        }
+    }
+
+    for call in calls {
+        let (name, info, listed) = call
+            .await
+            .map_err(|e| format!("federation task failed: {e}"))?;
+        // The peer may have been removed while it was being asked
+        if let Some(peer) = state.write().await.federation_peers.get_mut(&name) {
+            peer.contacted(&listed);
+            if let Ok(info) = &info {
+                peer.zid = info["zid"].as_str().map(str::to_string);
+            }
+        }
+        let (topic_count, error) = match listed {
+            Ok(listed) => {
+                let count = listed.len();
+                topics.extend(listed.into_iter().map(|topic| (name.clone(), topic)));
+                (count, None)
+            }
+            Err(e) => (0, Some(e)),
+        };
+        origins.push(FederatedOrigin {
+            origin: name,
+            reachable: error.is_none(),
+            error,
+            topic_count,
+            session: info.ok(),
+        });
+    }
+
+    let key = |topic: &Value| topic["key_expr"].as_str().unwrap_or_default().to_string();
+    topics.sort_by(|(a_origin, a), (b_origin, b)| key(a).cmp(&key(b)).then(a_origin.cmp(b_origin)));
+    let topics: Vec<Value> = topics
+        .into_iter()
+        .map(|(origin, mut topic)| {
+            if let Some(fields) = topic.as_object_mut() {
+                fields.insert("origin".into(), Value::String(origin));
+            }
+            topic
+        })
+        .collect();
+
+    Ok(GetFederatedViewResult {
+        origins,
+        topic_count: topics.len(),
+        topics,
+    })
+}
+
+pub async fn op_create_throttled_relay(
+    params: CreateThrottledRelayParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<CreateThrottledRelayResult> {
+    let min_interval = match params.max_rate_hz {
+        Some(hz) if hz > 0.0 => Some(Duration::from_secs_f64(1.0 / hz)),
+        Some(_) => return Err("max_rate_hz must be greater than 0".into()),
+        None if params.on_change => None,
+        None => return Err("set max_rate_hz, on_change, or both".into()),
+    };
+    let to_prefix = params.to_prefix.trim_matches('/').to_string();
+    let source = zenoh::key_expr::KeyExpr::try_from(params.key_expr.as_str())
+        .map_err(|e| format!("invalid key_expr {}: {e}", params.key_expr))?;
+    let target = zenoh::key_expr::KeyExpr::try_from(format!("{to_prefix}/**"))
+        .map_err(|e| format!("invalid to_prefix {to_prefix}: {e}"))?;
+    if to_prefix.contains('*') || target.intersects(&source) {
+        return Err(format!(
+            "to_prefix {to_prefix} must be a plain prefix that does not overlap key_expr"
+        ));
+    }
+
+    let config = ThrottleConfig {
+        min_interval,
+        on_change: params.on_change,
+    };
+    let namespace = state.read().await.namespace.clone();
+    let relay = start_relay(
+        session,
+        params.key_expr.clone(),
+        to_prefix.clone(),
+        config,
+        namespace,
+    )
+    .await?;
+
+    let relay_id = uuid::Uuid::new_v4().to_string();
+    state
+        .write()
+        .await
+        .throttled_relays
+        .insert(relay_id.clone(), relay);
+
+    Ok(CreateThrottledRelayResult {
+        relay_id,
+        key_expr: params.key_expr,
+        to_prefix,
+        max_rate_hz: params.max_rate_hz,
+        on_change: params.on_change,
+    })
+}
+
+pub async fn op_list_throttled_relays(state: Arc<StateLock>) -> Result<ListThrottledRelaysResult> {
+    let st = state.read().await;
+    let relays: Vec<ThrottledRelaySummary> = st
+        .throttled_relays
+        .iter()
+        .map(|(id, relay)| throttled_relay_summary(id, relay))
+        .collect();
+
+    Ok(ListThrottledRelaysResult {
+        count: relays.len(),
+        relays,
+    })
+}
+
+pub async fn op_delete_throttled_relay(
+    params: DeleteThrottledRelayParams,
+    state: Arc<StateLock>,
+) -> Result<DeleteThrottledRelayResult> {
+    let relay_id = params.relay_id;
+    let mut st = state.write().await;
+    match st.throttled_relays.remove(&relay_id) {
+        Some(relay) => {
+            let _ = relay.cancel.send(true);
+            Ok(DeleteThrottledRelayResult {
+                removed: true,
+                relay_id,
+            })
+        }
+        None => Err(format!("throttled relay not found: {relay_id}")),
+    }
+}
+
+pub async fn op_create_preview(
+    params: CreatePreviewParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<CreatePreviewResult> {
+    if !(params.rate_hz > 0.0 && params.rate_hz <= MAX_PREVIEW_RATE_HZ) {
+        return Err(format!(
+            "rate_hz must be greater than 0 and at most {MAX_PREVIEW_RATE_HZ}"
+        ));
+    }
+    if params.max_keys == 0 {
+        return Err("max_keys must be at least 1".into());
+    }
+
+    let preview_id = uuid::Uuid::new_v4().to_string();
+    let namespace = state.read().await.namespace.clone();
+    let preview = start_preview(
+        session,
+        preview_id.clone(),
+        params.key_expr.clone(),
+        params.rate_hz,
+        params.max_keys,
+        params.notify,
+        namespace,
+    )
+    .await?;
+    state
+        .write()
+        .await
+        .previews
+        .insert(preview_id.clone(), preview);
+
+    Ok(CreatePreviewResult {
+        preview_id,
+        key_expr: params.key_expr,
+        rate_hz: params.rate_hz,
+        notify: params.notify,
+        max_keys: params.max_keys,
+    })
+}
+
+pub async fn op_poll_preview(
+    params: PollPreviewParams,
+    state: Arc<StateLock>,
+    blocks: &mut PayloadBlocks,
+) -> Result<PollPreviewResult> {
+    let st = state.read().await;
+    let preview = st
+        .previews
+        .get(&params.preview_id)
+        .ok_or_else(|| format!("preview not found: {}", params.preview_id))?;
+    let frames = preview.frames.lock().unwrap();
+    let samples: Vec<Value> = frames
+        .since(params.since_frame)
+        .map(|s| blocks.sample_value(s))
+        .collect();
+
+    Ok(PollPreviewResult {
+        preview_id: params.preview_id,
+        frame: frames.frame,
+        sample_count: samples.len(),
+        samples,
+    })
+}
+
+pub async fn op_list_previews(state: Arc<StateLock>) -> Result<ListPreviewsResult> {
+    let st = state.read().await;
+    let previews: Vec<PreviewSummary> = st
+        .previews
+        .iter()
+        .map(|(id, preview)| preview_summary(id, preview))
+        .collect();
+
+    Ok(ListPreviewsResult {
+        count: previews.len(),
+        previews,
+    })
+}
+
+pub async fn op_delete_preview(
+    params: DeletePreviewParams,
+    state: Arc<StateLock>,
+) -> Result<DeletePreviewResult> {
+    let preview_id = params.preview_id;
+    let mut st = state.write().await;
+    match st.previews.remove(&preview_id) {
+        Some(preview) => {
+            let _ = preview.cancel.send(true);
+            Ok(DeletePreviewResult {
+                removed: true,
+                preview_id,
+            })
+        }
+        None => Err(format!("preview not found: {preview_id}")),
+    }
+}
+
+pub async fn op_query(
+    params: QueryParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<QueryResult> {
+    if params.batch_size == 0 {
+        return Err("batch_size must be at least 1".into());
+    }
+    let options = GetOptions {
+        latest: params.latest,
+        destination: parse_locality(params.destination.as_deref())?,
+        ..Default::default()
+    };
+
+    let query_id = uuid::Uuid::new_v4().to_string();
+    let mut st = state.write().await;
+    // Queries nobody will poll again would otherwise pile up
+    st.queries
+        .retain(|_, query| !query.replies.lock().unwrap().drained());
+    let query = start_query(
+        session,
+        query_id.clone(),
+        params.selector.clone(),
+        options,
+        params.timeout_ms,
+        params.notify.then_some(params.batch_size),
+        st.namespace.clone(),
+    );
+    st.queries.insert(query_id.clone(), query);
+
+    Ok(QueryResult {
+        query_id,
+        selector: params.selector,
+        timeout_ms: params.timeout_ms,
+        notify: params.notify,
+    })
+}
+
+pub async fn op_poll_query(
+    params: PollQueryParams,
+    state: Arc<StateLock>,
+    blocks: &mut PayloadBlocks,
+) -> Result<PollQueryResult> {
+    if params.max_replies == 0 {
+        return Err("max_replies must be at least 1".into());
+    }
+    let mut st = state.write().await;
+    let query = st
+        .queries
+        .get(&params.query_id)
+        .ok_or_else(|| format!("query not found: {}", params.query_id))?;
+    let mut replies = query.replies.lock().unwrap();
+    let (samples, errors) = replies.take(params.max_replies);
+    let samples: Vec<Value> = samples.iter().map(|s| blocks.sample_value(s)).collect();
+    let result = PollQueryResult {
+        query_id: params.query_id,
+        sample_count: samples.len(),
+        samples,
+        errors,
+        received: replies.received,
+        remaining: replies.pending(),
+        complete: replies.complete,
+        failure: replies.failure.clone(),
+    };
+    let drained = replies.drained();
+    drop(replies);
+    if drained {
+        st.queries.remove(&result.query_id);
+    }
+    Ok(result)
+}
+
+pub async fn op_list_queries(state: Arc<StateLock>) -> Result<ListQueriesResult> {
+    let st = state.read().await;
+    let queries: Vec<QuerySummary> = st
+        .queries
+        .iter()
+        .map(|(id, query)| query_summary(id, query))
+        .collect();
+
+    Ok(ListQueriesResult {
+        count: queries.len(),
+        queries,
+    })
+}
+
+pub async fn op_cancel_query(
+    params: CancelQueryParams,
+    state: Arc<StateLock>,
+) -> Result<CancelQueryResult> {
+    let query_id = params.query_id;
+    let mut st = state.write().await;
+    match st.queries.remove(&query_id) {
+        Some(query) => {
+            let _ = query.cancel.send(true);
+            Ok(CancelQueryResult {
+                removed: true,
+                query_id,
+            })
+        }
+        None => Err(format!("query not found: {query_id}")),
+    }
+}
+
+pub async fn op_query_history(
+    params: QueryHistoryParams,
+    session: &dyn ZenohBackend,
+    state: Arc<StateLock>,
+    blocks: &mut PayloadBlocks,
+) -> Result<QueryHistoryResult> {
+    let range = timespec::Range::resolve(
+        params.from.as_deref(),
+        params.to.as_deref(),
+        params.duration.as_deref(),
+        Utc::now(),
+    )?;
+    let selector = range.selector(&params.key_expr);
+    let options = GetOptions {
+        destination: parse_locality(params.destination.as_deref())?,
+        ..Default::default()
+    };
+    let (namespace, max_response_bytes) = {
+        let st = state.read().await;
+        (st.namespace.clone(), st.max_response_bytes)
+    };
+    let replies = session
+        .get(
+            &namespace::scope(namespace.as_deref(), &selector),
+            options,
+            Duration::from_millis(params.timeout_ms),
+        )
+        .await
+        .map_err(|e| format!("query {selector} failed: {e}"))?;
+
+    let mut collected = query::Replies::default();
+    for reply in replies {
+        collected.push(reply, namespace.as_deref());
+    }
+    let mut samples = Vec::from(std::mem::take(&mut collected.samples));
+    // Stable, so unstamped samples keep their arrival order
+    samples.sort_by_key(|s| s.source_timestamp.unwrap_or(s.timestamp));
+    let mut truncated = samples.len() > params.limit;
+    samples.drain(..samples.len().saturating_sub(params.limit));
+
+    // Reply order means nothing once sorted
+    let render = |blocks: &mut PayloadBlocks, sample: &BufferedSample| {
+        let mut value = blocks.sample_value(sample);
+        value.as_object_mut().unwrap().remove("seq");
+        value
+    };
+    // Keep the newest samples that fit
+    if let Some(max_bytes) = max_response_bytes {
+        let mut sizing = PayloadBlocks::new(blocks.collecting());
+        let sizes = samples
+            .iter()
+            .rev()
+            .map(|s| serde_json::to_vec(&render(&mut sizing, s)).unwrap().len());
+        let fits = continuation::fit(sizes, max_bytes);
+        truncated |= fits < samples.len();
+        samples.drain(..samples.len() - fits);
+    }
+    let samples: Vec<Value> = samples.iter().map(|s| render(blocks, s)).collect();
+
+    Ok(QueryHistoryResult {
+        key_expr: params.key_expr,
+        selector,
+        from: range.from,
+        to: range.to,
+        sample_count: samples.len(),
+        truncated,
+        samples,
+        errors: collected.errors.into(),
+    })
+}
+
+pub async fn op_load_plugin(
+    params: LoadPluginParams,
+    state: Arc<StateLock>,
+) -> Result<LoadPluginResult> {
+    if params.name.is_empty() {
+        return Err("name must not be empty".into());
+    }
+    let wasm = match (&params.wasm_b64, &params.path) {
+        (Some(b64), None) => base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .map_err(|e| format!("invalid wasm_b64: {e}"))?,
+        (None, Some(path)) => {
+            std::fs::read(path).map_err(|e| format!("failed to read {path}: {e}"))?
+        }
+        _ => return Err("provide exactly one of wasm_b64 or path".into()),
+    };
+    let plugin = Plugin::compile(&wasm)?;
+    let hooks = plugin.hooks.names();
+    let size = plugin.size;
+    let replaced = state
+        .write()
+        .await
+        .plugins
+        .insert(params.name.clone(), plugin)
+        .is_some();
+
+    Ok(LoadPluginResult {
+        name: params.name,
+        size,
+        hooks,
+        replaced,
+    })
+}
+
+pub async fn op_list_plugins(state: Arc<StateLock>) -> Result<ListPluginsResult> {
+    let st = state.read().await;
+    let mut plugins: Vec<PluginSummary> = st
+        .plugins
+        .iter()
+        .map(|(name, plugin)| PluginSummary {
+            name: name.clone(),
+            size: plugin.size,
+            hooks: plugin.hooks.names(),
+            subscriptions: st
+                .subscriptions
+                .values()
+                .filter(|sub| sub.ingest.plugin.as_ref().is_some_and(|p| &p.name == name))
+                .count(),
+            loaded_at: plugin.loaded_at.to_rfc3339(),
+        })
+        .collect();
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(ListPluginsResult {
+        count: plugins.len(),
+        plugins,
+    })
+}
+
+pub async fn op_unload_plugin(
+    params: UnloadPluginParams,
+    state: Arc<StateLock>,
+) -> Result<UnloadPluginResult> {
+    let name = params.name;
+    match state.write().await.plugins.remove(&name) {
+        Some(_) => Ok(UnloadPluginResult {
+            removed: true,
+            name,
+        }),
+        None => Err(format!("plugin not found: {name}")),
+    }
+}
+
+pub async fn op_render_sample(
+    params: RenderSampleParams,
+    state: Arc<StateLock>,
+) -> Result<RenderSampleResult> {
+    let payload = match (params.payload_b64, params.payload) {
+        (Some(b64), None) => base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .map_err(|e| format!("invalid payload_b64: {e}"))?,
+        (None, Some(text)) => text.into_bytes(),
+        _ => return Err("provide exactly one of payload_b64 or payload".into()),
+    };
+    let format = DecodeFormat::parse(params.decode.as_deref().unwrap_or("auto"))?;
+    let view = View::parse(params.view.as_deref().unwrap_or("auto"))?;
+
+    // A fresh instance, so rendering never disturbs a subscription's plugin state
+    let (payload, decoded) = match &params.plugin {
+        Some(name) => {
+            let instance = {
+                let st = state.read().await;
+                let plugin = st
+                    .plugins
+                    .get(name)
+                    .ok_or_else(|| format!("plugin not found: {name}"))?;
+                PluginInstance::new(name, plugin)?
+            };
+            let processed = instance
+                .process(payload)
+                .ok_or_else(|| format!("plugin {name} filtered the payload out"))?;
+            (processed.payload, processed.decoded.transpose()?)
+        }
+        None => (payload, None),
+    };
+    let rendered = render::render(
+        &payload,
+        &params.encoding,
+        format,
+        decoded,
+        view,
+        params.max_bytes,
+    )?;
+
+    Ok(RenderSampleResult {
+        encoding: params.encoding,
+        bytes: payload.len(),
+        rendered,
+    })
+}
+
+pub fn op_register_encoding(params: RegisterEncodingParams) -> Result<RegisterEncodingResult> {
+    if params.encoding.is_empty() || params.encoding == ";" {
+        return Err("encoding must not be empty".into());
+    }
+    let decode = match params.decode.as_str() {
+        "none" => None,
+        "auto" => return Err("decode must be json, cbor, protobuf or none".into()),
+        other => Some(DecodeFormat::parse(other)?),
+    };
+    let view = match params.view.as_deref().map(View::parse).transpose()? {
+        Some(View::Auto) => return Err("view must be json, text or hex".into()),
+        view => view,
+    };
+    let replaced = encodings::register(params.encoding.clone(), Rule { decode, view });
+    Ok(RegisterEncodingResult {
+        encoding: params.encoding,
+        replaced,
+    })
+}
+
+pub fn op_list_encodings() -> Result<ListEncodingsResult> {
+    let registered = encodings::registered()
+        .into_iter()
+        .map(|(encoding, rule)| EncodingSummary {
+            encoding,
+            decode: rule.decode.map_or("none", |format| format.as_str()),
+            view: rule.view.map(|view| view.as_str()),
+            builtin: false,
+        });
+    let builtin = encodings::BUILTIN.iter().map(|format| EncodingSummary {
+        encoding: format.as_str().to_string(),
+        decode: format.as_str(),
+        view: None,
+        builtin: true,
+    });
+    let encodings: Vec<EncodingSummary> = registered.chain(builtin).collect();
+    Ok(ListEncodingsResult {
+        count: encodings.len(),
+        encodings,
+    })
+}
+
+pub fn op_unregister_encoding(
+    params: UnregisterEncodingParams,
+) -> Result<UnregisterEncodingResult> {
+    match encodings::unregister(&params.encoding) {
+        true => Ok(UnregisterEncodingResult {
+            removed: true,
+            encoding: params.encoding,
+        }),
+        false => Err(format!("encoding not registered: {}", params.encoding)),
+    }
+}
+
+pub fn op_add_redaction_rule(params: AddRedactionRuleParams) -> Result<RedactionRuleSummary> {
+    let key_expr = zenoh::key_expr::KeyExpr::try_from(params.key_expr.clone())
+        .map_err(|e| format!("invalid key_expr {}: {e}", params.key_expr))?;
+    let path = jsonpath::Pattern::parse(&params.path)?;
+    let action = match (params.action.as_str(), params.mask) {
+        ("mask", mask) => Action::Mask(mask.unwrap_or_else(|| Value::from("***"))),
+        ("drop", None) => Action::Drop,
+        ("drop", Some(_)) => return Err("mask only applies to the mask action".into()),
+        (other, _) => return Err(format!("action must be mask or drop, not {other}")),
+    };
+    let rule = redaction::add(RedactionRule {
+        id: uuid::Uuid::new_v4().to_string(),
+        key_expr,
+        path,
+        action,
+        applied: AtomicU64::new(0),
+        created_at: Utc::now(),
+    });
+    Ok(redaction_rule_summary(&rule))
+}
+
+pub fn op_list_redaction_rules() -> Result<ListRedactionRulesResult> {
+    let rules: Vec<RedactionRuleSummary> = redaction::rules()
+        .iter()
+        .map(|rule| redaction_rule_summary(rule))
+        .collect();
+    Ok(ListRedactionRulesResult {
+        count: rules.len(),
+        rules,
+    })
+}
+
+pub fn op_remove_redaction_rule(
+    params: RemoveRedactionRuleParams,
+) -> Result<RemoveRedactionRuleResult> {
+    match redaction::remove(&params.rule_id) {
+        true => Ok(RemoveRedactionRuleResult {
+            removed: true,
+            rule_id: params.rule_id,
+        }),
+        false => Err(format!("redaction rule not found: {}", params.rule_id)),
+    }
+}
+
+pub async fn op_watch_key(
+    params: WatchKeyParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<WatchKeyResult> {
+    if params.buffer_size == 0 {
+        return Err("buffer_size must be at least 1".into());
+    }
+    let config = KeyWatchConfig {
+        compare: Compare::parse(&params.compare)?,
+        decode: params
+            .decode
+            .as_deref()
+            .map(DecodeFormat::parse)
+            .transpose()?,
+        filter: params
+            .filter
+            .as_deref()
+            .map(Expression::parse)
+            .transpose()?,
+        buffer_size: params.buffer_size,
+        notify: params.notify,
+    };
+
+    let watch_id = uuid::Uuid::new_v4().to_string();
+    let namespace = state.read().await.namespace.clone();
+    let key_watch = start_key_watch(
+        session,
+        watch_id.clone(),
+        params.key_expr.clone(),
+        config.clone(),
+        namespace,
+    )
+    .await?;
+    state
+        .write()
+        .await
+        .key_watches
+        .insert(watch_id.clone(), key_watch);
+
+    Ok(WatchKeyResult {
+        watch_id,
+        key_expr: params.key_expr,
+        compare: config.compare.as_str(),
+        decode: config.decode.map(|d| d.as_str()),
+        filter: params.filter,
+        buffer_size: config.buffer_size,
+        notify: config.notify,
+    })
+}
+
+pub async fn op_poll_watch(
+    params: PollWatchParams,
+    state: Arc<StateLock>,
+) -> Result<PollWatchResult> {
+    let st = state.read().await;
+    let key_watch = st
+        .key_watches
+        .get(&params.watch_id)
+        .ok_or_else(|| format!("key watch not found: {}", params.watch_id))?;
+    let mut changes = key_watch.changes.lock().unwrap();
+    let n = params.limit.min(changes.events.len());
+    let drained: Vec<_> = changes.events.drain(..n).collect();
+
+    Ok(PollWatchResult {
+        watch_id: params.watch_id,
+        change_count: drained.len(),
+        changes: drained,
+        remaining: changes.events.len(),
+    })
+}
+
+pub async fn op_list_key_watches(state: Arc<StateLock>) -> Result<ListKeyWatchesResult> {
+    let st = state.read().await;
+    let watches: Vec<KeyWatchSummary> = st
+        .key_watches
+        .iter()
+        .map(|(id, key_watch)| key_watch_summary(id, key_watch))
+        .collect();
+
+    Ok(ListKeyWatchesResult {
+        count: watches.len(),
+        watches,
+    })
+}
+
+pub async fn op_unwatch_key(
+    params: UnwatchKeyParams,
+    state: Arc<StateLock>,
+) -> Result<UnwatchKeyResult> {
+    let watch_id = params.watch_id;
+    let mut st = state.write().await;
+    match st.key_watches.remove(&watch_id) {
+        Some(key_watch) => {
+            let _ = key_watch.cancel.send(true);
+            Ok(UnwatchKeyResult {
+                removed: true,
+                watch_id,
+            })
+        }
+        None => Err(format!("key watch not found: {watch_id}")),
+    }
+}
+
+pub async fn op_materialize_table(
+    params: MaterializeTableParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<MaterializeTableResult> {
+    if params.changelog_size == 0 {
+        return Err("changelog_size must be at least 1".into());
+    }
+    let config = TableConfig {
+        decode: params
+            .decode
+            .as_deref()
+            .map(DecodeFormat::parse)
+            .transpose()?,
+        changelog_size: params.changelog_size,
+        initial_query_ms: params.initial_query.then_some(params.query_timeout_ms),
+    };
+
+    let table_id = uuid::Uuid::new_v4().to_string();
+    let namespace = state.read().await.namespace.clone();
+    let table = start_table(session, params.key_expr.clone(), config.clone(), namespace).await?;
+    state.write().await.tables.insert(table_id.clone(), table);
+
+    Ok(MaterializeTableResult {
+        table_id,
+        key_expr: params.key_expr,
+        decode: config.decode.map(|d| d.as_str()),
+        initial_query: params.initial_query,
+        changelog_size: config.changelog_size,
+    })
+}
+
+pub async fn op_get_table(params: GetTableParams, state: Arc<StateLock>) -> Result<GetTableResult> {
+    let st = state.read().await;
+    let table = st
+        .tables
+        .get(&params.table_id)
+        .ok_or_else(|| format!("table not found: {}", params.table_id))?;
+    let data = table.data.lock().unwrap();
+    let entries: Vec<TableEntry> = data.entries.values().cloned().collect();
+
+    Ok(GetTableResult {
+        key_expr: table.key_expr.clone(),
+        table_id: params.table_id,
+        revision: data.revision,
+        count: entries.len(),
+        entries,
+    })
+}
+
+pub async fn op_table_changes(
+    params: TableChangesParams,
+    state: Arc<StateLock>,
+) -> Result<TableChangesResult> {
+    let st = state.read().await;
+    let table = st
+        .tables
+        .get(&params.table_id)
+        .ok_or_else(|| format!("table not found: {}", params.table_id))?;
+    let data = table.data.lock().unwrap();
+    let (changes, truncated) = data.changes_since(params.since, params.limit);
+    let cursor = changes.last().map_or(params.since, |c| c.revision);
+
+    Ok(TableChangesResult {
+        table_id: params.table_id,
+        change_count: changes.len(),
+        changes,
+        cursor,
+        truncated,
+        remaining: data.revision.saturating_sub(cursor) as usize,
+    })
+}
+
+pub async fn op_list_tables(state: Arc<StateLock>) -> Result<ListTablesResult> {
+    let st = state.read().await;
+    let tables: Vec<TableSummary> = st
+        .tables
+        .iter()
+        .map(|(id, table)| table_summary(id, table))
+        .collect();
+
+    Ok(ListTablesResult {
+        count: tables.len(),
+        tables,
+    })
+}
+
+pub async fn op_delete_table(
+    params: DeleteTableParams,
+    state: Arc<StateLock>,
+) -> Result<DeleteTableResult> {
+    let table_id = params.table_id;
+    let mut st = state.write().await;
+    match st.tables.remove(&table_id) {
+        Some(table) => {
+            let _ = table.cancel.send(true);
+            Ok(DeleteTableResult {
+                removed: true,
+                table_id,
+            })
+        }
+        None => Err(format!("table not found: {table_id}")),
+    }
+}
+
+pub async fn op_watch_threshold(
+    params: WatchThresholdParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<WatchThresholdResult> {
+    if params.thresholds.is_empty() {
+        return Err("thresholds must not be empty".into());
+    }
+    if params.thresholds.iter().any(|t| !t.is_finite()) {
+        return Err("thresholds must be finite numbers".into());
+    }
+    if params.thresholds.windows(2).any(|w| w[0] >= w[1]) {
+        return Err("thresholds must be strictly ascending".into());
+    }
+    if !(params.hysteresis >= 0.0 && params.hysteresis.is_finite()) {
+        return Err("hysteresis must be a non-negative number".into());
+    }
+    if params.buffer_size == 0 {
+        return Err("buffer_size must be at least 1".into());
+    }
+    let config = ThresholdConfig {
+        path: params.path,
+        thresholds: params.thresholds,
+        hysteresis: params.hysteresis,
+        decode: params
+            .decode
+            .as_deref()
+            .map(DecodeFormat::parse)
+            .transpose()?,
+        filter: params
+            .filter
+            .as_deref()
+            .map(Expression::parse)
+            .transpose()?,
+        buffer_size: params.buffer_size,
+        notify: params.notify,
+    };
+
+    let watch_id = uuid::Uuid::new_v4().to_string();
+    let namespace = state.read().await.namespace.clone();
+    let threshold_watch = start_threshold_watch(
+        session,
+        watch_id.clone(),
+        params.key_expr.clone(),
+        config.clone(),
+        namespace,
+    )
+    .await?;
+    state
+        .write()
+        .await
+        .threshold_watches
+        .insert(watch_id.clone(), threshold_watch);
+
+    Ok(WatchThresholdResult {
+        watch_id,
+        key_expr: params.key_expr,
+        path: config.path,
+        thresholds: config.thresholds,
+        hysteresis: config.hysteresis,
+        decode: config.decode.map(|d| d.as_str()),
+        filter: params.filter,
+        buffer_size: config.buffer_size,
+        notify: config.notify,
+    })
+}
+
+pub async fn op_poll_threshold(
+    params: PollThresholdParams,
+    state: Arc<StateLock>,
+) -> Result<PollThresholdResult> {
+    let st = state.read().await;
+    let threshold_watch = st
+        .threshold_watches
+        .get(&params.watch_id)
+        .ok_or_else(|| format!("threshold watch not found: {}", params.watch_id))?;
+    let mut crossings = threshold_watch.crossings.lock().unwrap();
+    let n = params.limit.min(crossings.events.len());
+    let drained: Vec<_> = crossings.events.drain(..n).collect();
+
+    Ok(PollThresholdResult {
+        watch_id: params.watch_id,
+        crossing_count: drained.len(),
+        crossings: drained,
+        remaining: crossings.events.len(),
+        bands: crossings.bands().clone(),
+    })
+}
+
+pub async fn op_list_threshold_watches(
+    state: Arc<StateLock>,
+) -> Result<ListThresholdWatchesResult> {
+    let st = state.read().await;
+    let watches: Vec<ThresholdWatchSummary> = st
+        .threshold_watches
+        .iter()
+        .map(|(id, threshold_watch)| threshold_watch_summary(id, threshold_watch))
+        .collect();
+
+    Ok(ListThresholdWatchesResult {
+        count: watches.len(),
+        watches,
+    })
+}
+
+pub async fn op_unwatch_threshold(
+    params: UnwatchThresholdParams,
+    state: Arc<StateLock>,
+) -> Result<UnwatchThresholdResult> {
+    let watch_id = params.watch_id;
+    let mut st = state.write().await;
+    match st.threshold_watches.remove(&watch_id) {
+        Some(threshold_watch) => {
+            let _ = threshold_watch.cancel.send(true);
+            Ok(UnwatchThresholdResult {
+                removed: true,
+                watch_id,
+            })
+        }
+        None => Err(format!("threshold watch not found: {watch_id}")),
+    }
+}
+
+/// Key expressions a derived topic may read at once.
+const MAX_DERIVED_INPUTS: usize = 8;
+
+pub async fn op_create_derived_topic(
+    params: CreateDerivedTopicParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<CreateDerivedTopicResult> {
+    if !(1..=MAX_DERIVED_INPUTS).contains(&params.inputs.len()) {
+        return Err(format!(
+            "inputs must list 1 to {MAX_DERIVED_INPUTS} key expressions"
+        ));
+    }
+    let output = zenoh::key_expr::KeyExpr::try_from(params.output.as_str())
+        .map_err(|e| format!("invalid output {}: {e}", params.output))?;
+    if params.output.contains('*') {
+        return Err("output must be a single key, without wildcards".into());
+    }
+    for input in &params.inputs {
+        let source = zenoh::key_expr::KeyExpr::try_from(input.as_str())
+            .map_err(|e| format!("invalid key_expr {input}: {e}"))?;
+        if output.intersects(&source) {
+            return Err(format!(
+                "output overlaps input {input}; derived samples would feed back"
+            ));
+        }
+    }
+    let decode = params
+        .decode
+        .as_deref()
+        .map(DecodeFormat::parse)
+        .transpose()?;
+    let transform = match (params.expression.as_deref(), params.script.as_deref()) {
+        (Some(source), None) => {
+            Transform::Expression(Expression::parse_with(source, derived::VARIABLES)?)
+        }
+        (None, Some(source)) => {
+            let read_only = state.read().await.read_only;
+            Transform::Script(Script::compile(source, &params.inputs, !read_only)?)
+        }
+        _ => return Err("set exactly one of expression or script".into()),
+    };
+
+    let namespace = state.read().await.namespace.clone();
+    let derived_topic = start_derived_topic(
+        session,
+        params.inputs.clone(),
+        params.output.clone(),
+        transform,
+        decode,
+        namespace,
+    )
+    .await?;
+    let derived_id = uuid::Uuid::new_v4().to_string();
+    state
+        .write()
+        .await
+        .derived_topics
+        .insert(derived_id.clone(), derived_topic);
+
+    Ok(CreateDerivedTopicResult {
+        derived_id,
+        inputs: params.inputs,
+        output: params.output,
+        script: params.script.is_some(),
+        expression: params.expression,
+        decode: decode.map(|d| d.as_str()),
+    })
+}
+
+pub async fn op_list_derived_topics(state: Arc<StateLock>) -> Result<ListDerivedTopicsResult> {
+    let st = state.read().await;
+    let derived_topics: Vec<DerivedTopicSummary> = st
+        .derived_topics
+        .iter()
+        .map(|(id, derived_topic)| derived_topic_summary(id, derived_topic))
+        .collect();
+
+    Ok(ListDerivedTopicsResult {
+        count: derived_topics.len(),
+        derived_topics,
+    })
+}
+
+pub async fn op_delete_derived_topic(
+    params: DeleteDerivedTopicParams,
+    state: Arc<StateLock>,
+) -> Result<DeleteDerivedTopicResult> {
+    let derived_id = params.derived_id;
+    let mut st = state.write().await;
+    match st.derived_topics.remove(&derived_id) {
+        Some(derived_topic) => {
+            let _ = derived_topic.cancel.send(true);
+            Ok(DeleteDerivedTopicResult {
+                removed: true,
+                derived_id,
+            })
+        }
+        None => Err(format!("derived topic not found: {derived_id}")),
+    }
+}
+
+pub async fn op_mirror(
+    params: MirrorParams,
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+) -> Result<MirrorResult> {
+    let key_expr = params.key_expr;
+    if params.max_keys == 0 {
+        return Err("max_keys must be greater than 0".into());
+    }
+    let scoped = namespace::scope(state.read().await.namespace.as_deref(), &key_expr);
+    let mirror = start_mirror(session, key_expr.clone(), &scoped, params.max_keys)
+        .await
+        .map_err(|e| format!("mirror of {key_expr} failed: {e}"))?;
+
+    let mirror_id = uuid::Uuid::new_v4().to_string();
+    state
+        .write()
+        .await
+        .mirrors
+        .insert(mirror_id.clone(), mirror);
+
+    Ok(MirrorResult {
+        mirror_id,
+        key_expr,
+        max_keys: params.max_keys,
+    })
+}
+
+pub async fn op_list_mirrors(state: Arc<StateLock>) -> Result<ListMirrorsResult> {
+    let st = state.read().await;
+    let mirrors: Vec<MirrorSummary> = st
+        .mirrors
+        .iter()
+        .map(|(id, mirror)| mirror_summary(id, mirror))
+        .collect();
+
+    Ok(ListMirrorsResult {
+        count: mirrors.len(),
+        mirrors,
+    })
+}
+
+pub async fn op_delete_mirror(
+    params: DeleteMirrorParams,
+    state: Arc<StateLock>,
+) -> Result<DeleteMirrorResult> {
+    let mirror_id = params.mirror_id;
+
+    let mut st = state.write().await;
+    match st.mirrors.remove(&mirror_id) {
+        Some(mirror) => {
+            let _ = mirror.cancel.send(true);
+            Ok(DeleteMirrorResult {
+                removed: true,
+                mirror_id,
+            })
+        }
+        None => Err(format!("mirror not found: {mirror_id}")),
+    }
+}
+
+/// Flattened per-topic summary as returned by `get_topics`.
+pub fn topic_summary(t: &TopicMeta, now: DateTime<Utc>, st: &AppState) -> TopicSummary {
+    let silent_secs = (now - t.last_seen).num_seconds();
+    let seq_tracked = st.discovery_seq_source.is_some();
+    TopicSummary {
+        key_expr: t.key_expr.clone(),
+        first_seen: t.first_seen.to_rfc3339(),
+        last_seen: t.last_seen.to_rfc3339(),
+        sample_count: t.sample_count,
+        rate_hz: (t.rate_hz() * 100.0).round() / 100.0,
+        avg_payload_size: t.avg_payload_size(),
+        last_encoding: t.last_encoding.clone(),
+        encoding_count: t.encodings.len(),
+        mixed_encoding: t.mixed_encoding(),
+        stale: silent_secs >= TOPIC_STALE_SECS,
+        pinned: st.pinned_topics.contains(&t.key_expr),
+        silent_secs,
+        missed_estimate: seq_tracked.then_some(t.seq.missed_estimate),
+        last_sample_preview: t
+            .last_payload_head
+            .as_ref()
+            .map(|(head, bytes)| PayloadPreview::new(head, *bytes)),
+        tags: st.tags.of_topic(&t.key_expr),
+    }
+}
+
+fn block_timeout_ms(policy: DropPolicy) -> Option<u64> {
+    match policy {
+        DropPolicy::Block(timeout) => Some(timeout.as_millis() as u64),
+        _ => None,
+    }
+}
+
+/// Per-subscription stats as returned by `list_subscriptions`.
+pub fn subscription_summary(id: &str, sub: &Subscription, tags: &Tags) -> SubscriptionSummary {
+    let last_restart = sub.health.last_restart();
+    let reliability = &sub.ingest.reliability_stats;
+    SubscriptionSummary {
+        sub_id: id.to_string(),
+        key_expr: sub.key_expr.clone(),
+        buffered: sub.sample_count(),
+        buffer_capacity: sub.buffer_capacity,
+        drop_policy: sub.drop_policy.as_str(),
+        block_timeout_ms: block_timeout_ms(sub.drop_policy),
+        overflow_count: sub.overflow_count,
+        block_timeouts: sub.block_timeouts,
+        gap_markers: sub.gap_markers,
+        spill: sub.spill.as_ref().map(Spill::stats),
+        max_age_ms: sub.max_age_ms,
+        expired_count: sub.expired_count,
+        reorder_window_ms: sub.reorder_window_ms,
+        held: sub.held.len(),
+        out_of_order_count: sub.out_of_order_count,
+        total_received: sub.total_received,
+        missed_estimate: sub.missed_estimate(),
+        inter_arrival: sub.inter_arrival.stats(),
+        alert_if_silent_ms: sub.alert_if_silent_ms,
+        stalled: sub.stalled,
+        decode: sub.ingest.decode.map(|d| d.as_str()),
+        decode_queue_depth: sub.ingest.decode_queue_depth.load(Ordering::Relaxed),
+        checksum: sub.ingest.checksum.map(|c| c.as_str()),
+        channel: sub.ingest.subscribe.channel.policy.as_str(),
+        channel_capacity: sub.ingest.subscribe.channel.capacity,
+        origin: sub.ingest.subscribe.origin.as_str(),
+        reliability: sub.ingest.reliability.map(|r| r.as_str()),
+        filter: sub.ingest.filter.as_ref().map(|f| f.source().to_string()),
+        filtered_count: sub.ingest.filtered.load(Ordering::Relaxed),
+        plugin: sub.ingest.plugin.as_ref().map(|plugin| PluginUsage {
+            name: plugin.name.clone(),
+            calls: plugin.stats.calls.load(Ordering::Relaxed),
+            errors: plugin.stats.errors.load(Ordering::Relaxed),
+            filtered: plugin.stats.filtered.load(Ordering::Relaxed),
+            last_error: plugin.stats.last_error.lock().unwrap().clone(),
+        }),
+        script: sub.ingest.script.as_deref().map(script_usage),
+        effective_reliability: reliability.effective(sub.total_received),
+        best_effort_received: reliability.best_effort.load(Ordering::Relaxed),
+        reliability_rejected: reliability.rejected.load(Ordering::Relaxed),
+        restart_count: sub.health.restarts(),
+        last_restart: last_restart.as_ref().map(|(at, _)| at.to_rfc3339()),
+        last_restart_reason: last_restart.map(|(_, reason)| reason),
+        last_heartbeat: sub.health.last_heartbeat().map(|at| at.to_rfc3339()),
+        created_at: sub.created_at.to_rfc3339(),
+        tags: tags.of_subscription(id),
+    }
+}
+
+pub fn federation_peer_summary(name: &str, peer: &federation::Peer) -> FederationPeerSummary {
+    FederationPeerSummary {
+        name: name.to_string(),
+        admin_key_expr: peer.admin_key_expr.clone(),
+        locator: peer.locator.clone(),
+        zid: peer.zid.clone(),
+        added_at: peer.added_at.to_rfc3339(),
+        last_contact: peer.last_contact.map(|at| at.to_rfc3339()),
+        last_error: peer.last_error.clone(),
+    }
+}
+
+/// Cache size and hit/miss counters as returned by `list_mirrors`.
+pub fn mirror_summary(id: &str, mirror: &Mirror) -> MirrorSummary {
+    let load = |counter: &std::sync::atomic::AtomicU64| counter.load(Ordering::Relaxed);
+    MirrorSummary {
+        mirror_id: id.to_string(),
+        key_expr: mirror.key_expr.clone(),
+        cached_keys: mirror.cache.lock().unwrap().len(),
+        max_keys: mirror.max_keys,
+        queries: load(&mirror.stats.queries),
+        hits: load(&mirror.stats.hits),
+        misses: load(&mirror.stats.misses),
+        replies: load(&mirror.stats.replies),
+        updates: load(&mirror.stats.updates),
+        dropped_keys: load(&mirror.stats.dropped_keys),
+        created_at: mirror.created_at.to_rfc3339(),
+    }
+}
+
+/// Matching stats as returned by `list_synchronizers`.
+pub fn synchronizer_summary(id: &str, sync: &Synchronizer) -> SynchronizerSummary {
+    let matcher = sync.matcher.lock().unwrap();
+    SynchronizerSummary {
+        sync_id: id.to_string(),
+        key_exprs: sync.key_exprs.clone(),
+        slop_ms: sync.slop_ms,
+        buffered: matcher.matched.len(),
+        buffer_capacity: matcher.buffer_capacity,
+        pending: matcher.pending(),
+        total_matched: matcher.total_matched,
+        dropped: matcher.dropped,
+        overflow_count: matcher.overflow_count,
+        created_at: sync.created_at.to_rfc3339(),
+    }
+}
+
+/// Parse the optional `seq_path` input used for sequence gap detection.
+fn parse_seq_source(seq_path: Option<&str>) -> Result<Option<SeqSource>> {
+    seq_path.map(SeqSource::parse).transpose()
+}
+
+/// Parse an optional `origin` / `destination` input; `any` when omitted.
+fn parse_locality(locality: Option<&str>) -> Result<Locality> {
+    Ok(locality
+        .map(Locality::parse)
+        .transpose()?
+        .unwrap_or_default())
+}
+
+/// Ring usage as returned by `list_flight_recorders`.
+pub fn flight_recorder_summary(id: &str, recorder: &FlightRecorder) -> FlightRecorderSummary {
+    let ring = recorder.ring.stats();
+    FlightRecorderSummary {
+        recorder_id: id.to_string(),
+        key_exprs: recorder.key_exprs.clone(),
+        max_bytes: recorder.max_bytes,
+        disk_bytes: ring.disk_bytes,
+        records_written: ring.records,
+        write_errors: ring.write_errors,
+        oldest: ring.oldest,
+        created_at: recorder.created_at.to_rfc3339(),
+    }
+}
+
+/// Progress as returned by `list_generators`.
+pub fn generator_summary(id: &str, generator: &Generator) -> GeneratorSummary {
+    let stats = &generator.stats;
+    GeneratorSummary {
+        gen_id: id.to_string(),
+        key_expr: generator.key_expr.clone(),
+        pattern: generator.config.pattern.as_str(),
+        payload_size: generator.config.payload_size,
+        rate_hz: generator.config.rate_hz,
+        jitter_ms: generator.config.jitter.as_millis() as u64,
+        count: generator.config.count,
+        published: stats.published.load(Ordering::Relaxed),
+        errors: stats.errors.load(Ordering::Relaxed),
+        finished: stats.finished.load(Ordering::Relaxed),
+        created_at: generator.created_at.to_rfc3339(),
+    }
+}
+
+/// Counters as returned by `list_echoes`.
+pub fn echo_summary(id: &str, echo: &Echo) -> EchoSummary {
+    let stats = &echo.stats;
+    EchoSummary {
+        echo_id: id.to_string(),
+        from_key_expr: echo.from_key_expr.clone(),
+        to_key_expr: echo.to_key_expr.clone(),
+        delay_ms: echo.delay.as_millis() as u64,
+        received: stats.received.load(Ordering::Relaxed),
+        echoed: stats.echoed.load(Ordering::Relaxed),
+        errors: stats.errors.load(Ordering::Relaxed),
+        dropped: stats.dropped.load(Ordering::Relaxed),
+        created_at: echo.created_at.to_rfc3339(),
+    }
+}
+
+/// Counters as returned by `list_bridges`.
+pub fn bridge_summary(id: &str, bridge: &Bridge) -> BridgeSummary {
+    let stats = &bridge.stats;
+    BridgeSummary {
+        bridge_id: id.to_string(),
+        key_expr: bridge.key_expr.clone(),
+        rules: bridge.rules.clone(),
+        received: stats.received.load(Ordering::Relaxed),
+        forwarded: stats.forwarded.load(Ordering::Relaxed),
+        unmatched: stats.unmatched.load(Ordering::Relaxed),
+        errors: stats.errors.load(Ordering::Relaxed),
+        created_at: bridge.created_at.to_rfc3339(),
+    }
+}
+
+/// Counters as returned by `list_throttled_relays`.
+pub fn throttled_relay_summary(id: &str, relay: &ThrottledRelay) -> ThrottledRelaySummary {
+    let stats = &relay.stats;
+    ThrottledRelaySummary {
+        relay_id: id.to_string(),
+        key_expr: relay.key_expr.clone(),
+        to_prefix: relay.to_prefix.clone(),
+        max_rate_hz: relay.config.min_interval.map(|i| 1.0 / i.as_secs_f64()),
+        on_change: relay.config.on_change,
+        received: stats.received.load(Ordering::Relaxed),
+        published: stats.published.load(Ordering::Relaxed),
+        suppressed: stats.suppressed.load(Ordering::Relaxed),
+        errors: stats.errors.load(Ordering::Relaxed),
+        created_at: relay.created_at.to_rfc3339(),
+    }
+}
+
+/// Progress as returned by `list_previews`.
+pub fn preview_summary(id: &str, preview: &Preview) -> PreviewSummary {
+    let frames = preview.frames.lock().unwrap();
+    PreviewSummary {
+        preview_id: id.to_string(),
+        key_expr: preview.key_expr.clone(),
+        rate_hz: preview.rate_hz,
+        notify: preview.notify,
+        frame: frames.frame,
+        keys: frames.latest.len(),
+        received: frames.received,
+        dropped_keys: frames.dropped_keys,
+        created_at: preview.created_at.to_rfc3339(),
+    }
+}
+
+pub fn query_summary(id: &str, query: &QueryStream) -> QuerySummary {
+    let replies = query.replies.lock().unwrap();
+    QuerySummary {
+        query_id: id.to_string(),
+        selector: query.selector.clone(),
+        timeout_ms: query.timeout_ms,
+        notify: query.notify,
+        received: replies.received,
+        remaining: replies.pending(),
+        complete: replies.complete,
+        created_at: query.created_at.to_rfc3339(),
+    }
+}
+
+/// A script's counters as reported with its subscription or derived topic.
+fn script_usage(script: &Script) -> ScriptUsage {
+    let stats = &script.stats;
+    ScriptUsage {
+        calls: stats.calls.load(Ordering::Relaxed),
+        errors: stats.errors.load(Ordering::Relaxed),
+        dropped: stats.dropped.load(Ordering::Relaxed),
+        published: stats.published.load(Ordering::Relaxed),
+        last_error: stats.last_error.lock().unwrap().clone(),
+    }
+}
+
+/// Progress as returned by `list_key_watches`.
+pub fn table_summary(id: &str, table: &Table) -> TableSummary {
+    let data = table.data.lock().unwrap();
+    TableSummary {
+        table_id: id.to_string(),
+        key_expr: table.key_expr.clone(),
+        decode: table.config.decode.map(|d| d.as_str()),
+        keys: data.entries.len(),
+        revision: data.revision,
+        received: data.received,
+        changelog_size: table.config.changelog_size,
+        created_at: table.created_at.to_rfc3339(),
+    }
+}
+
+pub fn key_watch_summary(id: &str, key_watch: &KeyWatch) -> KeyWatchSummary {
+    let changes = key_watch.changes.lock().unwrap();
+    KeyWatchSummary {
+        watch_id: id.to_string(),
+        key_expr: key_watch.key_expr.clone(),
+        compare: key_watch.config.compare.as_str(),
+        decode: key_watch.config.decode.map(|d| d.as_str()),
+        filter: key_watch
+            .config
+            .filter
+            .as_ref()
+            .map(|f| f.source().to_string()),
+        notify: key_watch.config.notify,
+        keys: changes.keys(),
+        received: changes.received,
+        changes: changes.changes,
+        pending: changes.events.len(),
+        dropped: changes.dropped,
+        created_at: key_watch.created_at.to_rfc3339(),
+    }
+}
+
+/// Progress as returned by `list_threshold_watches`.
+pub fn threshold_watch_summary(
+    id: &str,
+    threshold_watch: &ThresholdWatch,
+) -> ThresholdWatchSummary {
+    let crossings = threshold_watch.crossings.lock().unwrap();
+    let config = &threshold_watch.config;
+    ThresholdWatchSummary {
+        watch_id: id.to_string(),
+        key_expr: threshold_watch.key_expr.clone(),
+        path: config.path.clone(),
+        thresholds: config.thresholds.clone(),
+        hysteresis: config.hysteresis,
+        filter: config.filter.as_ref().map(|f| f.source().to_string()),
+        notify: config.notify,
+        keys: crossings.keys(),
+        received: crossings.received,
+        missing: crossings.missing,
+        crossings: crossings.crossings,
+        pending: crossings.events.len(),
+        dropped: crossings.dropped,
+        created_at: threshold_watch.created_at.to_rfc3339(),
+    }
+}
+
+pub fn topology_watch_summary(id: &str, topology_watch: &TopologyWatch) -> TopologyWatchSummary {
+    let changes = topology_watch.changes.lock().unwrap();
+    let config = &topology_watch.config;
+    TopologyWatchSummary {
+        watch_id: id.to_string(),
+        interval_ms: config.interval.as_millis() as u64,
+        debounce_ms: config.debounce.as_millis() as u64,
+        notify: config.notify,
+        nodes: changes.nodes(),
+        edges: changes.edges(),
+        refreshes: changes.refreshes,
+        failures: changes.failures,
+        last_error: changes.last_error.clone(),
+        changes: changes.changes,
+        pending: changes.events.len(),
+        dropped: changes.dropped,
+        created_at: topology_watch.created_at.to_rfc3339(),
+    }
+}
+
+/// Counters as returned by `list_derived_topics`.
+pub fn derived_topic_summary(id: &str, derived_topic: &DerivedTopic) -> DerivedTopicSummary {
+    let stats = &derived_topic.stats;
+    let (expression, script) = match &*derived_topic.transform {
+        Transform::Expression(expression) => (Some(expression.source().to_string()), None),
+        Transform::Script(script) => (None, Some(script_usage(script))),
+    };
+    DerivedTopicSummary {
+        derived_id: id.to_string(),
+        inputs: derived_topic.inputs.clone(),
+        output: derived_topic.output.clone(),
+        expression,
+        script,
+        decode: derived_topic.decode.map(|d| d.as_str()),
+        received: stats.received.load(Ordering::Relaxed),
+        published: stats.published.load(Ordering::Relaxed),
+        skipped: stats.skipped.load(Ordering::Relaxed),
+        errors: stats.errors.load(Ordering::Relaxed),
+        last_error: stats.last_error.lock().unwrap().clone(),
+        created_at: derived_topic.created_at.to_rfc3339(),
+    }
+}
+
+pub fn recording_reader_summary(id: &str, reader: &RecordingReader) -> RecordingReaderSummary {
+    RecordingReaderSummary {
+        reader_id: id.to_string(),
+        name: reader.name.clone(),
+        position: reader.position,
+        records: reader.records,
+        first: reader.first,
+        last: reader.last,
+        created_at: reader.created_at,
+    }
+}
+
+pub fn redaction_rule_summary(rule: &RedactionRule) -> RedactionRuleSummary {
+    let (action, mask) = match &rule.action {
+        Action::Mask(with) => ("mask", Some(with.clone())),
+        Action::Drop => ("drop", None),
+    };
+    RedactionRuleSummary {
+        rule_id: rule.id.clone(),
+        key_expr: rule.key_expr.to_string(),
+        path: rule.path.as_str().to_string(),
+        action,
+        mask,
+        applied: rule.applied.load(Ordering::Relaxed),
+        created_at: rule.created_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Raised-risk operations that only touch local state, so read-only
+    /// mode leaves them alone.
+    const LOCAL_OPERATIONS: &[&str] = &[
+        "delete_recording",
+        "repair_recording",
+        "add_federation_peer",
+        "load_plugin",
+    ];
+
+    #[test]
+    fn read_only_gates_every_network_write() {
+        let manifest: Value = serde_json::from_str(include_str!("../../manifest.json")).unwrap();
+        for op in manifest["operations"].as_array().unwrap() {
+            let name = op["name"].as_str().unwrap();
+            if op["risk_level"] == "low" || LOCAL_OPERATIONS.contains(&name) {
+                assert!(!MUTATING_OPERATIONS.contains(&name), "{name} is gated");
+            } else {
+                assert!(MUTATING_OPERATIONS.contains(&name), "{name} is not gated");
+            }
+        }
+    }
+}
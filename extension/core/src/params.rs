@@ -0,0 +1,60 @@
+use crate::backend::RxSample;
+use serde_json::Value;
+use zenoh::key_expr::KeyExpr;
+
+/// Key prefix parameters live under unless an operation overrides it.
+pub const DEFAULT_PREFIX: &str = "params";
+
+/// Key expression for parameter `name` under `prefix`. Both must be plain
+/// keys; wildcards would turn a set into a broadcast over several params.
+pub fn key(prefix: &str, name: &str) -> Result<String, String> {
+    let prefix = prefix.trim_matches('/');
+    let name = name.trim_matches('/');
+    if name.is_empty() {
+        return Err("parameter name must not be empty".into());
+    }
+    let key = match prefix.is_empty() {
+        true => name.to_string(),
+        false => format!("{prefix}/{name}"),
+    };
+    validate(&key)?;
+    Ok(key)
+}
+
+/// Parameter name for a key received under `prefix`.
+pub fn name(prefix: &str, key: &str) -> String {
+    let prefix = prefix.trim_matches('/');
+    key.strip_prefix(prefix)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .unwrap_or(key)
+        .to_string()
+}
+
+/// Stored value: parsed JSON for JSON payloads, else the payload as text.
+pub fn value(sample: &RxSample) -> Value {
+    if sample.encoding.starts_with("application/json") {
+        if let Ok(value) = serde_json::from_slice(&sample.payload) {
+            return value;
+        }
+    }
+    Value::String(String::from_utf8_lossy(&sample.payload).into_owned())
+}
+
+/// Key expression matching every parameter under `prefix`.
+pub fn all(prefix: &str) -> Result<String, String> {
+    let prefix = prefix.trim_matches('/');
+    if prefix.is_empty() {
+        return Ok("**".into());
+    }
+    validate(prefix)?;
+    Ok(format!("{prefix}/**"))
+}
+
+fn validate(key: &str) -> Result<(), String> {
+    if key.contains('*') || key.contains('$') {
+        return Err(format!("parameter key must not contain wildcards: {key}"));
+    }
+    KeyExpr::try_from(key)
+        .map(|_| ())
+        .map_err(|e| format!("invalid parameter key {key}: {e}"))
+}
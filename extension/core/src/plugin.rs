@@ -0,0 +1,286 @@
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use wasmi::{
+    Config, Engine, ExternType, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+    TypedFunc,
+};
+
+/// Instructions one hook call may execute before it is aborted.
+const FUEL_PER_CALL: u64 = 50_000_000;
+/// Linear memory a plugin instance may grow to.
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+static ENGINE: LazyLock<Engine> = LazyLock::new(|| {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    Engine::new(&config)
+});
+
+/// Hooks a plugin exports. Each takes `(ptr, len)` of the payload, written
+/// into the plugin's memory through its `alloc(len) -> ptr` export:
+///
+/// - `decode(ptr, len) -> i64`: UTF-8 JSON text for the sample's decoded
+///   value, returned as `ptr << 32 | len`; 0 means the payload is not
+///   decodable.
+/// - `transform(ptr, len) -> i64`: a replacement payload, returned the same
+///   way; 0 keeps the original.
+/// - `filter(ptr, len) -> i32`: 0 drops the sample.
+#[derive(Clone, Copy, Default)]
+pub struct Hooks {
+    pub decode: bool,
+    pub transform: bool,
+    pub filter: bool,
+}
+
+impl Hooks {
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.decode, "decode"),
+            (self.transform, "transform"),
+            (self.filter, "filter"),
+        ]
+        .into_iter()
+        .filter_map(|(present, name)| present.then_some(name))
+        .collect()
+    }
+}
+
+/// A compiled plugin module, instantiated separately for each subscription
+/// that uses it.
+pub struct Plugin {
+    module: Module,
+    pub hooks: Hooks,
+    pub size: usize,
+    pub loaded_at: DateTime<Utc>,
+}
+
+impl Plugin {
+    /// Compile `wasm` and check it exports `memory`, `alloc` and at least one
+    /// hook. Plugins run sandboxed with no imports.
+    pub fn compile(wasm: &[u8]) -> Result<Self, String> {
+        let module = Module::new(&ENGINE, wasm).map_err(|e| format!("invalid wasm module: {e}"))?;
+        if let Some(import) = module.imports().next() {
+            return Err(format!(
+                "plugins may not import anything, found {}::{}",
+                import.module(),
+                import.name()
+            ));
+        }
+        let exported = |name: &str, func: bool| {
+            module.exports().any(|e| {
+                e.name() == name
+                    && match func {
+                        true => matches!(e.ty(), ExternType::Func(_)),
+                        false => matches!(e.ty(), ExternType::Memory(_)),
+                    }
+            })
+        };
+        if !exported("memory", false) || !exported("alloc", true) {
+            return Err("plugin must export memory and alloc(len) -> ptr".into());
+        }
+        let hooks = Hooks {
+            decode: exported("decode", true),
+            transform: exported("transform", true),
+            filter: exported("filter", true),
+        };
+        if hooks.names().is_empty() {
+            return Err("plugin exports none of decode, transform or filter".into());
+        }
+        Ok(Self {
+            module,
+            hooks,
+            size: wasm.len(),
+            loaded_at: Utc::now(),
+        })
+    }
+}
+
+/// Calls and failures of one subscription's plugin instance.
+#[derive(Default)]
+pub struct PluginStats {
+    pub calls: AtomicU64,
+    pub errors: AtomicU64,
+    /// Samples the `filter` hook dropped.
+    pub filtered: AtomicU64,
+    pub last_error: Mutex<Option<String>>,
+}
+
+impl PluginStats {
+    fn fail(&self, error: String) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        *self.last_error.lock().unwrap() = Some(error);
+    }
+}
+
+/// A payload after the plugin's hooks ran.
+pub struct Processed {
+    pub payload: Vec<u8>,
+    /// The decode hook's outcome, if the plugin decodes.
+    pub decoded: Option<Result<serde_json::Value, String>>,
+}
+
+struct Exports {
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    decode: Option<TypedFunc<(i32, i32), i64>>,
+    transform: Option<TypedFunc<(i32, i32), i64>>,
+    filter: Option<TypedFunc<(i32, i32), i32>>,
+}
+
+/// A subscription's own instance of a plugin, so plugin state is never
+/// shared between subscriptions.
+pub struct PluginInstance {
+    pub name: String,
+    store: Mutex<(Store<StoreLimits>, Exports)>,
+    pub stats: PluginStats,
+}
+
+impl PluginInstance {
+    pub fn new(name: &str, plugin: &Plugin) -> Result<Arc<Self>, String> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY_BYTES)
+            .build();
+        let mut store = Store::new(&ENGINE, limits);
+        store.limiter(|limits| limits);
+        store
+            .set_fuel(FUEL_PER_CALL)
+            .map_err(|e| format!("plugin {name}: {e}"))?;
+        let instance = Linker::new(&ENGINE)
+            .instantiate(&mut store, &plugin.module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| format!("plugin {name} failed to start: {e}"))?;
+        let func_err = |e: wasmi::Error| format!("plugin {name}: {e}");
+        let exports = Exports {
+            memory: instance
+                .get_memory(&store, "memory")
+                .ok_or_else(|| format!("plugin {name} has no memory"))?,
+            alloc: instance.get_typed_func(&store, "alloc").map_err(func_err)?,
+            decode: match plugin.hooks.decode {
+                true => Some(
+                    instance
+                        .get_typed_func(&store, "decode")
+                        .map_err(func_err)?,
+                ),
+                false => None,
+            },
+            transform: match plugin.hooks.transform {
+                true => Some(
+                    instance
+                        .get_typed_func(&store, "transform")
+                        .map_err(func_err)?,
+                ),
+                false => None,
+            },
+            filter: match plugin.hooks.filter {
+                true => Some(
+                    instance
+                        .get_typed_func(&store, "filter")
+                        .map_err(func_err)?,
+                ),
+                false => None,
+            },
+        };
+        Ok(Arc::new(Self {
+            name: name.to_string(),
+            store: Mutex::new((store, exports)),
+            stats: PluginStats::default(),
+        }))
+    }
+
+    pub fn has_decode(&self) -> bool {
+        self.store.lock().unwrap().1.decode.is_some()
+    }
+
+    /// Run the hooks on a received payload: `transform` first, then
+    /// `filter`, then `decode`. Returns `None` if the sample is filtered out.
+    /// A failing transform or filter keeps the sample unchanged.
+    pub fn process(&self, payload: Vec<u8>) -> Option<Processed> {
+        let mut guard = self.store.lock().unwrap();
+        let (store, exports) = &mut *guard;
+        let mut payload = payload;
+
+        if let Some(transform) = exports.transform {
+            match self.call_bytes(store, exports, transform, &payload) {
+                Ok(Some(out)) => payload = out,
+                Ok(None) => {}
+                Err(e) => self.stats.fail(format!("transform: {e}")),
+            }
+        }
+        if let Some(filter) = exports.filter {
+            self.stats.calls.fetch_add(1, Ordering::Relaxed);
+            let keep = write_input(store, exports, &payload).and_then(|(ptr, len)| {
+                filter
+                    .call(&mut *store, (ptr, len))
+                    .map_err(|e| e.to_string())
+            });
+            match keep {
+                Ok(0) => {
+                    self.stats.filtered.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+                Ok(_) => {}
+                Err(e) => self.stats.fail(format!("filter: {e}")),
+            }
+        }
+        let decoded =
+            exports.decode.map(
+                |decode| match self.call_bytes(store, exports, decode, &payload) {
+                    Ok(Some(out)) => serde_json::from_slice(&out).map_err(|e| {
+                        format!("plugin {}: decode returned invalid JSON: {e}", self.name)
+                    }),
+                    Ok(None) => Err(format!("plugin {}: payload not decodable", self.name)),
+                    Err(e) => Err(format!("plugin {}: {e}", self.name)),
+                },
+            );
+        if let Some(Err(e)) = &decoded {
+            self.stats.fail(e.clone());
+        }
+        Some(Processed { payload, decoded })
+    }
+
+    /// Call a hook returning `ptr << 32 | len` and copy out its result;
+    /// `None` when it returned 0.
+    fn call_bytes(
+        &self,
+        store: &mut Store<StoreLimits>,
+        exports: &Exports,
+        hook: TypedFunc<(i32, i32), i64>,
+        payload: &[u8],
+    ) -> Result<Option<Vec<u8>>, String> {
+        self.stats.calls.fetch_add(1, Ordering::Relaxed);
+        let (ptr, len) = write_input(store, exports, payload)?;
+        let packed = hook
+            .call(&mut *store, (ptr, len))
+            .map_err(|e| e.to_string())? as u64;
+        if packed == 0 {
+            return Ok(None);
+        }
+        let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        exports
+            .memory
+            .data(&*store)
+            .get(ptr..ptr + len)
+            .map(|out| Some(out.to_vec()))
+            .ok_or_else(|| "hook returned a range outside memory".into())
+    }
+}
+
+/// Refill the call's fuel and copy `payload` into memory from `alloc`.
+fn write_input(
+    store: &mut Store<StoreLimits>,
+    exports: &Exports,
+    payload: &[u8],
+) -> Result<(i32, i32), String> {
+    store.set_fuel(FUEL_PER_CALL).map_err(|e| e.to_string())?;
+    let len = i32::try_from(payload.len()).map_err(|_| "payload too large for plugin")?;
+    let ptr = exports
+        .alloc
+        .call(&mut *store, len)
+        .map_err(|e| e.to_string())?;
+    exports
+        .memory
+        .write(&mut *store, ptr as u32 as usize, payload)
+        .map_err(|e| e.to_string())?;
+    Ok((ptr, len))
+}
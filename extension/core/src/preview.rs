@@ -0,0 +1,153 @@
+use crate::backend::ZenohBackend;
+use crate::clock;
+use crate::namespace;
+use crate::notify;
+use crate::redaction;
+use crate::state::{BufferedSample, Payload};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::MissedTickBehavior;
+
+/// Newest sample per key, advanced one frame per tick.
+#[derive(Default)]
+pub struct Frames {
+    /// Newest sample per key and the frame that last updated it.
+    pub latest: BTreeMap<String, (u64, BufferedSample)>,
+    /// Frames emitted so far; frame `n` holds the keys updated in tick `n`.
+    pub frame: u64,
+    /// Samples that arrived since the last tick, newest per key.
+    pending: HashMap<String, BufferedSample>,
+    pub received: u64,
+    /// Samples on new keys ignored once `max_keys` was reached.
+    pub dropped_keys: u64,
+}
+
+impl Frames {
+    fn push(&mut self, sample: BufferedSample, max_keys: usize) {
+        self.received += 1;
+        let known = self.latest.contains_key(&sample.key_expr)
+            || self.pending.contains_key(&sample.key_expr);
+        if !known && self.latest.len() + self.pending.len() >= max_keys {
+            self.dropped_keys += 1;
+            return;
+        }
+        self.pending.insert(sample.key_expr.clone(), sample);
+    }
+
+    /// Promote pending samples into a new frame; `None` when nothing changed.
+    fn advance(&mut self) -> Option<Vec<BufferedSample>> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        self.frame += 1;
+        let mut updated: Vec<BufferedSample> = self.pending.drain().map(|(_, s)| s).collect();
+        updated.sort_by(|a, b| a.key_expr.cmp(&b.key_expr));
+        for sample in &updated {
+            self.latest
+                .insert(sample.key_expr.clone(), (self.frame, sample.clone()));
+        }
+        Some(updated)
+    }
+
+    /// Samples updated after frame `since`, or every key when `None`.
+    pub fn since(&self, since: Option<u64>) -> impl Iterator<Item = &BufferedSample> {
+        self.latest
+            .values()
+            .filter(move |(frame, _)| since.is_none_or(|since| *frame > since))
+            .map(|(_, sample)| sample)
+    }
+}
+
+pub struct Preview {
+    /// Host-facing key expression, without the session namespace.
+    pub key_expr: String,
+    pub rate_hz: f64,
+    pub max_keys: usize,
+    /// Emit a `preview_frame` notification for each frame.
+    pub notify: bool,
+    pub frames: Arc<Mutex<Frames>>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Subscribe to `key_expr` and keep the newest sample per key, publishing a
+/// frame of the keys that changed `rate_hz` times per second however fast
+/// the source is. Quiet ticks produce no frame.
+pub async fn start_preview(
+    session: Arc<dyn ZenohBackend>,
+    preview_id: String,
+    key_expr: String,
+    rate_hz: f64,
+    max_keys: usize,
+    notify: bool,
+    namespace: Option<String>,
+) -> Result<Preview, String> {
+    let scoped = namespace::scope(namespace.as_deref(), &key_expr);
+    let mut stream = session.subscribe(&scoped).await?;
+    let frames: Arc<Mutex<Frames>> = Arc::default();
+    let (cancel, mut cancel_rx) = watch::channel(false);
+
+    {
+        let frames = frames.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(Duration::from_secs_f64(1.0 / rate_hz));
+            tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    sample = stream.recv() => {
+                        let Some(sample) = sample else { break };
+                        let mut frames = frames.lock().unwrap();
+                        let key_expr = namespace::strip(namespace.as_deref(), &sample.key_expr);
+                        let buffered = BufferedSample {
+                            seq: frames.received,
+                            payload: Payload(redaction::redact(&key_expr, sample.payload)),
+                            key_expr,
+                            encoding: sample.encoding,
+                            timestamp: Utc::now(),
+                            arrived_us: clock::now_us(),
+                            source_timestamp: None,
+                            checksum: None,
+                            decoded: None,
+                            decode_error: None,
+                            tags: None,
+                            shm: false,
+                            gap: None,
+                            diff: None,
+                            historical: false,
+                        };
+                        frames.push(buffered, max_keys);
+                    }
+                    _ = tick.tick() => {
+                        let mut frames = frames.lock().unwrap();
+                        let Some(updated) = frames.advance() else { continue };
+                        if notify {
+                            notify::notify("preview_frame", serde_json::json!({
+                                "preview_id": preview_id,
+                                "frame": frames.frame,
+                                "samples": updated,
+                            }));
+                        }
+                    }
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(Preview {
+        key_expr,
+        rate_hz,
+        max_keys,
+        notify,
+        frames,
+        cancel,
+        created_at: Utc::now(),
+    })
+}
@@ -0,0 +1,120 @@
+use crate::api::ProbeReport;
+use crate::backend::{GetOptions, ZenohBackend, ZenohSession};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Outcome of one probe step.
+#[derive(Serialize, JsonSchema)]
+pub struct StepResult {
+    pub supported: bool,
+    pub replies: usize,
+    /// Replies attributed to the probed zid; liveliness replies come from the
+    /// token table rather than the declarer, so they are not attributed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_device: Option<usize>,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What to exercise against the device.
+pub struct ProbePlan {
+    pub zid: String,
+    pub liveliness_key: Option<String>,
+    pub get_key: Option<String>,
+    pub timeout: Duration,
+}
+
+/// Open a short-lived client session connected only to `locator`.
+pub async fn connect_locator(locator: &str) -> Result<ZenohSession, String> {
+    let mut config = zenoh::Config::default();
+    let endpoints = serde_json::json!([locator]).to_string();
+    for (key, value) in [
+        ("mode", "\"client\""),
+        ("connect/endpoints", endpoints.as_str()),
+        ("scouting/multicast/enabled", "false"),
+    ] {
+        config
+            .insert_json5(key, value)
+            .map_err(|e| format!("invalid probe config {key}: {e}"))?;
+    }
+    ZenohSession::open(config)
+        .await
+        .map_err(|e| format!("failed to connect to {locator}: {e}"))
+}
+
+/// Zids of everything `session` is directly connected to.
+pub async fn connected_zids(session: &dyn ZenohBackend) -> Vec<String> {
+    let mut zids = session.routers().await;
+    zids.extend(session.peers().await);
+    zids
+}
+
+/// Run the liveliness, admin-space and get steps of `plan` in sequence.
+pub async fn run(session: &dyn ZenohBackend, plan: &ProbePlan) -> ProbeReport {
+    let connected = connected_zids(session).await.contains(&plan.zid);
+    let liveliness = match &plan.liveliness_key {
+        Some(key) => Some(liveliness_step(session, key, plan).await),
+        None => None,
+    };
+    // zenoh-pico never answers here; full nodes do when their adminspace is enabled
+    let admin = get_step(session, &format!("@/{}/**", plan.zid), plan).await;
+    let get = match &plan.get_key {
+        Some(key) => Some(get_step(session, key, plan).await),
+        None => None,
+    };
+    ProbeReport {
+        zid: plan.zid.clone(),
+        connected,
+        liveliness,
+        admin,
+        get,
+        locator: None,
+    }
+}
+
+async fn liveliness_step(session: &dyn ZenohBackend, key: &str, plan: &ProbePlan) -> StepResult {
+    let started = Instant::now();
+    let count = match session.liveliness_get(key, plan.timeout).await {
+        Ok(keys) => keys.len(),
+        Err(e) => return failed(started, e),
+    };
+    StepResult {
+        supported: count > 0,
+        replies: count,
+        from_device: None,
+        latency_ms: started.elapsed().as_millis() as u64,
+        error: None,
+    }
+}
+
+async fn get_step(session: &dyn ZenohBackend, key: &str, plan: &ProbePlan) -> StepResult {
+    let started = Instant::now();
+    let replies = match session.get(key, GetOptions::default(), plan.timeout).await {
+        Ok(r) => r,
+        Err(e) => return failed(started, e),
+    };
+    let from_device = replies
+        .iter()
+        .filter(|r| r.replier_zid.as_deref() == Some(plan.zid.as_str()))
+        .count();
+    let first_reply = replies.first().map(|r| r.received_at);
+    StepResult {
+        supported: from_device > 0,
+        replies: replies.len(),
+        from_device: Some(from_device),
+        latency_ms: (first_reply.unwrap_or_else(Instant::now) - started).as_millis() as u64,
+        error: replies.into_iter().find_map(|r| r.result.err()),
+    }
+}
+
+fn failed(started: Instant, error: String) -> StepResult {
+    StepResult {
+        supported: false,
+        replies: 0,
+        from_device: None,
+        latency_ms: started.elapsed().as_millis() as u64,
+        error: Some(error),
+    }
+}
@@ -0,0 +1,74 @@
+use crate::backend::{ChannelConfig, ChannelPolicy};
+
+/// Latency/throughput trade-off chosen at initialize. Each profile picks
+/// the defaults below together, so they never pull against each other.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Profile {
+    LowLatency,
+    #[default]
+    Balanced,
+    Throughput,
+}
+
+/// Internal settings a profile decides.
+pub struct Tuning {
+    /// Channel for subscriptions that don't choose one.
+    pub channel: ChannelConfig,
+    /// Decoded samples allowed in flight per subscription before ingest waits.
+    pub decode_queue: usize,
+    /// Samples already queued that ingest buffers under one state lock.
+    pub ingest_batch: usize,
+    /// Puts skip Zenoh's batching and are sent at once.
+    pub express: bool,
+}
+
+impl Profile {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "low-latency" => Ok(Profile::LowLatency),
+            "balanced" => Ok(Profile::Balanced),
+            "throughput" => Ok(Profile::Throughput),
+            other => Err(format!("unsupported profile: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Profile::LowLatency => "low-latency",
+            Profile::Balanced => "balanced",
+            Profile::Throughput => "throughput",
+        }
+    }
+
+    /// Low latency keeps queues short and drops the oldest sample rather than
+    /// fall behind; throughput queues deep and amortizes locking over
+    /// batches.
+    pub fn tuning(self) -> Tuning {
+        match self {
+            Profile::LowLatency => Tuning {
+                channel: ChannelConfig {
+                    policy: ChannelPolicy::Ring,
+                    capacity: Some(64),
+                },
+                decode_queue: 16,
+                ingest_batch: 1,
+                express: true,
+            },
+            Profile::Balanced => Tuning {
+                channel: ChannelConfig::default(),
+                decode_queue: 256,
+                ingest_batch: 16,
+                express: false,
+            },
+            Profile::Throughput => Tuning {
+                channel: ChannelConfig {
+                    policy: ChannelPolicy::Fifo,
+                    capacity: Some(4096),
+                },
+                decode_queue: 2048,
+                ingest_batch: 256,
+                express: false,
+            },
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::backend::{GetOptions, Reply, ZenohBackend};
+use crate::clock;
+use crate::namespace;
+use crate::notify;
+use crate::redaction;
+use crate::state::{BufferedSample, Payload};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// A reply that carried an error instead of a sample.
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct ReplyError {
+    /// Position among all replies to the query, shared with samples.
+    pub seq: u64,
+    pub replier_zid: Option<String>,
+    pub error: String,
+}
+
+/// Replies received and not yet handed out.
+#[derive(Default)]
+pub struct Replies {
+    pub samples: VecDeque<BufferedSample>,
+    pub errors: VecDeque<ReplyError>,
+    pub received: u64,
+    /// No more replies will arrive.
+    pub complete: bool,
+    /// Why the query itself failed, when it did.
+    pub failure: Option<String>,
+}
+
+impl Replies {
+    pub fn push(&mut self, reply: Reply, namespace: Option<&str>) {
+        let seq = self.received;
+        self.received += 1;
+        match reply.result {
+            Ok(sample) => {
+                let key_expr = namespace::strip(namespace, &sample.key_expr);
+                self.samples.push_back(BufferedSample {
+                    seq,
+                    payload: Payload(redaction::redact(&key_expr, sample.payload)),
+                    key_expr,
+                    encoding: sample.encoding,
+                    timestamp: Utc::now(),
+                    arrived_us: clock::now_us(),
+                    source_timestamp: sample.timestamp.map(|(_, at)| at),
+                    checksum: None,
+                    decoded: None,
+                    decode_error: None,
+                    tags: None,
+                    shm: sample.shm,
+                    gap: None,
+                    diff: None,
+                    historical: false,
+                })
+            }
+            Err(error) => self.errors.push_back(ReplyError {
+                seq,
+                replier_zid: reply.replier_zid,
+                error,
+            }),
+        }
+    }
+
+    /// Replies not yet handed out.
+    pub fn pending(&self) -> usize {
+        self.samples.len() + self.errors.len()
+    }
+
+    /// Complete with every reply handed out.
+    pub fn drained(&self) -> bool {
+        self.complete && self.pending() == 0
+    }
+
+    /// Take up to `limit` replies in arrival order.
+    pub fn take(&mut self, limit: usize) -> (Vec<BufferedSample>, Vec<ReplyError>) {
+        let (mut samples, mut errors) = (Vec::new(), Vec::new());
+        while samples.len() + errors.len() < limit {
+            let next_sample = self.samples.front().map(|s| s.seq);
+            let next_error = self.errors.front().map(|e| e.seq);
+            match (next_sample, next_error) {
+                (Some(s), Some(e)) if e < s => errors.extend(self.errors.pop_front()),
+                (Some(_), _) => samples.extend(self.samples.pop_front()),
+                (None, Some(_)) => errors.extend(self.errors.pop_front()),
+                (None, None) => break,
+            }
+        }
+        (samples, errors)
+    }
+}
+
+pub struct QueryStream {
+    /// Host-facing selector, without the session namespace.
+    pub selector: String,
+    pub timeout_ms: u64,
+    /// Replies go out as `query_replies` notifications instead of waiting
+    /// for `poll_query`.
+    pub notify: bool,
+    pub replies: Arc<Mutex<Replies>>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Query `selector` and collect replies as they arrive, so a selector
+/// matching many keys never has to fit in one response. With `notify_batch`,
+/// replies are pushed in `query_replies` notifications of that many and the
+/// end is announced with `query_done`.
+pub fn start_query(
+    session: Arc<dyn ZenohBackend>,
+    query_id: String,
+    selector: String,
+    options: GetOptions,
+    timeout_ms: u64,
+    notify_batch: Option<usize>,
+    namespace: Option<String>,
+) -> QueryStream {
+    let replies: Arc<Mutex<Replies>> = Arc::default();
+    let (cancel, mut cancel_rx) = watch::channel(false);
+
+    {
+        let replies = replies.clone();
+        let scoped = namespace::scope(namespace.as_deref(), &selector);
+        tokio::spawn(async move {
+            let send_batch = |replies: &mut Replies, limit: usize| {
+                let (samples, errors) = replies.take(limit);
+                notify::notify(
+                    "query_replies",
+                    serde_json::json!({
+                        "query_id": query_id,
+                        "samples": samples,
+                        "errors": errors,
+                    }),
+                );
+            };
+            let mut on_reply = |reply: Reply| {
+                let mut replies = replies.lock().unwrap();
+                replies.push(reply, namespace.as_deref());
+                if let Some(batch) = notify_batch.filter(|batch| replies.pending() >= *batch) {
+                    send_batch(&mut replies, batch);
+                }
+            };
+            let timeout = Duration::from_millis(timeout_ms);
+            let result = tokio::select! {
+                result = session.get_each(&scoped, options, timeout, &mut on_reply) => result,
+                _ = cancel_rx.wait_for(|cancelled| *cancelled) => return,
+            };
+
+            let mut replies = replies.lock().unwrap();
+            replies.complete = true;
+            replies.failure = result.err();
+            if let Some(batch) = notify_batch {
+                while replies.pending() > 0 {
+                    send_batch(&mut replies, batch);
+                }
+                notify::notify(
+                    "query_done",
+                    serde_json::json!({
+                        "query_id": query_id,
+                        "received": replies.received,
+                        "failure": replies.failure,
+                    }),
+                );
+            }
+        });
+    }
+
+    QueryStream {
+        selector,
+        timeout_ms,
+        notify: notify_batch.is_some(),
+        replies,
+        cancel,
+        created_at: Utc::now(),
+    }
+}
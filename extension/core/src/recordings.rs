@@ -0,0 +1,614 @@
+use crate::flight::{self, RingIndex};
+use crate::notify;
+use crate::state::{BufferedSample, Payload, StateLock};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+/// Directory name prefix of flight recorder rings.
+pub const RING_PREFIX: &str = ".flight-";
+
+/// How often the background pruner enforces the retention policy.
+const PRUNE_EVERY: Duration = Duration::from_secs(60);
+
+/// Records between the seek index entries of a [`RecordingReader`].
+const INDEX_EVERY: u64 = 1000;
+
+/// Recordings a [`RecordingReader`] has open, with how many readers each,
+/// so pruning leaves them in place.
+static OPEN: LazyLock<Mutex<HashMap<PathBuf, usize>>> = LazyLock::new(Default::default);
+
+/// Start of an encrypted recording line; base64 of the nonce and sealed
+/// record follows.
+const SEALED_PREFIX: &[u8] = b"aes-gcm:";
+
+const NONCE_LEN: usize = 12;
+
+/// Where recordings are written and how much of them to keep, set at initialize.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RetentionPolicy {
+    pub dir: PathBuf,
+    /// Oldest recordings are removed until the directory fits.
+    pub max_total_bytes: Option<u64>,
+    /// Recordings last modified longer ago than this are removed.
+    pub max_age_secs: Option<u64>,
+    /// Base64 of a 32-byte AES-256-GCM key. Flight recorders write their
+    /// rings and dumps encrypted with it, and encrypted recordings are read
+    /// with it. Never echoed back.
+    #[serde(default, skip_serializing)]
+    pub encryption_key: Option<String>,
+    #[serde(skip)]
+    cipher: Option<Cipher>,
+}
+
+impl RetentionPolicy {
+    /// Create the directory if needed and load the encryption key.
+    pub fn validate(self) -> Result<Self, String> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| {
+            format!(
+                "failed to create recordings dir {}: {e}",
+                self.dir.display()
+            )
+        })?;
+        let cipher = self
+            .encryption_key
+            .as_deref()
+            .map(Cipher::from_base64)
+            .transpose()?;
+        Ok(Self { cipher, ..self })
+    }
+
+    pub fn cipher(&self) -> Option<&Cipher> {
+        self.cipher.as_ref()
+    }
+}
+
+/// The key recordings are encrypted with at rest. Each line is sealed on
+/// its own, so recordings stay appendable and seekable by line.
+#[derive(Clone)]
+pub struct Cipher(Aes256Gcm);
+
+impl Cipher {
+    pub fn from_base64(key: &str) -> Result<Self, String> {
+        let key = base64::engine::general_purpose::STANDARD
+            .decode(key.trim())
+            .map_err(|e| format!("encryption_key is not valid base64: {e}"))?;
+        if key.len() != 32 {
+            return Err(format!(
+                "encryption_key must be 32 bytes, not {}",
+                key.len()
+            ));
+        }
+        Ok(Self(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))))
+    }
+
+    fn seal(&self, plain: &[u8]) -> Vec<u8> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let mut sealed = nonce.to_vec();
+        sealed.extend(
+            self.0
+                .encrypt(&nonce, plain)
+                .expect("AES-GCM seals a record of any size"),
+        );
+        let mut line = SEALED_PREFIX.to_vec();
+        line.extend(
+            base64::engine::general_purpose::STANDARD
+                .encode(sealed)
+                .into_bytes(),
+        );
+        line
+    }
+
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, String> {
+        let failed = || "failed to decrypt recording: wrong encryption_key or corrupted line";
+        let sealed = base64::engine::general_purpose::STANDARD
+            .decode(sealed)
+            .map_err(|_| failed())?;
+        if sealed.len() < NONCE_LEN {
+            return Err(failed().into());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.0
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| failed().into())
+    }
+}
+
+/// One sample as stored in a recording file, one JSON object per line.
+#[derive(Serialize, Deserialize)]
+pub struct Record {
+    pub timestamp: DateTime<Utc>,
+    pub key_expr: String,
+    pub encoding: String,
+    pub payload_b64: String,
+}
+
+impl Record {
+    pub fn new(key_expr: String, encoding: String, payload: &[u8]) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            key_expr,
+            encoding,
+            payload_b64: base64::engine::general_purpose::STANDARD.encode(payload),
+        }
+    }
+
+    /// The record as a recording line ending in a newline, sealed when
+    /// there is a `cipher`.
+    pub fn to_line(&self, cipher: Option<&Cipher>) -> Vec<u8> {
+        let json = serde_json::to_vec(self).unwrap();
+        let mut line = match cipher {
+            Some(cipher) => cipher.seal(&json),
+            None => json,
+        };
+        line.push(b'\n');
+        line
+    }
+
+    /// Parse a recording line, plain or sealed. `None` when it is not a
+    /// record; an error when it is sealed and `cipher` can't open it.
+    pub fn from_line(line: &[u8], cipher: Option<&Cipher>) -> Result<Option<Self>, String> {
+        let line = line.trim_ascii_end();
+        let Some(sealed) = line.strip_prefix(SEALED_PREFIX) else {
+            return Ok(serde_json::from_slice(line).ok());
+        };
+        let cipher =
+            cipher.ok_or("recording is encrypted; pass recordings.encryption_key at initialize")?;
+        Ok(serde_json::from_slice(&cipher.open(sealed)?).ok())
+    }
+
+    /// The record as sample `seq` of a playback, shaped like a live one.
+    pub fn into_sample(self, seq: u64) -> BufferedSample {
+        let payload = base64::engine::general_purpose::STANDARD.decode(&self.payload_b64);
+        BufferedSample {
+            seq,
+            key_expr: self.key_expr,
+            decode_error: payload
+                .as_ref()
+                .err()
+                .map(|e| format!("invalid payload_b64: {e}")),
+            payload: Payload(payload.unwrap_or_default()),
+            encoding: self.encoding,
+            timestamp: self.timestamp,
+            arrived_us: 0,
+            source_timestamp: None,
+            checksum: None,
+            decoded: None,
+            tags: None,
+            shm: false,
+            gap: None,
+            diff: None,
+            historical: false,
+        }
+    }
+}
+
+/// One file in the recordings directory.
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct RecordingInfo {
+    pub name: String,
+    pub size_bytes: u64,
+    pub modified: DateTime<Utc>,
+}
+
+/// Files directly inside `dir`, oldest first.
+pub fn list(dir: &Path) -> Result<Vec<RecordingInfo>, String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("failed to read {}: {e}", dir.display()))?;
+    let mut recordings = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(meta) = entry.metadata() else { continue };
+        if !meta.is_file() {
+            continue;
+        }
+        recordings.push(RecordingInfo {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            size_bytes: meta.len(),
+            modified: meta
+                .modified()
+                .map(DateTime::from)
+                .unwrap_or_else(|_| Utc::now()),
+        });
+    }
+    recordings.sort_by(|a, b| a.modified.cmp(&b.modified).then(a.name.cmp(&b.name)));
+    Ok(recordings)
+}
+
+/// Path of recording `name` in `dir`; `name` must be a plain file name.
+pub fn path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(dir.join(name)),
+        _ => Err(format!("recording name must be a plain file name: {name}")),
+    }
+}
+
+/// Every record of the file at `path` that `keep` accepts, in file order.
+pub fn records(
+    path: &Path,
+    cipher: Option<&Cipher>,
+    keep: impl Fn(&Record) -> bool,
+) -> Result<Vec<Record>, String> {
+    let file = File::open(path).map_err(|e| format!("failed to open {}: {e}", path.display()))?;
+    let mut records = Vec::new();
+    for line in BufReader::new(file).split(b'\n').map_while(Result::ok) {
+        match Record::from_line(&line, cipher)? {
+            Some(record) if keep(&record) => records.push(record),
+            _ => {}
+        }
+    }
+    Ok(records)
+}
+
+/// Remove recording `name`, returning its size.
+pub fn delete(dir: &Path, name: &str) -> Result<u64, String> {
+    let path = path(dir, name)?;
+    let meta = std::fs::metadata(&path).map_err(|_| format!("recording not found: {name}"))?;
+    if !meta.is_file() {
+        return Err(format!("recording not found: {name}"));
+    }
+    std::fs::remove_file(&path).map_err(|e| format!("failed to delete {name}: {e}"))?;
+    Ok(meta.len())
+}
+
+/// Bytes held by the files directly under `dir`, such as a ring's segments.
+fn dir_bytes(dir: &Path) -> u64 {
+    std::fs::read_dir(dir).map_or(0, |entries| {
+        entries
+            .flatten()
+            .filter_map(|e| e.metadata().ok())
+            .filter(|meta| meta.is_file())
+            .map(|meta| meta.len())
+            .sum()
+    })
+}
+
+/// Flight recorder rings in `dir`, live or left behind by a crash.
+pub fn rings(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut rings: Vec<String> = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with(RING_PREFIX))
+        .collect();
+    rings.sort();
+    rings
+}
+
+/// What [`repair`] recovered.
+pub struct Repaired {
+    /// The repaired recording.
+    pub name: String,
+    pub records: u64,
+    /// Records a ring's index vouched for at its last checkpoint.
+    pub indexed_records: Option<u64>,
+    /// Complete lines that were not valid records.
+    pub dropped_lines: u64,
+    /// Bytes of a last line cut off mid-write.
+    pub truncated_bytes: u64,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+}
+
+/// Recover what a crash left of `name`. A recording cut off mid-write is
+/// rewritten with its complete records; a flight recorder ring left behind
+/// is joined into `flight-<id>-recovered.ndjson` and removed. Encrypted
+/// lines stay encrypted, but `cipher` must open them.
+pub fn repair(dir: &Path, name: &str, cipher: Option<&Cipher>) -> Result<Repaired, String> {
+    let source = path(dir, name)?;
+    let meta = std::fs::metadata(&source).map_err(|_| format!("recording not found: {name}"))?;
+    if !meta.is_dir() {
+        let tmp = dir.join(format!(".{name}.repair"));
+        let repaired = salvage(std::slice::from_ref(&source), &tmp, name, cipher)?;
+        std::fs::rename(&tmp, &source).map_err(|e| format!("failed to replace {name}: {e}"))?;
+        return Ok(repaired);
+    }
+
+    let id = name
+        .strip_prefix(RING_PREFIX)
+        .ok_or_else(|| format!("{name} is not a recording or flight recorder ring"))?;
+    let (segments, indexed_records) = ring_segments(&source)?;
+    let out = format!("flight-{id}-recovered.ndjson");
+    let mut repaired = salvage(&segments, &dir.join(&out), &out, cipher)?;
+    repaired.indexed_records = indexed_records;
+    std::fs::remove_dir_all(&source).map_err(|e| format!("failed to remove {name}: {e}"))?;
+    Ok(repaired)
+}
+
+/// A ring's segment files oldest first, and the records its index counted.
+/// Segments the index doesn't list yet are appended in name order.
+fn ring_segments(ring: &Path) -> Result<(Vec<PathBuf>, Option<u64>), String> {
+    let index = std::fs::read(ring.join(flight::INDEX_NAME))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<RingIndex>(&bytes).ok());
+    let mut names: Vec<String> = index
+        .iter()
+        .flat_map(|index| index.segments.iter().map(|s| s.name.clone()))
+        .collect();
+    let mut unindexed: Vec<String> = std::fs::read_dir(ring)
+        .map_err(|e| format!("failed to read {}: {e}", ring.display()))?
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("segment-") && !names.contains(name))
+        .collect();
+    unindexed.sort();
+    names.extend(unindexed);
+    let indexed = index.map(|index| index.segments.iter().map(|s| s.records).sum());
+    Ok((names.into_iter().map(|n| ring.join(n)).collect(), indexed))
+}
+
+/// Copy every complete, parseable record in `inputs` to `out`, in order.
+fn salvage(
+    inputs: &[PathBuf],
+    out: &Path,
+    name: &str,
+    cipher: Option<&Cipher>,
+) -> Result<Repaired, String> {
+    let file = File::create(out).map_err(|e| format!("failed to create {}: {e}", out.display()))?;
+    let mut writer = BufWriter::new(file);
+    let mut repaired = Repaired {
+        name: name.to_string(),
+        records: 0,
+        indexed_records: None,
+        dropped_lines: 0,
+        truncated_bytes: 0,
+        first: None,
+        last: None,
+    };
+    let mut line = Vec::new();
+    for input in inputs {
+        // The oldest segment may have been rotated out mid-crash
+        let Ok(file) = File::open(input) else {
+            continue;
+        };
+        let mut reader = BufReader::new(file);
+        loop {
+            line.clear();
+            let read = reader
+                .read_until(b'\n', &mut line)
+                .map_err(|e| format!("failed to read {}: {e}", input.display()))?;
+            if read == 0 {
+                break;
+            }
+            if line.last() != Some(&b'\n') {
+                repaired.truncated_bytes += line.len() as u64;
+                break;
+            }
+            let Some(record) = Record::from_line(&line, cipher)? else {
+                repaired.dropped_lines += 1;
+                continue;
+            };
+            writer
+                .write_all(&line)
+                .map_err(|e| format!("failed to write {}: {e}", out.display()))?;
+            repaired.records += 1;
+            repaired.first.get_or_insert(record.timestamp);
+            repaired.last = Some(record.timestamp);
+        }
+    }
+    writer
+        .flush()
+        .map_err(|e| format!("failed to write {}: {e}", out.display()))?;
+    Ok(repaired)
+}
+
+/// A read position in a recording, kept between `read_recording` calls.
+/// Records are taken to be in time order, as recorders write them.
+pub struct RecordingReader {
+    pub name: String,
+    path: PathBuf,
+    /// Byte offset of the next record.
+    offset: u64,
+    /// Number of the next record, counting from 0.
+    pub position: u64,
+    /// Records seen so far: those counted at open plus any appended since.
+    pub records: u64,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+    /// Timestamp and byte offset of every `INDEX_EVERY`th record.
+    index: Vec<(DateTime<Utc>, u64)>,
+    /// Paced reads offset samples from this: the first record read since
+    /// opening or the last seek.
+    origin: Option<DateTime<Utc>>,
+    cipher: Option<Cipher>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RecordingReader {
+    /// Open recording `name` in `dir` at its first record, indexing it for
+    /// seeks. Reads the whole file, so run it off the runtime. Encrypted
+    /// recordings need `cipher`.
+    pub fn open(dir: &Path, name: &str, cipher: Option<Cipher>) -> Result<Self, String> {
+        let path = path(dir, name)?;
+        if !std::fs::metadata(&path).is_ok_and(|m| m.is_file()) {
+            return Err(format!("recording not found: {name}"));
+        }
+        let mut reader = Self {
+            name: name.to_string(),
+            path,
+            offset: 0,
+            position: 0,
+            records: 0,
+            first: None,
+            last: None,
+            index: Vec::new(),
+            origin: None,
+            cipher,
+            created_at: Utc::now(),
+        };
+        *OPEN.lock().unwrap().entry(reader.path.clone()).or_default() += 1;
+        let mut offset = 0;
+        for (record, next) in reader.scan(0, usize::MAX)? {
+            if reader.records.is_multiple_of(INDEX_EVERY) {
+                reader.index.push((record.timestamp, offset));
+            }
+            reader.first.get_or_insert(record.timestamp);
+            reader.last = Some(record.timestamp);
+            reader.records += 1;
+            offset = next;
+        }
+        Ok(reader)
+    }
+
+    /// Move to the first record at or after `at`.
+    pub fn seek(&mut self, at: DateTime<Utc>) -> Result<(), String> {
+        let entry = self.index.partition_point(|(ts, _)| *ts < at);
+        let entry = entry.saturating_sub(1);
+        let (mut offset, mut position) = match self.index.get(entry) {
+            Some((_, offset)) => (*offset, entry as u64 * INDEX_EVERY),
+            None => (0, 0),
+        };
+        for (record, next) in self.scan(offset, usize::MAX)? {
+            if record.timestamp >= at {
+                break;
+            }
+            offset = next;
+            position += 1;
+        }
+        self.offset = offset;
+        self.position = position;
+        self.origin = None;
+        Ok(())
+    }
+
+    /// Up to `limit` records from the read position without moving it, each
+    /// with the byte offset following it.
+    pub fn peek(&self, limit: usize) -> Result<Vec<(Record, u64)>, String> {
+        self.scan(self.offset, limit)
+    }
+
+    /// Move past `count` records ending at byte `offset`, as returned by
+    /// [`RecordingReader::peek`].
+    pub fn advance(&mut self, count: u64, offset: u64) {
+        self.offset = offset;
+        self.position += count;
+        self.records = self.records.max(self.position);
+    }
+
+    /// Milliseconds from the paced origin to `at`, setting the origin to
+    /// `at` when there is none yet.
+    pub fn offset_ms(&mut self, at: DateTime<Utc>) -> f64 {
+        let origin = *self.origin.get_or_insert(at);
+        (at - origin).num_microseconds().unwrap_or_default() as f64 / 1000.0
+    }
+
+    /// Complete, parseable records from byte `offset`, up to `limit`, each
+    /// with the byte offset following it. Lines that are not records are
+    /// skipped; a last line still being written ends the scan.
+    fn scan(&self, offset: u64, limit: usize) -> Result<Vec<(Record, u64)>, String> {
+        let name = &self.name;
+        let mut file = File::open(&self.path).map_err(|e| format!("failed to open {name}: {e}"))?;
+        file.seek(SeekFrom::Start(offset))
+            .map_err(|e| format!("failed to read {name}: {e}"))?;
+        let mut reader = BufReader::new(file);
+        let (mut records, mut offset, mut line) = (Vec::new(), offset, Vec::new());
+        while records.len() < limit {
+            line.clear();
+            let read = reader
+                .read_until(b'\n', &mut line)
+                .map_err(|e| format!("failed to read {name}: {e}"))?;
+            if read == 0 || line.last() != Some(&b'\n') {
+                break;
+            }
+            offset += read as u64;
+            if let Some(record) = Record::from_line(&line, self.cipher.as_ref())? {
+                records.push((record, offset));
+            }
+        }
+        Ok(records)
+    }
+}
+
+impl Drop for RecordingReader {
+    fn drop(&mut self) {
+        let mut open = OPEN.lock().unwrap();
+        if let Some(readers) = open.get_mut(&self.path) {
+            *readers -= 1;
+            if *readers == 0 {
+                open.remove(&self.path);
+            }
+        }
+    }
+}
+
+/// Remove recordings older than the age limit, then the oldest ones until the
+/// directory fits the size limit. Flight recorder rings count toward the
+/// limit but are left to their recorders and `repair_recording`. `keep` and
+/// recordings a reader has open are never removed. Emits a
+/// `recordings_pruned` notification when anything was deleted.
+pub fn prune(policy: &RetentionPolicy, keep: Option<&str>) -> Result<Vec<RecordingInfo>, String> {
+    let recordings = list(&policy.dir)?;
+    let rings: u64 = rings(&policy.dir)
+        .iter()
+        .map(|ring| dir_bytes(&policy.dir.join(ring)))
+        .sum();
+    let mut total: u64 = rings + recordings.iter().map(|r| r.size_bytes).sum::<u64>();
+    let cutoff = policy
+        .max_age_secs
+        .map(|secs| Utc::now() - chrono::Duration::seconds(secs as i64));
+
+    let mut removed = Vec::new();
+    for recording in recordings {
+        if keep == Some(recording.name.as_str())
+            || OPEN
+                .lock()
+                .unwrap()
+                .contains_key(&policy.dir.join(&recording.name))
+        {
+            continue;
+        }
+        let expired = cutoff.is_some_and(|cutoff| recording.modified < cutoff);
+        let over_quota = policy.max_total_bytes.is_some_and(|max| total > max);
+        if !expired && !over_quota {
+            continue;
+        }
+        match delete(&policy.dir, &recording.name) {
+            Ok(size) => {
+                total -= size;
+                removed.push(recording);
+            }
+            Err(e) => eprintln!("recordings: {e}"),
+        }
+    }
+
+    if !removed.is_empty() {
+        notify::notify(
+            "recordings_pruned",
+            serde_json::json!({
+                "dir": policy.dir,
+                "removed": removed.iter().map(|r| &r.name).collect::<Vec<_>>(),
+                "freed_bytes": removed.iter().map(|r| r.size_bytes).sum::<u64>(),
+            }),
+        );
+    }
+    Ok(removed)
+}
+
+/// Spawn the background task that applies the retention policy, if any.
+pub fn spawn_pruner(state: Arc<StateLock>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PRUNE_EVERY);
+        loop {
+            interval.tick().await;
+            let Some(policy) = state.read().await.recordings.clone() else {
+                continue;
+            };
+            let result = tokio::task::spawn_blocking(move || prune(&policy, None)).await;
+            if let Ok(Err(e)) = result {
+                state.write().await.record_error("prune_recordings", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,95 @@
+use crate::jsonpath::Pattern;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, RwLock};
+use zenoh::key_expr::KeyExpr;
+
+/// Rules in the order they were added. Process-wide like the encoding
+/// registry, so every buffer, recorder and watch redacts alike.
+static RULES: LazyLock<RwLock<Vec<Arc<RedactionRule>>>> = LazyLock::new(Default::default);
+
+pub enum Action {
+    /// Replace matched values with this one.
+    Mask(Value),
+    /// Remove matched values from their object or array.
+    Drop,
+}
+
+pub struct RedactionRule {
+    pub id: String,
+    pub key_expr: KeyExpr<'static>,
+    pub path: Pattern,
+    pub action: Action,
+    /// Values this rule has masked or dropped.
+    pub applied: AtomicU64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RedactionRule {
+    fn apply(&self, value: &mut Value) -> usize {
+        let count = match &self.action {
+            Action::Mask(with) => self.path.replace(value, with),
+            Action::Drop => self.path.remove(value),
+        };
+        self.applied.fetch_add(count as u64, Ordering::Relaxed);
+        count
+    }
+}
+
+pub fn add(rule: RedactionRule) -> Arc<RedactionRule> {
+    let rule = Arc::new(rule);
+    RULES.write().unwrap().push(rule.clone());
+    rule
+}
+
+pub fn remove(id: &str) -> bool {
+    let mut rules = RULES.write().unwrap();
+    let before = rules.len();
+    rules.retain(|rule| rule.id != id);
+    rules.len() < before
+}
+
+pub fn rules() -> Vec<Arc<RedactionRule>> {
+    RULES.read().unwrap().clone()
+}
+
+/// Rules covering samples on `key_expr`.
+fn matching(key_expr: &str) -> Vec<Arc<RedactionRule>> {
+    let rules = RULES.read().unwrap();
+    if rules.is_empty() {
+        return Vec::new();
+    }
+    let Ok(key) = KeyExpr::try_from(key_expr) else {
+        return Vec::new();
+    };
+    rules
+        .iter()
+        .filter(|rule| rule.key_expr.includes(&key))
+        .cloned()
+        .collect()
+}
+
+/// `payload` with the rules for `key_expr` applied. Only JSON payloads are
+/// redacted; others, and JSON no rule matched in, come back unchanged.
+pub fn redact(key_expr: &str, payload: Vec<u8>) -> Vec<u8> {
+    let rules = matching(key_expr);
+    if rules.is_empty() {
+        return payload;
+    }
+    let Ok(mut value) = serde_json::from_slice::<Value>(&payload) else {
+        return payload;
+    };
+    let applied: usize = rules.iter().map(|rule| rule.apply(&mut value)).sum();
+    match applied {
+        0 => payload,
+        _ => serde_json::to_vec(&value).unwrap_or(payload),
+    }
+}
+
+/// Apply the rules for `key_expr` to a decoded payload.
+pub fn redact_value(key_expr: &str, value: &mut Value) {
+    for rule in matching(key_expr) {
+        rule.apply(value);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::backend::{RxSample, ZenohBackend};
+use crate::namespace;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// When a relay passes a sample on, applied per source key.
+#[derive(Clone)]
+pub struct ThrottleConfig {
+    /// Minimum spacing between republished samples on one key.
+    pub min_interval: Option<Duration>,
+    /// Skip samples whose payload equals the last one republished.
+    pub on_change: bool,
+}
+
+#[derive(Default)]
+pub struct RelayStats {
+    pub received: AtomicU64,
+    pub published: AtomicU64,
+    /// Samples skipped as unchanged or superseded within the interval.
+    pub suppressed: AtomicU64,
+    pub errors: AtomicU64,
+}
+
+pub struct ThrottledRelay {
+    /// Host-facing key expression, without the session namespace.
+    pub key_expr: String,
+    pub to_prefix: String,
+    pub config: ThrottleConfig,
+    pub stats: Arc<RelayStats>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct KeyState {
+    last_sent: Option<Instant>,
+    last_hash: Option<u64>,
+    /// Newest sample held back by the interval, sent when it elapses.
+    pending: Option<(RxSample, u64)>,
+}
+
+impl KeyState {
+    fn due(&self, min_interval: Option<Duration>) -> Option<Instant> {
+        match (self.last_sent, min_interval) {
+            (Some(at), Some(interval)) => Some(at + interval),
+            _ => None,
+        }
+    }
+}
+
+/// Subscribe to `key_expr` and republish each key's samples under
+/// `to_prefix`, at most once per interval and optionally only on change.
+/// Within an interval the newest sample wins and is sent when it elapses,
+/// so the relayed value never goes stale.
+pub async fn start_relay(
+    session: Arc<dyn ZenohBackend>,
+    key_expr: String,
+    to_prefix: String,
+    config: ThrottleConfig,
+    namespace: Option<String>,
+) -> Result<ThrottledRelay, String> {
+    let scoped = namespace::scope(namespace.as_deref(), &key_expr);
+    let mut stream = session.subscribe(&scoped).await?;
+    let stats: Arc<RelayStats> = Arc::default();
+    let (cancel, mut cancel_rx) = watch::channel(false);
+
+    {
+        let (config, stats, to_prefix) = (config.clone(), stats.clone(), to_prefix.clone());
+        tokio::spawn(async move {
+            let mut keys: HashMap<String, KeyState> = HashMap::new();
+            let send = |sample: RxSample| {
+                let key = namespace::strip(namespace.as_deref(), &sample.key_expr);
+                let target = namespace::scope(namespace.as_deref(), &format!("{to_prefix}/{key}"));
+                let (session, stats) = (session.clone(), stats.clone());
+                async move {
+                    let counter = match session.put(&target, sample.payload, &sample.encoding).await
+                    {
+                        Ok(()) => &stats.published,
+                        Err(_) => &stats.errors,
+                    };
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            };
+
+            loop {
+                let next_due = keys
+                    .values()
+                    .filter(|k| k.pending.is_some())
+                    .filter_map(|k| k.due(config.min_interval))
+                    .min();
+                let flush = async {
+                    match next_due {
+                        Some(at) => tokio::time::sleep_until(at).await,
+                        None => std::future::pending().await,
+                    }
+                };
+
+                tokio::select! {
+                    sample = stream.recv() => {
+                        let Some(sample) = sample else { break };
+                        stats.received.fetch_add(1, Ordering::Relaxed);
+                        let hash = xxhash_rust::xxh3::xxh3_64(&sample.payload);
+                        let state = keys.entry(sample.key_expr.clone()).or_default();
+                        if config.on_change && state.last_hash == Some(hash) {
+                            if state.pending.take().is_some() {
+                                stats.suppressed.fetch_add(1, Ordering::Relaxed);
+                            }
+                            stats.suppressed.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                        let now = Instant::now();
+                        if state.due(config.min_interval).is_some_and(|at| now < at) {
+                            if state.pending.replace((sample, hash)).is_some() {
+                                stats.suppressed.fetch_add(1, Ordering::Relaxed);
+                            }
+                            continue;
+                        }
+                        state.last_sent = Some(now);
+                        state.last_hash = Some(hash);
+                        send(sample).await;
+                    }
+                    _ = flush => {
+                        let now = Instant::now();
+                        let ready: Vec<RxSample> = keys
+                            .values_mut()
+                            .filter(|k| k.due(config.min_interval).is_some_and(|at| at <= now))
+                            .filter_map(|k| {
+                                let (sample, hash) = k.pending.take()?;
+                                k.last_sent = Some(now);
+                                k.last_hash = Some(hash);
+                                Some(sample)
+                            })
+                            .collect();
+                        for sample in ready {
+                            send(sample).await;
+                        }
+                    }
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(ThrottledRelay {
+        key_expr,
+        to_prefix,
+        config,
+        stats,
+        cancel,
+        created_at: Utc::now(),
+    })
+}
@@ -0,0 +1,267 @@
+use crate::decode::{self, DecodeFormat};
+use crate::encodings;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::Write as _;
+
+/// Bytes shown per hexdump row.
+const HEX_ROW: usize = 16;
+
+/// How a payload is shown.
+#[derive(Clone, Copy, PartialEq)]
+pub enum View {
+    /// Image metadata, then decoded JSON, then text, then a hexdump.
+    Auto,
+    Json,
+    Text,
+    Hex,
+}
+
+impl View {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "auto" => Ok(View::Auto),
+            "json" => Ok(View::Json),
+            "text" => Ok(View::Text),
+            "hex" => Ok(View::Hex),
+            other => Err(format!("unknown view: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            View::Auto => "auto",
+            View::Json => "json",
+            View::Text => "text",
+            View::Hex => "hex",
+        }
+    }
+}
+
+/// Format and size read from an image header.
+#[derive(Serialize, JsonSchema)]
+pub struct ImageInfo {
+    pub format: &'static str,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// A payload made readable.
+#[derive(Serialize, JsonSchema)]
+pub struct Rendered {
+    /// `image`, `json`, `text` or `hex`.
+    pub kind: &'static str,
+    /// The rendering itself: pretty JSON, text, a hexdump or an image
+    /// description.
+    pub text: String,
+    /// The decoded value, for `json`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<ImageInfo>,
+    /// The hexdump stopped at `max_bytes`.
+    pub truncated: bool,
+}
+
+/// Render `payload` for a person to read. `decoded` is a value a plugin
+/// already produced; otherwise `format` decodes, with `auto` choosing by
+/// encoding and falling back to JSON text. An `auto` view takes the view
+/// registered for the encoding, if any. `max_bytes` caps the hexdump.
+pub fn render(
+    payload: &[u8],
+    encoding: &str,
+    format: DecodeFormat,
+    decoded: Option<Value>,
+    view: View,
+    max_bytes: usize,
+) -> Result<Rendered, String> {
+    let json = |value: Value| Rendered {
+        kind: "json",
+        text: serde_json::to_string_pretty(&value).unwrap(),
+        decoded: Some(value),
+        image: None,
+        truncated: false,
+    };
+    let decode = || match decoded.clone() {
+        Some(value) => Ok(value),
+        None => decode::decode(format, encoding, payload).or_else(|e| {
+            // Untagged payloads are often JSON text anyway
+            match format {
+                DecodeFormat::Auto => {
+                    serde_json::from_slice(payload).map_err(|_| match encoding.is_empty() {
+                        true => "payload is not JSON text and has no encoding to decode by".into(),
+                        false => e,
+                    })
+                }
+                _ => Err(e),
+            }
+        }),
+    };
+
+    let view = match view {
+        View::Auto => encodings::lookup(encoding)
+            .and_then(|rule| rule.view)
+            .unwrap_or(View::Auto),
+        view => view,
+    };
+    match view {
+        View::Json => return decode().map(json),
+        View::Text => {
+            return std::str::from_utf8(payload)
+                .map(|text| text_rendering(text.to_string()))
+                .map_err(|e| format!("payload is not UTF-8: {e}"))
+        }
+        View::Hex => return Ok(hexdump(payload, max_bytes)),
+        View::Auto => {}
+    }
+    if let Some(image) = image_info(payload) {
+        return Ok(Rendered {
+            kind: "image",
+            text: format!(
+                "{} image, {}, {} bytes",
+                image.format.to_uppercase(),
+                match (image.width, image.height) {
+                    (Some(w), Some(h)) => format!("{w}x{h}"),
+                    _ => "size unknown".to_string(),
+                },
+                payload.len()
+            ),
+            decoded: None,
+            image: Some(image),
+            truncated: false,
+        });
+    }
+    if let Ok(value) = decode() {
+        return Ok(json(value));
+    }
+    match std::str::from_utf8(payload) {
+        Ok(text) if readable(text) => Ok(text_rendering(text.to_string())),
+        _ => Ok(hexdump(payload, max_bytes)),
+    }
+}
+
+/// Text with no control characters beyond line breaks and tabs.
+fn readable(text: &str) -> bool {
+    text.chars()
+        .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+}
+
+fn text_rendering(text: String) -> Rendered {
+    Rendered {
+        kind: "text",
+        text,
+        decoded: None,
+        image: None,
+        truncated: false,
+    }
+}
+
+/// Offset, hex bytes and printable ASCII per row, as `hexdump -C` shows them.
+fn hexdump(payload: &[u8], max_bytes: usize) -> Rendered {
+    let shown = &payload[..payload.len().min(max_bytes)];
+    let mut text = String::new();
+    for (row, chunk) in shown.chunks(HEX_ROW).enumerate() {
+        let _ = write!(text, "{:08x} ", row * HEX_ROW);
+        for i in 0..HEX_ROW {
+            if i == HEX_ROW / 2 {
+                text.push(' ');
+            }
+            match chunk.get(i) {
+                Some(byte) => {
+                    let _ = write!(text, " {byte:02x}");
+                }
+                None => text.push_str("   "),
+            }
+        }
+        text.push_str("  |");
+        text.extend(
+            chunk
+                .iter()
+                .map(|&b| match b.is_ascii_graphic() || b == b' ' {
+                    true => b as char,
+                    false => '.',
+                }),
+        );
+        text.push_str("|\n");
+    }
+    Rendered {
+        kind: "hex",
+        text,
+        decoded: None,
+        image: None,
+        truncated: shown.len() < payload.len(),
+    }
+}
+
+/// Recognize common image formats by their magic bytes and read the size
+/// from the header where it sits at a fixed place.
+pub fn image_info(payload: &[u8]) -> Option<ImageInfo> {
+    let be32 = |at: usize| {
+        Some(u32::from_be_bytes(
+            payload.get(at..at + 4)?.try_into().ok()?,
+        ))
+    };
+    let le16 =
+        |at: usize| Some(u16::from_le_bytes(payload.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le32 = |at: usize| {
+        Some(u32::from_le_bytes(
+            payload.get(at..at + 4)?.try_into().ok()?,
+        ))
+    };
+    let info = |format, width, height| ImageInfo {
+        format,
+        width,
+        height,
+    };
+
+    if payload.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(info("png", be32(16), be32(20)));
+    }
+    if payload.starts_with(b"GIF87a") || payload.starts_with(b"GIF89a") {
+        return Some(info("gif", le16(6), le16(8)));
+    }
+    if payload.starts_with(b"BM") && payload.len() >= 26 {
+        // Rows stored top-down have a negative height
+        let height = le32(22).map(|h| (h as i32).unsigned_abs());
+        return Some(info("bmp", le32(18), height));
+    }
+    if payload.starts_with(b"RIFF") && payload.get(8..12) == Some(b"WEBP") {
+        let (width, height) = match payload.get(12..16) {
+            Some(b"VP8X") => (
+                le32(24).map(|v| (v & 0xff_ffff) + 1),
+                le32(27).map(|v| (v & 0xff_ffff) + 1),
+            ),
+            Some(b"VP8L") => match le32(21) {
+                Some(bits) => (Some((bits & 0x3fff) + 1), Some(((bits >> 14) & 0x3fff) + 1)),
+                None => (None, None),
+            },
+            Some(b"VP8 ") => (le16(26).map(|w| w & 0x3fff), le16(28).map(|h| h & 0x3fff)),
+            _ => (None, None),
+        };
+        return Some(info("webp", width, height));
+    }
+    if payload.starts_with(&[0xff, 0xd8, 0xff]) {
+        let (width, height) = jpeg_size(payload).unzip();
+        return Some(info("jpeg", width, height));
+    }
+    None
+}
+
+/// Walk JPEG segments to the first start-of-frame marker, which holds the
+/// image size.
+fn jpeg_size(payload: &[u8]) -> Option<(u32, u32)> {
+    let be16 =
+        |at: usize| Some(u16::from_be_bytes(payload.get(at..at + 2)?.try_into().ok()?) as u32);
+    let mut at = 2;
+    while *payload.get(at)? == 0xff {
+        let marker = *payload.get(at + 1)?;
+        let len = be16(at + 2)? as usize;
+        // SOF0-SOF15, except DHT (c4), JPG (c8) and DAC (cc)
+        if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+            return Some((be16(at + 7)?, be16(at + 5)?));
+        }
+        at += 2 + len;
+    }
+    None
+}
@@ -0,0 +1,337 @@
+use crate::backend::ZenohBackend;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Liveliness key space used by rmw_zenoh.
+pub const RMW_ZENOH_LIVELINESS: &str = "@ros2_lv/**";
+
+/// Liveliness key space used by zenoh-bridge-ros2dds (prefixed by the bridge zid).
+pub const BRIDGE_LIVELINESS: &str = "@/*/@ros2_lv/**";
+
+/// Infix marking the hidden topics and services that implement an action.
+const ACTION_INFIX: &str = "/_action/";
+
+#[derive(Clone, Copy, PartialEq)]
+enum EntityKind {
+    Node,
+    Publisher,
+    Subscription,
+    ServiceServer,
+    ServiceClient,
+    ActionServer,
+    ActionClient,
+}
+
+impl EntityKind {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "NN" => Some(EntityKind::Node),
+            "MP" => Some(EntityKind::Publisher),
+            "MS" => Some(EntityKind::Subscription),
+            "SS" => Some(EntityKind::ServiceServer),
+            "SC" => Some(EntityKind::ServiceClient),
+            "AS" => Some(EntityKind::ActionServer),
+            "AC" => Some(EntityKind::ActionClient),
+            _ => None,
+        }
+    }
+}
+
+/// One ROS entity decoded from a liveliness token.
+struct Entity {
+    kind: EntityKind,
+    /// Fully qualified node name; `None` for bridge tokens, which carry no node.
+    node: Option<NodeId>,
+    /// Topic, service or action name.
+    name: Option<String>,
+    type_name: Option<String>,
+    type_hash: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct NodeId {
+    namespace: String,
+    name: String,
+    zid: String,
+    domain_id: Option<u32>,
+}
+
+impl NodeId {
+    fn fqn(&self) -> String {
+        if self.namespace == "/" {
+            format!("/{}", self.name)
+        } else {
+            format!("{}/{}", self.namespace, self.name)
+        }
+    }
+}
+
+/// rmw_zenoh mangles `/` as `%` inside liveliness segments.
+fn demangle_rmw(s: &str) -> String {
+    let s = s.replace('%', "/");
+    if s.is_empty() {
+        "/".into()
+    } else {
+        s
+    }
+}
+
+/// zenoh-bridge-ros2dds escapes `/` as `§` inside liveliness segments.
+fn demangle_bridge(s: &str) -> String {
+    s.replace('§', "/")
+}
+
+/// Parse an rmw_zenoh token:
+/// `@ros2_lv/<domain>/<zid>/<nid>/<id>/<kind>/<enclave>/<ns>/<node>[/<name>/<type>/<hash>/<qos>]`
+fn parse_rmw_token(key: &str) -> Option<Entity> {
+    let parts: Vec<&str> = key.strip_prefix("@ros2_lv/")?.split('/').collect();
+    if parts.len() < 8 {
+        return None;
+    }
+    let kind = EntityKind::parse(parts[4])?;
+    let node = NodeId {
+        namespace: demangle_rmw(parts[6]),
+        name: parts[7].to_string(),
+        zid: parts[1].to_string(),
+        domain_id: parts[0].parse().ok(),
+    };
+    Some(Entity {
+        kind,
+        node: Some(node),
+        name: parts.get(8).map(|s| demangle_rmw(s)),
+        type_name: parts.get(9).map(|s| demangle_rmw(s)),
+        type_hash: parts.get(10).map(|s| s.to_string()),
+    })
+}
+
+/// Parse a zenoh-bridge-ros2dds token: `@/<zid>/@ros2_lv/<kind>/<name>/<type>[/<qos>]`
+fn parse_bridge_token(key: &str) -> Option<Entity> {
+    let rest = key.strip_prefix("@/")?;
+    let (_zid, rest) = rest.split_once("/@ros2_lv/")?;
+    let parts: Vec<&str> = rest.split('/').collect();
+    if parts.len() < 3 {
+        return None;
+    }
+    let name = demangle_bridge(parts[1]);
+    Some(Entity {
+        kind: EntityKind::parse(parts[0])?,
+        node: None,
+        name: Some(if name.starts_with('/') {
+            name
+        } else {
+            format!("/{name}")
+        }),
+        type_name: Some(demangle_bridge(parts[2])),
+        type_hash: None,
+    })
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Endpoint {
+    name: String,
+    #[serde(rename = "type")]
+    type_name: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct NodeView {
+    fqn: String,
+    name: String,
+    namespace: String,
+    zid: String,
+    domain_id: Option<u32>,
+    publishers: Vec<Endpoint>,
+    subscriptions: Vec<Endpoint>,
+    service_servers: Vec<Endpoint>,
+    service_clients: Vec<Endpoint>,
+    action_servers: BTreeSet<String>,
+    action_clients: BTreeSet<String>,
+}
+
+#[derive(Default, Serialize, JsonSchema)]
+struct NameView {
+    types: BTreeSet<String>,
+    /// Publishers for topics, servers for services and actions.
+    providers: u32,
+    /// Subscribers for topics, clients for services and actions.
+    consumers: u32,
+}
+
+/// ROS graph assembled from liveliness tokens.
+#[derive(Serialize, JsonSchema)]
+pub struct RosGraph {
+    nodes: Vec<NodeView>,
+    topics: BTreeMap<String, NameView>,
+    services: BTreeMap<String, NameView>,
+    actions: BTreeMap<String, NameView>,
+    rmw_zenoh_tokens: usize,
+    bridge_tokens: usize,
+}
+
+/// Split an action's hidden member name into the action name.
+fn action_name(name: &str) -> Option<&str> {
+    name.split_once(ACTION_INFIX).map(|(action, _)| action)
+}
+
+/// Build a graph from liveliness token keys. `domain_id` filters rmw_zenoh
+/// tokens; bridge tokens carry no domain and are always included.
+pub fn build_graph(keys: &[String], domain_id: Option<u32>) -> RosGraph {
+    let mut nodes: BTreeMap<NodeId, NodeView> = BTreeMap::new();
+    let mut topics: BTreeMap<String, NameView> = BTreeMap::new();
+    let mut services: BTreeMap<String, NameView> = BTreeMap::new();
+    let mut actions: BTreeMap<String, NameView> = BTreeMap::new();
+    let (mut rmw_zenoh_tokens, mut bridge_tokens) = (0, 0);
+
+    for key in keys {
+        let entity = if key.starts_with("@ros2_lv/") {
+            let e = parse_rmw_token(key);
+            if e.as_ref()
+                .and_then(|e| e.node.as_ref())
+                .is_some_and(|n| domain_id.is_some_and(|d| n.domain_id != Some(d)))
+            {
+                continue;
+            }
+            rmw_zenoh_tokens += e.is_some() as usize;
+            e
+        } else {
+            let e = parse_bridge_token(key);
+            bridge_tokens += e.is_some() as usize;
+            e
+        };
+        let Some(entity) = entity else { continue };
+
+        let node = entity.node.as_ref().map(|id| {
+            nodes.entry(id.clone()).or_insert_with(|| NodeView {
+                fqn: id.fqn(),
+                name: id.name.clone(),
+                namespace: id.namespace.clone(),
+                zid: id.zid.clone(),
+                domain_id: id.domain_id,
+                publishers: Vec::new(),
+                subscriptions: Vec::new(),
+                service_servers: Vec::new(),
+                service_clients: Vec::new(),
+                action_servers: BTreeSet::new(),
+                action_clients: BTreeSet::new(),
+            })
+        });
+        let Some(name) = entity.name else { continue };
+        let endpoint = Endpoint {
+            name: name.clone(),
+            type_name: entity.type_name.clone(),
+        };
+
+        // Hidden action members roll up into their action; the goal service
+        // identifies servers and clients.
+        let action = action_name(&name).map(str::to_string);
+        let (table, provides) = match (entity.kind, &action) {
+            (EntityKind::ActionServer, _) => (&mut actions, true),
+            (EntityKind::ActionClient, _) => (&mut actions, false),
+            (EntityKind::ServiceServer, Some(_)) if name.ends_with("/send_goal") => {
+                (&mut actions, true)
+            }
+            (EntityKind::ServiceClient, Some(_)) if name.ends_with("/send_goal") => {
+                (&mut actions, false)
+            }
+            (_, Some(_)) => continue,
+            (EntityKind::Publisher, None) => (&mut topics, true),
+            (EntityKind::Subscription, None) => (&mut topics, false),
+            (EntityKind::ServiceServer, None) => (&mut services, true),
+            (EntityKind::ServiceClient, None) => (&mut services, false),
+            (EntityKind::Node, None) => continue,
+        };
+        let table_key = action.clone().unwrap_or(name);
+        let view = table.entry(table_key.clone()).or_default();
+        if let Some(t) = entity.type_name {
+            view.types.insert(t);
+        }
+        if provides {
+            view.providers += 1;
+        } else {
+            view.consumers += 1;
+        }
+
+        if let Some(node) = node {
+            match (entity.kind, action.is_some()) {
+                (EntityKind::Publisher, false) => node.publishers.push(endpoint),
+                (EntityKind::Subscription, false) => node.subscriptions.push(endpoint),
+                (EntityKind::ServiceServer, false) => node.service_servers.push(endpoint),
+                (EntityKind::ServiceClient, false) => node.service_clients.push(endpoint),
+                (EntityKind::ServiceServer | EntityKind::ActionServer, true) => {
+                    node.action_servers.insert(table_key);
+                }
+                (EntityKind::ServiceClient | EntityKind::ActionClient, true) => {
+                    node.action_clients.insert(table_key);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    RosGraph {
+        nodes: nodes.into_values().collect(),
+        topics,
+        services,
+        actions,
+        rmw_zenoh_tokens,
+        bridge_tokens,
+    }
+}
+
+/// Collect the keys of all liveliness tokens matching `key_expr`.
+pub async fn liveliness_keys(
+    session: &dyn ZenohBackend,
+    key_expr: &str,
+    timeout: Duration,
+) -> Result<Vec<String>, String> {
+    session
+        .liveliness_get(key_expr, timeout)
+        .await
+        .map_err(|e| format!("liveliness query {key_expr} failed: {e}"))
+}
+
+/// Find the rmw_zenoh key expression of a service server:
+/// `<domain>/<service name>/<type>/<type hash>`.
+pub async fn service_key_expr(
+    session: &dyn ZenohBackend,
+    domain_id: u32,
+    service: &str,
+    timeout: Duration,
+) -> Result<String, String> {
+    let service = format!("/{}", service.trim_matches('/'));
+    let keys = liveliness_keys(session, &format!("@ros2_lv/{domain_id}/**"), timeout).await?;
+    keys.iter()
+        .filter_map(|key| parse_rmw_token(key))
+        .find(|e| e.kind == EntityKind::ServiceServer && e.name.as_deref() == Some(&service))
+        .and_then(|e| {
+            Some(format!(
+                "{domain_id}/{}/{}/{}",
+                service.trim_start_matches('/'),
+                e.type_name?,
+                e.type_hash?
+            ))
+        })
+        .ok_or_else(|| format!("no server found for service {service} in domain {domain_id}"))
+}
+
+/// Client GID this process presents in service requests.
+static CLIENT_GID: LazyLock<[u8; 16]> = LazyLock::new(|| *uuid::Uuid::new_v4().as_bytes());
+
+static NEXT_SEQUENCE: AtomicI64 = AtomicI64::new(1);
+
+/// Request attachment expected by rmw_zenoh: sequence number and source
+/// timestamp as little-endian i64, then a length-prefixed 16-byte client GID.
+pub fn next_request_attachment() -> Vec<u8> {
+    let sequence_number = NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    let timestamp = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    let mut out = Vec::with_capacity(33);
+    out.extend(sequence_number.to_le_bytes());
+    out.extend(timestamp.to_le_bytes());
+    out.push(CLIENT_GID.len() as u8);
+    out.extend(*CLIENT_GID);
+    out
+}
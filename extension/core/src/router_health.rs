@@ -0,0 +1,315 @@
+use crate::backend::{GetOptions, ZenohBackend};
+use crate::storages::{self, StorageInfo};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Where a router reports its plugins, under its `@/<zid>/` admin space.
+const PLUGINS_PATH: &str = "router/plugins";
+
+/// Received and sent bytes per second, by router and peer zid.
+pub type Rates = HashMap<(String, String), (f64, f64)>;
+
+#[derive(Serialize, JsonSchema)]
+pub struct RouterHealth {
+    pub zid: String,
+    pub version: Option<String>,
+    pub locators: Vec<String>,
+    pub sessions: Vec<SessionHealth>,
+    pub plugins: Vec<PluginHealth>,
+    pub storages: Vec<StorageInfo>,
+    /// Problems worth a look: sessions without links and plugins that did
+    /// not start.
+    pub warnings: Vec<String>,
+}
+
+/// A transport the router holds to another node. Byte counters and rates
+/// cover all of its links together; they are absent when the router was
+/// built without transport stats.
+#[derive(Serialize, JsonSchema)]
+pub struct SessionHealth {
+    pub peer: String,
+    pub whatami: Option<String>,
+    /// `up` while the transport has a link, else `no_links`.
+    pub state: &'static str,
+    pub links: Vec<LinkHealth>,
+    pub rx_bytes: Option<u64>,
+    pub tx_bytes: Option<u64>,
+    pub rx_bytes_per_sec: Option<f64>,
+    pub tx_bytes_per_sec: Option<f64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct LinkHealth {
+    pub src: Option<String>,
+    pub dst: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PluginHealth {
+    pub name: String,
+    pub version: Option<String>,
+    pub path: Option<String>,
+    /// Lifecycle state the plugin reports, such as `started`.
+    pub state: Option<String>,
+}
+
+/// Health of `router`, or of every router answering `*`, and the errors
+/// routers replied with. With a non-zero `sample`, router info is read
+/// twice that far apart to turn byte counters into rates.
+pub async fn collect(
+    session: &dyn ZenohBackend,
+    router: &str,
+    sample: Duration,
+    timeout: Duration,
+) -> Result<(Vec<RouterHealth>, Vec<String>), String> {
+    let mut errors = Vec::new();
+    let started = Instant::now();
+    let infos = router_info(session, router, timeout, &mut errors).await?;
+    let mut rates = Rates::new();
+    if !sample.is_zero() {
+        tokio::time::sleep(sample.saturating_sub(started.elapsed())).await;
+        let elapsed = started.elapsed().as_secs_f64();
+        let later = router_info(session, router, timeout, &mut Vec::new()).await?;
+        for (zid, info) in &later {
+            let before = infos.get(zid).map(session_bytes).unwrap_or_default();
+            for (peer, (rx, tx)) in session_bytes(info) {
+                let Some((rx0, tx0)) = before.get(&peer) else {
+                    continue;
+                };
+                let rate = |now: u64, then: u64| now.saturating_sub(then) as f64 / elapsed;
+                rates.insert((zid.clone(), peer), (rate(rx, *rx0), rate(tx, *tx0)));
+            }
+        }
+    }
+    let mut plugins_by_router = plugin_status(session, router, timeout, &mut errors).await?;
+    let (storages, storage_errors) = storages::list(session, router, timeout).await?;
+    errors.extend(storage_errors);
+
+    let mut storages_by_router: BTreeMap<String, Vec<StorageInfo>> = BTreeMap::new();
+    for storage in storages {
+        storages_by_router
+            .entry(storage.router.clone())
+            .or_default()
+            .push(storage);
+    }
+    let routers = infos
+        .into_iter()
+        .map(|(zid, info)| {
+            let mut plugins = plugins_by_router.remove(&zid).unwrap_or_default();
+            merge_listed_plugins(&info, &mut plugins);
+            let storages = storages_by_router.remove(&zid).unwrap_or_default();
+            health(
+                zid,
+                &info,
+                plugins.into_values().collect(),
+                storages,
+                &rates,
+            )
+        })
+        .collect();
+    Ok((routers, errors))
+}
+
+/// Router info JSON from `@/<zid>/router`, by zid. `_stats` asks routers
+/// built with transport stats to include them.
+pub async fn router_info(
+    session: &dyn ZenohBackend,
+    router: &str,
+    timeout: Duration,
+    errors: &mut Vec<String>,
+) -> Result<BTreeMap<String, Value>, String> {
+    let replies = session
+        .get(
+            &format!("@/{router}/router?_stats=true"),
+            GetOptions::default(),
+            timeout,
+        )
+        .await?;
+    let mut infos = BTreeMap::new();
+    for reply in replies {
+        match reply.result {
+            Ok(sample) => {
+                let Some(zid) = router_zid(&sample.key_expr) else {
+                    continue;
+                };
+                let info = serde_json::from_slice(&sample.payload).unwrap_or(Value::Null);
+                infos.insert(zid.to_string(), info);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    Ok(infos)
+}
+
+/// Plugins by router, then id, from the status records routers serve at
+/// `@/<zid>/router/plugins/<id>`.
+async fn plugin_status(
+    session: &dyn ZenohBackend,
+    router: &str,
+    timeout: Duration,
+    errors: &mut Vec<String>,
+) -> Result<HashMap<String, BTreeMap<String, PluginHealth>>, String> {
+    let replies = session
+        .get(
+            &format!("@/{router}/{PLUGINS_PATH}/*"),
+            GetOptions::default(),
+            timeout,
+        )
+        .await?;
+    let mut plugins: HashMap<String, BTreeMap<String, PluginHealth>> = HashMap::new();
+    for reply in replies {
+        let sample = match reply.result {
+            Ok(sample) => sample,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        let Some((zid, rest)) = sample
+            .key_expr
+            .strip_prefix("@/")
+            .and_then(|k| k.split_once('/'))
+        else {
+            continue;
+        };
+        let Some(id) = rest
+            .strip_prefix(PLUGINS_PATH)
+            .and_then(|r| r.strip_prefix('/'))
+        else {
+            continue;
+        };
+        let status: Value = serde_json::from_slice(&sample.payload).unwrap_or(Value::Null);
+        plugins.entry(zid.to_string()).or_default().insert(
+            id.to_string(),
+            PluginHealth {
+                name: id.to_string(),
+                version: text(&status["version"]),
+                path: text(&status["path"]),
+                state: text(&status["state"]).map(|s| s.to_lowercase()),
+            },
+        );
+    }
+    Ok(plugins)
+}
+
+/// Add plugins the router info lists but no status record covered.
+fn merge_listed_plugins(info: &Value, plugins: &mut BTreeMap<String, PluginHealth>) {
+    let Some(listed) = info["plugins"].as_object() else {
+        return;
+    };
+    for (name, entry) in listed {
+        let plugin = plugins.entry(name.clone()).or_insert_with(|| plugin(name));
+        plugin.path = plugin.path.take().or_else(|| text(&entry["path"]));
+        plugin.version = plugin.version.take().or_else(|| text(&entry["version"]));
+    }
+}
+
+fn health(
+    zid: String,
+    info: &Value,
+    plugins: Vec<PluginHealth>,
+    storages: Vec<StorageInfo>,
+    rates: &Rates,
+) -> RouterHealth {
+    let sessions = sessions(&zid, info, rates);
+    let mut warnings: Vec<String> = sessions
+        .iter()
+        .filter(|s| s.links.is_empty())
+        .map(|s| format!("session to {} has no links", s.peer))
+        .collect();
+    warnings.extend(
+        plugins
+            .iter()
+            .filter_map(|p| Some((p, p.state.as_deref()?)))
+            .filter(|(_, state)| !state.eq_ignore_ascii_case("started"))
+            .map(|(p, state)| format!("plugin {} is {state}", p.name)),
+    );
+
+    RouterHealth {
+        version: text(&info["version"]),
+        locators: locators(info),
+        zid,
+        sessions,
+        plugins,
+        storages,
+        warnings,
+    }
+}
+
+/// Sessions listed in router `zid`'s info, with rates where sampled.
+pub fn sessions(zid: &str, info: &Value, rates: &Rates) -> Vec<SessionHealth> {
+    let bytes = session_bytes(info);
+    info["sessions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|s| {
+            let peer = s["peer"].as_str()?.to_string();
+            let links: Vec<LinkHealth> = s["links"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|l| LinkHealth {
+                    src: text(&l["src"]),
+                    dst: text(&l["dst"]),
+                })
+                .collect();
+            let counters = bytes.get(&peer);
+            let rate = rates.get(&(zid.to_string(), peer.clone()));
+            Some(SessionHealth {
+                whatami: text(&s["whatami"]),
+                state: if links.is_empty() { "no_links" } else { "up" },
+                links,
+                rx_bytes: counters.map(|c| c.0),
+                tx_bytes: counters.map(|c| c.1),
+                rx_bytes_per_sec: rate.map(|r| r.0),
+                tx_bytes_per_sec: rate.map(|r| r.1),
+                peer,
+            })
+        })
+        .collect()
+}
+
+pub fn locators(info: &Value) -> Vec<String> {
+    info["locators"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(text)
+        .collect()
+}
+
+/// Received and sent bytes per peer, for sessions reporting stats.
+fn session_bytes(info: &Value) -> HashMap<String, (u64, u64)> {
+    info["sessions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|s| {
+            let stats = &s["stats"];
+            let counters = (stats["rx_bytes"].as_u64()?, stats["tx_bytes"].as_u64()?);
+            Some((s["peer"].as_str()?.to_string(), counters))
+        })
+        .collect()
+}
+
+/// `<zid>` of `@/<zid>/router`.
+fn router_zid(key: &str) -> Option<&str> {
+    key.strip_prefix("@/")?.strip_suffix("/router")
+}
+
+fn plugin(name: &str) -> PluginHealth {
+    PluginHealth {
+        name: name.to_string(),
+        version: None,
+        path: None,
+        state: None,
+    }
+}
+
+fn text(value: &Value) -> Option<String> {
+    value.as_str().map(String::from)
+}
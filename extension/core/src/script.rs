@@ -0,0 +1,363 @@
+use crate::backend::ZenohBackend;
+use crate::namespace;
+use mlua::{HookTriggers, Lua, LuaOptions, LuaSerdeExt, StdLib, Table, Value as LuaValue};
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use zenoh::key_expr::KeyExpr;
+
+/// VM instructions one `on_sample` call may run before it is aborted.
+const INSTRUCTION_BUDGET: u64 = 10_000_000;
+/// How often the instruction budget is checked.
+const BUDGET_CHECK_EVERY: u32 = 10_000;
+/// Memory a script's Lua state may allocate.
+const MAX_MEMORY_BYTES: usize = 16 * 1024 * 1024;
+/// Samples one `on_sample` call may publish.
+const MAX_PUBLISHES_PER_SAMPLE: usize = 16;
+
+/// A sample a script published from `on_sample`.
+pub struct Derived {
+    /// Host-facing key expression, without the session namespace.
+    pub key_expr: String,
+    pub payload: Vec<u8>,
+    pub encoding: String,
+}
+
+/// A kept sample as the script left it.
+pub struct Rewritten {
+    pub payload: Vec<u8>,
+    pub encoding: String,
+    /// Whether the script replaced the payload.
+    pub replaced: bool,
+    pub tags: Option<Map<String, Value>>,
+}
+
+/// What `on_sample` replaced on a kept sample.
+#[derive(Default)]
+struct Kept {
+    payload: Option<Vec<u8>>,
+    encoding: Option<String>,
+    tags: Option<Map<String, Value>>,
+}
+
+/// The outcome of one `on_sample` call.
+pub struct Scripted {
+    /// `None` when the script dropped the sample.
+    pub sample: Option<Rewritten>,
+    /// Samples to publish once the call returns.
+    pub derived: Vec<Derived>,
+    /// Whether the call failed, leaving the sample as it was received.
+    pub failed: bool,
+}
+
+/// Calls and failures of one subscription's script.
+#[derive(Default)]
+pub struct ScriptStats {
+    pub calls: AtomicU64,
+    pub errors: AtomicU64,
+    /// Samples the script dropped.
+    pub dropped: AtomicU64,
+    /// Derived samples published.
+    pub published: AtomicU64,
+    pub last_error: Mutex<Option<String>>,
+}
+
+impl ScriptStats {
+    pub fn fail(&self, error: String) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        *self.last_error.lock().unwrap() = Some(error);
+    }
+}
+
+/// A subscription's Lua script. The script defines a global
+/// `on_sample(sample)` that receives a table with `key_expr`, `payload`,
+/// `encoding`, `timestamp_ms` and, when the payload is JSON or a plugin
+/// decoded it, `value`. It returns:
+///
+/// - `nil` or `false` to drop the sample;
+/// - `true` to keep it unchanged;
+/// - a table to keep it with any of `payload`, `encoding` and `tags`
+///   replaced. A `payload` that is not a string is encoded as JSON.
+///
+/// `publish(key_expr, payload, encoding)` derives new samples. Scripts run
+/// without `io`, `os` or file loading, within an instruction budget and a
+/// memory limit; global state persists between samples.
+pub struct Script {
+    lua: Mutex<Lua>,
+    /// Instructions used by the current call.
+    used: Arc<AtomicU64>,
+    derived: Arc<Mutex<Vec<Derived>>>,
+    pub stats: ScriptStats,
+}
+
+impl Script {
+    /// Load `source` to run on samples from `inputs`. Publishing back onto
+    /// an input is refused, since the derived samples would feed back;
+    /// publishing at all is refused when `can_publish` is false.
+    pub fn compile(
+        source: &str,
+        inputs: &[String],
+        can_publish: bool,
+    ) -> Result<Arc<Self>, String> {
+        let lua_err = |e: mlua::Error| format!("script: {e}");
+        let lua = Lua::new_with(
+            StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8,
+            LuaOptions::default(),
+        )
+        .map_err(lua_err)?;
+        lua.set_memory_limit(MAX_MEMORY_BYTES).map_err(lua_err)?;
+
+        let used = Arc::new(AtomicU64::new(0));
+        {
+            let used = used.clone();
+            lua.set_hook(
+                HookTriggers::new().every_nth_instruction(BUDGET_CHECK_EVERY),
+                move |_, _| {
+                    let spent = used.fetch_add(BUDGET_CHECK_EVERY as u64, Ordering::Relaxed);
+                    match spent >= INSTRUCTION_BUDGET {
+                        true => Err(mlua::Error::runtime("instruction budget exceeded")),
+                        false => Ok(()),
+                    }
+                },
+            );
+        }
+
+        let sources = inputs
+            .iter()
+            .map(|key_expr| {
+                KeyExpr::try_from(key_expr.clone())
+                    .map_err(|e| format!("invalid key_expr {key_expr}: {e}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let derived: Arc<Mutex<Vec<Derived>>> = Arc::default();
+        let globals = lua.globals();
+        for name in ["dofile", "loadfile"] {
+            globals.set(name, LuaValue::Nil).map_err(lua_err)?;
+        }
+        let print = lua
+            .create_function(|_, line: String| {
+                eprintln!("script: {line}");
+                Ok(())
+            })
+            .map_err(lua_err)?;
+        globals.set("print", print).map_err(lua_err)?;
+        let publish = {
+            let derived = derived.clone();
+            lua.create_function(
+                move |lua, (key_expr, payload, encoding): (String, LuaValue, Option<String>)| {
+                    if !can_publish {
+                        return Err(mlua::Error::runtime("publish: session is read-only"));
+                    }
+                    let target = KeyExpr::try_from(key_expr.clone())
+                        .map_err(|e| mlua::Error::runtime(format!("publish: {e}")))?;
+                    if sources.iter().any(|source| target.intersects(source)) {
+                        return Err(mlua::Error::runtime(format!(
+                            "publish: {key_expr} overlaps an input; it would feed back"
+                        )));
+                    }
+                    let mut derived = derived.lock().unwrap();
+                    if derived.len() >= MAX_PUBLISHES_PER_SAMPLE {
+                        return Err(mlua::Error::runtime(format!(
+                            "publish: at most {MAX_PUBLISHES_PER_SAMPLE} samples per call"
+                        )));
+                    }
+                    let (payload, json) = payload_bytes(lua, payload)?;
+                    derived.push(Derived {
+                        key_expr,
+                        payload,
+                        encoding: encoding.unwrap_or_else(|| default_encoding(json).into()),
+                    });
+                    Ok(())
+                },
+            )
+            .map_err(lua_err)?
+        };
+        globals.set("publish", publish).map_err(lua_err)?;
+
+        lua.load(source)
+            .set_name("script")
+            .exec()
+            .map_err(lua_err)?;
+        if !matches!(globals.get("on_sample"), Ok(LuaValue::Function(_))) {
+            return Err("script must define a function on_sample(sample)".into());
+        }
+        // Publishes made while loading are not tied to any sample
+        derived.lock().unwrap().clear();
+        drop(globals);
+
+        Ok(Arc::new(Self {
+            lua: Mutex::new(lua),
+            used,
+            derived,
+            stats: ScriptStats::default(),
+        }))
+    }
+
+    /// Run `on_sample` on a received sample. A failing call keeps the
+    /// sample unchanged and publishes nothing.
+    pub fn process(
+        &self,
+        key_expr: &str,
+        payload: Vec<u8>,
+        encoding: String,
+        value: Option<&Value>,
+    ) -> Scripted {
+        self.stats.calls.fetch_add(1, Ordering::Relaxed);
+        let lua = self.lua.lock().unwrap();
+        self.used.store(0, Ordering::Relaxed);
+        self.derived.lock().unwrap().clear();
+
+        let outcome = self.call(&lua, key_expr, &payload, &encoding, value);
+        let derived = std::mem::take(&mut *self.derived.lock().unwrap());
+        match outcome {
+            Ok(Some(kept)) => {
+                let replaced = kept.payload.is_some();
+                Scripted {
+                    sample: Some(Rewritten {
+                        payload: kept.payload.unwrap_or(payload),
+                        encoding: kept.encoding.unwrap_or(encoding),
+                        replaced,
+                        tags: kept.tags,
+                    }),
+                    derived,
+                    failed: false,
+                }
+            }
+            Ok(None) => {
+                self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+                Scripted {
+                    sample: None,
+                    derived,
+                    failed: false,
+                }
+            }
+            Err(e) => {
+                // Keep the message, not the Lua stack traceback
+                let e = e.to_string();
+                self.stats
+                    .fail(e.lines().next().unwrap_or_default().to_string());
+                Scripted {
+                    sample: Some(Rewritten {
+                        payload,
+                        encoding,
+                        replaced: false,
+                        tags: None,
+                    }),
+                    derived: Vec::new(),
+                    failed: true,
+                }
+            }
+        }
+    }
+
+    /// Call `on_sample`, returning `None` if it dropped the sample.
+    fn call(
+        &self,
+        lua: &Lua,
+        key_expr: &str,
+        payload: &[u8],
+        encoding: &str,
+        value: Option<&Value>,
+    ) -> mlua::Result<Option<Kept>> {
+        let sample = lua.create_table()?;
+        sample.set("key_expr", key_expr)?;
+        sample.set("payload", lua.create_string(payload)?)?;
+        sample.set("encoding", encoding)?;
+        sample.set("timestamp_ms", chrono::Utc::now().timestamp_millis())?;
+        let parsed;
+        let value = match value {
+            Some(value) => Some(value),
+            None => {
+                parsed = serde_json::from_slice::<Value>(payload).ok();
+                parsed.as_ref()
+            }
+        };
+        if let Some(value) = value {
+            sample.set("value", lua.to_value(value)?)?;
+        }
+
+        let on_sample: mlua::Function = lua.globals().get("on_sample")?;
+        let returned = match on_sample.call::<_, LuaValue>(sample)? {
+            LuaValue::Nil | LuaValue::Boolean(false) => return Ok(None),
+            LuaValue::Boolean(true) => return Ok(Some(Kept::default())),
+            LuaValue::Table(table) => table,
+            other => {
+                return Err(mlua::Error::runtime(format!(
+                    "on_sample returned a {}; expected nil, a boolean or a table",
+                    other.type_name()
+                )))
+            }
+        };
+        let (replacement, json) = match returned.get::<_, LuaValue>("payload")? {
+            LuaValue::Nil => (None, false),
+            payload => {
+                let (bytes, json) = payload_bytes(lua, payload)?;
+                (Some(bytes), json)
+            }
+        };
+        let encoding = match returned.get::<_, Option<String>>("encoding")? {
+            Some(encoding) => Some(encoding),
+            None if json => Some(default_encoding(true).into()),
+            None => None,
+        };
+        Ok(Some(Kept {
+            payload: replacement,
+            encoding,
+            tags: tags(lua, &returned)?,
+        }))
+    }
+}
+
+/// Put the samples a script derived, counting failures against the script.
+pub async fn publish_derived(
+    session: &dyn ZenohBackend,
+    namespace: Option<&str>,
+    script: &Script,
+    derived: Vec<Derived>,
+) {
+    for out in derived {
+        let scoped = namespace::scope(namespace, &out.key_expr);
+        match session.put(&scoped, out.payload, &out.encoding).await {
+            Ok(()) => {
+                script.stats.published.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => script
+                .stats
+                .fail(format!("publish to {} failed: {e}", out.key_expr)),
+        }
+    }
+}
+
+/// The `tags` field of a returned table, which must be a table of values.
+fn tags(lua: &Lua, returned: &Table) -> mlua::Result<Option<Map<String, Value>>> {
+    match returned.get::<_, LuaValue>("tags")? {
+        LuaValue::Nil => Ok(None),
+        tags @ LuaValue::Table(_) => match lua.from_value::<Value>(tags)? {
+            Value::Object(map) => Ok(Some(map)),
+            // An empty Lua table converts to an array
+            Value::Array(items) if items.is_empty() => Ok(None),
+            _ => Err(mlua::Error::runtime("tags must be a table keyed by name")),
+        },
+        _ => Err(mlua::Error::runtime("tags must be a table keyed by name")),
+    }
+}
+
+/// Bytes for a payload given to Lua: strings as they are, anything else as
+/// JSON. The flag is set when the payload was encoded as JSON.
+fn payload_bytes(lua: &Lua, payload: LuaValue) -> mlua::Result<(Vec<u8>, bool)> {
+    match payload {
+        LuaValue::String(s) => Ok((s.as_bytes().to_vec(), false)),
+        other => {
+            let json: Value = lua.from_value(other)?;
+            let bytes = serde_json::to_vec(&json).map_err(mlua::Error::external)?;
+            Ok((bytes, true))
+        }
+    }
+}
+
+fn default_encoding(json: bool) -> &'static str {
+    match json {
+        true => "application/json",
+        false => "text/plain",
+    }
+}
@@ -0,0 +1,74 @@
+use crate::jsonpath;
+use serde::Serialize;
+
+/// Where a publisher-embedded sequence number lives in the payload.
+#[derive(Clone)]
+pub enum SeqSource {
+    /// JSONPath into a JSON payload, e.g. `header.seq` or `$.header.seq`.
+    JsonPath(String),
+    /// Unsigned 32-bit integer at a byte offset into a CDR body (after the
+    /// 4-byte encapsulation header, whose flag selects the endianness).
+    Cdr(usize),
+}
+
+impl SeqSource {
+    /// Parse a `seq_path` spec: `cdr:<offset>` or a JSONPath.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        match spec.strip_prefix("cdr:") {
+            Some(offset) => offset
+                .parse()
+                .map(SeqSource::Cdr)
+                .map_err(|_| format!("invalid CDR offset in seq_path: {spec}")),
+            None if spec.is_empty() => Err("seq_path must not be empty".into()),
+            None => Ok(SeqSource::JsonPath(spec.to_string())),
+        }
+    }
+
+    pub fn spec(&self) -> String {
+        match self {
+            SeqSource::JsonPath(path) => path.clone(),
+            SeqSource::Cdr(offset) => format!("cdr:{offset}"),
+        }
+    }
+
+    pub fn extract(&self, payload: &[u8]) -> Option<u64> {
+        match self {
+            SeqSource::JsonPath(path) => {
+                let value: serde_json::Value = serde_json::from_slice(payload).ok()?;
+                jsonpath::lookup(&value, path)?.as_u64()
+            }
+            SeqSource::Cdr(offset) => {
+                // Encapsulation header: [0x00, kind, options, options];
+                // odd kinds are little-endian.
+                let little_endian = payload.get(1)? & 0x01 == 1;
+                let start = 4 + offset;
+                let bytes: [u8; 4] = payload.get(start..start + 4)?.try_into().ok()?;
+                Some(if little_endian {
+                    u32::from_le_bytes(bytes)
+                } else {
+                    u32::from_be_bytes(bytes)
+                } as u64)
+            }
+        }
+    }
+}
+
+/// Gap tracking state for one key's sequence numbers.
+#[derive(Clone, Default, Serialize)]
+pub struct SeqGap {
+    pub last_seq: Option<u64>,
+    pub missed_estimate: u64,
+}
+
+impl SeqGap {
+    /// Record an observed sequence number. Forward jumps count as missed
+    /// samples; a backwards jump is treated as a publisher restart.
+    pub fn observe(&mut self, seq: u64) {
+        if let Some(last) = self.last_seq {
+            if seq > last + 1 {
+                self.missed_estimate += seq - last - 1;
+            }
+        }
+        self.last_seq = Some(seq);
+    }
+}
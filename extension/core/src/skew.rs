@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Offsets kept per clock for the estimate.
+const WINDOW: usize = 200;
+/// Offset beyond which a clock is flagged, unless the caller overrides it.
+pub const DEFAULT_THRESHOLD_MS: f64 = 100.0;
+
+/// Receive time minus HLC time for recent samples stamped by one clock.
+/// Each offset is the clock error plus transit latency; latency is never
+/// negative, so the smallest offset in the window is the best estimate of
+/// the error alone.
+#[derive(Clone, Default)]
+pub struct ClockSkew {
+    samples: u64,
+    /// `(received, offset_ms)`, oldest first.
+    window: VecDeque<(DateTime<Utc>, f64)>,
+}
+
+/// Estimated error of a publisher's clock relative to ours.
+#[derive(Serialize, JsonSchema)]
+pub struct ClockSkewSummary {
+    /// Zenoh id of the HLC that stamped the samples.
+    pub clock: String,
+    /// Positive when the remote clock is behind ours.
+    pub offset_ms: f64,
+    /// Change in offset per minute over the window, once it spans a second.
+    pub drift_ms_per_min: Option<f64>,
+    pub samples: u64,
+    /// `|offset_ms|` exceeds the threshold.
+    pub skewed: bool,
+}
+
+impl ClockSkew {
+    pub fn observe(&mut self, stamped: DateTime<Utc>, received: DateTime<Utc>) {
+        let offset_us = (received - stamped).num_microseconds().unwrap_or(i64::MAX);
+        if self.window.len() >= WINDOW {
+            self.window.pop_front();
+        }
+        self.window.push_back((received, offset_us as f64 / 1000.0));
+        self.samples += 1;
+    }
+
+    pub fn summary(&self, clock: &str, threshold_ms: f64) -> Option<ClockSkewSummary> {
+        let offset_ms = self
+            .window
+            .iter()
+            .map(|&(_, offset)| offset)
+            .min_by(f64::total_cmp)?;
+        Some(ClockSkewSummary {
+            clock: clock.to_string(),
+            offset_ms: (offset_ms * 100.0).round() / 100.0,
+            drift_ms_per_min: self.drift_ms_per_min(),
+            samples: self.samples,
+            skewed: offset_ms.abs() > threshold_ms,
+        })
+    }
+
+    /// Least-squares slope of offset over receive time.
+    fn drift_ms_per_min(&self) -> Option<f64> {
+        let (first, _) = *self.window.front()?;
+        let (last, _) = *self.window.back()?;
+        if (last - first).num_milliseconds() < 1000 {
+            return None;
+        }
+        let points: Vec<(f64, f64)> = self
+            .window
+            .iter()
+            .map(|&(at, offset)| ((at - first).num_milliseconds() as f64 / 60_000.0, offset))
+            .collect();
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let (mut cov, mut var) = (0.0, 0.0);
+        for (x, y) in &points {
+            cov += (x - mean_x) * (y - mean_y);
+            var += (x - mean_x) * (x - mean_x);
+        }
+        (var > 0.0).then(|| (cov / var * 100.0).round() / 100.0)
+    }
+}
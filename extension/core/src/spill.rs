@@ -0,0 +1,247 @@
+use crate::diff::FieldChange;
+use crate::state::{BufferedSample, Gap, Payload};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+/// Sample fields written ahead of the raw payload in each spill frame.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    seq: u64,
+    key_expr: String,
+    encoding: String,
+    timestamp: DateTime<Utc>,
+    arrived_us: u64,
+    source_timestamp: Option<DateTime<Utc>>,
+    checksum: Option<String>,
+    decoded: Option<serde_json::Value>,
+    decode_error: Option<String>,
+    tags: Option<serde_json::Map<String, serde_json::Value>>,
+    shm: bool,
+    gap: Option<Gap>,
+    diff: Option<Vec<FieldChange>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SpillStats {
+    pub max_bytes: u64,
+    /// Samples waiting on disk, behind the in-memory buffer.
+    pub samples: usize,
+    pub bytes: u64,
+    /// Samples written to disk since the subscription started.
+    pub spilled_total: u64,
+    /// Samples lost because the spill file could not be read back.
+    pub lost: u64,
+}
+
+/// Overflow of a subscription buffer, kept in a temp file as a FIFO behind
+/// the in-memory samples. Frames are a little-endian `u32` length and the
+/// JSON record, then a `u32` length and the payload. The file is truncated
+/// whenever it empties, and compacted once the consumed prefix outgrows the
+/// quota, so it stays within twice `max_bytes`.
+pub struct Spill {
+    path: PathBuf,
+    file: File,
+    /// Start of the oldest unread frame.
+    read_at: u64,
+    /// End of the newest frame.
+    written: u64,
+    samples: usize,
+    max_bytes: u64,
+    spilled_total: u64,
+    lost: u64,
+    /// Marker for samples dropped once the quota filled, written after the
+    /// frames already on disk so it keeps its place in the stream.
+    pub gap: Option<BufferedSample>,
+}
+
+impl Spill {
+    pub fn new(max_bytes: u64) -> Result<Self, String> {
+        let path = std::env::temp_dir().join(format!(
+            "nexus-zenoh-spill-{}.bin",
+            uuid::Uuid::new_v4().simple()
+        ));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| format!("failed to create spill file {}: {e}", path.display()))?;
+        Ok(Self {
+            path,
+            file,
+            read_at: 0,
+            written: 0,
+            samples: 0,
+            max_bytes,
+            spilled_total: 0,
+            lost: 0,
+            gap: None,
+        })
+    }
+
+    /// Nothing on disk and no pending gap marker.
+    pub fn is_empty(&self) -> bool {
+        self.samples == 0 && self.gap.is_none()
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Bytes of unread frames.
+    pub fn bytes(&self) -> u64 {
+        self.written - self.read_at
+    }
+
+    pub fn has_room(&self) -> bool {
+        self.bytes() < self.max_bytes
+    }
+
+    /// Append `sample`, after the pending gap marker if there is one. False
+    /// if the frames would exceed the quota or the write failed.
+    pub fn push(&mut self, sample: &BufferedSample) -> bool {
+        let mut frames = Vec::new();
+        if let Some(marker) = &self.gap {
+            frames.extend(frame(marker));
+        }
+        frames.extend(frame(sample));
+        if self.bytes() + frames.len() as u64 > self.max_bytes {
+            return false;
+        }
+        let written = self
+            .file
+            .seek(SeekFrom::Start(self.written))
+            .and_then(|_| self.file.write_all(&frames));
+        if written.is_err() {
+            return false;
+        }
+        self.written += frames.len() as u64;
+        self.samples += 1 + usize::from(self.gap.take().is_some());
+        self.spilled_total += 1;
+        true
+    }
+
+    /// Read back the oldest frame. A frame that cannot be read discards the
+    /// whole file, counting its samples as lost.
+    pub fn pop(&mut self) -> Option<BufferedSample> {
+        if self.samples == 0 {
+            return None;
+        }
+        match self.read_frame() {
+            Ok((sample, len)) => {
+                self.read_at += len;
+                self.samples -= 1;
+                if let Err(e) = self.reclaim() {
+                    eprintln!("spill: failed to reclaim {}: {e}", self.path.display());
+                }
+                Some(sample)
+            }
+            Err(e) => {
+                eprintln!("spill: {} unreadable: {e}", self.path.display());
+                self.lost += self.samples as u64;
+                self.samples = 0;
+                self.read_at = self.written;
+                let _ = self.reclaim();
+                None
+            }
+        }
+    }
+
+    pub fn stats(&self) -> SpillStats {
+        SpillStats {
+            max_bytes: self.max_bytes,
+            samples: self.samples,
+            bytes: self.bytes(),
+            spilled_total: self.spilled_total,
+            lost: self.lost,
+        }
+    }
+
+    fn read_frame(&mut self) -> io::Result<(BufferedSample, u64)> {
+        self.file.seek(SeekFrom::Start(self.read_at))?;
+        let record = read_chunk(&mut self.file)?;
+        let payload = read_chunk(&mut self.file)?;
+        let len = 8 + (record.len() + payload.len()) as u64;
+        let record: Record = serde_json::from_slice(&record)?;
+        let sample = BufferedSample {
+            seq: record.seq,
+            key_expr: record.key_expr,
+            payload: Payload(payload),
+            encoding: record.encoding,
+            timestamp: record.timestamp,
+            arrived_us: record.arrived_us,
+            source_timestamp: record.source_timestamp,
+            checksum: record.checksum,
+            decoded: record.decoded,
+            decode_error: record.decode_error,
+            tags: record.tags,
+            shm: record.shm,
+            gap: record.gap,
+            diff: record.diff,
+            historical: false,
+        };
+        Ok((sample, len))
+    }
+
+    /// Truncate an emptied file, or move the unread frames to the start once
+    /// the consumed prefix outgrows the quota.
+    fn reclaim(&mut self) -> io::Result<()> {
+        if self.samples == 0 {
+            self.file.set_len(0)?;
+            (self.read_at, self.written) = (0, 0);
+        } else if self.read_at > self.max_bytes {
+            let mut unread = Vec::with_capacity(self.bytes() as usize);
+            self.file.seek(SeekFrom::Start(self.read_at))?;
+            self.file.read_to_end(&mut unread)?;
+            self.file.seek(SeekFrom::Start(0))?;
+            self.file.write_all(&unread)?;
+            self.file.set_len(unread.len() as u64)?;
+            (self.read_at, self.written) = (0, unread.len() as u64);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn frame(sample: &BufferedSample) -> Vec<u8> {
+    let record = serde_json::to_vec(&Record {
+        seq: sample.seq,
+        key_expr: sample.key_expr.clone(),
+        encoding: sample.encoding.clone(),
+        timestamp: sample.timestamp,
+        arrived_us: sample.arrived_us,
+        source_timestamp: sample.source_timestamp,
+        checksum: sample.checksum.clone(),
+        decoded: sample.decoded.clone(),
+        decode_error: sample.decode_error.clone(),
+        tags: sample.tags.clone(),
+        shm: sample.shm,
+        gap: sample.gap.clone(),
+        diff: sample.diff.clone(),
+    })
+    .unwrap();
+    let payload = &sample.payload.0;
+    let mut frame = Vec::with_capacity(8 + record.len() + payload.len());
+    frame.extend((record.len() as u32).to_le_bytes());
+    frame.extend(&record);
+    frame.extend((payload.len() as u32).to_le_bytes());
+    frame.extend(payload);
+    frame
+}
+
+fn read_chunk(file: &mut File) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    file.read_exact(&mut len)?;
+    let mut chunk = vec![0u8; u32::from_le_bytes(len) as usize];
+    file.read_exact(&mut chunk)?;
+    Ok(chunk)
+}
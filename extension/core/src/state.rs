@@ -0,0 +1,1099 @@
+use crate::admin::AdminQueryable;
+use crate::alerts::AlertState;
+use crate::api::SubscribeParams;
+use crate::bridge::Bridge;
+use crate::clock;
+use crate::compress::Compression;
+use crate::derived::DerivedTopic;
+use crate::diff::{self, FieldChange};
+use crate::discovery::KeyFilter;
+use crate::echo::Echo;
+use crate::expr;
+use crate::federation;
+use crate::flight::FlightRecorder;
+use crate::generator::Generator;
+use crate::history::History;
+use crate::key_watch::{self, KeyWatch};
+use crate::mirror::Mirror;
+use crate::plugin::Plugin;
+use crate::preview::Preview;
+use crate::profile::Profile;
+use crate::query::QueryStream;
+use crate::recordings::{RecordingReader, RetentionPolicy};
+use crate::relay::ThrottledRelay;
+use crate::seq::SeqGap;
+use crate::skew::ClockSkew;
+use crate::spill::Spill;
+use crate::state_file::StateFile;
+use crate::subscriber::IngestConfig;
+use crate::synchronizer::Synchronizer;
+use crate::table::Table;
+use crate::tags::Tags;
+use crate::threshold::ThresholdWatch;
+use crate::topology::TopologyWatch;
+use crate::trace;
+use crate::watchdog::TaskHealth;
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Notify, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+
+/// Number of recent arrival timestamps kept per topic.
+const RECENT_TIMESTAMPS: usize = 20;
+
+/// Metadata tracked per discovered key expression (no payload buffering).
+#[derive(Clone, Serialize)]
+pub struct TopicMeta {
+    pub key_expr: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub sample_count: u64,
+    pub total_payload_bytes: u64,
+    pub last_encoding: String,
+    pub seq: SeqGap,
+    pub recent_timestamps: VecDeque<DateTime<Utc>>,
+    /// Monotonic arrival times matching `recent_timestamps`.
+    #[serde(skip)]
+    pub recent_arrivals_us: VecDeque<u64>,
+    /// Monotonic times of the first and latest sample, for the rate.
+    #[serde(skip)]
+    pub first_seen_us: u64,
+    #[serde(skip)]
+    pub last_seen_us: u64,
+    /// Sample counts per encoding seen on this key.
+    pub encodings: HashMap<String, u64>,
+    /// Sample counts per publishing zid, where the sample identifies its source.
+    pub publishers: HashMap<String, u64>,
+    /// Payload bytes per publishing zid.
+    pub publisher_bytes: HashMap<String, u64>,
+    #[serde(skip)]
+    pub history: History,
+    /// Leading bytes of the newest payload and its full length, kept when
+    /// discovery was started with `preview_bytes`.
+    #[serde(skip)]
+    pub last_payload_head: Option<(Vec<u8>, u64)>,
+}
+
+/// Start of a topic's newest payload, for eyeballing its content.
+#[derive(Serialize, JsonSchema)]
+pub struct PayloadPreview {
+    /// The head as text, when it is UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_str: Option<String>,
+    /// The head in base64, for binary payloads.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_b64: Option<String>,
+    /// Size of the whole payload.
+    pub bytes: u64,
+    pub truncated: bool,
+}
+
+impl PayloadPreview {
+    pub fn new(head: &[u8], bytes: u64) -> Self {
+        // A multi-byte character cut by the cap still counts as text
+        let text = match std::str::from_utf8(head) {
+            Ok(text) => Some(text),
+            Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok(),
+            Err(_) => None,
+        };
+        Self {
+            payload_str: text.map(str::to_string),
+            payload_b64: match text {
+                Some(_) => None,
+                None => Some(base64::engine::general_purpose::STANDARD.encode(head)),
+            },
+            bytes,
+            truncated: (head.len() as u64) < bytes,
+        }
+    }
+}
+
+impl TopicMeta {
+    pub fn new(
+        key_expr: String,
+        encoding: String,
+        payload_len: u64,
+        publisher: Option<String>,
+    ) -> Self {
+        let now = Utc::now();
+        let now_us = clock::now_us();
+        let mut meta = Self {
+            key_expr,
+            first_seen: now,
+            last_seen: now,
+            sample_count: 0,
+            total_payload_bytes: 0,
+            last_encoding: String::new(),
+            seq: SeqGap::default(),
+            recent_timestamps: VecDeque::with_capacity(RECENT_TIMESTAMPS),
+            recent_arrivals_us: VecDeque::with_capacity(RECENT_TIMESTAMPS),
+            first_seen_us: now_us,
+            last_seen_us: now_us,
+            encodings: HashMap::new(),
+            publishers: HashMap::new(),
+            publisher_bytes: HashMap::new(),
+            history: History::default(),
+            last_payload_head: None,
+        };
+        meta.update(encoding, payload_len, publisher);
+        meta
+    }
+
+    pub fn update(&mut self, encoding: String, payload_len: u64, publisher: Option<String>) {
+        self.last_seen = Utc::now();
+        self.last_seen_us = clock::now_us();
+        self.sample_count += 1;
+        self.total_payload_bytes += payload_len;
+        if self.recent_timestamps.len() >= RECENT_TIMESTAMPS {
+            self.recent_timestamps.pop_front();
+            self.recent_arrivals_us.pop_front();
+        }
+        self.recent_timestamps.push_back(self.last_seen);
+        self.recent_arrivals_us.push_back(self.last_seen_us);
+        self.history.record(self.last_seen, payload_len);
+        *self.encodings.entry(encoding.clone()).or_default() += 1;
+        if let Some(zid) = publisher {
+            *self.publisher_bytes.entry(zid.clone()).or_default() += payload_len;
+            *self.publishers.entry(zid).or_default() += 1;
+        }
+        self.last_encoding = encoding;
+    }
+
+    pub fn rate_hz(&self) -> f64 {
+        let elapsed = (self.last_seen_us - self.first_seen_us) as f64 / 1_000_000.0;
+        if elapsed < 0.001 {
+            0.0
+        } else {
+            self.sample_count as f64 / elapsed
+        }
+    }
+
+    /// Encodings seen on this key, most frequent first, with their share of samples.
+    pub fn encoding_histogram(&self) -> Vec<EncodingCount> {
+        encoding_histogram(&self.encodings)
+    }
+
+    /// Microseconds between consecutive recent arrivals.
+    pub fn recent_intervals_us(&self) -> Vec<u64> {
+        let arrivals = &self.recent_arrivals_us;
+        arrivals
+            .iter()
+            .zip(arrivals.iter().skip(1))
+            .map(|(a, b)| b - a)
+            .collect()
+    }
+
+    /// More than one encoding on a key usually means a misconfigured publisher.
+    pub fn mixed_encoding(&self) -> bool {
+        self.encodings.len() > 1
+    }
+
+    #[allow(clippy::manual_checked_ops)]
+    pub fn avg_payload_size(&self) -> u64 {
+        if self.sample_count == 0 {
+            0
+        } else {
+            self.total_payload_bytes / self.sample_count
+        }
+    }
+}
+
+/// Sample counts per encoding, most frequent first, with their share of the total.
+pub fn encoding_histogram(counts: &HashMap<String, u64>) -> Vec<EncodingCount> {
+    let total: u64 = counts.values().sum();
+    let mut hist: Vec<EncodingCount> = counts
+        .iter()
+        .map(|(encoding, &count)| EncodingCount {
+            encoding: encoding.clone(),
+            count,
+            fraction: (count as f64 / total.max(1) as f64 * 1000.0).round() / 1000.0,
+        })
+        .collect();
+    hist.sort_by(|a, b| b.count.cmp(&a.count).then(a.encoding.cmp(&b.encoding)));
+    hist
+}
+
+/// One bucket of a topic's encoding histogram.
+#[derive(Serialize, JsonSchema)]
+pub struct EncodingCount {
+    pub encoding: String,
+    pub count: u64,
+    pub fraction: f64,
+}
+
+/// Raw sample payload. Serializes as `payload_b64`, plus `payload_str` when
+/// the bytes are valid UTF-8; both are computed only when a sample is emitted.
+#[derive(Clone)]
+pub struct Payload(pub Vec<u8>);
+
+impl Payload {
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.0).ok()
+    }
+}
+
+impl Serialize for Payload {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use base64::Engine as _;
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry(
+            "payload_b64",
+            &base64::engine::general_purpose::STANDARD.encode(&self.0),
+        )?;
+        if let Some(s) = self.as_str() {
+            map.serialize_entry("payload_str", s)?;
+        }
+        map.end()
+    }
+}
+
+impl JsonSchema for Payload {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Payload".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "object",
+            "properties": {
+                "payload_b64": { "type": "string", "contentEncoding": "base64" },
+                "payload_str": {
+                    "type": "string",
+                    "description": "Present when the payload is valid UTF-8."
+                }
+            },
+            "required": ["payload_b64"]
+        })
+    }
+}
+
+/// A single buffered sample from a subscription.
+#[derive(Clone, Serialize)]
+pub struct BufferedSample {
+    /// Per-subscription sequence id, assigned in buffer order starting at 0.
+    pub seq: u64,
+    pub key_expr: String,
+    #[serde(flatten)]
+    pub payload: Payload,
+    pub encoding: String,
+    pub timestamp: DateTime<Utc>,
+    /// Arrival on the monotonic clock, for offsets between samples.
+    #[serde(skip)]
+    pub arrived_us: u64,
+    /// HLC time the publisher's router stamped on the sample, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_timestamp: Option<DateTime<Utc>>,
+    /// `<algorithm>:<hex>` content hash of the payload.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decode_error: Option<String>,
+    /// Labels a subscription script attached to the sample.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Delivered through Zenoh shared memory; reported only when set.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub shm: bool,
+    /// Set on a gap marker: an entry with no payload standing in for samples
+    /// the buffer dropped on overflow.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap: Option<Gap>,
+    /// Changes from the previous sample on the key, for subscriptions with
+    /// `diff`; absent on a key's first sample.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<Vec<FieldChange>>,
+    /// Fetched from a storage when the subscription was backfilled, rather
+    /// than received live; reported only when set.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub historical: bool,
+}
+
+/// Samples dropped from a subscription buffer at one point in its stream.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Gap {
+    pub dropped: u64,
+    pub first_seq: u64,
+    pub last_seq: u64,
+    /// Arrival times of the first and last dropped samples.
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl Gap {
+    fn add(&mut self, sample: &BufferedSample) {
+        self.dropped += 1;
+        self.first_seq = self.first_seq.min(sample.seq);
+        self.last_seq = self.last_seq.max(sample.seq);
+        self.from = self.from.min(sample.timestamp);
+        self.to = self.to.max(sample.timestamp);
+    }
+}
+
+impl BufferedSample {
+    /// A gap marker for the dropped `sample`, on the subscription's key.
+    fn gap_marker(key_expr: &str, sample: &BufferedSample) -> Self {
+        Self {
+            seq: sample.seq,
+            key_expr: key_expr.to_string(),
+            payload: Payload(Vec::new()),
+            encoding: String::new(),
+            timestamp: sample.timestamp,
+            arrived_us: sample.arrived_us,
+            source_timestamp: None,
+            checksum: None,
+            decoded: None,
+            decode_error: None,
+            tags: None,
+            shm: false,
+            gap: Some(Gap {
+                dropped: 1,
+                first_seq: sample.seq,
+                last_seq: sample.seq,
+                from: sample.timestamp,
+                to: sample.timestamp,
+            }),
+            diff: None,
+            historical: false,
+        }
+    }
+
+    /// Variables for evaluating an [`Expression`](crate::expr::Expression)
+    /// against this sample; `value` is its JSON, else its text.
+    pub fn expr_vars(&self) -> serde_json::Value {
+        self.expr_vars_of(self.json())
+    }
+
+    /// [`Self::expr_vars`] with the sample's [`Self::json`] already parsed.
+    pub fn expr_vars_of(&self, json: Option<serde_json::Value>) -> serde_json::Value {
+        let value = json.unwrap_or_else(|| {
+            self.payload
+                .as_str()
+                .map_or(serde_json::Value::Null, |s| s.into())
+        });
+        expr::sample_vars(
+            &self.key_expr,
+            &self.encoding,
+            self.payload.0.len(),
+            self.timestamp.timestamp_millis(),
+            value,
+        )
+    }
+
+    pub fn is_gap(&self) -> bool {
+        self.gap.is_some()
+    }
+
+    /// The decoded value when decoding is enabled, else the payload parsed
+    /// as JSON text.
+    pub fn json(&self) -> Option<serde_json::Value> {
+        match &self.decoded {
+            Some(decoded) => Some(decoded.clone()),
+            None => self
+                .payload
+                .as_str()
+                .and_then(|s| serde_json::from_str(s).ok()),
+        }
+    }
+}
+
+/// Number of recent inter-arrival intervals kept for percentile stats.
+const INTER_ARRIVAL_WINDOW: usize = 1000;
+
+/// Inter-arrival percentiles over the recent window, in milliseconds.
+#[derive(Serialize, JsonSchema)]
+pub struct InterArrivalStats {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Rolling window of gaps between consecutive sample arrivals.
+pub struct InterArrival {
+    pub last_arrival: Option<Instant>,
+    intervals_ms: VecDeque<f64>,
+}
+
+impl Default for InterArrival {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InterArrival {
+    pub fn new() -> Self {
+        Self {
+            last_arrival: None,
+            intervals_ms: VecDeque::with_capacity(INTER_ARRIVAL_WINDOW),
+        }
+    }
+
+    pub fn record(&mut self, now: Instant) {
+        if let Some(last) = self.last_arrival {
+            if self.intervals_ms.len() >= INTER_ARRIVAL_WINDOW {
+                self.intervals_ms.pop_front();
+            }
+            self.intervals_ms
+                .push_back((now - last).as_micros() as f64 / 1000.0);
+        }
+        self.last_arrival = Some(now);
+    }
+
+    /// Percentiles over the window to the microsecond, or `None` until two
+    /// samples have arrived.
+    pub fn stats(&self) -> Option<InterArrivalStats> {
+        if self.intervals_ms.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = self.intervals_ms.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let pick = |q: f64| {
+            let idx = ((sorted.len() - 1) as f64 * q).round() as usize;
+            (sorted[idx] * 1000.0).round() / 1000.0
+        };
+        Some(InterArrivalStats {
+            p50_ms: pick(0.5),
+            p95_ms: pick(0.95),
+            max_ms: pick(1.0),
+        })
+    }
+}
+
+/// What a full subscription buffer does with a new sample.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum DropPolicy {
+    /// Evict the oldest buffered sample to make room.
+    #[default]
+    Oldest,
+    /// Discard the incoming sample, keeping what is buffered.
+    Newest,
+    /// Hold ingest until a reader makes room, up to a timeout, then evict the
+    /// oldest.
+    Block(Duration),
+}
+
+impl DropPolicy {
+    /// `block_timeout` applies to `block` only.
+    pub fn parse(s: &str, block_timeout: Duration) -> Result<Self, String> {
+        match s {
+            "oldest" => Ok(DropPolicy::Oldest),
+            "newest" => Ok(DropPolicy::Newest),
+            "block" => Ok(DropPolicy::Block(block_timeout)),
+            other => Err(format!("unsupported drop policy: {other}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DropPolicy::Oldest => "oldest",
+            DropPolicy::Newest => "newest",
+            DropPolicy::Block(_) => "block",
+        }
+    }
+}
+
+/// An active subscription with a bounded ring buffer.
+pub struct Subscription {
+    pub key_expr: String,
+    pub buffer: VecDeque<BufferedSample>,
+    pub buffer_capacity: usize,
+    pub drop_policy: DropPolicy,
+    /// Samples dropped because the buffer was full.
+    pub overflow_count: u64,
+    /// Times a `block` policy gave up waiting and evicted the oldest sample.
+    pub block_timeouts: u64,
+    /// Record overflow drops as gap markers in the buffer.
+    pub gap_markers: bool,
+    /// Gap markers currently buffered; they do not count toward capacity.
+    markers: usize,
+    /// Signalled whenever samples leave the buffer, waking blocked ingest.
+    pub space: Arc<Notify>,
+    /// Samples are evicted once older than this, even if the buffer has room.
+    pub max_age_ms: Option<u64>,
+    /// Samples evicted for exceeding `max_age_ms`.
+    pub expired_count: u64,
+    /// Hold samples this long and buffer them in source timestamp order.
+    pub reorder_window_ms: Option<u64>,
+    /// Samples whose source timestamp was older than an earlier sample's on
+    /// the same key, counted on arrival whether or not reordering fixed them.
+    pub out_of_order_count: u64,
+    /// Newest source timestamp seen per key.
+    last_source_ts: HashMap<String, DateTime<Utc>>,
+    /// Samples waiting out the reorder window, in arrival order.
+    pub held: Vec<BufferedSample>,
+    /// The `seq` given to the next buffered sample.
+    next_seq: u64,
+    pub total_received: u64,
+    /// Received samples and bytes over the last hour.
+    pub history: History,
+    pub created_at: DateTime<Utc>,
+    pub cancel: watch::Sender<bool>,
+    pub ingest: IngestConfig,
+    pub seq_gaps: HashMap<String, SeqGap>,
+    pub inter_arrival: InterArrival,
+    /// Instant the subscription was created, the stall reference before any sample arrives.
+    pub started: Instant,
+    pub alert_if_silent_ms: Option<u64>,
+    pub stalled: bool,
+    /// Heartbeat and restarts of the receiving task.
+    pub health: Arc<TaskHealth>,
+    /// The `subscribe` parameters it was created with.
+    pub definition: SubscribeParams,
+    /// Temp file taking overflow instead of the drop policy, up to its quota.
+    pub spill: Option<Spill>,
+    /// Last value per key, kept when samples carry a diff against it.
+    pub diff_last: Option<HashMap<String, serde_json::Value>>,
+}
+
+impl Subscription {
+    pub fn new(
+        key_expr: String,
+        buffer_capacity: usize,
+        cancel: watch::Sender<bool>,
+        ingest: IngestConfig,
+        alert_if_silent_ms: Option<u64>,
+        max_age_ms: Option<u64>,
+        definition: SubscribeParams,
+    ) -> Self {
+        Self {
+            key_expr,
+            buffer: VecDeque::with_capacity(buffer_capacity),
+            buffer_capacity,
+            drop_policy: DropPolicy::default(),
+            overflow_count: 0,
+            block_timeouts: 0,
+            gap_markers: true,
+            markers: 0,
+            space: Arc::default(),
+            max_age_ms,
+            expired_count: 0,
+            reorder_window_ms: None,
+            out_of_order_count: 0,
+            last_source_ts: HashMap::new(),
+            held: Vec::new(),
+            next_seq: 0,
+            total_received: 0,
+            history: History::default(),
+            created_at: Utc::now(),
+            cancel,
+            ingest,
+            seq_gaps: HashMap::new(),
+            inter_arrival: InterArrival::new(),
+            started: Instant::now(),
+            alert_if_silent_ms,
+            stalled: false,
+            health: Arc::default(),
+            definition,
+            spill: None,
+            diff_last: None,
+        }
+    }
+
+    /// Milliseconds since the last sample (or since creation if none arrived).
+    pub fn silent_ms(&self, now: Instant) -> u64 {
+        let since = self.inter_arrival.last_arrival.unwrap_or(self.started);
+        (now - since).as_millis() as u64
+    }
+
+    /// Estimated samples lost across all keys, if sequence tracking is enabled.
+    pub fn missed_estimate(&self) -> Option<u64> {
+        self.ingest
+            .seq_source
+            .as_ref()
+            .map(|_| self.seq_gaps.values().map(|g| g.missed_estimate).sum())
+    }
+
+    /// Accept a received sample: buffer it, or hold it for reordering when a
+    /// reorder window is set.
+    pub fn push(&mut self, sample: BufferedSample) {
+        self.total_received += 1;
+        self.history
+            .record(sample.timestamp, sample.payload.0.len() as u64);
+        self.check_order(&sample);
+        let now = sample.timestamp;
+        match self.reorder_window_ms {
+            Some(_) => {
+                self.held.push(sample);
+                self.housekeep(now);
+            }
+            None => {
+                self.expire(now);
+                self.buffer_sample(sample);
+            }
+        }
+    }
+
+    /// Count the sample as out of order if its key already saw a later
+    /// source timestamp.
+    fn check_order(&mut self, sample: &BufferedSample) {
+        let Some(source) = sample.source_timestamp else {
+            return;
+        };
+        match self.last_source_ts.get_mut(&sample.key_expr) {
+            Some(last) if source < *last => self.out_of_order_count += 1,
+            Some(last) => *last = source,
+            None => {
+                self.last_source_ts.insert(sample.key_expr.clone(), source);
+            }
+        }
+    }
+
+    /// Release held samples that have waited out the reorder window, then
+    /// evict expired ones. Held samples are sorted by source timestamp
+    /// (arrival time when unstamped) and released up to the last ripe one,
+    /// so a ripe sample never overtakes one stamped before it.
+    pub fn housekeep(&mut self, now: DateTime<Utc>) {
+        if let Some(window_ms) = self.reorder_window_ms {
+            let cutoff = now - chrono::Duration::milliseconds(window_ms as i64);
+            self.held
+                .sort_by_key(|s| s.source_timestamp.unwrap_or(s.timestamp));
+            if let Some(last) = self.held.iter().rposition(|s| s.timestamp <= cutoff) {
+                let ripe: Vec<BufferedSample> = self.held.drain(..=last).collect();
+                self.expire(now);
+                for sample in ripe {
+                    self.buffer_sample(sample);
+                }
+                return;
+            }
+        }
+        self.expire(now);
+    }
+
+    /// Append a sample, stamping it with the next per-subscription `seq`.
+    fn buffer_sample(&mut self, mut sample: BufferedSample) {
+        sample.seq = self.next_seq;
+        self.next_seq += 1;
+        if let Some(last) = &mut self.diff_last {
+            let value = sample.decoded.clone().unwrap_or_else(|| {
+                key_watch::payload_value(None, &sample.encoding, &sample.payload.0)
+            });
+            sample.diff = last
+                .get(&sample.key_expr)
+                .map(|previous| diff::diff(previous, &value));
+            last.insert(sample.key_expr.clone(), value);
+        }
+        let full = self.is_full();
+        if let Some(spill) = self.spill.as_mut().filter(|s| full || !s.is_empty()) {
+            if spill.push(&sample) {
+                return;
+            }
+            // Samples already on disk are older, so the newcomer is the one
+            // to drop whatever the policy
+            if !spill.is_empty() {
+                self.overflow_count += 1;
+                if self.gap_markers {
+                    match spill.gap.as_mut().and_then(|s| s.gap.as_mut()) {
+                        Some(gap) => gap.add(&sample),
+                        None => {
+                            spill.gap = Some(BufferedSample::gap_marker(&self.key_expr, &sample))
+                        }
+                    }
+                }
+                return;
+            }
+        }
+        if full {
+            self.overflow_count += 1;
+            if self.drop_policy == DropPolicy::Newest {
+                self.mark_gap(sample, false);
+                return;
+            }
+            // The oldest sample sits behind the front gap marker, if any
+            let oldest = usize::from(self.buffer.front().is_some_and(|s| s.is_gap()));
+            if let Some(dropped) = self.buffer.remove(oldest) {
+                self.mark_gap(dropped, true);
+            }
+        }
+        self.buffer.push_back(sample);
+    }
+
+    /// Fold a dropped sample into the gap marker at the front or back of the
+    /// buffer, inserting one if that end holds a sample.
+    fn mark_gap(&mut self, dropped: BufferedSample, front: bool) {
+        if !self.gap_markers {
+            return;
+        }
+        let end = match front {
+            true => self.buffer.front_mut(),
+            false => self.buffer.back_mut(),
+        };
+        if let Some(gap) = end.and_then(|s| s.gap.as_mut()) {
+            gap.add(&dropped);
+            return;
+        }
+        let marker = BufferedSample::gap_marker(&self.key_expr, &dropped);
+        match front {
+            true => self.buffer.push_front(marker),
+            false => self.buffer.push_back(marker),
+        }
+        self.markers += 1;
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buffer.len() - self.markers >= self.buffer_capacity
+    }
+
+    /// A new sample would be kept: the buffer has room, or it can go to disk.
+    pub fn has_room(&self) -> bool {
+        match &self.spill {
+            Some(spill) if !spill.is_empty() || self.is_full() => spill.has_room(),
+            _ => !self.is_full(),
+        }
+    }
+
+    /// Move spilled samples back into the buffer, oldest first, until it
+    /// holds `target` samples or the spill is empty. A pending gap marker
+    /// follows the last spilled sample.
+    pub fn refill(&mut self, target: usize) {
+        let Some(spill) = self.spill.as_mut() else {
+            return;
+        };
+        while self.buffer.len() - self.markers < target {
+            let Some(sample) = spill.pop() else {
+                break;
+            };
+            self.markers += usize::from(sample.is_gap());
+            self.buffer.push_back(sample);
+        }
+        if spill.samples() == 0 {
+            if let Some(marker) = spill.gap.take() {
+                self.markers += 1;
+                self.buffer.push_back(marker);
+            }
+        }
+    }
+
+    /// Samples waiting in the spill file.
+    pub fn spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, Spill::samples)
+    }
+
+    /// Update the marker count for entries leaving the buffer.
+    fn removed<'a>(&mut self, entries: impl IntoIterator<Item = &'a BufferedSample>) {
+        self.markers -= entries.into_iter().filter(|s| s.is_gap()).count();
+    }
+
+    /// Evict samples older than `max_age_ms` as of `now`.
+    pub fn expire(&mut self, now: DateTime<Utc>) {
+        let Some(max_age_ms) = self.max_age_ms else {
+            return;
+        };
+        let cutoff = now - chrono::Duration::milliseconds(max_age_ms as i64);
+        let before = self.buffer.len();
+        while self.buffer.front().is_some_and(|s| s.timestamp < cutoff) {
+            let expired = self.buffer.pop_front().unwrap();
+            match expired.is_gap() {
+                true => self.markers -= 1,
+                false => self.expired_count += 1,
+            }
+        }
+        if self.buffer.len() < before {
+            self.refill(self.buffer_capacity);
+            self.space.notify_waiters();
+        }
+    }
+
+    /// The `seq` the next buffered sample will get.
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Put `historical` samples ahead of everything buffered, in timestamp
+    /// order, renumbering so `seq` still follows buffer order. Samples the
+    /// buffer already holds live (same key and source timestamp) are
+    /// skipped, and only the newest that fit beside the live ones are kept.
+    /// Only sound before the subscription is first polled. Returns how many
+    /// were buffered.
+    pub fn backfill(&mut self, mut historical: Vec<BufferedSample>) -> usize {
+        let live: HashSet<(&str, DateTime<Utc>)> = self
+            .buffer
+            .iter()
+            .chain(&self.held)
+            .filter_map(|s| Some((s.key_expr.as_str(), s.source_timestamp?)))
+            .collect();
+        historical.retain(|s| {
+            s.source_timestamp
+                .is_none_or(|at| !live.contains(&(s.key_expr.as_str(), at)))
+        });
+        historical.sort_by_key(|s| s.timestamp);
+        let room = self.buffer_capacity.saturating_sub(self.sample_count());
+        historical.drain(..historical.len().saturating_sub(room));
+
+        let count = historical.len() as u64;
+        for sample in &mut self.buffer {
+            sample.seq += count;
+            if let Some(gap) = &mut sample.gap {
+                gap.first_seq += count;
+                gap.last_seq += count;
+            }
+        }
+        self.next_seq += count;
+        for (seq, sample) in historical.iter_mut().enumerate() {
+            sample.seq = seq as u64;
+        }
+        for sample in historical.into_iter().rev() {
+            self.buffer.push_front(sample);
+        }
+        count as usize
+    }
+
+    /// Buffered samples, not counting gap markers.
+    pub fn sample_count(&self) -> usize {
+        self.buffer.len() - self.markers
+    }
+
+    /// Raw payload bytes currently buffered.
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffer.iter().map(|s| s.payload.0.len()).sum()
+    }
+
+    /// Put samples drained but not delivered back at the front of the
+    /// buffer, in their original order.
+    pub fn requeue(&mut self, samples: Vec<BufferedSample>) {
+        self.markers += samples.iter().filter(|s| s.is_gap()).count();
+        for sample in samples.into_iter().rev() {
+            self.buffer.push_front(sample);
+        }
+    }
+
+    pub fn drain(&mut self, limit: usize) -> Vec<BufferedSample> {
+        let n = limit.min(self.buffer.len());
+        let drained: Vec<BufferedSample> = self.buffer.drain(..n).collect();
+        self.removed(&drained);
+        self.refill(self.buffer_capacity);
+        self.space.notify_waiters();
+        drained
+    }
+
+    /// Index of the newest buffered sample stamped at or before `at`.
+    pub fn latest_at(&self, at: DateTime<Utc>) -> Option<usize> {
+        self.buffer
+            .iter()
+            .rposition(|s| !s.is_gap() && s.timestamp <= at)
+    }
+
+    /// Timestamp of the newest buffered sample.
+    pub fn newest_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.buffer
+            .iter()
+            .rev()
+            .find(|s| !s.is_gap())
+            .map(|s| s.timestamp)
+    }
+
+    /// Drain up to `limit` samples whose key matches `filter`, leaving the rest
+    /// buffered in their original order. Gap markers are always drained.
+    pub fn drain_matching(
+        &mut self,
+        limit: usize,
+        filter: impl Fn(&BufferedSample) -> bool,
+    ) -> Vec<BufferedSample> {
+        let mut taken = Vec::new();
+        let mut kept = VecDeque::with_capacity(self.buffer.len());
+        for sample in self.buffer.drain(..) {
+            if taken.len() < limit && (sample.is_gap() || filter(&sample)) {
+                taken.push(sample);
+            } else {
+                kept.push_back(sample);
+            }
+        }
+        self.buffer = kept;
+        self.removed(&taken);
+        self.refill(self.buffer_capacity);
+        self.space.notify_waiters();
+        taken
+    }
+}
+
+/// Maximum number of recent errors kept for `dump_state`.
+const RECENT_ERRORS_CAPACITY: usize = 50;
+
+/// An error surfaced by an operation or background task.
+#[derive(Clone, Serialize)]
+pub struct ErrorRecord {
+    pub timestamp: DateTime<Utc>,
+    pub context: String,
+    pub message: String,
+}
+
+/// Top-level shared state behind Arc<StateLock>.
+pub struct AppState {
+    pub topics: HashMap<String, TopicMeta>,
+    /// Clock offset estimates per HLC id, from discovered samples.
+    pub publisher_clocks: HashMap<String, ClockSkew>,
+    pub subscriptions: HashMap<String, Subscription>,
+    pub mirrors: HashMap<String, Mirror>,
+    pub synchronizers: HashMap<String, Synchronizer>,
+    pub flight_recorders: HashMap<String, FlightRecorder>,
+    /// Playback positions in recordings.
+    pub recording_readers: HashMap<String, RecordingReader>,
+    pub generators: HashMap<String, Generator>,
+    pub echoes: HashMap<String, Echo>,
+    pub bridges: HashMap<String, Bridge>,
+    pub throttled_relays: HashMap<String, ThrottledRelay>,
+    pub previews: HashMap<String, Preview>,
+    /// Queries whose replies are still arriving or unpolled.
+    pub queries: HashMap<String, QueryStream>,
+    pub key_watches: HashMap<String, KeyWatch>,
+    /// Materialized key-value tables.
+    pub tables: HashMap<String, Table>,
+    /// Compiled WASM plugins by name.
+    pub plugins: HashMap<String, Plugin>,
+    pub threshold_watches: HashMap<String, ThresholdWatch>,
+    pub topology_watches: HashMap<String, TopologyWatch>,
+    pub derived_topics: HashMap<String, DerivedTopic>,
+    /// Next `counter` value per key expression for `publish_template`.
+    pub template_counters: HashMap<String, u64>,
+    pub discovery_active: bool,
+    pub discovery_cancel: Option<watch::Sender<bool>>,
+    pub discovery_health: Option<Arc<TaskHealth>>,
+    pub discovery_key_expr: String,
+    pub discovery_seq_source: Option<crate::seq::SeqSource>,
+    /// Payload bytes discovery keeps per topic for `last_sample_preview`.
+    pub discovery_preview_bytes: Option<usize>,
+    pub discovery_filter: KeyFilter,
+    /// Topic keys the discovery cleanup never expires.
+    pub pinned_topics: HashSet<String>,
+    /// Discovery stopped consuming samples but kept its topics.
+    pub discovery_paused: bool,
+    /// Last `resume_discovery`; topic expiry counts from here at the earliest.
+    pub discovery_resumed_at: Option<DateTime<Utc>>,
+    pub alerts: AlertState,
+    pub recent_errors: VecDeque<ErrorRecord>,
+    pub started: Instant,
+    /// Compression for poll sample blocks, negotiated at initialize.
+    pub compression: Option<Compression>,
+    /// Key prefix applied to host key expressions, set at initialize or via `set_namespace`.
+    pub namespace: Option<String>,
+    /// Mutating operations are refused; set by `--read-only` or at initialize.
+    pub read_only: bool,
+    /// Latency/throughput trade-off, set at initialize.
+    pub profile: Profile,
+    /// Largest `poll` or `get_topics` response before the rest is left for a
+    /// continuation, set at initialize.
+    pub max_response_bytes: Option<usize>,
+    /// Recordings directory and retention limits, set at initialize.
+    pub recordings: Option<RetentionPolicy>,
+    /// Control surface served over zenoh, enabled at initialize.
+    pub admin: Option<AdminQueryable>,
+    /// Labels on topics and subscriptions.
+    pub tags: Tags,
+    /// Where tags are kept across restarts, set at initialize.
+    pub state_file: Option<PathBuf>,
+    /// Other instances merged into `get_federated_view`, by name.
+    pub federation_peers: HashMap<String, federation::Peer>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        Self {
+            topics: HashMap::new(),
+            publisher_clocks: HashMap::new(),
+            subscriptions: HashMap::new(),
+            mirrors: HashMap::new(),
+            synchronizers: HashMap::new(),
+            flight_recorders: HashMap::new(),
+            recording_readers: HashMap::new(),
+            generators: HashMap::new(),
+            echoes: HashMap::new(),
+            bridges: HashMap::new(),
+            throttled_relays: HashMap::new(),
+            previews: HashMap::new(),
+            queries: HashMap::new(),
+            key_watches: HashMap::new(),
+            tables: HashMap::new(),
+            plugins: HashMap::new(),
+            threshold_watches: HashMap::new(),
+            topology_watches: HashMap::new(),
+            derived_topics: HashMap::new(),
+            template_counters: HashMap::new(),
+            discovery_active: false,
+            discovery_cancel: None,
+            discovery_health: None,
+            discovery_key_expr: String::new(),
+            discovery_seq_source: None,
+            discovery_preview_bytes: None,
+            discovery_filter: KeyFilter::default(),
+            pinned_topics: HashSet::new(),
+            discovery_paused: false,
+            discovery_resumed_at: None,
+            alerts: AlertState::default(),
+            recent_errors: VecDeque::with_capacity(RECENT_ERRORS_CAPACITY),
+            started: Instant::now(),
+            compression: None,
+            namespace: None,
+            read_only: false,
+            profile: Profile::default(),
+            max_response_bytes: None,
+            recordings: None,
+            admin: None,
+            tags: Tags::default(),
+            state_file: None,
+            federation_peers: HashMap::new(),
+        }
+    }
+
+    /// Write what outlives the process to the state file, if one is set.
+    pub fn save_state_file(&self) -> Result<(), String> {
+        let Some(path) = &self.state_file else {
+            return Ok(());
+        };
+        StateFile {
+            tags: self.tags.clone(),
+        }
+        .save(path)
+    }
+
+    pub fn record_error(&mut self, context: &str, message: String) {
+        if self.recent_errors.len() >= RECENT_ERRORS_CAPACITY {
+            self.recent_errors.pop_front();
+        }
+        self.recent_errors.push_back(ErrorRecord {
+            timestamp: Utc::now(),
+            context: context.to_string(),
+            message,
+        });
+    }
+}
+
+/// The state lock. Waits to acquire it count toward a traced request's
+/// lock time.
+pub struct StateLock(RwLock<AppState>);
+
+impl StateLock {
+    pub fn new(state: AppState) -> Self {
+        Self(RwLock::new(state))
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, AppState> {
+        let since = Instant::now();
+        let guard = self.0.read().await;
+        trace::lock_acquired(since);
+        guard
+    }
+
+    pub async fn write(&self) -> RwLockWriteGuard<'_, AppState> {
+        let since = Instant::now();
+        let guard = self.0.write().await;
+        trace::lock_acquired(since);
+        guard
+    }
+
+    pub fn try_read(&self) -> Result<RwLockReadGuard<'_, AppState>, TryLockError> {
+        self.0.try_read()
+    }
+}
@@ -0,0 +1,33 @@
+use crate::tags::Tags;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// What outlives the process, kept in the JSON file named by `state_file`
+/// at initialize.
+#[derive(Default, Serialize, Deserialize)]
+pub struct StateFile {
+    #[serde(default)]
+    pub tags: Tags,
+}
+
+impl StateFile {
+    /// Read `path`; a file not written yet is an empty state.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("failed to read {}: {e}", path.display())),
+        };
+        serde_json::from_slice(&bytes)
+            .map_err(|e| format!("invalid state file {}: {e}", path.display()))
+    }
+
+    /// Replace `path` through a temp file beside it, so a crash mid-write
+    /// leaves the previous state.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self).unwrap())
+            .and_then(|()| std::fs::rename(&tmp, path))
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+}
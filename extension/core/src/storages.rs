@@ -0,0 +1,136 @@
+use crate::backend::{GetOptions, ZenohBackend};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+/// Where the storage manager plugin reports running storages, under a
+/// router's `@/<zid>/` admin space.
+const STATUS_PATH: &str = "router/status/plugins/storage_manager/storages";
+
+/// Where storages are configured. Routers apply puts and deletes here only
+/// when `adminspace.permissions.write` is enabled.
+const CONFIG_PATH: &str = "router/config/plugins/storage_manager/storages";
+
+/// How often a create or delete is checked against the status tree.
+const CONFIRM_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A storage a router reports running.
+#[derive(Serialize, JsonSchema)]
+pub struct StorageInfo {
+    pub router: String,
+    pub name: String,
+    pub key_expr: Option<String>,
+    pub volume: Option<String>,
+    /// The plugin's full status for the storage.
+    pub status: Value,
+}
+
+pub fn status_key(router: &str, name: &str) -> String {
+    format!("@/{router}/{STATUS_PATH}/{name}")
+}
+
+pub fn config_key(router: &str, name: &str) -> String {
+    format!("@/{router}/{CONFIG_PATH}/{name}")
+}
+
+/// Storage names are a single key chunk of the admin space.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+    if name.is_empty() || !name.chars().all(allowed) {
+        return Err("storage name must be letters, digits, '-', '_' or '.'".into());
+    }
+    Ok(())
+}
+
+/// `router` when given, else the one router the session is connected to.
+pub async fn resolve_router(
+    session: &dyn ZenohBackend,
+    router: Option<String>,
+) -> Result<String, String> {
+    if let Some(router) = router {
+        return Ok(router);
+    }
+    let mut routers = session.routers().await;
+    match routers.len() {
+        1 => Ok(routers.remove(0)),
+        0 => Err("not connected to a router; pass router".into()),
+        _ => Err(format!(
+            "connected to several routers ({}); pass router",
+            routers.join(", ")
+        )),
+    }
+}
+
+/// Storages running on `router`, or on every router answering `*`, and the
+/// errors routers replied with.
+pub async fn list(
+    session: &dyn ZenohBackend,
+    router: &str,
+    timeout: Duration,
+) -> Result<(Vec<StorageInfo>, Vec<String>), String> {
+    let replies = session
+        .get(&status_key(router, "*"), GetOptions::default(), timeout)
+        .await?;
+    let mut storages = Vec::new();
+    let mut errors = Vec::new();
+    for reply in replies {
+        match reply.result {
+            Ok(sample) => storages.extend(parse_status(&sample.key_expr, &sample.payload)),
+            Err(e) => errors.push(e),
+        }
+    }
+    storages.sort_by(|a, b| (&a.router, &a.name).cmp(&(&b.router, &b.name)));
+    Ok((storages, errors))
+}
+
+/// Read `@/<zid>/router/status/plugins/storage_manager/storages/<name>`.
+fn parse_status(key: &str, payload: &[u8]) -> Option<StorageInfo> {
+    let (router, rest) = key.strip_prefix("@/")?.split_once('/')?;
+    let name = rest.strip_prefix(STATUS_PATH)?.strip_prefix('/')?;
+    let status: Value = serde_json::from_slice(payload).unwrap_or(Value::Null);
+    // Volumes show as a plain id or as an object carrying one
+    let volume = match &status["volume"] {
+        Value::String(id) => Some(id.clone()),
+        volume => volume["id"].as_str().map(String::from),
+    };
+    Some(StorageInfo {
+        router: router.to_string(),
+        name: name.to_string(),
+        key_expr: status["key_expr"].as_str().map(String::from),
+        volume,
+        status,
+    })
+}
+
+/// Poll the status tree until the storage's presence matches `present` or
+/// `timeout` passes. Admin space writes are not acknowledged, so this is
+/// the only sign a router accepted one.
+pub async fn confirm(
+    session: &dyn ZenohBackend,
+    router: &str,
+    name: &str,
+    present: bool,
+    timeout: Duration,
+) -> bool {
+    let deadline = Instant::now() + timeout;
+    let key = status_key(router, name);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let found = session
+            .get(
+                &key,
+                GetOptions::default(),
+                remaining.min(CONFIRM_INTERVAL * 5),
+            )
+            .await
+            .is_ok_and(|replies| replies.iter().any(|r| r.result.is_ok()));
+        if found == present {
+            return true;
+        }
+        if Instant::now() + CONFIRM_INTERVAL >= deadline {
+            return false;
+        }
+        tokio::time::sleep(CONFIRM_INTERVAL).await;
+    }
+}
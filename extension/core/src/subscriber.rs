@@ -0,0 +1,540 @@
+use crate::alerts::{self, AlertEvent};
+use crate::backend::{Reliability, RxSample, SubscribeOptions, ZenohBackend};
+use crate::checksum::Checksum;
+use crate::clock;
+use crate::decode::{self, DecodeFormat};
+use crate::expr::Expression;
+use crate::metrics;
+use crate::namespace;
+use crate::notify;
+use crate::plugin::PluginInstance;
+use crate::redaction;
+use crate::script::{self, Script};
+use crate::seq::SeqSource;
+use crate::state::{AppState, BufferedSample, DropPolicy, Payload, StateLock};
+use crate::telemetry;
+use crate::watchdog::{self, TaskHealth};
+use chrono::Utc;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch, RwLockWriteGuard};
+use tokio::task::JoinHandle;
+
+/// Per-subscription ingest settings, fixed at subscribe time.
+#[derive(Clone)]
+pub struct IngestConfig {
+    pub seq_source: Option<SeqSource>,
+    pub decode: Option<DecodeFormat>,
+    /// Decoded samples allowed in flight before ingest waits.
+    pub decode_queue: usize,
+    /// Most samples buffered under one state lock.
+    pub ingest_batch: usize,
+    /// Shared with the subscription so stats can report the decode backlog.
+    pub decode_queue_depth: Arc<AtomicUsize>,
+    pub checksum: Option<Checksum>,
+    /// Namespace stripped from received keys.
+    pub namespace: Option<String>,
+    /// Backend channel and origin filter for the Zenoh subscriber.
+    pub subscribe: SubscribeOptions,
+    /// Keep only samples delivered with this reliability.
+    pub reliability: Option<Reliability>,
+    /// Shared with the subscription so stats can report the effective mode.
+    pub reliability_stats: Arc<ReliabilityStats>,
+    /// Only samples this expression matches are buffered.
+    pub filter: Option<Arc<Expression>>,
+    /// Samples the filter rejected.
+    pub filtered: Arc<AtomicU64>,
+    /// This subscription's instance of a WASM plugin, run before decoding.
+    pub plugin: Option<Arc<PluginInstance>>,
+    /// Lua script run on each sample after the plugin.
+    pub script: Option<Arc<Script>>,
+    /// Set once the first attempt has declared its Zenoh subscriber.
+    pub declared: Arc<watch::Sender<bool>>,
+}
+
+impl IngestConfig {
+    /// Whether `sample` passes the filter, counting it if not.
+    fn passes_filter(&self, sample: &BufferedSample) -> bool {
+        let Some(filter) = &self.filter else {
+            return true;
+        };
+        let passes = filter.matches(&sample.expr_vars());
+        if !passes {
+            self.filtered.fetch_add(1, Ordering::Relaxed);
+        }
+        passes
+    }
+}
+
+/// Per-subscription counts of how samples were delivered.
+#[derive(Default)]
+pub struct ReliabilityStats {
+    /// Kept samples the publisher sent best-effort.
+    pub best_effort: AtomicU64,
+    /// Samples dropped for not matching the requested reliability.
+    pub rejected: AtomicU64,
+}
+
+impl ReliabilityStats {
+    /// `reliable`, `best_effort` or `mixed` over the `kept` samples so far.
+    pub fn effective(&self, kept: u64) -> Option<&'static str> {
+        match self.best_effort.load(Ordering::Relaxed) {
+            _ if kept == 0 => None,
+            0 => Some(Reliability::Reliable.as_str()),
+            n if n >= kept => Some(Reliability::BestEffort.as_str()),
+            _ => Some("mixed"),
+        }
+    }
+}
+
+/// A received sample plus the bookkeeping gathered before it is buffered.
+struct Ingested {
+    sample: BufferedSample,
+    seq: Option<u64>,
+    arrived: Instant,
+}
+
+type DecodeJob = (Ingested, JoinHandle<Result<Value, String>>);
+
+/// Spawn the supervised task that receives samples for `sub_id` into its buffer.
+/// With decoding enabled, payloads are decoded on the shared pool and a
+/// collector task buffers them in arrival order. If the subscriber fails or its
+/// stream ends before cancellation, the watchdog resubscribes.
+pub fn spawn_subscriber(
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+    sub_id: String,
+    key_expr: String,
+    config: IngestConfig,
+    cancel_rx: watch::Receiver<bool>,
+    health: Arc<TaskHealth>,
+) {
+    let task = format!("subscription {sub_id}");
+    let attempt = {
+        let (state, cancel_rx, health) = (state.clone(), cancel_rx.clone(), health.clone());
+        move || {
+            run_subscriber(
+                session.clone(),
+                state.clone(),
+                sub_id.clone(),
+                key_expr.clone(),
+                config.clone(),
+                cancel_rx.clone(),
+                health.clone(),
+            )
+        }
+    };
+    watchdog::supervise(state, "subscribe", task, health, cancel_rx, attempt);
+}
+
+/// One subscriber attempt. `Ok` means it was stopped on purpose; `Err` asks
+/// the watchdog for a restart.
+async fn run_subscriber(
+    session: Arc<dyn ZenohBackend>,
+    state: Arc<StateLock>,
+    sub_id: String,
+    key_expr: String,
+    config: IngestConfig,
+    mut cancel_rx: watch::Receiver<bool>,
+    health: Arc<TaskHealth>,
+) -> Result<(), String> {
+    let mut stream = session
+        .subscribe_with(&key_expr, config.subscribe)
+        .await
+        .map_err(|e| format!("failed for {key_expr}: {e}"))?;
+    config.declared.send_replace(true);
+
+    let decode_tx = config.decode.map(|format| {
+        let (tx, rx) = mpsc::channel(config.decode_queue);
+        spawn_decode_collector(state.clone(), sub_id.clone(), config.clone(), rx);
+        (format, tx)
+    });
+
+    let mut heartbeat = tokio::time::interval(watchdog::HEARTBEAT_INTERVAL);
+    loop {
+        tokio::select! {
+            sample = stream.recv() => {
+                let Some(sample) = sample else {
+                    return Err(format!("stream for {key_expr} closed"));
+                };
+                health.beat();
+
+                // Take what is already queued too, up to the batch size, and
+                // buffer it under one lock
+                let mut batch = Vec::new();
+                let mut next = Some(sample);
+                let mut taken = 0;
+                while let Some(sample) = next {
+                    taken += 1;
+                    if let Some(ingested) = ingest(&*session, &config, sample).await {
+                        match &decode_tx {
+                            Some((format, tx)) => {
+                                let job = decode::spawn(
+                                    *format,
+                                    ingested.sample.encoding.clone(),
+                                    ingested.sample.payload.0.clone(),
+                                );
+                                config.decode_queue_depth.fetch_add(1, Ordering::Relaxed);
+                                if tx.send((ingested, job)).await.is_err() {
+                                    // Subscription was removed, stop the task
+                                    return Ok(());
+                                }
+                            }
+                            None if config.passes_filter(&ingested.sample) => batch.push(ingested),
+                            None => {}
+                        }
+                    }
+                    next = if taken < config.ingest_batch { stream.try_recv() } else { None };
+                }
+                if !batch.is_empty() && !deliver(&state, &sub_id, batch).await {
+                    // Subscription was removed, stop the task
+                    return Ok(());
+                }
+            }
+            _ = heartbeat.tick() => health.beat(),
+            changed = cancel_rx.changed() => {
+                if changed.is_err() || *cancel_rx.borrow() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Run one received sample through the reliability check, plugin and
+/// script. `None` if any of them dropped it.
+async fn ingest(
+    session: &dyn ZenohBackend,
+    config: &IngestConfig,
+    sample: RxSample,
+) -> Option<Ingested> {
+    let arrived = Instant::now();
+    metrics::record_sample();
+    let stats = &config.reliability_stats;
+    if config.reliability.is_some_and(|r| r != sample.reliability) {
+        stats.rejected.fetch_add(1, Ordering::Relaxed);
+        return None;
+    }
+    if sample.reliability == Reliability::BestEffort {
+        stats.best_effort.fetch_add(1, Ordering::Relaxed);
+    }
+    let mut payload_bytes = sample.payload;
+    let mut plugin_decoded = None;
+    if let Some(plugin) = &config.plugin {
+        let processed = plugin.process(payload_bytes)?;
+        payload_bytes = processed.payload;
+        plugin_decoded = processed.decoded;
+    }
+    let key_expr = namespace::strip(config.namespace.as_deref(), &sample.key_expr);
+    let mut encoding = sample.encoding;
+    let mut tags = None;
+    if let Some(script) = &config.script {
+        let value = plugin_decoded.as_ref().and_then(|d| d.as_ref().ok());
+        let scripted = script.process(&key_expr, payload_bytes, encoding, value);
+        script::publish_derived(
+            session,
+            config.namespace.as_deref(),
+            script,
+            scripted.derived,
+        )
+        .await;
+        let kept = scripted.sample?;
+        if kept.replaced {
+            // The plugin decoded the payload the script replaced
+            plugin_decoded = None;
+        }
+        payload_bytes = kept.payload;
+        encoding = kept.encoding;
+        tags = kept.tags;
+    }
+    let payload_bytes = redaction::redact(&key_expr, payload_bytes);
+    let seq = config
+        .seq_source
+        .as_ref()
+        .and_then(|src| src.extract(&payload_bytes));
+
+    let (decoded, decode_error) = match plugin_decoded {
+        Some(Ok(mut value)) => {
+            redaction::redact_value(&key_expr, &mut value);
+            (Some(value), None)
+        }
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+    Some(Ingested {
+        sample: BufferedSample {
+            seq: 0,
+            key_expr,
+            checksum: config.checksum.map(|c| c.digest(&payload_bytes)),
+            payload: Payload(payload_bytes),
+            encoding,
+            timestamp: Utc::now(),
+            arrived_us: clock::now_us(),
+            source_timestamp: sample.timestamp.map(|(_, at)| at),
+            decoded,
+            decode_error,
+            tags,
+            shm: sample.shm,
+            gap: None,
+            diff: None,
+            historical: false,
+        },
+        seq,
+        arrived,
+    })
+}
+
+/// Run samples a storage returned for a subscription's key expression
+/// through its ingest, decoding and filter, for
+/// [`Subscription::backfill`](crate::state::Subscription::backfill).
+/// Each is stamped with its source timestamp where it has one, since when
+/// it arrived says nothing about when it was published.
+pub async fn historical(
+    session: &dyn ZenohBackend,
+    config: &IngestConfig,
+    samples: Vec<RxSample>,
+) -> Vec<BufferedSample> {
+    let mut out = Vec::new();
+    for sample in samples {
+        let Some(Ingested { mut sample, .. }) = ingest(session, config, sample).await else {
+            continue;
+        };
+        if let Some(format) = config.decode {
+            let job = decode::spawn(format, sample.encoding.clone(), sample.payload.0.clone());
+            match job
+                .await
+                .unwrap_or_else(|e| Err(format!("decode task failed: {e}")))
+            {
+                Ok(mut value) => {
+                    redaction::redact_value(&sample.key_expr, &mut value);
+                    sample.decoded = Some(value);
+                }
+                Err(e) => sample.decode_error = Some(e),
+            }
+        }
+        if config.passes_filter(&sample) {
+            sample.timestamp = sample.source_timestamp.unwrap_or(sample.timestamp);
+            sample.historical = true;
+            out.push(sample);
+        }
+    }
+    out
+}
+
+/// Await decode jobs in submission order and buffer the results.
+fn spawn_decode_collector(
+    state: Arc<StateLock>,
+    sub_id: String,
+    config: IngestConfig,
+    mut rx: mpsc::Receiver<DecodeJob>,
+) {
+    tokio::spawn(async move {
+        while let Some(first) = rx.recv().await {
+            let mut batch = Vec::new();
+            let mut next = Some(first);
+            let mut taken = 0;
+            while let Some((mut ingested, job)) = next {
+                taken += 1;
+                let result = job
+                    .await
+                    .unwrap_or_else(|e| Err(format!("decode task failed: {e}")));
+                config.decode_queue_depth.fetch_sub(1, Ordering::Relaxed);
+                match result {
+                    Ok(mut value) => {
+                        redaction::redact_value(&ingested.sample.key_expr, &mut value);
+                        ingested.sample.decoded = Some(value);
+                    }
+                    Err(e) => ingested.sample.decode_error = Some(e),
+                }
+                if config.passes_filter(&ingested.sample) {
+                    batch.push(ingested);
+                }
+                next = match taken < config.ingest_batch {
+                    true => rx.try_recv().ok(),
+                    false => None,
+                };
+            }
+            if !batch.is_empty() && !deliver(&state, &sub_id, batch).await {
+                break;
+            }
+        }
+    });
+}
+
+/// Lock the state once `sub_id` has room for a sample. Under the `block`
+/// drop policy this waits for a reader to drain the buffer, up to the policy
+/// timeout; after that the sample evicts the oldest as usual. `None` if the
+/// subscription no longer exists.
+async fn lock_with_room<'a>(
+    state: &'a Arc<StateLock>,
+    sub_id: &str,
+) -> Option<RwLockWriteGuard<'a, AppState>> {
+    let mut deadline = None;
+    loop {
+        let mut guard = state.write().await;
+        let sub = guard.subscriptions.get_mut(sub_id)?;
+        let DropPolicy::Block(timeout) = sub.drop_policy else {
+            return Some(guard);
+        };
+        if sub.has_room() {
+            return Some(guard);
+        }
+        let deadline = *deadline.get_or_insert_with(|| tokio::time::Instant::now() + timeout);
+        let space = sub.space.clone();
+        let freed = space.notified();
+        drop(guard);
+        if tokio::time::timeout_at(deadline, freed).await.is_err() {
+            let mut guard = state.write().await;
+            guard.subscriptions.get_mut(sub_id)?.block_timeouts += 1;
+            return Some(guard);
+        }
+    }
+}
+
+/// Buffer samples and update subscription stats and alerts, holding the
+/// lock for the whole batch unless the `block` policy has to wait for room.
+/// Notifications go out once the lock is released. Returns false if the
+/// subscription no longer exists.
+async fn deliver(state: &Arc<StateLock>, sub_id: &str, batch: Vec<Ingested>) -> bool {
+    let mut batch = batch.into_iter().peekable();
+    while batch.peek().is_some() {
+        let Some(mut guard) = lock_with_room(state, sub_id).await else {
+            return false;
+        };
+        let (exists, resumed, alerts) = buffer(&mut guard, sub_id, &mut batch);
+        drop(guard);
+        if let Some(resumed) = resumed {
+            notify::notify("subscription_resumed", resumed);
+        }
+        alerts::announce(alerts);
+        if !exists {
+            return false;
+        }
+    }
+    true
+}
+
+/// Buffer what `batch` has for `sub_id` until the `block` policy runs out of
+/// room. Returns whether the subscription still exists, plus the
+/// `subscription_resumed` event and alert transitions to send.
+fn buffer(
+    st: &mut AppState,
+    sub_id: &str,
+    batch: &mut impl Iterator<Item = Ingested>,
+) -> (bool, Option<Value>, Vec<AlertEvent>) {
+    let (mut resumed, mut alerts) = (None, Vec::new());
+    let Some(overflow) = st.subscriptions.get(sub_id).map(|s| s.overflow_count) else {
+        return (false, resumed, alerts);
+    };
+    let (mut samples, mut bytes) = (0, 0);
+    for ingested in batch.by_ref() {
+        let Some(sub) = st.subscriptions.get_mut(sub_id) else {
+            return (false, resumed, alerts);
+        };
+
+        let Ingested {
+            sample,
+            seq,
+            arrived,
+        } = ingested;
+        alerts.extend(st.alerts.on_sample(sub_id, &sample));
+        if let Some(seq) = seq {
+            sub.seq_gaps
+                .entry(sample.key_expr.clone())
+                .or_default()
+                .observe(seq);
+        }
+        sub.inter_arrival.record(arrived);
+        if sub.stalled {
+            sub.stalled = false;
+            resumed = Some(serde_json::json!({
+                "sub_id": sub_id,
+                "key_expr": sub.key_expr,
+            }));
+        }
+        samples += 1;
+        bytes += sample.payload.0.len() as u64;
+        sub.push(sample);
+        if matches!(sub.drop_policy, DropPolicy::Block(_)) && !sub.has_room() {
+            break;
+        }
+    }
+    if let Some(sub) = st.subscriptions.get(sub_id) {
+        let dropped = sub.overflow_count - overflow;
+        telemetry::record_ingest(&sub.key_expr, samples, bytes, dropped);
+    }
+    (true, resumed, alerts)
+}
+
+/// Release held samples and evict expired ones every `check_every`, so the
+/// buffer ages out and the reorder window drains even when nothing new
+/// arrives.
+pub fn spawn_housekeeping(
+    state: Arc<StateLock>,
+    sub_id: String,
+    check_every: Duration,
+    mut cancel_rx: watch::Receiver<bool>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(check_every);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let mut st = state.write().await;
+                    let Some(sub) = st.subscriptions.get_mut(&sub_id) else {
+                        break;
+                    };
+                    sub.housekeep(Utc::now());
+                }
+                _ = cancel_rx.changed() => {
+                    if *cancel_rx.borrow() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Watch a subscription and emit a `subscription_stalled` notification once it
+/// has been silent for `silent_ms`. Re-arms when a sample arrives.
+pub fn spawn_stall_watch(
+    state: Arc<StateLock>,
+    sub_id: String,
+    silent_ms: u64,
+    mut cancel_rx: watch::Receiver<bool>,
+) {
+    tokio::spawn(async move {
+        let check_every = Duration::from_millis((silent_ms / 4).clamp(10, 1000));
+        let mut interval = tokio::time::interval(check_every);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let mut st = state.write().await;
+                    let Some(sub) = st.subscriptions.get_mut(&sub_id) else {
+                        break;
+                    };
+                    let silent_for = sub.silent_ms(Instant::now());
+                    if !sub.stalled && silent_for >= silent_ms {
+                        sub.stalled = true;
+                        let stalled = serde_json::json!({
+                            "sub_id": sub_id,
+                            "key_expr": sub.key_expr,
+                            "silent_ms": silent_for,
+                            "alert_if_silent_ms": silent_ms,
+                        });
+                        drop(st);
+                        notify::notify("subscription_stalled", stalled);
+                    }
+                }
+                _ = cancel_rx.changed() => {
+                    if *cancel_rx.borrow() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
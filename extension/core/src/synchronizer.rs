@@ -0,0 +1,178 @@
+use crate::backend::ZenohBackend;
+use crate::clock;
+use crate::namespace;
+use crate::redaction;
+use crate::state::{BufferedSample, Payload};
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// Unmatched samples kept per input while waiting for partners.
+pub const QUEUE_SIZE: usize = 10;
+
+/// One sample from each input, all within `slop` of each other.
+pub struct MatchedTuple {
+    pub seq: u64,
+    /// Arrival spread between the oldest and newest member.
+    pub spread_ms: i64,
+    pub samples: Vec<BufferedSample>,
+}
+
+/// Approximate-time matcher over 2-4 inputs. Each input keeps a short queue;
+/// heads too old to pair with the newest head are dropped, and once every
+/// queue has a head the heads form a tuple.
+pub struct Matcher {
+    queues: Vec<VecDeque<BufferedSample>>,
+    slop_us: u64,
+    pub matched: VecDeque<MatchedTuple>,
+    pub buffer_capacity: usize,
+    pub total_matched: u64,
+    /// Samples discarded without a partner within the slop.
+    pub dropped: u64,
+    pub overflow_count: u64,
+}
+
+impl Matcher {
+    pub fn new(inputs: usize, slop_ms: u64, buffer_capacity: usize) -> Self {
+        Self {
+            queues: vec![VecDeque::with_capacity(QUEUE_SIZE); inputs],
+            slop_us: slop_ms * 1000,
+            matched: VecDeque::with_capacity(buffer_capacity),
+            buffer_capacity,
+            total_matched: 0,
+            dropped: 0,
+            overflow_count: 0,
+        }
+    }
+
+    pub fn push(&mut self, input: usize, sample: BufferedSample) {
+        let queue = &mut self.queues[input];
+        if queue.len() >= QUEUE_SIZE {
+            queue.pop_front();
+            self.dropped += 1;
+        }
+        queue.push_back(sample);
+        self.try_match();
+    }
+
+    fn try_match(&mut self) {
+        while self.queues.iter().all(|q| !q.is_empty()) {
+            let pivot = self.queues.iter().map(|q| q[0].arrived_us).max().unwrap();
+            let mut stale = false;
+            for queue in &mut self.queues {
+                while queue
+                    .front()
+                    .is_some_and(|s| s.arrived_us + self.slop_us < pivot)
+                {
+                    queue.pop_front();
+                    self.dropped += 1;
+                    stale = true;
+                }
+            }
+            if stale {
+                continue;
+            }
+
+            let samples: Vec<BufferedSample> = self
+                .queues
+                .iter_mut()
+                .map(|q| q.pop_front().unwrap())
+                .collect();
+            let oldest = samples.iter().map(|s| s.arrived_us).min().unwrap();
+            if self.matched.len() >= self.buffer_capacity {
+                self.matched.pop_front();
+                self.overflow_count += 1;
+            }
+            self.matched.push_back(MatchedTuple {
+                seq: self.total_matched,
+                spread_ms: ((pivot - oldest) / 1000) as i64,
+                samples,
+            });
+            self.total_matched += 1;
+        }
+    }
+
+    /// Unmatched samples currently queued across inputs.
+    pub fn pending(&self) -> usize {
+        self.queues.iter().map(VecDeque::len).sum()
+    }
+}
+
+pub struct Synchronizer {
+    pub key_exprs: Vec<String>,
+    pub slop_ms: u64,
+    pub matcher: Arc<Mutex<Matcher>>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Subscribe to every input and start feeding the matcher. `key_exprs` are
+/// host-facing; `namespace` is applied on the wire and stripped from samples.
+pub async fn start_synchronizer(
+    session: Arc<dyn ZenohBackend>,
+    key_exprs: Vec<String>,
+    namespace: Option<String>,
+    slop_ms: u64,
+    buffer_size: usize,
+) -> Result<Synchronizer, String> {
+    let mut streams = Vec::with_capacity(key_exprs.len());
+    for key_expr in &key_exprs {
+        let scoped = namespace::scope(namespace.as_deref(), key_expr);
+        streams.push(session.subscribe(&scoped).await?);
+    }
+
+    let matcher = Arc::new(Mutex::new(Matcher::new(
+        key_exprs.len(),
+        slop_ms,
+        buffer_size,
+    )));
+    let (cancel, cancel_rx) = watch::channel(false);
+    for (input, mut stream) in streams.into_iter().enumerate() {
+        let (matcher, namespace, mut cancel_rx) =
+            (matcher.clone(), namespace.clone(), cancel_rx.clone());
+        tokio::spawn(async move {
+            let mut received = 0u64;
+            loop {
+                tokio::select! {
+                    sample = stream.recv() => {
+                        let Some(sample) = sample else { break };
+                        let key_expr = namespace::strip(namespace.as_deref(), &sample.key_expr);
+                        let sample = BufferedSample {
+                            seq: received,
+                            payload: Payload(redaction::redact(&key_expr, sample.payload)),
+                            key_expr,
+                            encoding: sample.encoding,
+                            timestamp: Utc::now(),
+                            arrived_us: clock::now_us(),
+                            source_timestamp: None,
+                            checksum: None,
+                            decoded: None,
+                            decode_error: None,
+                            tags: None,
+                            shm: false,
+                            gap: None,
+                            diff: None,
+                            historical: false,
+                        };
+                        received += 1;
+                        matcher.lock().unwrap().push(input, sample);
+                    }
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(Synchronizer {
+        key_exprs,
+        slop_ms,
+        matcher,
+        cancel,
+        created_at: Utc::now(),
+    })
+}
@@ -0,0 +1,222 @@
+use crate::backend::{GetOptions, Reply, RxSample, ZenohBackend};
+use crate::decode::DecodeFormat;
+use crate::key_watch::payload_value;
+use crate::namespace;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use zenoh::key_expr::KeyExpr;
+
+#[derive(Clone)]
+pub struct TableConfig {
+    pub decode: Option<DecodeFormat>,
+    /// Changes kept for `table_changes`; the oldest is evicted beyond this.
+    pub changelog_size: usize,
+    /// Seed the table from a query before live updates arrive.
+    pub initial_query_ms: Option<u64>,
+}
+
+/// The latest value on one concrete key.
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct TableEntry {
+    pub key_expr: String,
+    pub value: Value,
+    pub encoding: String,
+    /// Revision of the change that set this value.
+    pub revision: u64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A key set to a new value or removed.
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct TableChange {
+    /// Table-wide change counter, starting at 1.
+    pub revision: u64,
+    pub key_expr: String,
+    /// `put` or `delete`.
+    pub op: &'static str,
+    /// The new value, for `put`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Latest value per key and the recent changes that led there.
+#[derive(Default)]
+pub struct TableData {
+    pub entries: BTreeMap<String, TableEntry>,
+    pub changelog: VecDeque<TableChange>,
+    /// Revision of the newest change; 0 before any.
+    pub revision: u64,
+    pub received: u64,
+}
+
+impl TableData {
+    /// Apply a sample. A put repeating a key's current value is not a change.
+    /// With `seed`, a key already set by a live sample is left alone.
+    fn apply(&mut self, sample: RxSample, key: String, config: &TableConfig, seed: bool) {
+        self.received += 1;
+        if sample.delete {
+            self.delete(&key, config.changelog_size);
+            return;
+        }
+        if seed && self.entries.contains_key(&key) {
+            return;
+        }
+        let value = payload_value(config.decode, &sample.encoding, &sample.payload);
+        if self.entries.get(&key).is_some_and(|e| e.value == value) {
+            return;
+        }
+        let change = self.record(&key, "put", Some(value.clone()), config.changelog_size);
+        self.entries.insert(
+            key.clone(),
+            TableEntry {
+                key_expr: key,
+                value,
+                encoding: sample.encoding,
+                revision: change.revision,
+                updated_at: change.timestamp,
+            },
+        );
+    }
+
+    /// Remove `key`, or every key it matches when it is a wildcard.
+    fn delete(&mut self, key: &str, capacity: usize) {
+        let removed: Vec<String> = match KeyExpr::try_from(key) {
+            Ok(filter) if key.contains('*') || key.contains('$') => self
+                .entries
+                .keys()
+                .filter(|k| KeyExpr::try_from(k.as_str()).is_ok_and(|k| filter.intersects(&k)))
+                .cloned()
+                .collect(),
+            _ => self
+                .entries
+                .contains_key(key)
+                .then(|| key.to_string())
+                .into_iter()
+                .collect(),
+        };
+        for key in removed {
+            self.entries.remove(&key);
+            self.record(&key, "delete", None, capacity);
+        }
+    }
+
+    fn record(
+        &mut self,
+        key: &str,
+        op: &'static str,
+        value: Option<Value>,
+        capacity: usize,
+    ) -> TableChange {
+        self.revision += 1;
+        let change = TableChange {
+            revision: self.revision,
+            key_expr: key.to_string(),
+            op,
+            value,
+            timestamp: Utc::now(),
+        };
+        if self.changelog.len() >= capacity {
+            self.changelog.pop_front();
+        }
+        self.changelog.push_back(change.clone());
+        change
+    }
+
+    /// Up to `limit` changes after revision `since`. True alongside when
+    /// changes after `since` were already evicted, so the caller should
+    /// re-read the whole table.
+    pub fn changes_since(&self, since: u64, limit: usize) -> (Vec<TableChange>, bool) {
+        let oldest = self
+            .changelog
+            .front()
+            .map_or(self.revision + 1, |c| c.revision);
+        let truncated = since + 1 < oldest && since < self.revision;
+        let changes = self
+            .changelog
+            .iter()
+            .skip_while(|c| c.revision <= since)
+            .take(limit)
+            .cloned()
+            .collect();
+        (changes, truncated)
+    }
+}
+
+pub struct Table {
+    /// Host-facing key expression, without the session namespace.
+    pub key_expr: String,
+    pub config: TableConfig,
+    pub data: Arc<Mutex<TableData>>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Subscribe to `key_expr` and keep the latest value of every concrete key
+/// it matches, with a changelog readers follow by revision. Deletes remove
+/// keys. With `initial_query_ms`, a query seeds keys no live sample has set.
+pub async fn start_table(
+    session: Arc<dyn ZenohBackend>,
+    key_expr: String,
+    config: TableConfig,
+    namespace: Option<String>,
+) -> Result<Table, String> {
+    let scoped = namespace::scope(namespace.as_deref(), &key_expr);
+    let mut stream = session.subscribe(&scoped).await?;
+    let data: Arc<Mutex<TableData>> = Arc::default();
+    let (cancel, cancel_rx) = watch::channel(false);
+
+    {
+        let (data, config, namespace) = (data.clone(), config.clone(), namespace.clone());
+        let mut cancel_rx = cancel_rx.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    sample = stream.recv() => {
+                        let Some(sample) = sample else { break };
+                        let key = namespace::strip(namespace.as_deref(), &sample.key_expr);
+                        data.lock().unwrap().apply(sample, key, &config, false);
+                    }
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    if let Some(timeout_ms) = config.initial_query_ms {
+        let (data, config) = (data.clone(), config.clone());
+        let mut cancel_rx = cancel_rx;
+        tokio::spawn(async move {
+            let mut on_reply = |reply: Reply| {
+                if let Ok(sample) = reply.result {
+                    let key = namespace::strip(namespace.as_deref(), &sample.key_expr);
+                    data.lock().unwrap().apply(sample, key, &config, true);
+                }
+            };
+            let options = GetOptions::default();
+            let timeout = Duration::from_millis(timeout_ms);
+            tokio::select! {
+                _ = session.get_each(&scoped, options, timeout, &mut on_reply) => {}
+                _ = cancel_rx.wait_for(|cancelled| *cancelled) => {}
+            }
+        });
+    }
+
+    Ok(Table {
+        key_expr,
+        config,
+        data,
+        cancel,
+        created_at: Utc::now(),
+    })
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Longest tag accepted, in characters.
+const MAX_TAG_LEN: usize = 64;
+
+/// Labels on topics by key and on subscriptions by `sub_id`. A key or id
+/// need not exist to be tagged; its tags apply whenever it shows up, as a
+/// topic rediscovered or a subscription re-created after a restart.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Tags {
+    #[serde(default)]
+    pub topics: BTreeMap<String, BTreeSet<String>>,
+    #[serde(default)]
+    pub subscriptions: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Tags {
+    pub fn of_topic(&self, key_expr: &str) -> Vec<String> {
+        listed(&self.topics, key_expr)
+    }
+
+    pub fn of_subscription(&self, sub_id: &str) -> Vec<String> {
+        listed(&self.subscriptions, sub_id)
+    }
+}
+
+fn listed(map: &BTreeMap<String, BTreeSet<String>>, id: &str) -> Vec<String> {
+    map.get(id)
+        .map_or_else(Vec::new, |tags| tags.iter().cloned().collect())
+}
+
+/// `tag` trimmed, checked to be 1 to [`MAX_TAG_LEN`] characters.
+pub fn parse(tag: &str) -> Result<String, String> {
+    let tag = tag.trim();
+    if tag.is_empty() || tag.chars().count() > MAX_TAG_LEN {
+        return Err(format!("tags must be 1 to {MAX_TAG_LEN} characters"));
+    }
+    Ok(tag.to_string())
+}
+
+/// Add then remove tags on `id`, forgetting it once it has none left.
+/// Returns its tags afterwards.
+pub fn update(
+    map: &mut BTreeMap<String, BTreeSet<String>>,
+    id: &str,
+    add: Vec<String>,
+    remove: &[String],
+) -> Vec<String> {
+    let tags = map.entry(id.to_string()).or_default();
+    tags.extend(add);
+    tags.retain(|tag| !remove.contains(tag));
+    if tags.is_empty() {
+        map.remove(id);
+    }
+    listed(map, id)
+}
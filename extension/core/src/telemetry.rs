@@ -0,0 +1,282 @@
+use crate::trace::Timing;
+use opentelemetry::metrics::{Counter, Histogram, MeterProvider as _};
+use opentelemetry::trace::{Span as _, SpanKind, Status, Tracer as _, TracerProvider as _};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig, WithHttpConfig};
+use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+/// Instrumentation scope of every span and metric.
+const SCOPE: &str = "nexus-zenoh";
+
+/// Exports more often than this would mostly ship empty batches.
+pub const MIN_EXPORT_INTERVAL_MS: u64 = 1000;
+
+/// The exporter in use, if initialize configured one.
+static TELEMETRY: RwLock<Option<Arc<Telemetry>>> = RwLock::new(None);
+
+fn default_service_name() -> String {
+    SCOPE.into()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_export_interval_ms() -> u64 {
+    10_000
+}
+
+fn default_export_timeout_ms() -> u64 {
+    10_000
+}
+
+/// Where and what to export over OTLP/HTTP, set at initialize.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TelemetryConfig {
+    /// Collector base URL, e.g. `http://localhost:4318`. Traces go to
+    /// `/v1/traces` and metrics to `/v1/metrics` under it.
+    pub endpoint: String,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+    /// Extra resource attributes, such as `deployment.environment`.
+    #[serde(default)]
+    pub resource: BTreeMap<String, String>,
+    /// Sent with every export, e.g. for collector authentication. Never
+    /// echoed back.
+    #[serde(default, skip_serializing)]
+    pub headers: HashMap<String, String>,
+    /// A span per `execute` request.
+    #[serde(default = "default_true")]
+    pub traces: bool,
+    /// Request and subscription ingest counters.
+    #[serde(default = "default_true")]
+    pub metrics: bool,
+    #[serde(default = "default_export_interval_ms")]
+    pub export_interval_ms: u64,
+    #[serde(default = "default_export_timeout_ms")]
+    pub export_timeout_ms: u64,
+}
+
+impl TelemetryConfig {
+    pub fn validate(self) -> Result<Self, String> {
+        let endpoint = self.endpoint.trim_end_matches('/');
+        if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+            return Err("telemetry endpoint must be an http:// or https:// URL".into());
+        }
+        if !self.traces && !self.metrics {
+            return Err("telemetry needs traces or metrics enabled".into());
+        }
+        if self.export_interval_ms < MIN_EXPORT_INTERVAL_MS {
+            return Err(format!(
+                "telemetry export_interval_ms must be at least {MIN_EXPORT_INTERVAL_MS}"
+            ));
+        }
+        Ok(Self {
+            endpoint: endpoint.to_string(),
+            ..self
+        })
+    }
+}
+
+struct Telemetry {
+    tracer_provider: Option<SdkTracerProvider>,
+    tracer: Option<SdkTracer>,
+    meter_provider: Option<SdkMeterProvider>,
+    instruments: Option<Instruments>,
+}
+
+struct Instruments {
+    requests: Counter<u64>,
+    request_duration: Histogram<f64>,
+    samples: Counter<u64>,
+    bytes: Counter<u64>,
+    dropped: Counter<u64>,
+}
+
+/// Start exporting as `config` says, replacing any exporter already
+/// running. The previous one is flushed first.
+pub async fn install(config: &TelemetryConfig) -> Result<(), String> {
+    let resource = Resource::builder()
+        .with_service_name(config.service_name.clone())
+        .with_attributes(
+            config
+                .resource
+                .iter()
+                .map(|(k, v)| KeyValue::new(k.clone(), v.clone())),
+        )
+        .build();
+    let timeout = Duration::from_millis(config.export_timeout_ms);
+    let url = |signal: &str| format!("{}/v1/{signal}", config.endpoint);
+
+    let tracer_provider = match config.traces {
+        true => {
+            let exporter = SpanExporter::builder()
+                .with_http()
+                .with_endpoint(url("traces"))
+                .with_headers(config.headers.clone())
+                .with_timeout(timeout)
+                .build()
+                .map_err(|e| format!("failed to build trace exporter: {e}"))?;
+            Some(
+                SdkTracerProvider::builder()
+                    .with_batch_exporter(exporter)
+                    .with_resource(resource.clone())
+                    .build(),
+            )
+        }
+        false => None,
+    };
+    let meter_provider = match config.metrics {
+        true => {
+            let exporter = MetricExporter::builder()
+                .with_http()
+                .with_endpoint(url("metrics"))
+                .with_headers(config.headers.clone())
+                .with_timeout(timeout)
+                .build()
+                .map_err(|e| format!("failed to build metric exporter: {e}"))?;
+            let reader = PeriodicReader::builder(exporter)
+                .with_interval(Duration::from_millis(config.export_interval_ms))
+                .build();
+            Some(
+                SdkMeterProvider::builder()
+                    .with_reader(reader)
+                    .with_resource(resource)
+                    .build(),
+            )
+        }
+        false => None,
+    };
+
+    let telemetry = Telemetry {
+        tracer: tracer_provider.as_ref().map(|p| p.tracer(SCOPE)),
+        instruments: meter_provider.as_ref().map(instruments),
+        tracer_provider,
+        meter_provider,
+    };
+    let previous = TELEMETRY.write().unwrap().replace(Arc::new(telemetry));
+    close(previous).await;
+    Ok(())
+}
+
+/// Flush and stop the exporter, if any.
+pub async fn shutdown() {
+    let previous = TELEMETRY.write().unwrap().take();
+    close(previous).await;
+}
+
+/// Exporters block on their last export and own blocking HTTP clients,
+/// which may not be dropped on a runtime thread.
+async fn close(telemetry: Option<Arc<Telemetry>>) {
+    let Some(telemetry) = telemetry else {
+        return;
+    };
+    let _ = tokio::task::spawn_blocking(move || {
+        if let Some(provider) = &telemetry.tracer_provider {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("zenoh: telemetry: trace shutdown: {e}");
+            }
+        }
+        if let Some(provider) = &telemetry.meter_provider {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("zenoh: telemetry: metric shutdown: {e}");
+            }
+        }
+        drop(telemetry);
+    })
+    .await;
+}
+
+fn instruments(provider: &SdkMeterProvider) -> Instruments {
+    let meter = provider.meter(SCOPE);
+    Instruments {
+        requests: meter
+            .u64_counter("nexus_zenoh.requests")
+            .with_description("execute requests by operation and outcome")
+            .build(),
+        request_duration: meter
+            .f64_histogram("nexus_zenoh.request.duration")
+            .with_description("time from reading an execute request to its response")
+            .with_unit("s")
+            .build(),
+        samples: meter
+            .u64_counter("nexus_zenoh.subscription.samples")
+            .with_description("samples buffered by subscriptions")
+            .build(),
+        bytes: meter
+            .u64_counter("nexus_zenoh.subscription.bytes")
+            .with_description("payload bytes buffered by subscriptions")
+            .with_unit("By")
+            .build(),
+        dropped: meter
+            .u64_counter("nexus_zenoh.subscription.dropped")
+            .with_description("samples dropped because a subscription buffer was full")
+            .build(),
+    }
+}
+
+fn current() -> Option<Arc<Telemetry>> {
+    TELEMETRY.read().unwrap().clone()
+}
+
+/// Export a span and request metrics for one `execute` request.
+pub fn record_request(operation: &str, error: Option<&str>, timing: &Timing) {
+    let Some(telemetry) = current() else {
+        return;
+    };
+    let total = Duration::from_micros(timing.total_us);
+    if let Some(tracer) = &telemetry.tracer {
+        let end = SystemTime::now();
+        let us = |v: u64| v as i64;
+        let mut span = tracer
+            .span_builder(operation.to_string())
+            .with_kind(SpanKind::Server)
+            .with_start_time(end - total)
+            .with_attributes([
+                KeyValue::new("rpc.system", "jsonrpc"),
+                KeyValue::new("rpc.method", operation.to_string()),
+                KeyValue::new("nexus_zenoh.queue_wait_us", us(timing.queue_wait_us)),
+                KeyValue::new("nexus_zenoh.lock_wait_us", us(timing.lock_wait_us)),
+                KeyValue::new("nexus_zenoh.zenoh_us", us(timing.zenoh_us)),
+                KeyValue::new("nexus_zenoh.zenoh_calls", us(timing.zenoh_calls)),
+                KeyValue::new("nexus_zenoh.serialization_us", us(timing.serialization_us)),
+            ])
+            .start(tracer);
+        if let Some(error) = error {
+            span.set_status(Status::error(error.to_string()));
+        }
+        span.end_with_timestamp(end);
+    }
+    if let Some(instruments) = &telemetry.instruments {
+        let operation = KeyValue::new("operation", operation.to_string());
+        let outcome = KeyValue::new("outcome", if error.is_some() { "error" } else { "ok" });
+        instruments.requests.add(1, &[operation.clone(), outcome]);
+        instruments
+            .request_duration
+            .record(total.as_secs_f64(), &[operation]);
+    }
+}
+
+/// Count samples a subscription on `key_expr` buffered, and those its full
+/// buffer dropped.
+pub fn record_ingest(key_expr: &str, samples: u64, bytes: u64, dropped: u64) {
+    let Some(telemetry) = current() else {
+        return;
+    };
+    let Some(instruments) = &telemetry.instruments else {
+        return;
+    };
+    let attrs = [KeyValue::new("key_expr", key_expr.to_string())];
+    instruments.samples.add(samples, &attrs);
+    instruments.bytes.add(bytes, &attrs);
+    if dropped > 0 {
+        instruments.dropped.add(dropped, &attrs);
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value};
+
+/// Values available to `{{name}}` placeholders for one render.
+pub struct Context<'a> {
+    pub counter: u64,
+    pub now: DateTime<Utc>,
+    pub uuid: String,
+    /// Caller-supplied values, consulted before the built-ins.
+    pub values: &'a Map<String, Value>,
+}
+
+impl Context<'_> {
+    /// Caller values first, then `timestamp` (RFC 3339), `timestamp_ms`,
+    /// `counter` and `uuid`.
+    fn lookup(&self, name: &str) -> Result<Value, String> {
+        if let Some(value) = self.values.get(name) {
+            return Ok(value.clone());
+        }
+        match name {
+            "timestamp" => Ok(Value::String(
+                self.now.to_rfc3339_opts(SecondsFormat::Micros, true),
+            )),
+            "timestamp_ms" => Ok(Value::from(self.now.timestamp_millis())),
+            "counter" => Ok(Value::from(self.counter)),
+            "uuid" => Ok(Value::String(self.uuid.clone())),
+            other => Err(format!("unknown template placeholder: {other}")),
+        }
+    }
+}
+
+/// Replace placeholders throughout `template`. A string that is exactly one
+/// `{{name}}` becomes the value itself, keeping its JSON type; placeholders
+/// inside longer strings are interpolated as text. Object keys are left as is.
+pub fn render(template: &Value, ctx: &Context) -> Result<Value, String> {
+    match template {
+        Value::String(s) => render_str(s, ctx),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| render(item, ctx))
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), render(v, ctx)?)))
+            .collect::<Result<_, String>>()
+            .map(Value::Object),
+        other => Ok(other.clone()),
+    }
+}
+
+fn render_str(s: &str, ctx: &Context) -> Result<Value, String> {
+    if let Some(name) = whole_placeholder(s) {
+        return ctx.lookup(name);
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let name = rest[start + 2..start + 2 + len].trim();
+        match ctx.lookup(name)? {
+            Value::String(text) => out.push_str(&text),
+            value => out.push_str(&value.to_string()),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    Ok(Value::String(out))
+}
+
+fn whole_placeholder(s: &str) -> Option<&str> {
+    let name = s.strip_prefix("{{")?.strip_suffix("}}")?.trim();
+    (!name.contains("{{") && !name.contains("}}")).then_some(name)
+}
@@ -0,0 +1,194 @@
+use crate::backend::ZenohBackend;
+use crate::decode::DecodeFormat;
+use crate::expr::Expression;
+use crate::jsonpath;
+use crate::key_watch::{passes, payload_value};
+use crate::namespace;
+use crate::notify;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// A key's value moved into another band, or was seen for the first time.
+/// Band `i` lies between threshold `i - 1` and threshold `i`, so with
+/// thresholds `[20, 80]` band 0 is below 20 and band 2 above 80.
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct ThresholdCrossing {
+    /// Per-watch event counter, starting at 0.
+    pub seq: u64,
+    pub key_expr: String,
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+    pub band: usize,
+    /// `None` on the first value seen for the key.
+    pub previous_band: Option<usize>,
+    /// up or down; `None` on the first value.
+    pub direction: Option<&'static str>,
+    /// The last threshold crossed on the way to `band`.
+    pub threshold: Option<f64>,
+}
+
+#[derive(Clone)]
+pub struct ThresholdConfig {
+    /// JSONPath to the numeric field.
+    pub path: String,
+    /// Band boundaries, ascending.
+    pub thresholds: Vec<f64>,
+    /// How far past a threshold a value must go to cross it, in either
+    /// direction, so noise around a boundary does not flap.
+    pub hysteresis: f64,
+    pub decode: Option<DecodeFormat>,
+    /// Only samples this expression matches are checked.
+    pub filter: Option<Expression>,
+    /// Unpolled crossings kept; the oldest is evicted beyond this.
+    pub buffer_size: usize,
+    /// Emit a `threshold_crossed` notification for each crossing.
+    pub notify: bool,
+}
+
+impl ThresholdConfig {
+    /// The band `value` falls in when coming from `band`, or `None` if
+    /// hysteresis keeps it where it is.
+    fn next_band(&self, band: Option<usize>, value: f64) -> Option<usize> {
+        let t = &self.thresholds;
+        let Some(mut next) = band else {
+            return Some(t.iter().take_while(|&&th| value >= th).count());
+        };
+        while next < t.len() && value >= t[next] + self.hysteresis {
+            next += 1;
+        }
+        while next > 0 && value < t[next - 1] - self.hysteresis {
+            next -= 1;
+        }
+        (Some(next) != band).then_some(next)
+    }
+}
+
+/// Current band per key and the crossings not yet polled.
+#[derive(Default)]
+pub struct Crossings {
+    bands: HashMap<String, usize>,
+    pub events: VecDeque<ThresholdCrossing>,
+    pub received: u64,
+    /// Samples without a numeric value at `path`.
+    pub missing: u64,
+    pub crossings: u64,
+    /// Crossings evicted unpolled because the buffer was full.
+    pub dropped: u64,
+}
+
+impl Crossings {
+    pub fn keys(&self) -> usize {
+        self.bands.len()
+    }
+
+    /// Band of every key seen so far.
+    pub fn bands(&self) -> &HashMap<String, usize> {
+        &self.bands
+    }
+
+    fn observe(
+        &mut self,
+        key_expr: String,
+        value: f64,
+        config: &ThresholdConfig,
+    ) -> Option<ThresholdCrossing> {
+        let previous_band = self.bands.get(&key_expr).copied();
+        let band = config.next_band(previous_band, value)?;
+        let (direction, threshold) = match previous_band {
+            Some(prev) if band > prev => (Some("up"), Some(config.thresholds[band - 1])),
+            Some(_) => (Some("down"), Some(config.thresholds[band])),
+            None => (None, None),
+        };
+        let crossing = ThresholdCrossing {
+            seq: self.crossings,
+            key_expr: key_expr.clone(),
+            timestamp: Utc::now(),
+            value,
+            band,
+            previous_band,
+            direction,
+            threshold,
+        };
+        self.crossings += 1;
+        self.bands.insert(key_expr, band);
+        if self.events.len() >= config.buffer_size {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back(crossing.clone());
+        Some(crossing)
+    }
+}
+
+pub struct ThresholdWatch {
+    /// Host-facing key expression, without the session namespace.
+    pub key_expr: String,
+    pub config: ThresholdConfig,
+    pub crossings: Arc<Mutex<Crossings>>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Subscribe to `key_expr` and buffer an event only when the field at
+/// `config.path` moves between the bands set by `config.thresholds`.
+pub async fn start_threshold_watch(
+    session: Arc<dyn ZenohBackend>,
+    watch_id: String,
+    key_expr: String,
+    config: ThresholdConfig,
+    namespace: Option<String>,
+) -> Result<ThresholdWatch, String> {
+    let scoped = namespace::scope(namespace.as_deref(), &key_expr);
+    let mut stream = session.subscribe(&scoped).await?;
+    let crossings: Arc<Mutex<Crossings>> = Arc::default();
+    let (cancel, mut cancel_rx) = watch::channel(false);
+
+    {
+        let (crossings, config) = (crossings.clone(), config.clone());
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    sample = stream.recv() => {
+                        let Some(sample) = sample else { break };
+                        let key = namespace::strip(namespace.as_deref(), &sample.key_expr);
+                        let payload = payload_value(config.decode, &sample.encoding, &sample.payload);
+                        if !passes(config.filter.as_ref(), &key, &sample.encoding, &sample.payload, &payload) {
+                            continue;
+                        }
+                        let value = jsonpath::lookup(&payload, &config.path).and_then(|v| v.as_f64());
+                        let mut crossings = crossings.lock().unwrap();
+                        crossings.received += 1;
+                        // Samples without the field leave the band as it is
+                        let Some(value) = value else {
+                            crossings.missing += 1;
+                            continue;
+                        };
+                        let crossing = crossings.observe(key, value, &config);
+                        if let (true, Some(crossing)) = (config.notify, crossing) {
+                            let mut event = serde_json::to_value(crossing).unwrap();
+                            event["watch_id"] = serde_json::Value::String(watch_id.clone());
+                            notify::notify("threshold_crossed", event);
+                        }
+                    }
+                    _ = cancel_rx.changed() => {
+                        if *cancel_rx.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(ThresholdWatch {
+        key_expr,
+        config,
+        crossings,
+        cancel,
+        created_at: Utc::now(),
+    })
+}
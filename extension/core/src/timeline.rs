@@ -0,0 +1,181 @@
+use crate::recordings::Record;
+use crate::state::{BufferedSample, Subscription};
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use xxhash_rust::xxh3::xxh3_64;
+use zenoh::key_expr::KeyExpr;
+
+/// Recorders and subscriptions each stamp a sample when they receive it.
+/// Copies of one payload on a key stamped this close are one publication.
+const SKEW_MS: i64 = 1000;
+
+/// The keys and time range a timeline covers, both ends inclusive.
+pub struct Window {
+    key_expr: KeyExpr<'static>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl Window {
+    pub fn new(key_expr: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Self, String> {
+        if from > to {
+            return Err("from must not be after to".into());
+        }
+        let key_expr = KeyExpr::try_from(key_expr.to_string())
+            .map_err(|e| format!("invalid key_expr {key_expr}: {e}"))?;
+        Ok(Self { key_expr, from, to })
+    }
+
+    pub fn contains(&self, key: &str, at: DateTime<Utc>) -> bool {
+        at >= self.from
+            && at <= self.to
+            && KeyExpr::try_from(key).is_ok_and(|key| self.key_expr.intersects(&key))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Live,
+    FlightRecorder,
+    Recording,
+}
+
+/// A timeline sample and where it came from: a subscription, flight
+/// recorder or recording file.
+pub struct Entry {
+    pub sample: BufferedSample,
+    pub kind: SourceKind,
+    pub source_id: String,
+}
+
+/// What the live buffers hold, so recorded data fills in around it.
+pub struct Live {
+    /// When each key's live samples start.
+    starts: HashMap<String, DateTime<Utc>>,
+    /// Live sample times by key and payload hash.
+    payloads: HashMap<(String, u64), Vec<DateTime<Utc>>>,
+}
+
+impl Live {
+    /// When the earliest live sample was received.
+    pub fn start(&self) -> Option<DateTime<Utc>> {
+        self.starts.values().min().copied()
+    }
+
+    /// Whether `record` is, or falls among, samples a live buffer holds.
+    pub fn covers(&self, record: &Record) -> bool {
+        let Some(start) = self.starts.get(&record.key_expr) else {
+            return false;
+        };
+        let skew = chrono::Duration::milliseconds(SKEW_MS);
+        let id = (
+            record.key_expr.clone(),
+            xxh3_64(record.payload_b64.as_bytes()),
+        );
+        record.timestamp >= *start
+            || self
+                .payloads
+                .get(&id)
+                .is_some_and(|times| times.iter().any(|t| (*t - record.timestamp).abs() <= skew))
+    }
+}
+
+/// Live samples in `window`, and what they cover. A key is taken from the
+/// subscription holding its oldest sample, so keys two subscriptions share
+/// appear once.
+pub fn live(subscriptions: &HashMap<String, Subscription>, window: &Window) -> (Vec<Entry>, Live) {
+    let in_window = |s: &&BufferedSample| !s.is_gap() && window.contains(&s.key_expr, s.timestamp);
+    let mut starts: HashMap<String, (DateTime<Utc>, &str)> = HashMap::new();
+    for (id, sub) in subscriptions {
+        for sample in sub.buffer.iter().filter(in_window) {
+            let start = starts
+                .entry(sample.key_expr.clone())
+                .or_insert((sample.timestamp, id));
+            if sample.timestamp < start.0 {
+                *start = (sample.timestamp, id);
+            }
+        }
+    }
+    let entries = subscriptions
+        .iter()
+        .flat_map(|(id, sub)| {
+            let starts = &starts;
+            sub.buffer
+                .iter()
+                .filter(in_window)
+                .filter(move |s| {
+                    starts
+                        .get(&s.key_expr)
+                        .is_some_and(|(_, owner)| owner == id)
+                })
+                .map(|sample| Entry {
+                    sample: sample.clone(),
+                    kind: SourceKind::Live,
+                    source_id: id.clone(),
+                })
+        })
+        .collect::<Vec<Entry>>();
+    let mut payloads: HashMap<(String, u64), Vec<DateTime<Utc>>> = HashMap::new();
+    for entry in &entries {
+        let b64 = base64::engine::general_purpose::STANDARD.encode(&entry.sample.payload.0);
+        payloads
+            .entry((entry.sample.key_expr.clone(), xxh3_64(b64.as_bytes())))
+            .or_default()
+            .push(entry.sample.timestamp);
+    }
+    let starts = starts
+        .into_iter()
+        .map(|(key, (start, _))| (key, start))
+        .collect();
+    (entries, Live { starts, payloads })
+}
+
+/// Records from flight recorders and recording files as entries. A record
+/// found in several, as when a flight recorder was dumped to a recording,
+/// is kept once.
+pub fn recorded(sources: Vec<(SourceKind, String, Vec<Record>)>) -> Vec<Entry> {
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    for (kind, source_id, records) in sources {
+        for record in records {
+            let id = (
+                record.timestamp,
+                record.key_expr.clone(),
+                xxh3_64(record.payload_b64.as_bytes()),
+            );
+            if seen.insert(id) {
+                entries.push(Entry {
+                    sample: record.into_sample(0),
+                    kind,
+                    source_id: source_id.clone(),
+                });
+            }
+        }
+    }
+    entries
+}
+
+/// All entries in time order, keeping the newest `limit`. True when older
+/// ones were left out.
+pub fn merge(mut entries: Vec<Entry>, limit: usize) -> (Vec<Entry>, bool) {
+    entries.sort_by_key(|e| e.sample.timestamp);
+    let truncated = entries.len() > limit;
+    entries.drain(..entries.len().saturating_sub(limit));
+    (entries, truncated)
+}
+
+/// How many entries each source contributed.
+pub fn sources(entries: &[Entry]) -> Vec<(SourceKind, String, usize)> {
+    let mut counts: BTreeMap<(SourceKind, &str), usize> = BTreeMap::new();
+    for entry in entries {
+        *counts.entry((entry.kind, &entry.source_id)).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .map(|((kind, id), samples)| (kind, id.to_string(), samples))
+        .collect()
+}
@@ -0,0 +1,217 @@
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// Units a duration may use, longest suffix first so `ms` is not read as `m`.
+const UNITS: [(&str, i64); 6] = [
+    ("ms", 1),
+    ("s", 1000),
+    ("m", 60_000),
+    ("h", 3_600_000),
+    ("d", 86_400_000),
+    ("w", 604_800_000),
+];
+
+/// A duration like `500ms`, `90s`, `5m` or `1h30m`.
+pub fn parse_duration(s: &str) -> Result<TimeDelta, String> {
+    let invalid = || format!("invalid duration {s}: expected e.g. 500ms, 90s, 5m or 1h30m");
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    let mut ms: i64 = 0;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let count: i64 = rest[..digits].parse().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+        let (unit, scale) = UNITS
+            .iter()
+            .find(|(unit, _)| {
+                rest.strip_prefix(unit)
+                    .is_some_and(|after| !after.starts_with(|c: char| c.is_ascii_alphabetic()))
+            })
+            .ok_or_else(invalid)?;
+        rest = &rest[unit.len()..];
+        ms = count
+            .checked_mul(*scale)
+            .and_then(|part| ms.checked_add(part))
+            .ok_or_else(invalid)?;
+    }
+    TimeDelta::try_milliseconds(ms).ok_or_else(invalid)
+}
+
+/// An instant given as RFC 3339, `now`, or a duration before now like `-5m`.
+pub fn parse_instant(s: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let s = s.trim();
+    if s == "now" {
+        return Ok(now);
+    }
+    if let Some(ago) = s.strip_prefix('-') {
+        return now
+            .checked_sub_signed(parse_duration(ago)?)
+            .ok_or_else(|| format!("invalid time {s}: too far in the past"));
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|e| format!("invalid time {s}: expected RFC 3339, now or e.g. -5m ({e})"))
+}
+
+/// A time range; an open end reaches as far as the storage holds.
+pub struct Range {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl Range {
+    /// The range `from`, `to` and `duration` describe. A duration alone
+    /// reaches back from now; with one end it fixes the other.
+    pub fn resolve(
+        from: Option<&str>,
+        to: Option<&str>,
+        duration: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<Self, String> {
+        let from = from.map(|s| parse_instant(s, now)).transpose()?;
+        let to = to.map(|s| parse_instant(s, now)).transpose()?;
+        let duration = duration.map(parse_duration).transpose()?;
+        let out_of_range = || "duration reaches out of the supported time range".to_string();
+        let (from, to) = match (from, to, duration) {
+            (Some(_), Some(_), Some(_)) => {
+                return Err("pass at most two of from, to and duration".into())
+            }
+            (from, to, None) => (from, to),
+            (Some(from), None, Some(d)) => (
+                Some(from),
+                Some(from.checked_add_signed(d).ok_or_else(out_of_range)?),
+            ),
+            (None, Some(to), Some(d)) => (
+                Some(to.checked_sub_signed(d).ok_or_else(out_of_range)?),
+                Some(to),
+            ),
+            (None, None, Some(d)) => (
+                Some(now.checked_sub_signed(d).ok_or_else(out_of_range)?),
+                None,
+            ),
+        };
+        if from.is_none() && to.is_none() {
+            return Err("pass from, to or duration".into());
+        }
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err("from must not be after to".into());
+            }
+        }
+        Ok(Self { from, to })
+    }
+
+    /// `key_expr` with the `_time` parameter storages filter on.
+    pub fn selector(&self, key_expr: &str) -> String {
+        let at = |t: Option<DateTime<Utc>>| {
+            t.map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+                .unwrap_or_default()
+        };
+        format!("{key_expr}?_time=[{}..{}]", at(self.from), at(self.to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn durations_add_up_their_parts() {
+        assert_eq!(parse_duration("1h30m"), Ok(TimeDelta::minutes(90)));
+        assert_eq!(parse_duration("1d2h"), Ok(TimeDelta::hours(26)));
+        assert_eq!(parse_duration(" 90s "), Ok(TimeDelta::seconds(90)));
+        assert_eq!(parse_duration("2w"), Ok(TimeDelta::weeks(2)));
+    }
+
+    #[test]
+    fn ms_is_not_read_as_minutes() {
+        assert_eq!(parse_duration("500ms"), Ok(TimeDelta::milliseconds(500)));
+        assert_eq!(parse_duration("5m"), Ok(TimeDelta::minutes(5)));
+        assert_eq!(
+            parse_duration("1m500ms"),
+            Ok(TimeDelta::milliseconds(60_500))
+        );
+    }
+
+    #[test]
+    fn malformed_durations_are_rejected() {
+        for s in ["", "5", "m", "5min", "5m3", "1.5h", "-5m", "5 m"] {
+            let e = parse_duration(s).unwrap_err();
+            assert_eq!(
+                e,
+                format!("invalid duration {s}: expected e.g. 500ms, 90s, 5m or 1h30m")
+            );
+        }
+    }
+
+    #[test]
+    fn overflowing_durations_are_rejected() {
+        assert!(parse_duration("99999999999999999999s").is_err());
+        assert!(parse_duration("9999999999999999w").is_err());
+        assert!(parse_duration("9223372036854775807ms1ms").is_err());
+        // Fits a duration but not a date
+        let now = at("2024-05-01T12:00:00Z");
+        assert!(parse_instant("-9000000000000000000ms", now).is_err());
+        assert!(Range::resolve(None, None, Some("9000000000000000000ms"), now).is_err());
+    }
+
+    #[test]
+    fn instants_relative_to_now() {
+        let now = at("2024-05-01T12:00:00Z");
+        assert_eq!(parse_instant("now", now), Ok(now));
+        assert_eq!(parse_instant("-5m", now), Ok(at("2024-05-01T11:55:00Z")));
+        assert_eq!(parse_instant("-1h30m", now), Ok(at("2024-05-01T10:30:00Z")));
+        assert!(parse_instant("-5", now).is_err());
+    }
+
+    #[test]
+    fn rfc3339_instants_are_converted_to_utc() {
+        let now = at("2024-05-01T12:00:00Z");
+        assert_eq!(
+            parse_instant("2024-05-01T14:00:00+02:00", now),
+            Ok(at("2024-05-01T12:00:00Z"))
+        );
+        let e = parse_instant("2024-05-01", now).unwrap_err();
+        assert!(
+            e.starts_with("invalid time 2024-05-01: expected RFC 3339"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn ranges_from_two_of_three() {
+        let now = at("2024-05-01T12:00:00Z");
+        let range = Range::resolve(None, None, Some("10m"), now).unwrap();
+        assert_eq!(
+            (range.from, range.to),
+            (Some(at("2024-05-01T11:50:00Z")), None)
+        );
+        let range = Range::resolve(Some("-1h"), None, Some("30m"), now).unwrap();
+        assert_eq!(range.to, Some(at("2024-05-01T11:30:00Z")));
+        assert_eq!(
+            range.selector("a/**"),
+            "a/**?_time=[2024-05-01T11:00:00.000Z..2024-05-01T11:30:00.000Z]"
+        );
+
+        let e = |from, to, duration| Range::resolve(from, to, duration, now).err();
+        assert_eq!(
+            e(Some("-1h"), Some("now"), Some("5m")).as_deref(),
+            Some("pass at most two of from, to and duration")
+        );
+        assert_eq!(
+            e(None, None, None).as_deref(),
+            Some("pass from, to or duration")
+        );
+        assert_eq!(
+            e(Some("now"), Some("-1h"), None).as_deref(),
+            Some("from must not be after to")
+        );
+    }
+}
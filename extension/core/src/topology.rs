@@ -0,0 +1,406 @@
+use crate::backend::ZenohBackend;
+use crate::notify;
+use crate::router_health::{self, Rates};
+use crate::versions;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::time::MissedTickBehavior;
+
+#[derive(Serialize, JsonSchema)]
+pub struct TopologyNode {
+    pub zid: String,
+    /// `router`, `peer` or `client`, when some source said.
+    pub whatami: Option<String>,
+    pub locators: Vec<String>,
+    /// Zenoh version, for this session and nodes answering on their admin
+    /// space.
+    pub version: Option<String>,
+    /// Capabilities named like Zenoh's cargo features; see `session_info`
+    /// with `versions`.
+    pub features: Vec<String>,
+    /// This extension's own session.
+    pub is_self: bool,
+    /// Where the node was seen: `self`, `session`, `scout` or `admin`.
+    pub sources: Vec<&'static str>,
+}
+
+/// Nodes connected by a transport, reported from either end. `source` and
+/// `target` are ordered by zid, so each pair appears once.
+#[derive(Serialize, JsonSchema)]
+pub struct TopologyEdge {
+    pub source: String,
+    pub target: String,
+    /// Locator pairs of the links, as `src -> dst` seen from `source`.
+    pub links: Vec<String>,
+    /// Zids of the nodes that reported the transport.
+    pub reported_by: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Topology {
+    nodes: BTreeMap<String, TopologyNode>,
+    edges: BTreeMap<(String, String), TopologyEdge>,
+}
+
+impl Topology {
+    fn node(&mut self, zid: &str, source: &'static str) -> &mut TopologyNode {
+        let node = self
+            .nodes
+            .entry(zid.to_string())
+            .or_insert_with(|| TopologyNode {
+                zid: zid.to_string(),
+                whatami: None,
+                locators: Vec::new(),
+                version: None,
+                features: Vec::new(),
+                is_self: false,
+                sources: Vec::new(),
+            });
+        if !node.sources.contains(&source) {
+            node.sources.push(source);
+        }
+        node
+    }
+
+    /// Record a transport `reporter` holds to `peer`, with the local and
+    /// remote locator of each link.
+    fn edge(&mut self, reporter: &str, peer: &str, links: Vec<(String, String)>) {
+        let forward = reporter < peer;
+        let key = if forward {
+            (reporter.to_string(), peer.to_string())
+        } else {
+            (peer.to_string(), reporter.to_string())
+        };
+        let edge = self
+            .edges
+            .entry(key.clone())
+            .or_insert_with(|| TopologyEdge {
+                source: key.0,
+                target: key.1,
+                links: Vec::new(),
+                reported_by: Vec::new(),
+            });
+        if !edge.reported_by.iter().any(|r| r == reporter) {
+            edge.reported_by.push(reporter.to_string());
+        }
+        for (local, remote) in links {
+            let link = if forward {
+                format!("{local} -> {remote}")
+            } else {
+                format!("{remote} -> {local}")
+            };
+            if !edge.links.contains(&link) {
+                edge.links.push(link);
+            }
+        }
+    }
+
+    pub fn into_parts(self) -> (Vec<TopologyNode>, Vec<TopologyEdge>) {
+        (
+            self.nodes.into_values().collect(),
+            self.edges.into_values().collect(),
+        )
+    }
+}
+
+/// Map the network from this session's transports, nodes answering
+/// scouting for `scout` (skipped when zero), and the sessions every node
+/// lists in its admin space. Returns the errors nodes replied with.
+pub async fn collect(
+    session: &dyn ZenohBackend,
+    scout: Duration,
+    timeout: Duration,
+) -> Result<(Topology, Vec<String>), String> {
+    let mut topology = Topology::default();
+    let mut errors = Vec::new();
+
+    let local = versions::local(session);
+    let zid = local.zid.clone();
+    let me = topology.node(&zid, "self");
+    me.is_self = true;
+    me.whatami = Some(local.whatami);
+    me.version = local.version;
+    me.features = local.features;
+
+    for transport in session.transports().await {
+        topology.node(&transport.zid, "session").whatami = Some(transport.whatami);
+        topology.edge(&zid, &transport.zid, transport.links);
+    }
+
+    if !scout.is_zero() {
+        for hello in session.scout(scout).await? {
+            let node = topology.node(&hello.zid, "scout");
+            node.whatami = Some(hello.whatami);
+            merge(&mut node.locators, hello.locators);
+        }
+    }
+
+    let nodes = versions::admin_nodes(session, timeout, &mut errors).await?;
+    let mut node_versions = versions::node_versions(&nodes);
+    for (reporter, admin) in nodes.into_iter().filter(|(z, _)| *z != zid) {
+        let info = admin.info;
+        let node = topology.node(&reporter, "admin");
+        node.whatami = Some(admin.whatami);
+        if let Some(version) = node_versions.remove(&reporter) {
+            node.version = version.version;
+            node.features = version.features;
+        }
+        merge(&mut node.locators, router_health::locators(&info));
+        for peer in router_health::sessions(&reporter, &info, &Rates::new()) {
+            let node = topology.node(&peer.peer, "admin");
+            node.whatami = node.whatami.take().or(peer.whatami);
+            let links = peer
+                .links
+                .iter()
+                .map(|l| {
+                    let end = |locator: &Option<String>| locator.clone().unwrap_or("?".into());
+                    (end(&l.src), end(&l.dst))
+                })
+                .collect();
+            topology.edge(&reporter, &peer.peer, links);
+        }
+    }
+    Ok((topology, errors))
+}
+
+fn merge(into: &mut Vec<String>, locators: Vec<String>) {
+    for locator in locators {
+        if !into.contains(&locator) {
+            into.push(locator);
+        }
+    }
+}
+
+/// A node or edge that appeared, disappeared or changed, once the new state
+/// held for the watch's debounce.
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct TopologyChange {
+    /// Per-watch event counter, starting at 0.
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    /// `node_added`, `node_removed`, `node_changed`, `link_added`,
+    /// `link_removed` or `link_changed`.
+    pub kind: &'static str,
+    /// The node, for node changes.
+    pub zid: Option<String>,
+    /// The edge's ends, for link changes.
+    pub source: Option<String>,
+    pub target: Option<String>,
+    /// What is compared: `whatami` for a node, `links` for an edge.
+    pub previous: Option<Value>,
+    pub current: Option<Value>,
+}
+
+#[derive(Clone)]
+pub struct TopologyWatchConfig {
+    pub interval: Duration,
+    /// How long a new state must hold before it is reported, so a node
+    /// missing from one refresh does not flap.
+    pub debounce: Duration,
+    /// Scouting time per refresh; zero skips scouting.
+    pub scout: Duration,
+    pub timeout: Duration,
+    /// Unpolled changes kept; the oldest is evicted beyond this.
+    pub buffer_size: usize,
+    /// Emit a `topology_changed` notification for each change.
+    pub notify: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Subject {
+    Node(String),
+    Edge(String, String),
+}
+
+/// The reported topology, changes waiting out the debounce, and the
+/// changes not yet polled.
+#[derive(Default)]
+pub struct TopologyChanges {
+    reported: BTreeMap<Subject, Value>,
+    /// State differing from the reported one, and when it was first seen.
+    pending: BTreeMap<Subject, (Option<Value>, Instant)>,
+    pub events: VecDeque<TopologyChange>,
+    pub refreshes: u64,
+    pub failures: u64,
+    pub last_refresh: Option<DateTime<Utc>>,
+    /// The last refresh's failure or router error replies.
+    pub last_error: Option<String>,
+    pub changes: u64,
+    /// Changes evicted unpolled because the buffer was full.
+    pub dropped: u64,
+}
+
+impl TopologyChanges {
+    pub fn nodes(&self) -> usize {
+        self.reported
+            .keys()
+            .filter(|s| matches!(s, Subject::Node(_)))
+            .count()
+    }
+
+    pub fn edges(&self) -> usize {
+        self.reported.len() - self.nodes()
+    }
+
+    /// Compare a refresh taken at `at` with the reported topology. The
+    /// first refresh is the baseline and reports nothing.
+    fn observe(
+        &mut self,
+        topology: Topology,
+        at: Instant,
+        config: &TopologyWatchConfig,
+    ) -> Vec<TopologyChange> {
+        let mut current: BTreeMap<Subject, Value> = BTreeMap::new();
+        for (zid, node) in topology.nodes {
+            current.insert(Subject::Node(zid), json!({ "whatami": node.whatami }));
+        }
+        for ((source, target), mut edge) in topology.edges {
+            edge.links.sort();
+            current.insert(
+                Subject::Edge(source, target),
+                json!({ "links": edge.links }),
+            );
+        }
+        self.refreshes += 1;
+        self.last_refresh = Some(Utc::now());
+        if self.refreshes == 1 {
+            self.reported = current;
+            return Vec::new();
+        }
+
+        let mut subjects: Vec<Subject> = self.reported.keys().cloned().collect();
+        subjects.extend(current.keys().cloned());
+        subjects.sort();
+        subjects.dedup();
+        let mut changes = Vec::new();
+        for subject in subjects {
+            let (previous, now) = (self.reported.get(&subject), current.get(&subject));
+            if previous == now {
+                self.pending.remove(&subject);
+                continue;
+            }
+            let since = match self.pending.get(&subject) {
+                Some((candidate, since)) if candidate.as_ref() == now => *since,
+                _ => {
+                    self.pending.insert(subject.clone(), (now.cloned(), at));
+                    at
+                }
+            };
+            if at.duration_since(since) < config.debounce {
+                continue;
+            }
+            self.pending.remove(&subject);
+            let change = self.change(&subject, previous.cloned(), now.cloned());
+            match now {
+                Some(now) => self.reported.insert(subject, now.clone()),
+                None => self.reported.remove(&subject),
+            };
+            if self.events.len() >= config.buffer_size {
+                self.events.pop_front();
+                self.dropped += 1;
+            }
+            self.events.push_back(change.clone());
+            changes.push(change);
+        }
+        changes
+    }
+
+    fn change(
+        &mut self,
+        subject: &Subject,
+        previous: Option<Value>,
+        current: Option<Value>,
+    ) -> TopologyChange {
+        let kind = match (subject, &previous, &current) {
+            (Subject::Node(_), None, _) => "node_added",
+            (Subject::Node(_), _, None) => "node_removed",
+            (Subject::Node(_), _, _) => "node_changed",
+            (Subject::Edge(..), None, _) => "link_added",
+            (Subject::Edge(..), _, None) => "link_removed",
+            (Subject::Edge(..), _, _) => "link_changed",
+        };
+        let (zid, source, target) = match subject {
+            Subject::Node(zid) => (Some(zid.clone()), None, None),
+            Subject::Edge(source, target) => (None, Some(source.clone()), Some(target.clone())),
+        };
+        let change = TopologyChange {
+            seq: self.changes,
+            timestamp: Utc::now(),
+            kind,
+            zid,
+            source,
+            target,
+            previous,
+            current,
+        };
+        self.changes += 1;
+        change
+    }
+}
+
+pub struct TopologyWatch {
+    pub config: TopologyWatchConfig,
+    pub changes: Arc<Mutex<TopologyChanges>>,
+    pub cancel: watch::Sender<bool>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Refresh the topology every `config.interval` and buffer a change for each
+/// node or edge whose state differs from the reported one for at least
+/// `config.debounce`.
+pub fn start_topology_watch(
+    session: Arc<dyn ZenohBackend>,
+    watch_id: String,
+    config: TopologyWatchConfig,
+) -> TopologyWatch {
+    let changes: Arc<Mutex<TopologyChanges>> = Arc::default();
+    let (cancel, mut cancel_rx) = watch::channel(false);
+
+    {
+        let (changes, config) = (changes.clone(), config.clone());
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(config.interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                let at = tokio::select! {
+                    at = ticker.tick() => at.into_std(),
+                    _ = cancel_rx.wait_for(|cancelled| *cancelled) => break,
+                };
+                let refreshed = tokio::select! {
+                    refreshed = collect(session.as_ref(), config.scout, config.timeout) => refreshed,
+                    _ = cancel_rx.wait_for(|cancelled| *cancelled) => break,
+                };
+                let mut changes = changes.lock().unwrap();
+                let (topology, errors) = match refreshed {
+                    Ok(refreshed) => refreshed,
+                    Err(e) => {
+                        changes.failures += 1;
+                        changes.last_error = Some(e);
+                        continue;
+                    }
+                };
+                changes.last_error = (!errors.is_empty()).then(|| errors.join("; "));
+                for change in changes.observe(topology, at, &config) {
+                    if config.notify {
+                        let mut event = serde_json::to_value(change).unwrap();
+                        event["watch_id"] = Value::String(watch_id.clone());
+                        notify::notify("topology_changed", event);
+                    }
+                }
+            }
+        });
+    }
+
+    TopologyWatch {
+        config,
+        changes,
+        cancel,
+        created_at: Utc::now(),
+    }
+}
@@ -0,0 +1,211 @@
+use crate::backend::{
+    Declaration, GetOptions, Hello, QueryHandler, Reply, SampleStream, SubscribeOptions,
+    TransportInfo, ZenohBackend,
+};
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::cell::Cell;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+tokio::task_local! {
+    static TRACE: Trace;
+}
+
+/// Time spent so far by the traced request, in the task that runs it.
+/// Background tasks an operation spawns are not counted.
+#[derive(Default)]
+struct Trace {
+    lock_wait: Cell<Duration>,
+    locks: Cell<u64>,
+    zenoh: Cell<Duration>,
+    zenoh_calls: Cell<u64>,
+    serialization: Cell<Duration>,
+}
+
+/// Where a request's time went inside the extension.
+#[derive(Serialize, JsonSchema)]
+pub struct Timing {
+    /// From reading the request to starting the operation.
+    pub queue_wait_us: u64,
+    /// Waiting to acquire the extension state lock.
+    pub lock_wait_us: u64,
+    pub lock_acquisitions: u64,
+    /// Inside Zenoh session calls: puts, queries, declarations.
+    pub zenoh_us: u64,
+    pub zenoh_calls: u64,
+    /// Parsing the input and converting the result to JSON.
+    pub serialization_us: u64,
+    /// From reading the request until the response was built.
+    pub total_us: u64,
+}
+
+/// Run `request` with tracing, returning its output and timing. `received`
+/// is when the request was read, for the queue wait.
+pub async fn traced<T>(received: Instant, request: impl Future<Output = T>) -> (T, Timing) {
+    let started = Instant::now();
+    let (output, trace) = TRACE
+        .scope(Trace::default(), async {
+            let output = request.await;
+            let trace = TRACE.with(|t| Trace {
+                lock_wait: t.lock_wait.clone(),
+                locks: t.locks.clone(),
+                zenoh: t.zenoh.clone(),
+                zenoh_calls: t.zenoh_calls.clone(),
+                serialization: t.serialization.clone(),
+            });
+            (output, trace)
+        })
+        .await;
+    let us = |d: Duration| d.as_micros() as u64;
+    let timing = Timing {
+        queue_wait_us: us(started.saturating_duration_since(received)),
+        lock_wait_us: us(trace.lock_wait.get()),
+        lock_acquisitions: trace.locks.get(),
+        zenoh_us: us(trace.zenoh.get()),
+        zenoh_calls: trace.zenoh_calls.get(),
+        serialization_us: us(trace.serialization.get()),
+        total_us: us(received.elapsed()),
+    };
+    (output, timing)
+}
+
+fn add(field: impl Fn(&Trace) -> &Cell<Duration>, since: Instant) {
+    let elapsed = since.elapsed();
+    let _ = TRACE.try_with(|t| field(t).set(field(t).get() + elapsed));
+}
+
+/// Count a state lock acquired after waiting since `since`.
+pub fn lock_acquired(since: Instant) {
+    add(|t| &t.lock_wait, since);
+    let _ = TRACE.try_with(|t| t.locks.set(t.locks.get() + 1));
+}
+
+/// Count serialization work started at `since`.
+pub fn serialized(since: Instant) {
+    add(|t| &t.serialization, since);
+}
+
+async fn zenoh_call<T>(call: impl Future<Output = T>) -> T {
+    let since = Instant::now();
+    let output = call.await;
+    add(|t| &t.zenoh, since);
+    let _ = TRACE.try_with(|t| t.zenoh_calls.set(t.zenoh_calls.get() + 1));
+    output
+}
+
+/// A backend that counts the time each request spends in Zenoh calls.
+/// Outside a traced request it only forwards.
+pub struct TracedBackend(pub Arc<dyn ZenohBackend>);
+
+#[async_trait]
+impl ZenohBackend for TracedBackend {
+    fn kind(&self) -> &'static str {
+        self.0.kind()
+    }
+
+    fn attached(&self) -> bool {
+        self.0.attached()
+    }
+
+    fn set_express(&self, express: bool) {
+        self.0.set_express(express)
+    }
+
+    fn zid(&self) -> String {
+        self.0.zid()
+    }
+
+    async fn peers(&self) -> Vec<String> {
+        zenoh_call(self.0.peers()).await
+    }
+
+    async fn routers(&self) -> Vec<String> {
+        zenoh_call(self.0.routers()).await
+    }
+
+    async fn subscribe_with(
+        &self,
+        key_expr: &str,
+        options: SubscribeOptions,
+    ) -> Result<Box<dyn SampleStream>, String> {
+        zenoh_call(self.0.subscribe_with(key_expr, options)).await
+    }
+
+    async fn put(&self, key_expr: &str, payload: Vec<u8>, encoding: &str) -> Result<(), String> {
+        zenoh_call(self.0.put(key_expr, payload, encoding)).await
+    }
+
+    async fn delete(&self, key_expr: &str) -> Result<(), String> {
+        zenoh_call(self.0.delete(key_expr)).await
+    }
+
+    async fn declare_queryable(
+        &self,
+        key_expr: &str,
+        handler: QueryHandler,
+    ) -> Result<Declaration, String> {
+        zenoh_call(self.0.declare_queryable(key_expr, handler)).await
+    }
+
+    async fn get(
+        &self,
+        key_expr: &str,
+        options: GetOptions,
+        timeout: Duration,
+    ) -> Result<Vec<Reply>, String> {
+        zenoh_call(self.0.get(key_expr, options, timeout)).await
+    }
+
+    async fn get_each(
+        &self,
+        key_expr: &str,
+        options: GetOptions,
+        timeout: Duration,
+        on_reply: &mut (dyn FnMut(Reply) + Send),
+    ) -> Result<(), String> {
+        zenoh_call(self.0.get_each(key_expr, options, timeout, on_reply)).await
+    }
+
+    async fn liveliness_get(
+        &self,
+        key_expr: &str,
+        timeout: Duration,
+    ) -> Result<Vec<String>, String> {
+        zenoh_call(self.0.liveliness_get(key_expr, timeout)).await
+    }
+
+    fn config_json(&self) -> String {
+        self.0.config_json()
+    }
+
+    fn config_insert(&self, key: &str, value: &str) -> Result<(), String> {
+        self.0.config_insert(key, value)
+    }
+
+    fn connect_endpoints(&self) -> Vec<String> {
+        self.0.connect_endpoints()
+    }
+
+    async fn connected_locators(&self) -> Vec<String> {
+        zenoh_call(self.0.connected_locators()).await
+    }
+
+    async fn transports(&self) -> Vec<TransportInfo> {
+        zenoh_call(self.0.transports()).await
+    }
+
+    async fn scout(&self, timeout: Duration) -> Result<Vec<Hello>, String> {
+        zenoh_call(self.0.scout(timeout)).await
+    }
+
+    async fn set_connect_endpoints(&self, endpoints: Vec<String>) -> Result<(), String> {
+        zenoh_call(self.0.set_connect_endpoints(endpoints)).await
+    }
+
+    async fn close(&self) {
+        self.0.close().await
+    }
+}
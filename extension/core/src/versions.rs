@@ -0,0 +1,213 @@
+use crate::backend::{GetOptions, ZenohBackend};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+/// What a node reports at its `@/<zid>/<whatami>` admin space root.
+pub struct AdminNode {
+    pub whatami: String,
+    pub info: Value,
+}
+
+/// Version and capabilities of one node. Features are named like Zenoh's
+/// cargo features so they compare with this session's build.
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct NodeVersion {
+    pub zid: String,
+    pub whatami: String,
+    /// Full version string, e.g. `v1.10.1-<commit> built with rustc ...`.
+    pub version: Option<String>,
+    /// `major.minor.patch` from `version`.
+    pub release: Option<String>,
+    /// `transport_<protocol>` per locator protocol, `shared-memory` when a
+    /// transport to the node negotiated it, and `stats` when it reports
+    /// transport statistics.
+    pub features: Vec<String>,
+    /// Plugins the node runs, for routers.
+    pub plugins: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct VersionReport {
+    /// This session, from the Zenoh library it was built with.
+    pub local: NodeVersion,
+    /// Nodes answering on their admin space, by zid.
+    pub nodes: Vec<NodeVersion>,
+    /// More than one release among the nodes and this session.
+    pub mixed_versions: bool,
+    /// Nodes on another major or minor release than this session.
+    pub warnings: Vec<String>,
+    /// Error replies from the admin space query.
+    pub errors: Vec<String>,
+}
+
+/// Admin space roots of every node that answers, by zid. Routers answer by
+/// default; peers and clients only with `adminspace.enabled`. `_stats` asks
+/// nodes built with transport stats to include them.
+pub async fn admin_nodes(
+    session: &dyn ZenohBackend,
+    timeout: Duration,
+    errors: &mut Vec<String>,
+) -> Result<BTreeMap<String, AdminNode>, String> {
+    let replies = session
+        .get("@/*/*?_stats=true", GetOptions::default(), timeout)
+        .await?;
+    let mut nodes = BTreeMap::new();
+    for reply in replies {
+        let sample = match reply.result {
+            Ok(sample) => sample,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        let Some((zid, whatami)) = sample
+            .key_expr
+            .strip_prefix("@/")
+            .and_then(|k| k.split_once('/'))
+        else {
+            continue;
+        };
+        if !matches!(whatami, "router" | "peer" | "client") {
+            continue;
+        }
+        let Ok(info) = serde_json::from_slice::<Value>(&sample.payload) else {
+            continue;
+        };
+        nodes.insert(
+            zid.to_string(),
+            AdminNode {
+                whatami: whatami.to_string(),
+                info,
+            },
+        );
+    }
+    Ok(nodes)
+}
+
+/// Versions of `nodes`. Shared memory is negotiated per transport, so a
+/// transport using it marks both of its ends.
+pub fn node_versions(nodes: &BTreeMap<String, AdminNode>) -> BTreeMap<String, NodeVersion> {
+    let mut shm = BTreeSet::new();
+    for (zid, node) in nodes {
+        for session in node.info["sessions"].as_array().into_iter().flatten() {
+            if session["shm"].as_bool() == Some(true) {
+                shm.insert(zid.clone());
+                shm.extend(session["peer"].as_str().map(String::from));
+            }
+        }
+    }
+    nodes
+        .iter()
+        .map(|(zid, node)| {
+            let info = &node.info;
+            let mut features: BTreeSet<String> = info["locators"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|l| transport_feature(l.as_str()?))
+                .collect();
+            if shm.contains(zid) {
+                features.insert("shared-memory".into());
+            }
+            if info.get("stats").is_some() {
+                features.insert("stats".into());
+            }
+            let version = info["version"].as_str().map(String::from);
+            let plugins = info["plugins"]
+                .as_object()
+                .map(|p| p.keys().cloned().collect())
+                .unwrap_or_default();
+            let version = NodeVersion {
+                zid: zid.clone(),
+                whatami: node.whatami.clone(),
+                release: version.as_deref().and_then(release),
+                version,
+                features: features.into_iter().collect(),
+                plugins,
+            };
+            (zid.clone(), version)
+        })
+        .collect()
+}
+
+/// This session's version, from the Zenoh library linked in.
+pub fn local(session: &dyn ZenohBackend) -> NodeVersion {
+    let config: Value = serde_json::from_str(&session.config_json()).unwrap_or_default();
+    NodeVersion {
+        zid: session.zid(),
+        whatami: config["mode"].as_str().unwrap_or("peer").to_string(),
+        version: Some(zenoh::GIT_VERSION.to_string()),
+        release: release(zenoh::GIT_VERSION),
+        features: zenoh::FEATURES
+            .split_whitespace()
+            .map(|f| f.strip_prefix("zenoh/").unwrap_or(f).to_string())
+            .filter(|f| f != "default")
+            .collect(),
+        plugins: Vec::new(),
+    }
+}
+
+/// Versions of this session and every node answering on the admin space.
+pub async fn report(
+    session: &dyn ZenohBackend,
+    timeout: Duration,
+) -> Result<VersionReport, String> {
+    let mut errors = Vec::new();
+    let local = local(session);
+    let nodes: Vec<NodeVersion> = node_versions(&admin_nodes(session, timeout, &mut errors).await?)
+        .into_values()
+        .filter(|n| n.zid != local.zid)
+        .collect();
+
+    let releases: BTreeSet<&str> = nodes
+        .iter()
+        .chain([&local])
+        .filter_map(|n| n.release.as_deref())
+        .collect();
+    let minor = |release: &str| release.rsplit_once('.').map(|(minor, _)| minor.to_string());
+    let local_minor = local.release.as_deref().and_then(minor);
+    let warnings = nodes
+        .iter()
+        .filter_map(|n| {
+            let release = n.release.as_deref()?;
+            (minor(release) != local_minor).then(|| {
+                format!(
+                    "{} {} runs {release}, this session {}",
+                    n.whatami,
+                    n.zid,
+                    local.release.as_deref().unwrap_or("unknown")
+                )
+            })
+        })
+        .collect();
+    Ok(VersionReport {
+        mixed_versions: releases.len() > 1,
+        local,
+        nodes,
+        warnings,
+        errors,
+    })
+}
+
+/// `major.minor.patch` at the start of a version string such as
+/// `v1.10.1-<commit> built with ...`.
+fn release(version: &str) -> Option<String> {
+    let release = version
+        .trim_start_matches('v')
+        .split(['-', ' ', '+'])
+        .next()?;
+    let parts: Vec<&str> = release.split('.').collect();
+    let numeric = parts
+        .iter()
+        .all(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()));
+    (parts.len() == 3 && numeric).then(|| release.to_string())
+}
+
+/// Zenoh's cargo feature for a locator's protocol, e.g. `transport_tcp`.
+fn transport_feature(locator: &str) -> Option<String> {
+    let (protocol, _) = locator.split_once('/')?;
+    Some(format!("transport_{protocol}"))
+}
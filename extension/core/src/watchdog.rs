@@ -0,0 +1,138 @@
+use crate::notify;
+use crate::state::StateLock;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// How often supervised tasks beat while idle.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// A task silent for this long is assumed hung and restarted.
+const STALE_AFTER: Duration = Duration::from_secs(30);
+const BACKOFF_MIN: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// An attempt that ran this long resets the backoff.
+const HEALTHY_RUN: Duration = Duration::from_secs(60);
+
+/// Heartbeat and restart bookkeeping for one supervised task.
+#[derive(Default)]
+pub struct TaskHealth {
+    /// Unix milliseconds of the last heartbeat; 0 before the first.
+    heartbeat_ms: AtomicI64,
+    restarts: AtomicU64,
+    last_restart: Mutex<Option<(DateTime<Utc>, String)>>,
+}
+
+impl TaskHealth {
+    pub fn beat(&self) {
+        self.heartbeat_ms
+            .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    pub fn last_heartbeat(&self) -> Option<DateTime<Utc>> {
+        match self.heartbeat_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => DateTime::from_timestamp_millis(ms),
+        }
+    }
+
+    pub fn restarts(&self) -> u64 {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    /// When the task was last restarted, and why.
+    pub fn last_restart(&self) -> Option<(DateTime<Utc>, String)> {
+        self.last_restart.lock().unwrap().clone()
+    }
+
+    fn stale(&self) -> bool {
+        let age = Utc::now().timestamp_millis() - self.heartbeat_ms.load(Ordering::Relaxed);
+        age > STALE_AFTER.as_millis() as i64
+    }
+
+    fn record_restart(&self, reason: String) -> u64 {
+        *self.last_restart.lock().unwrap() = Some((Utc::now(), reason));
+        self.restarts.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+/// Run `attempt` until it returns `Ok` or `cancel_rx` fires. An attempt that
+/// fails, panics or stops beating for 30 seconds is logged to the error ring,
+/// reported as a `task_restarted` event and started again after an
+/// exponential backoff.
+pub fn supervise<F, Fut>(
+    state: Arc<StateLock>,
+    context: &'static str,
+    task: String,
+    health: Arc<TaskHealth>,
+    mut cancel_rx: watch::Receiver<bool>,
+    mut attempt: F,
+) where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut backoff = BACKOFF_MIN;
+        loop {
+            health.beat();
+            let started = Instant::now();
+            let mut handle = tokio::spawn(attempt());
+            let mut check = tokio::time::interval(HEARTBEAT_INTERVAL);
+            let reason = loop {
+                tokio::select! {
+                    result = &mut handle => break match result {
+                        Ok(Ok(())) => return,
+                        Ok(Err(e)) => e,
+                        Err(e) => format!("task panicked: {e}"),
+                    },
+                    _ = check.tick() => {
+                        if health.stale() {
+                            handle.abort();
+                            break format!("no heartbeat for {}s", STALE_AFTER.as_secs());
+                        }
+                    }
+                    // The attempt watches the same channel and winds down itself
+                    changed = cancel_rx.changed() => {
+                        if changed.is_err() || *cancel_rx.borrow() {
+                            return;
+                        }
+                    }
+                }
+            };
+            if *cancel_rx.borrow() {
+                return;
+            }
+
+            if started.elapsed() >= HEALTHY_RUN {
+                backoff = BACKOFF_MIN;
+            }
+            eprintln!("{context}: {task} died ({reason}), restarting in {backoff:?}");
+            state.write().await.record_error(
+                context,
+                format!("{task} died ({reason}), restarting in {backoff:?}"),
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                changed = cancel_rx.changed() => {
+                    if changed.is_err() || *cancel_rx.borrow() {
+                        return;
+                    }
+                }
+            }
+            backoff = (backoff * 2).min(BACKOFF_MAX);
+
+            let restarts = health.record_restart(reason.clone());
+            notify::notify(
+                "task_restarted",
+                serde_json::json!({
+                    "task": task,
+                    "restarts": restarts,
+                    "reason": reason,
+                }),
+            );
+        }
+    });
+}
@@ -0,0 +1,86 @@
+use crate::framing::Framing;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long background tasks keep running once the host closes stdin
+/// without a `shutdown`.
+#[derive(Clone, Copy)]
+pub enum Linger {
+    /// For this long after stdin closes (`--linger=<secs>`).
+    For(Duration),
+    /// Until this time (`--linger-until=<rfc3339>`).
+    Until(DateTime<Utc>),
+}
+
+impl Linger {
+    pub fn deadline(self, closed_at: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Linger::For(period) => {
+                closed_at + chrono::Duration::from_std(period).unwrap_or(chrono::Duration::MAX)
+            }
+            Linger::Until(deadline) => deadline,
+        }
+    }
+}
+
+/// Command-line options for the extension binary.
+pub struct CliOptions {
+    /// Write a `dump_state` snapshot here if the process panics.
+    pub dump_on_panic: Option<PathBuf>,
+    /// stdin/stdout framing (`--framing=json|binary`).
+    pub framing: Framing,
+    /// Refuse mutating operations (`--read-only`).
+    pub read_only: bool,
+    /// Replace the Zenoh session with the in-process fake (`--mock[=config.json]`).
+    pub mock: bool,
+    pub mock_config: Option<PathBuf>,
+    pub linger: Option<Linger>,
+}
+
+impl Default for CliOptions {
+    fn default() -> Self {
+        Self {
+            dump_on_panic: None,
+            framing: Framing::Json,
+            read_only: false,
+            mock: false,
+            mock_config: None,
+            linger: None,
+        }
+    }
+}
+
+impl CliOptions {
+    pub fn parse() -> Self {
+        let mut opts = Self::default();
+        for arg in std::env::args().skip(1) {
+            match arg.split_once('=') {
+                Some(("--dump-on-panic", path)) => opts.dump_on_panic = Some(PathBuf::from(path)),
+                Some(("--framing", name)) => match Framing::parse(name) {
+                    Ok(framing) => opts.framing = framing,
+                    Err(e) => eprintln!("zenoh: {e}, using json"),
+                },
+                None if arg == "--dump-on-panic" => {
+                    opts.dump_on_panic = Some(std::env::temp_dir().join("zenoh-ext-dump.json"));
+                }
+                Some(("--mock", path)) => {
+                    opts.mock = true;
+                    opts.mock_config = Some(PathBuf::from(path));
+                }
+                Some(("--linger", secs)) => match secs.parse() {
+                    Ok(secs) => opts.linger = Some(Linger::For(Duration::from_secs(secs))),
+                    Err(_) => eprintln!("zenoh: invalid --linger seconds {secs}, not lingering"),
+                },
+                Some(("--linger-until", at)) => match DateTime::parse_from_rfc3339(at) {
+                    Ok(at) => opts.linger = Some(Linger::Until(at.to_utc())),
+                    Err(e) => eprintln!("zenoh: invalid --linger-until {at}: {e}, not lingering"),
+                },
+                None if arg == "--read-only" => opts.read_only = true,
+                None if arg == "--mock" => opts.mock = true,
+                _ => eprintln!("zenoh: ignoring unknown argument {arg}"),
+            }
+        }
+        opts
+    }
+}
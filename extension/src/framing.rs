@@ -0,0 +1,131 @@
+use nexus_zenoh_core::metrics;
+use serde::Serialize;
+use std::io::{self, BufRead, Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// Wire framing for stdin/stdout, selected at startup.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Framing {
+    /// One JSON message per line; payloads inline as base64/UTF-8.
+    Json,
+    /// Length-prefixed frames: `[u32 BE header_len][u32 BE blocks_len]`,
+    /// a JSON header, then raw payload blocks referenced by offset/len.
+    /// Requests reference theirs as `payload_block` in place of `payload_b64`.
+    Binary,
+}
+
+static FRAMING: OnceLock<Framing> = OnceLock::new();
+
+/// Results whose JSON exceeds this go out as `result_chunk` notifications;
+/// 0 while chunked delivery is off.
+static CHUNK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+impl Framing {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "json" => Ok(Framing::Json),
+            "binary" => Ok(Framing::Binary),
+            other => Err(format!("unknown framing: {other}")),
+        }
+    }
+}
+
+/// Select the process-wide framing; must be called before any I/O.
+pub fn init(framing: Framing) {
+    let _ = FRAMING.set(framing);
+}
+
+pub fn current() -> Framing {
+    *FRAMING.get().unwrap_or(&Framing::Json)
+}
+
+/// Turn chunked delivery on with a chunk size, or off.
+pub fn set_chunk_bytes(bytes: Option<usize>) {
+    CHUNK_BYTES.store(bytes.unwrap_or(0), Ordering::Relaxed);
+}
+
+pub fn chunk_bytes() -> Option<usize> {
+    Some(CHUNK_BYTES.load(Ordering::Relaxed)).filter(|&n| n > 0)
+}
+
+/// Largest JSON header or block section accepted in one binary request.
+const MAX_SECTION_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Read the next request as its JSON body and payload blocks (always empty
+/// under JSON framing), or `None` at EOF.
+pub fn read_request(reader: &mut impl BufRead) -> io::Result<Option<(Vec<u8>, Vec<u8>)>> {
+    match current() {
+        Framing::Json => {
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line)? {
+                0 => Ok(None),
+                _ => Ok(Some((line, Vec::new()))),
+            }
+        }
+        Framing::Binary => {
+            let mut header = [0u8; 8];
+            match reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            let json_len = u32::from_be_bytes(header[..4].try_into().unwrap()) as u64;
+            let blocks_len = u32::from_be_bytes(header[4..].try_into().unwrap()) as u64;
+            if json_len.max(blocks_len) > MAX_SECTION_BYTES {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("frame section over {MAX_SECTION_BYTES} bytes"),
+                ));
+            }
+            // Buffers grow as bytes arrive rather than trusting the header
+            let mut read_section = |len: u64| {
+                let mut section = Vec::new();
+                if reader.take(len).read_to_end(&mut section)? as u64 != len {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "truncated frame",
+                    ));
+                }
+                Ok(section)
+            };
+            let body = read_section(json_len)?;
+            let blocks = read_section(blocks_len)?;
+            Ok(Some((body, blocks)))
+        }
+    }
+}
+
+/// Write one message in the active framing. Returns the bytes written.
+pub fn write_frame(out: &mut impl Write, json: &[u8], blocks: &[u8]) -> io::Result<usize> {
+    match current() {
+        Framing::Json => {
+            out.write_all(json)?;
+            out.write_all(b"\n")?;
+            Ok(json.len() + 1)
+        }
+        Framing::Binary => {
+            out.write_all(&(json.len() as u32).to_be_bytes())?;
+            out.write_all(&(blocks.len() as u32).to_be_bytes())?;
+            out.write_all(json)?;
+            out.write_all(blocks)?;
+            Ok(8 + json.len() + blocks.len())
+        }
+    }
+}
+
+/// Write one JSON message to stdout in the active framing, with optional
+/// raw payload blocks (binary framing only). Responses and notifications
+/// share stdout, so each message is written under the lock.
+pub fn write_message(msg: &impl Serialize, blocks: &[u8]) {
+    write_json(&serde_json::to_vec(msg).unwrap(), blocks);
+}
+
+/// [`write_message`] for a message already serialized.
+pub fn write_json(json: &[u8], blocks: &[u8]) {
+    let mut stdout = io::stdout().lock();
+    if let Ok(written) = write_frame(&mut stdout, json, blocks) {
+        metrics::record_stdout(written);
+    }
+    let _ = io::Write::flush(&mut stdout);
+}
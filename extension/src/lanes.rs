@@ -0,0 +1,113 @@
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// Operations cheap enough to run ahead of queued data requests, so the
+/// host can see and stop what is running while a large poll or export is
+/// still working. Their responses can arrive before those of data requests
+/// sent earlier: an `unsubscribe` may run before the `subscribe` sent just
+/// ahead of it, and then fails as an unknown subscription.
+pub const CONTROL_OPERATIONS: [&str; 3] = ["list_subscriptions", "unsubscribe", "cancel_query"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Lane {
+    /// `shutdown` and [`CONTROL_OPERATIONS`].
+    Control,
+    /// Everything else, including `initialize`.
+    Data,
+}
+
+/// Two request queues, each run in order by its own task. Control requests
+/// never wait behind data requests; responses of the two lanes may overtake
+/// each other, so hosts match them by id.
+pub struct Lanes<T> {
+    control: mpsc::UnboundedSender<T>,
+    data: mpsc::UnboundedSender<T>,
+}
+
+/// The lane tasks, which end once every [`Lanes`] sender is dropped and
+/// their queues are empty.
+pub struct LaneTasks {
+    control: JoinHandle<()>,
+    data: JoinHandle<()>,
+    stop_data: watch::Sender<bool>,
+}
+
+impl<T: Send + Sync + 'static> Lanes<T> {
+    /// Run each request through `handler`; `abandon` answers those the data
+    /// lane gives up on at [`LaneTasks::abandon_data`].
+    pub fn spawn<F, Fut, A>(handler: F, abandon: A) -> (Self, LaneTasks)
+    where
+        F: Fn(Arc<T>) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+        A: Fn(&T) + Clone + Send + 'static,
+    {
+        let (control, control_rx) = mpsc::unbounded_channel();
+        let (data, data_rx) = mpsc::unbounded_channel();
+        let (stop_data, data_stopped) = watch::channel(false);
+        // The control lane is never stopped
+        let control_stopped = watch::channel(false).1;
+        let tasks = LaneTasks {
+            control: tokio::spawn(run(
+                control_rx,
+                handler.clone(),
+                abandon.clone(),
+                control_stopped,
+            )),
+            data: tokio::spawn(run(data_rx, handler, abandon, data_stopped)),
+            stop_data,
+        };
+        (Self { control, data }, tasks)
+    }
+
+    pub fn send(&self, lane: Lane, request: T) {
+        let _ = match lane {
+            Lane::Control => self.control.send(request),
+            Lane::Data => self.data.send(request),
+        };
+    }
+}
+
+impl LaneTasks {
+    /// Wait for both lanes to drain.
+    pub async fn drain(self) {
+        let _ = self.control.await;
+        let _ = self.data.await;
+    }
+
+    /// Wait for the control lane to finish, as it does after `shutdown`,
+    /// then stop the data lane, answering the request it was running and
+    /// every one still queued through `abandon`.
+    pub async fn abandon_data(self) {
+        let _ = self.control.await;
+        let _ = self.stop_data.send(true);
+        let _ = self.data.await;
+    }
+}
+
+async fn run<T, F, Fut, A>(
+    mut rx: mpsc::UnboundedReceiver<T>,
+    handler: F,
+    abandon: A,
+    mut stopped: watch::Receiver<bool>,
+) where
+    F: Fn(Arc<T>) -> Fut,
+    Fut: Future<Output = ()>,
+    A: Fn(&T),
+{
+    while let Some(request) = rx.recv().await {
+        let request = Arc::new(request);
+        tokio::select! {
+            () = handler(request.clone()) => {}
+            Ok(()) = stopped.changed() => {
+                abandon(&request);
+                break;
+            }
+        }
+    }
+    rx.close();
+    while let Some(request) = rx.recv().await {
+        abandon(&request);
+    }
+}
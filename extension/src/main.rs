@@ -1,13 +1,25 @@
-mod discovery;
-mod ops;
-mod state;
+mod cli;
+mod framing;
+mod lanes;
+mod signal;
 
+use chrono::Utc;
+use lanes::{Lane, Lanes};
+use nexus_zenoh_core::backend::{self, ZenohBackend};
+use nexus_zenoh_core::blocks::{self, PayloadBlocks};
+use nexus_zenoh_core::profile::Profile;
+use nexus_zenoh_core::recordings::RetentionPolicy;
+use nexus_zenoh_core::state::AppState;
+use nexus_zenoh_core::state_file::StateFile;
+use nexus_zenoh_core::{
+    admin, chunks, compress, continuation, dump, mock, namespace, notify, telemetry, Core,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use state::AppState;
-use std::io::{self, Write};
+use std::io;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Instant;
 
 #[derive(Deserialize)]
 struct JsonRpcRequest {
@@ -17,6 +29,12 @@ struct JsonRpcRequest {
     #[serde(default)]
     params: Value,
     id: u64,
+    /// When the request was read, for the queue wait of traced requests.
+    #[serde(skip, default = "Instant::now")]
+    received: Instant,
+    /// Payload blocks trailing the JSON header under binary framing.
+    #[serde(skip)]
+    blocks: Vec<u8>,
 }
 
 #[derive(Serialize)]
@@ -27,12 +45,18 @@ struct JsonRpcResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<JsonRpcError>,
     id: u64,
+    /// Raw payload blocks trailing the JSON header under binary framing.
+    #[serde(skip)]
+    blocks: Vec<u8>,
 }
 
 #[derive(Serialize)]
 struct JsonRpcError {
     code: i64,
     message: String,
+    /// The `timing` block of a traced request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
 }
 
 fn ok_response(id: u64, data: Value) -> JsonRpcResponse {
@@ -45,6 +69,7 @@ fn ok_response(id: u64, data: Value) -> JsonRpcResponse {
         })),
         error: None,
         id,
+        blocks: Vec::new(),
     }
 }
 
@@ -52,149 +77,425 @@ fn err_response(id: u64, code: i64, message: String) -> JsonRpcResponse {
     JsonRpcResponse {
         jsonrpc: "2.0",
         result: None,
-        error: Some(JsonRpcError { code, message }),
+        error: Some(JsonRpcError {
+            code,
+            message,
+            data: None,
+        }),
         id,
+        blocks: Vec::new(),
     }
 }
 
 #[tokio::main]
 async fn main() {
-    // Open zenoh session
-    let config = match std::env::var("ZENOH_CONFIG") {
-        Ok(path) => zenoh::Config::from_file(&path).unwrap_or_else(|e| {
-            eprintln!("zenoh: failed to load config from {path}: {e}, using default");
-            zenoh::Config::default()
-        }),
-        Err(_) => zenoh::Config::default(),
+    let opts = cli::CliOptions::parse();
+    framing::init(opts.framing);
+
+    // Open zenoh session, or the in-process fake with --mock
+    let session: Arc<dyn ZenohBackend> = if opts.mock {
+        match mock::MockConfig::load(opts.mock_config.as_deref()) {
+            Ok(config) => mock::MockBackend::start(config),
+            Err(e) => {
+                eprintln!("zenoh: {e}");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        Arc::new(backend::open_from_env().await.unwrap_or_else(|e| {
+            eprintln!("zenoh: failed to open session: {e}");
+            std::process::exit(1);
+        }))
     };
 
-    let session = Arc::new(zenoh::open(config).await.unwrap_or_else(|e| {
-        eprintln!("zenoh: failed to open session: {e}");
-        std::process::exit(1);
-    }));
+    let mut app_state = AppState::new();
+    app_state.read_only = opts.read_only;
+    let core = Core::new(session, app_state);
+    notify::set_sink(write_notification);
+    let linger = opts.linger;
 
-    let state = Arc::new(RwLock::new(AppState::new()));
+    if let Some(path) = opts.dump_on_panic {
+        dump::install_panic_hook(path, core.state.clone(), core.backend.zid());
+    }
 
-    // Read stdin in a blocking thread, dispatch to async handlers
-    let session_clone = session.clone();
-    let state_clone = state.clone();
-    let handle = tokio::runtime::Handle::current();
+    let (lanes, lane_tasks) = Lanes::spawn(
+        {
+            let core = core.clone();
+            move |request: Arc<JsonRpcRequest>| {
+                let core = core.clone();
+                async move {
+                    let response = handle_request(&request, &core).await;
+                    write_response(&response);
+                }
+            }
+        },
+        |request: &JsonRpcRequest| {
+            let message = "abandoned: the extension shut down".to_string();
+            write_response(&err_response(request.id, -32000, message));
+        },
+    );
 
-    tokio::task::spawn_blocking(move || {
-        let stdin = io::stdin();
-        let stdout = io::stdout();
-        let mut stdout = stdout.lock();
-        let mut line = String::new();
+    // Read stdin in a blocking thread, queue requests on their lane
+    let stdin_loop = tokio::task::spawn_blocking(move || {
+        let mut stdin = io::stdin().lock();
 
         loop {
-            line.clear();
-            match stdin.read_line(&mut line) {
-                Ok(0) | Err(_) => break,
-                _ => {}
-            }
-
-            if line.trim().is_empty() {
+            let (body, blocks) = match framing::read_request(&mut stdin) {
+                Ok(Some(request)) => request,
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("zenoh: failed to read request: {e}");
+                    break;
+                }
+            };
+            if body.trim_ascii().is_empty() {
                 continue;
             }
 
-            let request: JsonRpcRequest = match serde_json::from_str(&line) {
-                Ok(r) => r,
+            let request = match serde_json::from_slice::<JsonRpcRequest>(&body) {
+                Ok(r) => JsonRpcRequest { blocks, ..r },
                 Err(e) => {
                     let resp = err_response(0, -32700, format!("Parse error: {e}"));
-                    let _ = writeln!(stdout, "{}", serde_json::to_string(&resp).unwrap());
-                    let _ = stdout.flush();
+                    framing::write_message(&resp, &resp.blocks);
                     continue;
                 }
             };
 
             let is_shutdown = request.method == "shutdown";
 
-            let response = handle.block_on(handle_request(
-                &request,
-                &session_clone,
-                &state_clone,
-            ));
-
-            let _ = writeln!(stdout, "{}", serde_json::to_string(&response).unwrap());
-            let _ = stdout.flush();
+            lanes.send(lane(&request), request);
 
             if is_shutdown {
-                break;
+                return true;
             }
         }
-    })
-    .await
-    .unwrap();
+        false
+    });
+    let served = async {
+        let shut_down = stdin_loop.await.unwrap();
+        if shut_down {
+            // Shutdown already stopped what data requests were working on;
+            // answer them and any still queued with an error
+            lane_tasks.abandon_data().await;
+        } else {
+            lane_tasks.drain().await;
+            linger_after_eof(&core, linger).await;
+        }
+        shut_down
+    };
+
+    let shut_down = tokio::select! {
+        shut_down = served => shut_down,
+        name = signal::terminated() => {
+            eprintln!("zenoh: received {name}, shutting down");
+            false
+        }
+    };
+    if !shut_down {
+        core.shutdown().await;
+    }
+    core.backend.close().await;
+    // The stdin thread may still be blocked in a read after a signal
+    std::process::exit(0);
+}
+
+/// The host closed stdin without a `shutdown`. Keep recorders and relays
+/// running for the linger period rather than abort them mid-capture.
+async fn linger_after_eof(core: &Core, linger: Option<cli::Linger>) {
+    let running = core.background_tasks().await;
+    if let (Some(linger), true) = (linger, running > 0) {
+        let deadline = linger.deadline(Utc::now());
+        eprintln!("zenoh: stdin closed, keeping {running} background tasks until {deadline}");
+        core.linger(deadline).await;
+    }
+}
+
+/// Write a response. Under chunked delivery a result too large for one
+/// message is replaced by a handle and follows as `result_chunk`
+/// notifications.
+fn write_response(response: &JsonRpcResponse) {
+    let (Some(max_bytes), Some(result)) = (framing::chunk_bytes(), &response.result) else {
+        framing::write_message(response, &response.blocks);
+        return;
+    };
+    let json = serde_json::to_vec(response).unwrap();
+    if json.len() <= max_bytes {
+        framing::write_json(&json, &response.blocks);
+        return;
+    }
+    let result = serde_json::to_vec(result).unwrap();
+    let (chunked, chunks) = chunks::split(response.id, &result, max_bytes);
+    framing::write_message(
+        &serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "chunked": chunked },
+            "id": response.id,
+        }),
+        &[],
+    );
+    for chunk in chunks {
+        write_notification("result_chunk", serde_json::to_value(chunk).unwrap());
+    }
+}
+
+/// Emit a JSON-RPC notification (no id) carrying an extension event.
+fn write_notification(event: &str, data: Value) {
+    framing::write_message(
+        &serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notify",
+            "params": {
+                "event": event,
+                "data": data,
+            },
+        }),
+        &[],
+    );
+}
+
+fn lane(req: &JsonRpcRequest) -> Lane {
+    let operation = req.params.get("operation").and_then(Value::as_str);
+    match req.method.as_str() {
+        "shutdown" => Lane::Control,
+        "execute" if operation.is_some_and(|op| lanes::CONTROL_OPERATIONS.contains(&op)) => {
+            Lane::Control
+        }
+        _ => Lane::Data,
+    }
 }
 
-async fn handle_request(
-    req: &JsonRpcRequest,
-    session: &Arc<zenoh::Session>,
-    state: &Arc<RwLock<AppState>>,
-) -> JsonRpcResponse {
+async fn handle_request(req: &JsonRpcRequest, core: &Core) -> JsonRpcResponse {
     match req.method.as_str() {
-        "initialize" => JsonRpcResponse {
-            jsonrpc: "2.0",
-            result: Some(serde_json::json!({ "ready": true })),
-            error: None,
-            id: req.id,
-        },
+        "initialize" => handle_initialize(req, core).await,
 
         "shutdown" => {
             // Clean up: stop discovery and all subscriptions
-            {
-                let mut st = state.write().await;
-                if let Some(cancel) = st.discovery_cancel.take() {
-                    let _ = cancel.send(true);
-                }
-                for (_, sub) in st.subscriptions.drain() {
-                    let _ = sub.cancel.send(true);
-                }
-            }
+            core.shutdown().await;
             JsonRpcResponse {
                 jsonrpc: "2.0",
                 result: Some(serde_json::json!({})),
                 error: None,
                 id: req.id,
+                blocks: Vec::new(),
             }
         }
 
-        "execute" => handle_execute(req, session, state).await,
+        "execute" => handle_execute(req, core).await,
 
         _ => err_response(req.id, -32601, format!("Unknown method: {}", req.method)),
     }
 }
 
-async fn handle_execute(
-    req: &JsonRpcRequest,
-    session: &Arc<zenoh::Session>,
-    state: &Arc<RwLock<AppState>>,
-) -> JsonRpcResponse {
+async fn handle_initialize(req: &JsonRpcRequest, core: &Core) -> JsonRpcResponse {
+    let compression = match req.params.get("compression").and_then(|v| v.as_str()) {
+        Some(name) => match compress::Compression::parse(name) {
+            Ok(c) => Some(c),
+            Err(msg) => return err_response(req.id, -32602, msg),
+        },
+        None => None,
+    };
+    let namespace = match req.params.get("namespace").and_then(|v| v.as_str()) {
+        Some(ns) => match namespace::parse(ns) {
+            Ok(ns) => ns,
+            Err(msg) => return err_response(req.id, -32602, msg),
+        },
+        None => None,
+    };
+
+    let recordings = match req.params.get("recordings") {
+        Some(value) => match RetentionPolicy::deserialize(value)
+            .map_err(|e| format!("invalid recordings: {e}"))
+            .and_then(RetentionPolicy::validate)
+        {
+            Ok(policy) => Some(policy),
+            Err(msg) => return err_response(req.id, -32602, msg),
+        },
+        None => None,
+    };
+
+    let admin_prefix = match req.params.get("admin_key_expr").and_then(|v| v.as_str()) {
+        Some(prefix) => match admin::parse_prefix(prefix) {
+            Ok(prefix) => Some(prefix),
+            Err(msg) => return err_response(req.id, -32602, msg),
+        },
+        None => None,
+    };
+
+    let profile = match req.params.get("profile").and_then(|v| v.as_str()) {
+        Some(name) => match Profile::parse(name) {
+            Ok(profile) => profile,
+            Err(msg) => return err_response(req.id, -32602, msg),
+        },
+        None => Profile::default(),
+    };
+
+    let max_response_bytes = match req.params.get("max_response_bytes") {
+        Some(value) => match value.as_u64().map(|n| n as usize) {
+            Some(n) if n >= continuation::MIN_RESPONSE_BYTES => Some(n),
+            _ => {
+                return err_response(
+                    req.id,
+                    -32602,
+                    format!(
+                        "max_response_bytes must be an integer of at least {}",
+                        continuation::MIN_RESPONSE_BYTES
+                    ),
+                )
+            }
+        },
+        None => None,
+    };
+
+    let chunk_bytes = match req.params.get("chunk_bytes") {
+        Some(_) if framing::current() == framing::Framing::Binary => {
+            return err_response(
+                req.id,
+                -32602,
+                "chunk_bytes applies to json framing only".into(),
+            )
+        }
+        Some(value) => match value.as_u64().map(|n| n as usize) {
+            Some(n) if n >= chunks::MIN_CHUNK_BYTES => Some(n),
+            _ => {
+                return err_response(
+                    req.id,
+                    -32602,
+                    format!(
+                        "chunk_bytes must be an integer of at least {}",
+                        chunks::MIN_CHUNK_BYTES
+                    ),
+                )
+            }
+        },
+        None => None,
+    };
+
+    let telemetry = match req.params.get("telemetry") {
+        Some(value) => match telemetry::TelemetryConfig::deserialize(value)
+            .map_err(|e| format!("invalid telemetry: {e}"))
+            .and_then(telemetry::TelemetryConfig::validate)
+        {
+            Ok(config) => Some(config),
+            Err(msg) => return err_response(req.id, -32602, msg),
+        },
+        None => None,
+    };
+
+    let state_file = match req.params.get("state_file") {
+        Some(value) => match value.as_str() {
+            Some(path) if !path.is_empty() => {
+                let path = PathBuf::from(path);
+                match StateFile::load(&path) {
+                    Ok(saved) => Some((path, saved)),
+                    Err(msg) => return err_response(req.id, -32000, msg),
+                }
+            }
+            _ => return err_response(req.id, -32602, "state_file must be a file path".into()),
+        },
+        None => None,
+    };
+    let state_file_path = state_file
+        .as_ref()
+        .map(|(path, _)| path.display().to_string());
+
+    // Initialize can turn read-only mode on but never off
+    let read_only = req.params.get("read_only").and_then(|v| v.as_bool()) == Some(true);
+
+    // Start what can fail before committing any setting, so a failed
+    // initialize leaves the extension as it was; dropping the admin
+    // queryable on a telemetry failure undeclares it
+    let admin = match &admin_prefix {
+        Some(prefix) => match admin::serve(core.clone(), prefix.clone()).await {
+            Ok(admin) => Some(admin),
+            Err(msg) => {
+                return err_response(req.id, -32000, format!("failed to serve admin: {msg}"))
+            }
+        },
+        None => None,
+    };
+    if let Some(config) = &telemetry {
+        if let Err(msg) = telemetry::install(config).await {
+            return err_response(req.id, -32000, format!("failed to start telemetry: {msg}"));
+        }
+    }
+
+    let mut st = core.state.write().await;
+    st.compression = compression;
+    st.namespace = namespace.clone();
+    st.read_only |= read_only;
+    st.recordings = recordings.clone();
+    st.profile = profile;
+    st.max_response_bytes = max_response_bytes;
+    if let Some((path, saved)) = state_file {
+        st.state_file = Some(path);
+        st.tags = saved.tags;
+    }
+    if admin.is_some() {
+        st.admin = admin;
+    }
+    let read_only = st.read_only;
+    drop(st);
+    core.backend.set_express(profile.tuning().express);
+    framing::set_chunk_bytes(chunk_bytes);
+
+    JsonRpcResponse {
+        jsonrpc: "2.0",
+        result: Some(serde_json::json!({
+            "ready": true,
+            "compression": compression.map(|c| c.as_str()),
+            "namespace": namespace,
+            "read_only": read_only,
+            "profile": profile.as_str(),
+            "max_response_bytes": max_response_bytes,
+            "chunk_bytes": chunk_bytes,
+            "recordings": recordings,
+            "admin_key_expr": admin_prefix.map(|p| format!("{p}/*")),
+            "telemetry": telemetry,
+            "state_file": state_file_path,
+        })),
+        error: None,
+        id: req.id,
+        blocks: Vec::new(),
+    }
+}
+
+async fn handle_execute(req: &JsonRpcRequest, core: &Core) -> JsonRpcResponse {
     let operation = req
         .params
         .get("operation")
         .and_then(|v| v.as_str())
         .unwrap_or("");
-    let input = req
+    let mut input = req
         .params
         .get("input")
         .cloned()
         .unwrap_or(Value::Object(Default::default()));
+    if let Err(e) = blocks::resolve_inbound(&mut input, &req.blocks) {
+        return err_response(req.id, -32602, e);
+    }
 
-    let result = match operation {
-        "session_info" => ops::op_session_info(session).await,
-        "start_discovery" => ops::op_start_discovery(&input, session.clone(), state.clone()).await,
-        "stop_discovery" => ops::op_stop_discovery(state.clone()).await,
-        "get_topics" => ops::op_get_topics(&input, state.clone()).await,
-        "subscribe" => ops::op_subscribe(&input, session.clone(), state.clone()).await,
-        "unsubscribe" => ops::op_unsubscribe(&input, state.clone()).await,
-        "poll" => ops::op_poll(&input, state.clone()).await,
-        "list_subscriptions" => ops::op_list_subscriptions(state.clone()).await,
-        _ => Err(format!("Unknown operation: {operation}")),
-    };
+    let traced = req.params.get("trace").and_then(Value::as_bool) == Some(true);
+
+    let mut blocks = PayloadBlocks::new(framing::current() == framing::Framing::Binary);
 
-    match result {
-        Ok(data) => ok_response(req.id, data),
+    let (result, timing) = core
+        .execute_timed(operation, &input, &mut blocks, req.received)
+        .await;
+    let timing = traced.then(|| serde_json::to_value(timing).ok()).flatten();
+    let mut response = match result {
+        Ok(data) => JsonRpcResponse {
+            blocks: blocks.into_bytes(),
+            ..ok_response(req.id, data)
+        },
         Err(msg) => err_response(req.id, -32000, msg),
+    };
+    // Timing covers the operation, not encoding the response itself
+    if let Some(timing) = timing {
+        match (&mut response.result, &mut response.error) {
+            (Some(result), _) => result["timing"] = timing,
+            (_, Some(error)) => error.data = Some(serde_json::json!({ "timing": timing })),
+            _ => {}
+        }
     }
+    response
 }
@@ -0,0 +1,35 @@
+/// Wait for a supervisor to ask the process to stop: SIGTERM or SIGINT on
+/// Unix, a console control event on Windows. Returns the signal's name.
+/// Never resolves if the handlers cannot be installed.
+#[cfg(unix)]
+pub async fn terminated() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+    let (Ok(mut term), Ok(mut int)) = (
+        signal(SignalKind::terminate()),
+        signal(SignalKind::interrupt()),
+    ) else {
+        eprintln!("zenoh: failed to install signal handlers");
+        return std::future::pending().await;
+    };
+    tokio::select! {
+        _ = term.recv() => "SIGTERM",
+        _ = int.recv() => "SIGINT",
+    }
+}
+
+#[cfg(windows)]
+pub async fn terminated() -> &'static str {
+    use tokio::signal::windows::{ctrl_break, ctrl_c, ctrl_close, ctrl_shutdown};
+    let (Ok(mut c), Ok(mut brk), Ok(mut close), Ok(mut shutdown)) =
+        (ctrl_c(), ctrl_break(), ctrl_close(), ctrl_shutdown())
+    else {
+        eprintln!("zenoh: failed to install console event handlers");
+        return std::future::pending().await;
+    };
+    tokio::select! {
+        _ = c.recv() => "CTRL_C_EVENT",
+        _ = brk.recv() => "CTRL_BREAK_EVENT",
+        _ = close.recv() => "CTRL_CLOSE_EVENT",
+        _ = shutdown.recv() => "CTRL_SHUTDOWN_EVENT",
+    }
+}